@@ -0,0 +1,13 @@
+//! 构建脚本：区分 JS 宿主与其它目标
+//!
+//! `js_host` 仅在 wasm32-unknown-unknown（浏览器 / Node，由 wasm-bindgen 提供 JS 胶水）上启用；
+//! wasm32-wasip1 等 WASI 目标没有 JS 环境，与原生目标一样走纯 Rust 路径。
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(js_host)");
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if arch == "wasm32" && (os == "unknown" || os == "none") {
+        println!("cargo::rustc-cfg=js_host");
+    }
+}
@@ -0,0 +1,408 @@
+//! 无浏览器的 HTTP 渲染服务
+//!
+//! 基于标准库的最小 HTTP/1.1 实现（固定大小的工作线程池，不支持 keep-alive），
+//! 请求体与 WASM 接口相同：
+//!
+//! - `POST /render`，`Content-Type: application/msgpack`：同 `render_map_msgpack`
+//! - `POST /render`，其它类型：JSON 请求，同 `render_map`
+//! - `GET /health`：存活检查
+//!
+//! 成功返回 `image/png`；失败返回 JSON `{ code, message, field? }`。渲染错误沿用
+//! `RenderErrorCode`（配置与几何错误为 400，画布过大为 413，其余为 500），
+//! HTTP 层的错误使用与状态码对应的 code：`bad_request`（400）、`not_found`（404）、
+//! `timeout`（408）、`payload_too_large`（413）、`not_implemented`（501）、`busy`（503）。
+//!
+//! 连接由 `--workers` 个线程处理，排队的连接数上限与线程数相同，队列已满时
+//! 直接返回 503；读写超过 `--timeout` 秒的连接返回 408 或被关闭。
+//! 请求体只支持 `Content-Length`，带 `Transfer-Encoding`（如 chunked）的请求返回 501。
+//!
+//! 渲染是同步的 CPU 密集调用，异步运行时（axum / hyper）只会把请求转交给同样大小的
+//! 阻塞线程池，因此这里保持仅依赖标准库。
+//!
+//! ```text
+//! maptoposter-server [--addr 127.0.0.1:8080] [--workers N] [--max-body-mb 32]
+//!                    [--timeout 30] [--verbose]
+//! ```
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasm::error::{RenderError, RenderErrorCode};
+use wasm::logging::{self, LogLevel};
+
+/// 默认请求体上限（MiB）
+const DEFAULT_MAX_BODY_MB: usize = 32;
+/// 默认读写超时（秒）
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+/// 请求行与请求头的总长度上限
+const MAX_HEAD: u64 = 16 << 10;
+
+const USAGE: &str = "Usage: maptoposter-server [--addr <HOST:PORT>] [--workers <N>] \
+[--max-body-mb <MiB>] [--timeout <SECONDS>] [--verbose]";
+
+struct Options {
+    addr: String,
+    workers: usize,
+    max_body: usize,
+    timeout: Duration,
+    verbose: bool,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+/// HTTP 层错误的响应体，与 `RenderError` 的 `{ code, message }` 形状相同
+#[derive(serde::Serialize)]
+struct HttpError<'a> {
+    code: &'static str,
+    message: &'a str,
+}
+
+impl Response {
+    fn error(status: u16, error: &RenderError) -> Self {
+        Response::json(status, serde_json::to_vec(error).unwrap_or_default())
+    }
+
+    /// 非渲染错误，`code` 由状态码决定
+    fn http_error(status: u16, message: &str) -> Self {
+        let code = match status {
+            400 => "bad_request",
+            404 => "not_found",
+            408 => "timeout",
+            413 => "payload_too_large",
+            501 => "not_implemented",
+            503 => "busy",
+            _ => "internal",
+        };
+        let body = serde_json::to_vec(&HttpError { code, message }).unwrap_or_default();
+        Response::json(status, body)
+    }
+
+    fn json(status: u16, body: Vec<u8>) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+}
+
+/// 读取失败对应的响应：超时为 408，其余 I/O 错误为 400
+fn io_error(e: std::io::Error) -> Response {
+    let status = match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => 408,
+        _ => 400,
+    };
+    Response::http_error(status, &e.to_string())
+}
+
+fn read_request(stream: impl Read, max_body: usize) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::http_error(400, message);
+    // 请求头按 MAX_HEAD 限长，读完后再按 Content-Length 放开
+    let mut reader = BufReader::new(stream.take(MAX_HEAD));
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(io_error)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("Malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let (mut content_length, mut content_type) = (0usize, String::new());
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(io_error)? == 0 {
+            return Err(bad_request("Request head is truncated or too large"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse()
+                        .map_err(|_| bad_request("Invalid Content-Length"))?
+                }
+                "content-type" => content_type = value.to_ascii_lowercase(),
+                // 不解析分块编码：按 Content-Length 读取会把分块数据当作下一请求或空请求体
+                "transfer-encoding" => {
+                    let message = "Transfer-Encoding is not supported; send Content-Length";
+                    return Err(Response::http_error(501, message));
+                }
+                _ => {}
+            }
+        }
+    }
+    if content_length > max_body {
+        let message = format!("Request body exceeds {} bytes", max_body);
+        return Err(Response::http_error(413, &message));
+    }
+    reader.get_mut().set_limit(content_length as u64);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(io_error)?;
+    Ok(Request {
+        method,
+        path,
+        content_type,
+        body,
+    })
+}
+
+fn render(request: &Request) -> Response {
+    let result = if request.content_type.starts_with("application/msgpack")
+        || request.content_type.starts_with("application/x-msgpack")
+    {
+        wasm::render_map_msgpack(&request.body)
+    } else {
+        match std::str::from_utf8(&request.body) {
+            Ok(json) => wasm::render_map(json),
+            Err(e) => {
+                let error = RenderError::invalid_config(format!("Body is not UTF-8: {}", e));
+                return Response::error(400, &error);
+            }
+        }
+    };
+    if let Some(error) = result.error_info() {
+        let status = match error.code {
+            RenderErrorCode::InvalidConfig | RenderErrorCode::BadGeometry => 400,
+            RenderErrorCode::CanvasTooLarge => 413,
+            _ => 500,
+        };
+        return Response::error(status, error);
+    }
+    Response {
+        status: 200,
+        content_type: "image/png",
+        body: result.into_data().unwrap_or_default(),
+    }
+}
+
+fn route(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/render") => render(request),
+        ("GET", "/health") => Response {
+            status: 200,
+            content_type: "text/plain",
+            body: b"ok".to_vec(),
+        },
+        _ => Response::http_error(404, "Not found"),
+    }
+}
+
+fn handle(mut stream: TcpStream, options: &Options) {
+    let timeout = Some(options.timeout);
+    if stream
+        .set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+        .is_err()
+    {
+        return;
+    }
+    let response = match read_request(&stream, options.max_body) {
+        Ok(request) => {
+            let response = route(&request);
+            if options.verbose {
+                eprintln!(
+                    "[info] {} {} -> {}",
+                    request.method, request.path, response.status
+                );
+            }
+            response
+        }
+        Err(response) => response,
+    };
+    write_response(&mut stream, &response);
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    );
+    let _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(&response.body));
+}
+
+/// 工作线程：逐个取出排队的连接处理
+fn worker(queue: Arc<Mutex<Receiver<TcpStream>>>, options: Arc<Options>) {
+    loop {
+        let stream = match queue.lock() {
+            Ok(queue) => queue.recv(),
+            Err(_) => return,
+        };
+        match stream {
+            Ok(stream) => handle(stream, &options),
+            Err(_) => return,
+        }
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        addr: "127.0.0.1:8080".to_string(),
+        workers: std::thread::available_parallelism().map_or(4, |n| n.get()),
+        max_body: DEFAULT_MAX_BODY_MB << 20,
+        timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        verbose: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--verbose" {
+            options.verbose = true;
+            continue;
+        }
+        let value = match arg.as_str() {
+            "--addr" | "--workers" | "--max-body-mb" | "--timeout" => args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", arg))?,
+            _ => return Err(USAGE.to_string()),
+        };
+        let number = || match value.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Invalid value for {}: {}", arg, value)),
+        };
+        match arg.as_str() {
+            "--addr" => options.addr = value.clone(),
+            "--workers" => options.workers = number()?,
+            "--max-body-mb" => options.max_body = number()? << 20,
+            _ => options.timeout = Duration::from_secs(number()? as u64),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => Arc::new(options),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    logging::set_log_level(if options.verbose {
+        LogLevel::Info
+    } else {
+        LogLevel::Warn
+    });
+    logging::set_log_sink(Some(Box::new(|level, message| {
+        eprintln!("[{}] {}", level.as_str(), message)
+    })));
+
+    let listener = match TcpListener::bind(&options.addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", options.addr, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (sender, receiver) = mpsc::sync_channel(options.workers);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..options.workers {
+        let (receiver, options) = (Arc::clone(&receiver), Arc::clone(&options));
+        std::thread::spawn(move || worker(receiver, options));
+    }
+    eprintln!(
+        "Listening on http://{} ({} workers)",
+        options.addr, options.workers
+    );
+    for stream in listener.incoming().flatten() {
+        if let Err(TrySendError::Full(mut stream)) = sender.try_send(stream) {
+            let _ = stream.set_write_timeout(Some(options.timeout));
+            write_response(&mut stream, &Response::http_error(503, "Server is busy"));
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(response: &Response) -> String {
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        body["code"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_read_request_body() {
+        let raw = b"POST /render HTTP/1.1\r\nContent-Type: application/json\r\n\
+Content-Length: 2\r\n\r\n{}";
+        let request = read_request(&raw[..], 1024).ok().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/render")
+        );
+        assert_eq!(request.content_type, "application/json");
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[test]
+    fn test_read_request_rejects_transfer_encoding() {
+        let raw = b"POST /render HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+2\r\n{}\r\n0\r\n\r\n";
+        let response = read_request(&raw[..], 1024).err().unwrap();
+        assert_eq!(response.status, 501);
+        assert_eq!(error_code(&response), "not_implemented");
+    }
+
+    #[test]
+    fn test_http_error_codes_match_status() {
+        let raw = b"POST /render HTTP/1.1\r\nContent-Length: 4096\r\n\r\n";
+        let response = read_request(&raw[..], 1024).err().unwrap();
+        assert_eq!(
+            (response.status, error_code(&response).as_str()),
+            (413, "payload_too_large")
+        );
+
+        let request = Request {
+            method: "GET".to_string(),
+            path: "/missing".to_string(),
+            content_type: String::new(),
+            body: Vec::new(),
+        };
+        let response = route(&request);
+        assert_eq!(
+            (response.status, error_code(&response).as_str()),
+            (404, "not_found")
+        );
+
+        let timeout = io_error(std::io::ErrorKind::TimedOut.into());
+        assert_eq!(
+            (timeout.status, error_code(&timeout).as_str()),
+            (408, "timeout")
+        );
+        assert_eq!(error_code(&Response::http_error(503, "busy")), "busy");
+    }
+}
@@ -0,0 +1,216 @@
+//! 命令行海报生成器
+//!
+//! 读取 GeoJSON / Overpass JSON / .osm XML 与主题 JSON，经与 WASM 相同的渲染管线输出 PNG，
+//! 用于批量生成与主题的自动化测试：
+//!
+//! ```text
+//! maptoposter --input city.geojson --output poster.png [--theme theme.json]
+//!             [--center LAT,LON] [--radius METERS] [--size WxH]
+//!             [--city NAME] [--country NAME] [--verbose]
+//! ```
+
+use std::process::ExitCode;
+use wasm::data_processor::{self, OsmLayers};
+use wasm::logging::{self, LogLevel};
+use wasm::types::{Crs, RenderConfig, Theme};
+
+const USAGE: &str = "Usage: maptoposter --input <FILE> --output <PNG> [--theme <JSON>] \
+[--center <LAT,LON>] [--radius <METERS>] [--size <WxH>] [--city <NAME>] [--country <NAME>] [--verbose]";
+
+/// 命令行参数
+struct Args {
+    input: String,
+    output: String,
+    theme: Option<String>,
+    center: Option<(f64, f64)>,
+    radius: f64,
+    size: (u32, u32),
+    city: String,
+    country: String,
+    verbose: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        input: String::new(),
+        output: String::new(),
+        theme: None,
+        center: None,
+        radius: 5000.0,
+        size: (3508, 4961),
+        city: String::new(),
+        country: String::new(),
+        verbose: false,
+    };
+    while let Some(flag) = args.next() {
+        if flag == "--verbose" {
+            parsed.verbose = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        let pair = |sep: char| -> Result<(&str, &str), String> {
+            value
+                .split_once(sep)
+                .ok_or_else(|| format!("Invalid value for {}: {}", flag, value))
+        };
+        let number = |s: &str| -> Result<f64, String> {
+            s.trim()
+                .parse()
+                .map_err(|_| format!("Invalid number for {}: {}", flag, s))
+        };
+        match flag.as_str() {
+            "--input" => parsed.input = value.clone(),
+            "--output" => parsed.output = value.clone(),
+            "--theme" => parsed.theme = Some(value.clone()),
+            "--center" => {
+                let (lat, lon) = pair(',')?;
+                parsed.center = Some((number(lat)?, number(lon)?));
+            }
+            "--radius" => parsed.radius = number(&value)?,
+            "--size" => {
+                let (w, h) = pair('x')?;
+                let side = |s: &str| match s.trim().parse::<u32>() {
+                    Ok(n) if n > 0 => Ok(n),
+                    _ => Err(format!("Invalid size for {}: {}", flag, value)),
+                };
+                parsed.size = (side(w)?, side(h)?);
+            }
+            "--city" => parsed.city = value.clone(),
+            "--country" => parsed.country = value.clone(),
+            _ => return Err(format!("Unknown option {}", flag)),
+        }
+    }
+    if parsed.input.is_empty() || parsed.output.is_empty() {
+        return Err("--input and --output are required".to_string());
+    }
+    Ok(parsed)
+}
+
+/// 数据范围的中心点 (lat, lon)
+fn data_center(layers: &OsmLayers) -> Option<(f64, f64)> {
+    let points = layers.roads.iter().flat_map(|r| r.coords.iter()).chain(
+        layers
+            .water
+            .iter()
+            .chain(&layers.parks)
+            .flat_map(|p| p.exterior.iter()),
+    );
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(lon, lat) in points {
+        min = (min.0.min(lon), min.1.min(lat));
+        max = (max.0.max(lon), max.1.max(lat));
+    }
+    (min.0 <= max.0).then(|| ((min.1 + max.1) / 2.0, (min.0 + max.0) / 2.0))
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let input = std::fs::read_to_string(&args.input)
+        .map_err(|e| format!("Failed to read {}: {}", args.input, e))?;
+    let layers = data_processor::parse_osm_auto(&input)?;
+
+    let theme: Theme = match &args.theme {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            serde_json::from_str(&json).map_err(|e| format!("Invalid theme {}: {}", path, e))?
+        }
+        None => Theme::default(),
+    };
+    let (lat, lon) = args
+        .center
+        .or_else(|| data_center(&layers))
+        .ok_or("Input contains no geometry; pass --center")?;
+
+    let mut config: RenderConfig = serde_json::from_value(serde_json::json!({
+        "center": { "lat": lat, "lon": lon },
+        "radius": args.radius,
+        "theme": theme,
+        "width": args.size.0,
+        "height": args.size.1,
+        "display_city": args.city,
+        "display_country": args.country,
+        "text_position": null,
+    }))
+    .map_err(|e| format!("Invalid config: {}", e))?;
+    // OSM 数据为经纬度，由渲染管线投影
+    config.crs = Some(Crs::Wgs84);
+
+    let result = wasm::render_binary(
+        vec![data_processor::roads_to_bin(&layers.roads)],
+        data_processor::polygons_to_bin(&layers.water),
+        data_processor::polygons_to_bin(&layers.parks),
+        config,
+        None,
+    );
+    if let Some(error) = result.error_info() {
+        return Err(format!(
+            "Render failed ({}): {}",
+            error.code.as_str(),
+            error
+        ));
+    }
+    for warning in result.warnings() {
+        let field = warning.field.as_deref().unwrap_or("input");
+        eprintln!("[warn] {}: {} ({})", field, warning.message, warning.count);
+    }
+    if args.verbose {
+        for timing in result.timings() {
+            eprintln!("[info] {}: {:.1}ms", timing.stage, timing.ms);
+        }
+    }
+    let png = result.into_data().ok_or("Render produced no image")?;
+    std::fs::write(&args.output, png).map_err(|e| format!("Failed to write {}: {}", args.output, e))
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().is_none_or(|a| a == "--help" || a == "-h") {
+        eprintln!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    logging::set_log_level(if args.verbose {
+        LogLevel::Info
+    } else {
+        LogLevel::Warn
+    });
+    logging::set_log_sink(Some(Box::new(|level, message| {
+        eprintln!("[{}] {}", level.as_str(), message)
+    })));
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        let required = ["--input", "in.geojson", "--output", "out.png"];
+        parse_args(required.iter().chain(args).map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse(&["--size", "800x600"]).unwrap().size, (800, 600));
+        assert_eq!(parse(&[]).unwrap().size, (3508, 4961));
+        for size in ["-5x300", "300.7x400", "0x400", "400x1e12", "400", "x400"] {
+            assert!(parse(&["--size", size]).is_err(), "{}", size);
+        }
+    }
+}
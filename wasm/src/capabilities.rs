@@ -0,0 +1,87 @@
+//! 构建能力描述
+//!
+//! 列出当前 WASM 构建支持的输出格式、投影、画布上限、二进制格式版本与启用的 cargo feature，
+//! 前端据此适配不同版本的构建，而不必按版本号硬编码。
+
+use serde::Serialize;
+
+use crate::data_processor::{BIN_MAGIC, BIN_VERSION};
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct Capabilities {
+    /// 包版本
+    pub version: &'static str,
+    /// 输出图像格式
+    pub output_formats: Vec<&'static str>,
+    /// PNG 压缩级别（`png_compression`）
+    pub png_compression: Vec<&'static str>,
+    /// 输出投影类型（`projection.type`）
+    pub projections: Vec<&'static str>,
+    /// `projection.type = "epsg"` 支持的代码
+    pub epsg_codes: Vec<&'static str>,
+    /// 几何输入的坐标参考系（`crs`）
+    pub input_crs: Vec<&'static str>,
+    /// 最大逻辑像素数（宽 × 高）
+    pub max_canvas_pixels: u64,
+    /// 单边最大逻辑像素数
+    pub max_canvas_side: u32,
+    /// 二进制头部魔数
+    pub bin_magic: f64,
+    /// 可读取的二进制格式版本（无头部的旧数据始终可读）
+    pub bin_versions: Vec<u32>,
+    /// 构建时启用的 cargo feature
+    pub features: Vec<&'static str>,
+}
+
+pub fn capabilities() -> Capabilities {
+    let features = [
+        ("embedded-font", cfg!(feature = "embedded-font")),
+        ("threads", cfg!(feature = "threads")),
+        ("simd", cfg!(feature = "simd")),
+        ("cli", cfg!(feature = "cli")),
+        ("server", cfg!(feature = "server")),
+    ];
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        output_formats: vec!["png"],
+        png_compression: vec!["fast", "default", "best"],
+        projections: vec!["webmercator", "utm", "epsg"],
+        epsg_codes: crate::projection::SUPPORTED_EPSG.to_vec(),
+        input_crs: vec!["wgs84", "epsg3857"],
+        max_canvas_pixels: crate::validate::max_canvas_pixels(),
+        max_canvas_side: crate::validate::MAX_CANVAS_SIDE,
+        bin_magic: BIN_MAGIC,
+        bin_versions: vec![BIN_VERSION],
+        features: features
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Crs, MapProjection, PngCompression};
+
+    /// 列出的取值须能被配置反序列化接受
+    #[test]
+    fn test_listed_values_deserialize() {
+        let caps = capabilities();
+        for level in &caps.png_compression {
+            serde_json::from_value::<PngCompression>(serde_json::json!(level)).unwrap();
+        }
+        for crs in &caps.input_crs {
+            serde_json::from_value::<Crs>(serde_json::json!(crs)).unwrap();
+        }
+        for kind in &caps.projections {
+            let projection = serde_json::json!({ "type": kind, "zone": 31, "code": 3857 });
+            serde_json::from_value::<MapProjection>(projection).unwrap();
+        }
+        assert_eq!(
+            caps.features.contains(&"embedded-font"),
+            cfg!(feature = "embedded-font")
+        );
+    }
+}
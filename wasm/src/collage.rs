@@ -0,0 +1,185 @@
+//! 多城市拼贴的版面计算
+//!
+//! 画布扣除四周间距与标题区后，按排列方式均分给各分格；分格与标题区均以逻辑像素表示，
+//! 分格各自按普通海报渲染后再合成到拼贴画布上。
+
+use crate::types::{CollageConfig, CollageLayout, TextPosition};
+
+/// 分格数量范围
+pub const MIN_PANELS: usize = 2;
+pub const MAX_PANELS: usize = 4;
+
+/// 标题区占画布高度的上限（百分比）
+const MAX_TITLE_PCT: f32 = 50.0;
+
+/// 画布上的矩形区域（逻辑像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 拼贴版面：各分格与标题区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub panels: Vec<Rect>,
+    /// 标题区，`title_pct` 为 0 时为空
+    pub title: Option<Rect>,
+}
+
+/// 按排列方式计算 `n` 个分格的列数与行数
+fn grid(layout: CollageLayout, n: usize) -> (usize, usize) {
+    match layout {
+        CollageLayout::Row => (n, 1),
+        CollageLayout::Column => (1, n),
+        CollageLayout::Grid => {
+            let cols = (n as f64).sqrt().ceil() as usize;
+            (cols, n.div_ceil(cols))
+        }
+    }
+}
+
+/// 将 `[start, start + len)` 的浮点区间取整为像素区间
+fn span(start: f64, len: f64) -> (u32, u32) {
+    let a = start.round() as u32;
+    let b = (start + len).round() as u32;
+    (a, b.saturating_sub(a))
+}
+
+/// 计算拼贴版面
+pub fn layout(config: &CollageConfig) -> Result<Layout, String> {
+    let n = config.panels.len();
+    if !(MIN_PANELS..=MAX_PANELS).contains(&n) {
+        return Err(format!(
+            "Collage needs {}-{} panels, got {}",
+            MIN_PANELS, MAX_PANELS, n
+        ));
+    }
+    let (width, height) = (config.width as f64, config.height as f64);
+    let gap = if config.gap.is_finite() {
+        config.gap.max(0.0) as f64
+    } else {
+        0.0
+    };
+    let title_pct = if config.title_pct.is_finite() {
+        config.title_pct.clamp(0.0, MAX_TITLE_PCT) as f64
+    } else {
+        0.0
+    };
+    let title_height = height * title_pct / 100.0;
+
+    // 标题区紧贴分格，位于顶部或底部的间距以内
+    let title_on_top = matches!(config.text_position, Some(TextPosition::Top));
+    let (content_y, title_y) = if title_on_top {
+        (gap + title_height, gap)
+    } else {
+        (gap, height - gap - title_height)
+    };
+    let content_width = width - 2.0 * gap;
+    let content_height = height - 2.0 * gap - title_height;
+
+    let (cols, rows) = grid(config.layout, n);
+    let panel_width = (content_width - (cols - 1) as f64 * gap) / cols as f64;
+    let panel_height = (content_height - (rows - 1) as f64 * gap) / rows as f64;
+    if panel_width < 1.0 || panel_height < 1.0 {
+        return Err(format!(
+            "Collage gap {} and title leave no room for {} panels on a {}x{} canvas",
+            gap, n, config.width, config.height
+        ));
+    }
+
+    let panels = (0..n)
+        .map(|i| {
+            let (col, row) = ((i % cols) as f64, (i / cols) as f64);
+            let (x, width) = span(gap + col * (panel_width + gap), panel_width);
+            let (y, height) = span(content_y + row * (panel_height + gap), panel_height);
+            Rect {
+                x,
+                y,
+                width: width.max(1),
+                height: height.max(1),
+            }
+        })
+        .collect();
+    let title = (title_height >= 1.0).then(|| {
+        let (x, width) = span(gap, content_width);
+        let (y, height) = span(title_y, title_height);
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    });
+    Ok(Layout { panels, title })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(layout: CollageLayout, panels: usize) -> CollageConfig {
+        serde_json::from_value(serde_json::json!({
+            "panels": vec![serde_json::json!({}); panels],
+            "layout": layout,
+            "width": 1000,
+            "height": 1000,
+            "theme": crate::types::Theme::default(),
+            "gap": 20,
+            "title_pct": 20,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collage_layout() {
+        let row = layout(&config(CollageLayout::Row, 2)).unwrap();
+        // 内容区 960 × 760（扣除标题 200），两列间隔 20
+        assert_eq!(
+            row.panels,
+            [
+                Rect {
+                    x: 20,
+                    y: 20,
+                    width: 470,
+                    height: 760
+                },
+                Rect {
+                    x: 510,
+                    y: 20,
+                    width: 470,
+                    height: 760
+                },
+            ]
+        );
+        assert_eq!(
+            row.title,
+            Some(Rect {
+                x: 20,
+                y: 780,
+                width: 960,
+                height: 200
+            })
+        );
+
+        let column = layout(&config(CollageLayout::Column, 2)).unwrap();
+        assert_eq!(column.panels[1].y, 20 + 370 + 20);
+        assert_eq!(column.panels[1].width, 960);
+
+        let grid = layout(&config(CollageLayout::Grid, 4)).unwrap();
+        assert_eq!(
+            grid.panels[3],
+            Rect {
+                x: 510,
+                y: 410,
+                width: 470,
+                height: 370
+            }
+        );
+
+        assert!(layout(&config(CollageLayout::Row, 1)).is_err());
+        assert!(layout(&config(CollageLayout::Row, 5)).is_err());
+    }
+}
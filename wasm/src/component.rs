@@ -0,0 +1,396 @@
+//! WASI 组件导出（`wit/maptoposter.wit`）
+//!
+//! 不依赖 wit-bindgen，直接按 canonical ABI 导出核心模块函数：参数中的字符串与列表由宿主经
+//! `cabi_realloc` 写入本模块内存并转交所有权；返回值写入堆上的返回区，宿主读取后调用对应的
+//! `cabi_post_*` 释放。构建组件：
+//!
+//! ```text
+//! cargo build --release --target wasm32-wasip1 --no-default-features --features embedded-font,component
+//! wasm-tools component embed wit target/wasm32-wasip1/release/wasm.wasm -o embedded.wasm
+//! wasm-tools component new embedded.wasm --adapt wasi_snapshot_preview1.reactor.wasm -o maptoposter.wasm
+//! ```
+//!
+//! 指针与长度按 `usize` 存放，即 wasm32 上 canonical ABI 要求的 32 位。导出名（含 `:` `/` `#`）
+//! 只在 wasm32 上设置：原生链接器的符号版本脚本不接受这些字符，原生构建中各函数保持 Rust 符号，
+//! 由测试直接调用。
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use std::alloc::{self, Layout};
+use std::mem::ManuallyDrop;
+
+use crate::error::RenderError;
+use crate::types::{RenderConfig, RenderResult};
+
+/// `string` / `list<T>`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WitList {
+    ptr: *mut u8,
+    len: usize,
+}
+
+/// `render-error`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WitError {
+    code: WitList,
+    message: WitList,
+    has_field: u8,
+    field: WitList,
+}
+
+/// `result<list<u8>, render-error>`
+#[repr(C)]
+struct PngResult {
+    is_err: u8,
+    payload: PngPayload,
+}
+
+#[repr(C)]
+union PngPayload {
+    ok: WitList,
+    err: ManuallyDrop<WitError>,
+}
+
+/// `result<layers, string>`
+#[repr(C)]
+struct ParseResult {
+    is_err: u8,
+    payload: ParsePayload,
+}
+
+#[repr(C)]
+union ParsePayload {
+    ok: [WitList; 3],
+    err: WitList,
+}
+
+fn into_list<T>(items: Vec<T>) -> WitList {
+    let items = items.into_boxed_slice();
+    let len = items.len();
+    WitList {
+        ptr: Box::into_raw(items).cast(),
+        len,
+    }
+}
+
+fn into_error(error: &RenderError) -> WitError {
+    let field = error.field.clone().map(String::into_bytes);
+    WitError {
+        code: into_list(error.code.as_str().as_bytes().to_vec()),
+        message: into_list(error.message.clone().into_bytes()),
+        has_field: u8::from(field.is_some()),
+        field: into_list(field.unwrap_or_default()),
+    }
+}
+
+/// 释放 `into_list` 转出的列表
+unsafe fn free_list<T>(list: WitList) {
+    let items = std::ptr::slice_from_raw_parts_mut(list.ptr.cast::<T>(), list.len);
+    drop(unsafe { Box::from_raw(items) });
+}
+
+unsafe fn free_error(error: &WitError) {
+    unsafe {
+        free_list::<u8>(error.code);
+        free_list::<u8>(error.message);
+        free_list::<u8>(error.field);
+    }
+}
+
+/// 接管宿主经 `cabi_realloc` 写入的列表参数
+unsafe fn take_list<T>(ptr: *mut u8, len: usize) -> Vec<T> {
+    unsafe { Vec::from_raw_parts(ptr.cast(), len, len) }
+}
+
+unsafe fn take_string(ptr: *mut u8, len: usize) -> String {
+    let bytes = unsafe { take_list::<u8>(ptr, len) };
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+fn png_result(result: RenderResult) -> *mut PngResult {
+    let out = match result.error_info() {
+        Some(error) => PngResult {
+            is_err: 1,
+            payload: PngPayload {
+                err: ManuallyDrop::new(into_error(error)),
+            },
+        },
+        None => PngResult {
+            is_err: 0,
+            payload: PngPayload {
+                ok: into_list(result.into_data().unwrap_or_default()),
+            },
+        },
+    };
+    Box::into_raw(Box::new(out))
+}
+
+unsafe fn free_png_result(ret: *mut PngResult) {
+    let ret = unsafe { Box::from_raw(ret) };
+    unsafe {
+        if ret.is_err == 0 {
+            free_list::<u8>(ret.payload.ok);
+        } else {
+            free_error(&ret.payload.err);
+        }
+    }
+}
+
+/// 宿主为参数分配内存的入口（canonical ABI 约定的签名）
+#[cfg_attr(target_arch = "wasm32", unsafe(no_mangle))]
+unsafe extern "C" fn cabi_realloc(
+    old_ptr: *mut u8,
+    old_len: usize,
+    align: usize,
+    new_len: usize,
+) -> *mut u8 {
+    if new_len == 0 {
+        return align as *mut u8;
+    }
+    let ptr = unsafe {
+        if old_len == 0 {
+            alloc::alloc(Layout::from_size_align_unchecked(new_len, align))
+        } else {
+            let layout = Layout::from_size_align_unchecked(old_len, align);
+            alloc::realloc(old_ptr, layout, new_len)
+        }
+    };
+    if ptr.is_null() {
+        std::process::abort();
+    }
+    ptr
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "maptoposter:renderer/renderer@0.1.0#render")
+)]
+unsafe extern "C" fn render(ptr: *mut u8, len: usize) -> *mut PngResult {
+    let request = unsafe { take_string(ptr, len) };
+    png_result(crate::render_map(&request))
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "cabi_post_maptoposter:renderer/renderer@0.1.0#render")
+)]
+unsafe extern "C" fn render_post(ret: *mut PngResult) {
+    unsafe { free_png_result(ret) }
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "maptoposter:renderer/renderer@0.1.0#render-msgpack")
+)]
+unsafe extern "C" fn render_msgpack(ptr: *mut u8, len: usize) -> *mut PngResult {
+    let request = unsafe { take_list::<u8>(ptr, len) };
+    png_result(crate::render_map_msgpack(&request))
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "cabi_post_maptoposter:renderer/renderer@0.1.0#render-msgpack")
+)]
+unsafe extern "C" fn render_msgpack_post(ret: *mut PngResult) {
+    unsafe { free_png_result(ret) }
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "maptoposter:renderer/renderer@0.1.0#render-binary")
+)]
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn render_binary(
+    roads_ptr: *mut u8,
+    roads_len: usize,
+    water_ptr: *mut u8,
+    water_len: usize,
+    parks_ptr: *mut u8,
+    parks_len: usize,
+    config_ptr: *mut u8,
+    config_len: usize,
+) -> *mut PngResult {
+    let roads = unsafe { take_list::<f64>(roads_ptr, roads_len) };
+    let water = unsafe { take_list::<f64>(water_ptr, water_len) };
+    let parks = unsafe { take_list::<f64>(parks_ptr, parks_len) };
+    let config_json = unsafe { take_string(config_ptr, config_len) };
+    let result = match serde_json::from_str::<RenderConfig>(&config_json) {
+        Ok(config) => crate::render_binary(vec![roads], water, parks, config, None),
+        Err(e) => RenderError::config_parse("Config JSON parse failed", e).into(),
+    };
+    png_result(result)
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "cabi_post_maptoposter:renderer/renderer@0.1.0#render-binary")
+)]
+unsafe extern "C" fn render_binary_post(ret: *mut PngResult) {
+    unsafe { free_png_result(ret) }
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "maptoposter:renderer/renderer@0.1.0#parse")
+)]
+unsafe extern "C" fn parse(ptr: *mut u8, len: usize) -> *mut ParseResult {
+    use crate::data_processor::{parse_osm_auto, polygons_to_bin, roads_to_bin};
+
+    let input = unsafe { take_string(ptr, len) };
+    let out = match parse_osm_auto(&input) {
+        Ok(layers) => ParseResult {
+            is_err: 0,
+            payload: ParsePayload {
+                ok: [
+                    into_list(roads_to_bin(&layers.roads)),
+                    into_list(polygons_to_bin(&layers.water)),
+                    into_list(polygons_to_bin(&layers.parks)),
+                ],
+            },
+        },
+        Err(e) => ParseResult {
+            is_err: 1,
+            payload: ParsePayload {
+                err: into_list(e.into_bytes()),
+            },
+        },
+    };
+    Box::into_raw(Box::new(out))
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "cabi_post_maptoposter:renderer/renderer@0.1.0#parse")
+)]
+unsafe extern "C" fn parse_post(ret: *mut ParseResult) {
+    let ret = unsafe { Box::from_raw(ret) };
+    unsafe {
+        if ret.is_err == 0 {
+            ret.payload.ok.iter().for_each(|&l| free_list::<f64>(l));
+        } else {
+            free_list::<u8>(ret.payload.err);
+        }
+    }
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "maptoposter:renderer/renderer@0.1.0#validate")
+)]
+unsafe extern "C" fn validate(
+    config_ptr: *mut u8,
+    config_len: usize,
+    lengths_ptr: *mut u8,
+    lengths_len: usize,
+) -> *mut WitList {
+    let config_json = unsafe { take_string(config_ptr, config_len) };
+    let lengths = unsafe { take_list::<u32>(lengths_ptr, lengths_len) };
+    let errors: Vec<WitError> = crate::validate_request_inner(&config_json, &lengths)
+        .iter()
+        .map(into_error)
+        .collect();
+    Box::into_raw(Box::new(into_list(errors)))
+}
+
+#[cfg_attr(
+    target_arch = "wasm32",
+    unsafe(export_name = "cabi_post_maptoposter:renderer/renderer@0.1.0#validate")
+)]
+unsafe extern "C" fn validate_post(ret: *mut WitList) {
+    let list = unsafe { *Box::from_raw(ret) };
+    unsafe {
+        let errors = std::slice::from_raw_parts(list.ptr.cast::<WitError>(), list.len);
+        errors.iter().for_each(|e| free_error(e));
+        free_list::<WitError>(list);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟宿主：经 `cabi_realloc` 写入参数
+    fn lower<T: Copy>(items: &[T]) -> (*mut u8, usize) {
+        let size = std::mem::size_of_val(items);
+        let ptr = unsafe { cabi_realloc(std::ptr::null_mut(), 0, std::mem::align_of::<T>(), size) };
+        unsafe { std::ptr::copy_nonoverlapping(items.as_ptr().cast(), ptr, size) };
+        (ptr, items.len())
+    }
+
+    unsafe fn read_str(list: WitList) -> String {
+        let bytes = unsafe { std::slice::from_raw_parts(list.ptr, list.len) };
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_validate_and_parse_round_trip() {
+        let (config_ptr, config_len) = lower(b"{}");
+        let (lengths_ptr, lengths_len) = lower(&[7u32, 2]);
+        unsafe {
+            let ret = validate(config_ptr, config_len, lengths_ptr, lengths_len);
+            let list = *ret;
+            let errors = std::slice::from_raw_parts(list.ptr.cast::<WitError>(), list.len);
+            let codes: Vec<String> = errors.iter().map(|e| read_str(e.code)).collect();
+            assert_eq!(codes, ["invalid_config", "bad_geometry"]);
+            assert_eq!(errors[1].has_field, 1);
+            assert_eq!(read_str(errors[1].field), "roads[1]");
+            validate_post(ret);
+        }
+
+        let geojson = br#"{"type":"FeatureCollection","features":[{"type":"Feature",
+            "properties":{"highway":"primary"},
+            "geometry":{"type":"LineString","coordinates":[[2.3,48.8],[2.4,48.9]]}}]}"#;
+        let (ptr, len) = lower(geojson);
+        unsafe {
+            let ret = parse(ptr, len);
+            assert_eq!((*ret).is_err, 0);
+            let roads = (*ret).payload.ok[0];
+            let roads = std::slice::from_raw_parts(roads.ptr.cast::<f64>(), roads.len);
+            assert_eq!(roads[..3], [1.0, 1.0, 2.0]);
+            parse_post(ret);
+
+            let (ptr, len) = lower(b"not json");
+            let ret = parse(ptr, len);
+            assert_eq!((*ret).is_err, 1);
+            assert!(read_str((*ret).payload.err).starts_with("Unrecognized input"));
+            parse_post(ret);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_render_binary_canonical_abi() {
+        let mut config = crate::RenderRequestBuilder::new(0.0, 0.0)
+            .radius(1000.0)
+            .size(40, 40)
+            .theme(crate::types::Theme::default())
+            .build()
+            .unwrap();
+        config.crs = Some(crate::types::Crs::Epsg3857);
+        let config_json = serde_json::to_string(&config).unwrap();
+
+        let (roads_ptr, roads_len) = lower(&[1.0f64, 1.0, 2.0, -500.0, 0.0, 500.0, 0.0]);
+        let (water_ptr, water_len) = lower::<f64>(&[]);
+        let (parks_ptr, parks_len) = lower::<f64>(&[]);
+        let (config_ptr, config_len) = lower(config_json.as_bytes());
+        unsafe {
+            let ret = render_binary(
+                roads_ptr, roads_len, water_ptr, water_len, parks_ptr, parks_len, config_ptr,
+                config_len,
+            );
+            assert_eq!((*ret).is_err, 0);
+            let png = (*ret).payload.ok;
+            let png = std::slice::from_raw_parts(png.ptr, png.len);
+            assert_eq!(png[..4], *b"\x89PNG");
+            render_binary_post(ret);
+
+            let (ptr, len) = lower(b"{}");
+            let ret = render(ptr, len);
+            assert_eq!((*ret).is_err, 1);
+            let error = &(*ret).payload.err;
+            assert_eq!(read_str(error.code), "invalid_config");
+            render_post(ret);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! 压缩载荷检测与解压
+//!
+//! 通过魔数识别 gzip（1F 8B）与 zlib（RFC 1950）封装的 DEFLATE 数据，
+//! 未压缩的数据原样返回。
+
+use std::borrow::Cow;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// gzip 头部 FLG 位
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+fn is_zlib(data: &[u8]) -> bool {
+    data.len() >= 2
+        && data[0] & 0x0f == 8
+        && (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 == 0
+}
+
+/// 跳过 gzip 头部，返回 DEFLATE 数据起始位置
+fn gzip_body_start(data: &[u8]) -> Result<usize, String> {
+    let truncated = || "gzip: truncated header".to_string();
+    if data.len() < 10 || data[2] != 8 {
+        return Err("gzip: unsupported compression method".to_string());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let xlen = u16::from_le_bytes([
+            *data.get(pos).ok_or_else(truncated)?,
+            *data.get(pos + 1).ok_or_else(truncated)?,
+        ]) as usize;
+        pos += 2 + xlen;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(truncated)?;
+            pos += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    if pos > data.len() {
+        return Err(truncated());
+    }
+    Ok(pos)
+}
+
+/// 若数据为 gzip / zlib 压缩则解压，否则原样借用
+pub fn maybe_decompress(data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    if data.starts_with(&GZIP_MAGIC) {
+        let start = gzip_body_start(data)?;
+        miniz_oxide::inflate::decompress_to_vec(&data[start..])
+            .map(Cow::Owned)
+            .map_err(|e| format!("gzip: failed to inflate payload: {:?}", e))
+    } else if is_zlib(data) {
+        miniz_oxide::inflate::decompress_to_vec_zlib(data)
+            .map(Cow::Owned)
+            .map_err(|e| format!("zlib: failed to inflate payload: {:?}", e))
+    } else {
+        Ok(Cow::Borrowed(data))
+    }
+}
+
+/// 将（可能压缩的）小端字节解码为 f64 数组，用于二进制分片
+pub fn decode_f64_le(data: &[u8]) -> Result<Vec<f64>, String> {
+    let bytes = maybe_decompress(data)?;
+    if bytes.len() % 8 != 0 {
+        return Err(format!(
+            "binary shard length {} is not a multiple of 8 bytes",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1f, 0x8b, 8, FNAME, 0, 0, 0, 0, 0, 3];
+        out.extend_from_slice(b"roads.json\0");
+        out.extend(miniz_oxide::deflate::compress_to_vec(payload, 6));
+        out.extend_from_slice(&[0; 8]); // CRC32 + ISIZE（解压时不校验）
+        out
+    }
+
+    #[test]
+    fn test_maybe_decompress_formats() {
+        let payload = br#"{"type":"FeatureCollection","features":[]}"#;
+        assert_eq!(&*maybe_decompress(&gzip(payload)).unwrap(), payload);
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(payload, 6);
+        assert_eq!(&*maybe_decompress(&zlib).unwrap(), payload);
+        assert!(matches!(
+            maybe_decompress(payload).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_decode_f64_le_shard() {
+        let values = [2.0f64, 1.5, -3.25];
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(decode_f64_le(&gzip(&raw)).unwrap(), values);
+        assert!(decode_f64_le(&raw[..7]).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! 多图层二进制容器
+//!
+//! 用单个 Float64Array 携带全部图层，新增图层无需修改入口函数签名：
+//!
+//! `[BIN_MAGIC, version, 4, section_count, (tag, len, data[len])...]`
+//!
+//! 每个分段的数据就是对应图层原有的扁平格式（也可以带自己的头部）。
+//! 道路分段可以出现多次（即多个分片）；读取时跳过未知标签，便于向前兼容。
+
+use crate::data_processor::{BIN_MAGIC, BinLayer, strip_bin_header, with_bin_header};
+
+/// 容器分段标签
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionTag {
+    Roads = 0,
+    Water = 1,
+    Parks = 2,
+    Pois = 3,
+    Routes = 4,
+    Buildings = 5,
+}
+
+impl SectionTag {
+    fn from_id(id: f64) -> Option<Self> {
+        Some(match id as i64 {
+            0 => SectionTag::Roads,
+            1 => SectionTag::Water,
+            2 => SectionTag::Parks,
+            3 => SectionTag::Pois,
+            4 => SectionTag::Routes,
+            5 => SectionTag::Buildings,
+            _ => return None,
+        })
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "roads" => Some(SectionTag::Roads),
+            "water" => Some(SectionTag::Water),
+            "parks" => Some(SectionTag::Parks),
+            "pois" => Some(SectionTag::Pois),
+            "routes" | "route" => Some(SectionTag::Routes),
+            "buildings" => Some(SectionTag::Buildings),
+            _ => None,
+        }
+    }
+}
+
+/// 从容器中取出的各图层数据（仍为扁平格式）
+#[derive(Debug, Default)]
+pub struct ContainerLayers {
+    pub roads: Vec<Vec<f64>>,
+    pub water: Vec<f64>,
+    pub parks: Vec<f64>,
+    pub buildings: Vec<f64>,
+    pub pois: Option<Vec<f64>>,
+    pub routes: Option<Vec<f64>>,
+}
+
+/// 解析容器
+pub fn parse_container<T: Copy + Into<f64>>(data: &[T]) -> Result<ContainerLayers, String> {
+    if data.first().map(|&v| v.into()) != Some(BIN_MAGIC) {
+        return Err("Invalid container: missing binary header".to_string());
+    }
+    let body = strip_bin_header(data, BinLayer::Container)
+        .map_err(|e| format!("Invalid container: {}", e))?;
+    let Some((&count, mut rest)) = body.split_first() else {
+        return Err("Invalid container: missing section count".to_string());
+    };
+
+    let mut layers = ContainerLayers::default();
+    for i in 0..count.into() as usize {
+        let [tag, len, tail @ ..] = rest else {
+            return Err(format!("Invalid container: section {} header truncated", i));
+        };
+        let len = (*len).into() as usize;
+        if tail.len() < len {
+            return Err(format!("Invalid container: section {} data truncated", i));
+        }
+        let (section, next) = tail.split_at(len);
+        rest = next;
+
+        let values: Vec<f64> = section.iter().map(|&v| v.into()).collect();
+        // 未知图层，留给更新的版本
+        let Some(tag) = SectionTag::from_id((*tag).into()) else {
+            continue;
+        };
+        let slot = match tag {
+            SectionTag::Roads => {
+                layers.roads.push(values);
+                continue;
+            }
+            SectionTag::Pois => {
+                layers.pois = Some(values);
+                continue;
+            }
+            SectionTag::Routes => {
+                layers.routes = Some(values);
+                continue;
+            }
+            SectionTag::Water => &mut layers.water,
+            SectionTag::Parks => &mut layers.parks,
+            SectionTag::Buildings => &mut layers.buildings,
+        };
+        if !slot.is_empty() {
+            return Err(format!("Invalid container: duplicate section {:?}", tag));
+        }
+        *slot = values;
+    }
+    Ok(layers)
+}
+
+/// 将若干分段打包为容器
+pub fn build_container(sections: &[(SectionTag, &[f64])]) -> Vec<f64> {
+    let mut body = vec![sections.len() as f64];
+    for (tag, data) in sections {
+        body.push(*tag as u32 as f64);
+        body.push(data.len() as f64);
+        body.extend_from_slice(data);
+    }
+    with_bin_header(BinLayer::Container, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_round_trip() {
+        let roads_a = [1.0, 0.0, 2.0, 0.0, 0.0, 1.0, 1.0];
+        let roads_b = [0.0];
+        let water = [1.0, 3.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0];
+        let pois = [1.0, 5.0, 6.0];
+        let mut packed = build_container(&[
+            (SectionTag::Roads, &roads_a),
+            (SectionTag::Water, &water),
+            (SectionTag::Roads, &roads_b),
+            (SectionTag::Pois, &pois),
+        ]);
+        // 追加一个未知标签的分段
+        packed[3] += 1.0;
+        packed.extend([99.0, 2.0, 7.0, 8.0]);
+
+        let layers = parse_container(&packed).unwrap();
+        assert_eq!(layers.roads, vec![roads_a.to_vec(), roads_b.to_vec()]);
+        assert_eq!(layers.water, water.to_vec());
+        assert!(layers.parks.is_empty() && layers.routes.is_none());
+        assert_eq!(layers.pois.as_deref(), Some(&pois[..]));
+    }
+
+    #[test]
+    fn test_container_errors() {
+        assert!(parse_container(&[1.0, 0.0]).is_err());
+        let mut packed = build_container(&[(SectionTag::Parks, &[0.0, 1.0])]);
+        packed.pop();
+        assert!(parse_container(&packed).is_err());
+        let dup = build_container(&[(SectionTag::Parks, &[0.0]), (SectionTag::Parks, &[0.0])]);
+        assert!(parse_container(&dup).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! 视口剔除与裁剪
+//!
+//! 解析阶段在投影之前按（扩展后的）视口矩形剔除完全不可见的要素，
+//! 并裁剪跨越视口边界的要素，避免超大 Overpass 查询在不可见几何上浪费投影和建路径的时间。
+//! 渲染器在建路径前也以扩展后的画布矩形（屏幕坐标）裁剪，见 `MapRenderer::ring_to_screen`。
+//! 视口向外扩展一定边距，裁剪产生的边界不会出现在画面中。
+
+use crate::projection::normalize_lon;
+use crate::types::GeoBounds;
+
+/// 默认边距：视口宽高的 10%
+pub const DEFAULT_CULL_MARGIN: f64 = 0.1;
+
+/// 剔除用的矩形（与输入数据同一坐标系）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+type Point = (f64, f64);
+
+fn bbox(points: &[Point]) -> Option<Viewport> {
+    let (&(x, y), rest) = points.split_first()?;
+    Some(rest.iter().fold(
+        Viewport {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        },
+        |b, &(x, y)| Viewport {
+            min_x: b.min_x.min(x),
+            min_y: b.min_y.min(y),
+            max_x: b.max_x.max(x),
+            max_y: b.max_y.max(y),
+        },
+    ))
+}
+
+impl Viewport {
+    /// 由经纬度范围构造，四边各向外扩展宽高的 `margin` 倍
+    ///
+    /// `min_lon > max_lon`（跨越 ±180° 经线）时 `max_x` 展开到 `[min_lon, min_lon + 360)`，
+    /// 与渲染器 `wrap_x` 的做法一致；要素坐标需先经 `wrap_lons` 展开到同一侧
+    pub fn from_bounds(bounds: &GeoBounds, margin: f64) -> Self {
+        let max_lon = if bounds.min_lon > bounds.max_lon {
+            bounds.max_lon + 360.0
+        } else {
+            bounds.max_lon
+        };
+        let dx = (max_lon - bounds.min_lon) * margin;
+        let dy = (bounds.max_lat - bounds.min_lat) * margin;
+        Self {
+            min_x: bounds.min_lon - dx,
+            min_y: bounds.min_lat - dy,
+            max_x: max_lon + dx,
+            max_y: bounds.max_lat + dy,
+        }
+    }
+
+    /// 经纬度视口超出 ±180° 时，将要素的经度展开到视口所在的一侧
+    ///
+    /// 首点取距视口中心最近的副本，其后各点取距前一点最近的副本，要素保持连续，
+    /// 跨越 ±180° 的线段不会被拉成横贯全球的长线；视口在 ±180° 以内时不做改动
+    pub fn wrap_lons(&self, coords: &mut [Point]) {
+        if self.min_x >= -180.0 && self.max_x <= 180.0 {
+            return;
+        }
+        let mut reference = (self.min_x + self.max_x) / 2.0;
+        for p in coords {
+            p.0 = reference + normalize_lon(p.0 - reference);
+            reference = p.0;
+        }
+    }
+
+    fn intersects(&self, other: &Viewport) -> bool {
+        other.min_x <= self.max_x
+            && other.max_x >= self.min_x
+            && other.min_y <= self.max_y
+            && other.max_y >= self.min_y
+    }
+
+    fn contains(&self, other: &Viewport) -> bool {
+        other.min_x >= self.min_x
+            && other.max_x <= self.max_x
+            && other.min_y >= self.min_y
+            && other.max_y <= self.max_y
+    }
+
+    /// 裁剪折线：保留与视口相交的连续线段，视口外的部分将折线断开
+    pub fn clip_polyline(&self, coords: &[Point]) -> Vec<Vec<Point>> {
+        let Some(b) = bbox(coords) else {
+            return vec![];
+        };
+        if self.contains(&b) {
+            return vec![coords.to_vec()];
+        }
+        if !self.intersects(&b) {
+            return vec![];
+        }
+
+        let mut parts = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        for pair in coords.windows(2) {
+            if bbox(pair).is_some_and(|seg| self.intersects(&seg)) {
+                if current.is_empty() {
+                    current.push(pair[0]);
+                }
+                current.push(pair[1]);
+            } else if current.len() >= 2 {
+                parts.push(std::mem::take(&mut current));
+            } else {
+                current.clear();
+            }
+        }
+        if current.len() >= 2 {
+            parts.push(current);
+        }
+        parts
+    }
+
+    /// 裁剪折线并在视口边界处截断线段（Liang–Barsky），视口外的部分将折线断开
+    ///
+    /// 与 `clip_polyline` 不同，保留的线段端点都在视口内，适合坐标量级需要受限的场合
+    pub fn cut_polyline(&self, coords: &[Point]) -> Vec<Vec<Point>> {
+        let mut parts = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        let mut flush = |current: &mut Vec<Point>| {
+            if current.len() >= 2 {
+                parts.push(std::mem::take(current));
+            } else {
+                current.clear();
+            }
+        };
+        for pair in coords.windows(2) {
+            match self.clip_segment(pair[0], pair[1]) {
+                Some((a, b)) => {
+                    if current.last() != Some(&a) {
+                        flush(&mut current);
+                        current.push(a);
+                    }
+                    current.push(b);
+                }
+                None => flush(&mut current),
+            }
+        }
+        flush(&mut current);
+        parts
+    }
+
+    /// 线段 a-b 在视口内的部分，不相交时返回 `None`
+    fn clip_segment(&self, a: Point, b: Point) -> Option<(Point, Point)> {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        let edges = [
+            (-dx, a.0 - self.min_x),
+            (dx, self.max_x - a.0),
+            (-dy, a.1 - self.min_y),
+            (dy, self.max_y - a.1),
+        ];
+        for (p, q) in edges {
+            if p == 0.0 {
+                // 与该边平行：整段在外侧则不可见
+                if q < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        let at = |t: f64| (a.0 + dx * t, a.1 + dy * t);
+        Some((
+            if t0 > 0.0 { at(t0) } else { a },
+            if t1 < 1.0 { at(t1) } else { b },
+        ))
+    }
+
+    /// 裁剪多边形环（Sutherland–Hodgman），完全在视口外时返回空
+    pub fn clip_ring(&self, ring: &[Point]) -> Vec<Point> {
+        let Some(b) = bbox(ring) else {
+            return vec![];
+        };
+        if self.contains(&b) {
+            return ring.to_vec();
+        }
+        if !self.intersects(&b) {
+            return vec![];
+        }
+
+        let closed = ring.len() > 1 && ring.first() == ring.last();
+        let mut points = ring[..ring.len() - usize::from(closed)].to_vec();
+        // 依次用四条边裁剪：(坐标轴, 边界值, 内侧是否为较大一侧)
+        let edges = [
+            (Axis::X, self.min_x, true),
+            (Axis::X, self.max_x, false),
+            (Axis::Y, self.min_y, true),
+            (Axis::Y, self.max_y, false),
+        ];
+        for (axis, bound, keep_greater) in edges {
+            let inside = |p: Point| {
+                let v = axis.of(p);
+                if keep_greater { v >= bound } else { v <= bound }
+            };
+            let Some(&last) = points.last() else {
+                break;
+            };
+            let mut out = Vec::with_capacity(points.len() + 4);
+            let mut prev = last;
+            for &p in &points {
+                match (inside(prev), inside(p)) {
+                    (true, true) => out.push(p),
+                    (true, false) => out.push(axis.cross(prev, p, bound)),
+                    (false, true) => {
+                        out.push(axis.cross(prev, p, bound));
+                        out.push(p);
+                    }
+                    (false, false) => {}
+                }
+                prev = p;
+            }
+            points = out;
+        }
+        if points.len() < 3 {
+            return vec![];
+        }
+        if closed {
+            points.push(points[0]);
+        }
+        points
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    fn of(self, p: Point) -> f64 {
+        match self {
+            Axis::X => p.0,
+            Axis::Y => p.1,
+        }
+    }
+
+    /// 线段 a-b 与该轴上 `value` 处边界的交点
+    fn cross(self, a: Point, b: Point, value: f64) -> Point {
+        let t = (value - self.of(a)) / (self.of(b) - self.of(a));
+        match self {
+            Axis::X => (value, a.1 + (b.1 - a.1) * t),
+            Axis::Y => (a.0 + (b.0 - a.0) * t, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit() -> Viewport {
+        Viewport {
+            min_x: 0.0,
+            min_y: 0.0,
+            max_x: 1.0,
+            max_y: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_clip_polyline_splits_at_viewport() {
+        let view = unit();
+        // 进入、离开、再进入视口
+        let line = [
+            (0.5, 0.5),
+            (0.8, 0.5),
+            (5.0, 5.0),
+            (6.0, 5.0),
+            (0.9, 0.9),
+            (0.2, 0.2),
+        ];
+        let parts = view.clip_polyline(&line);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], vec![(0.5, 0.5), (0.8, 0.5), (5.0, 5.0)]);
+        assert_eq!(parts[1], vec![(6.0, 5.0), (0.9, 0.9), (0.2, 0.2)]);
+        assert!(view.clip_polyline(&[(2.0, 2.0), (3.0, 3.0)]).is_empty());
+    }
+
+    #[test]
+    fn test_cut_polyline_clamps_to_viewport() {
+        let view = unit();
+        // 两端远在视口外的长线段被截断到边界；离开后再进入则断开
+        let line = [
+            (-1024.0, 0.5),
+            (1024.0, 0.5),
+            (1024.0, 5.0),
+            (0.5, 5.0),
+            (0.5, 0.2),
+        ];
+        let parts = view.cut_polyline(&line);
+        assert_eq!(
+            parts,
+            vec![vec![(0.0, 0.5), (1.0, 0.5)], vec![(0.5, 1.0), (0.5, 0.2)]]
+        );
+        assert!(view.cut_polyline(&[(2.0, 2.0), (3.0, 3.0)]).is_empty());
+    }
+
+    #[test]
+    fn test_clip_ring() {
+        let view = unit();
+        let square = [(0.5, 0.5), (2.0, 0.5), (2.0, 2.0), (0.5, 2.0), (0.5, 0.5)];
+        let clipped = view.clip_ring(&square);
+        assert_eq!(clipped.first(), clipped.last());
+        assert_eq!(clipped.len(), 5);
+        assert!(clipped.iter().all(|&(x, y)| x <= 1.0 && y <= 1.0));
+        assert!(
+            view.clip_ring(&[(2.0, 2.0), (3.0, 2.0), (3.0, 3.0)])
+                .is_empty()
+        );
+
+        let bounds = GeoBounds {
+            min_lon: 0.0,
+            min_lat: 0.0,
+            max_lon: 10.0,
+            max_lat: 10.0,
+        };
+        assert_eq!(Viewport::from_bounds(&bounds, 0.1).min_x, -1.0);
+    }
+
+    #[test]
+    fn test_viewport_across_antimeridian() {
+        // 170°E – 170°W
+        let bounds = GeoBounds {
+            min_lon: 170.0,
+            min_lat: -10.0,
+            max_lon: -170.0,
+            max_lat: 10.0,
+        };
+        let view = Viewport::from_bounds(&bounds, 0.1);
+        assert_eq!((view.min_x, view.max_x), (168.0, 192.0));
+
+        // 道路两侧分别在 ±180° 两边，跨越经线的线段保持连续
+        let mut road = vec![(175.0, 0.0), (179.0, 0.0), (-179.0, 0.0), (-175.0, 0.0)];
+        view.wrap_lons(&mut road);
+        assert_eq!(
+            road,
+            [(175.0, 0.0), (179.0, 0.0), (181.0, 0.0), (185.0, 0.0)]
+        );
+        assert_eq!(view.clip_polyline(&road), vec![road.clone()]);
+
+        // 完全位于西半球一侧的要素同样保留
+        let mut west = vec![
+            (-178.0, -1.0),
+            (-176.0, -1.0),
+            (-176.0, 1.0),
+            (-178.0, -1.0),
+        ];
+        view.wrap_lons(&mut west);
+        assert_eq!(view.clip_ring(&west).len(), 4);
+
+        // 对跖经线附近的线段不会横穿视口
+        let mut far = vec![(-1.0, 0.0), (1.0, 0.0)];
+        view.wrap_lons(&mut far);
+        assert!(view.clip_polyline(&far).is_empty());
+        assert!(view.cut_polyline(&far).is_empty());
+
+        // 不跨经线的视口不改动坐标
+        let mut plain = vec![(-179.0, 0.0)];
+        unit().wrap_lons(&mut plain);
+        assert_eq!(plain, [(-179.0, 0.0)]);
+    }
+}
@@ -1,7 +1,18 @@
-use crate::projection::project_points;
-use crate::types::{PolyFeature, Road, RoadType};
-use crate::utils::{time, time_end};
+use crate::cull::Viewport;
+use crate::merge;
+use crate::par;
+#[cfg(js_host)]
+use crate::profile::{time, time_end};
+use crate::projection::{coord_in_range, project_point, project_points, validate_crs};
+use crate::style::{self, StyleLayer, StyleRule};
+use crate::types::{
+    Crs, POI, PoiFeatureCollection, PoiGeometry, PolyFeature, Road, RoadFilter, RoadType,
+};
+use crate::warnings;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Range;
+#[cfg(js_host)]
 use wasm_bindgen::prelude::*;
 
 // --- 极简解析结构 ---
@@ -13,71 +24,232 @@ struct SimpleFC {
 
 #[derive(Deserialize)]
 struct SimpleFeature {
-    geometry: SimpleGeometry,
-    properties: SimpleProps,
+    // GeoJSON 允许 geometry / properties 为 null
+    geometry: Option<SimpleGeometry>,
+    #[serde(default)]
+    properties: Option<SimpleProps>,
 }
 
 #[derive(Deserialize)]
 struct SimpleGeometry {
     #[serde(rename = "type")]
     geom_type: String,
+    #[serde(default)]
     coordinates: serde_json::Value,
+    // GeometryCollection 的成员几何
+    #[serde(default)]
+    geometries: Vec<SimpleGeometry>,
 }
 
 #[derive(Deserialize)]
 struct SimpleProps {
     #[serde(default)]
     highway: serde_json::Value,
+    // 图层分类使用的 OSM 标签
+    #[serde(default)]
+    leisure: serde_json::Value,
+    #[serde(default)]
+    landuse: serde_json::Value,
+    #[serde(default)]
+    natural: serde_json::Value,
+    #[serde(default)]
+    waterway: serde_json::Value,
+}
+
+impl SimpleProps {
+    /// 读取保留的标签值（数组取第一个元素）
+    fn tag(&self, key: &str) -> Option<&str> {
+        let value = match key {
+            "highway" => &self.highway,
+            "leisure" => &self.leisure,
+            "landuse" => &self.landuse,
+            "natural" => &self.natural,
+            "waterway" => &self.waterway,
+            _ => return None,
+        };
+        match value {
+            serde_json::Value::String(s) => Some(s),
+            serde_json::Value::Array(a) => a.first().and_then(|v| v.as_str()),
+            _ => None,
+        }
+    }
+
+    fn road_type(&self) -> RoadType {
+        RoadType::from_highway(self.tag("highway").unwrap_or("unclassified"))
+    }
 }
 
 /// 解析道路 (从 JS 对象)
-pub fn parse_roads_js(js_val: JsValue) -> Result<Vec<Road>, String> {
+#[cfg(js_host)]
+pub fn parse_roads_js(js_val: JsValue, filter: &RoadFilter) -> Result<Vec<Road>, String> {
     time("parse_roads_obj: Total");
     let collection: SimpleFC = serde_wasm_bindgen::from_value(js_val)
         .map_err(|e| format!("Fast-path deserialization failed: {}", e))?;
+    let roads = roads_from_collection(collection, filter);
+    time_end("parse_roads_obj: Total");
+    Ok(roads)
+}
 
+/// 解析道路（从 GeoJSON 字节，例如解压后的响应体）
+pub fn parse_roads_slice(json: &[u8], filter: &RoadFilter) -> Result<Vec<Road>, String> {
+    let collection: SimpleFC =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON: {}", e))?;
+    Ok(roads_from_collection(collection, filter))
+}
+
+fn roads_from_collection(collection: SimpleFC, filter: &RoadFilter) -> Vec<Road> {
     let mut roads = Vec::with_capacity(collection.features.len());
     for f in collection.features {
-        let highway = match f.properties.highway {
-            serde_json::Value::String(s) => s,
-            serde_json::Value::Array(a) => a
-                .get(0)
-                .and_then(|v| v.as_str())
-                .unwrap_or("unclassified")
-                .to_string(),
-            _ => "unclassified".to_string(),
-        };
-        if f.geometry.geom_type == "LineString" {
-            if let Some(coords) = parse_coords_val(&f.geometry.coordinates) {
-                roads.push(Road {
-                    coords: project_points(&coords),
-                    road_type: RoadType::from_highway(&highway),
-                });
-            }
-        } else if f.geometry.geom_type == "MultiLineString" {
-            if let Some(lines) = f.geometry.coordinates.as_array() {
-                for line in lines {
-                    if let Some(coords) = parse_coords_val(line) {
-                        roads.push(Road {
-                            coords: project_points(&coords),
-                            road_type: RoadType::from_highway(&highway),
-                        });
-                    }
-                }
-            }
+        push_feature_roads(f, filter, &mut roads);
+    }
+    roads
+}
+
+fn push_feature_roads(f: SimpleFeature, filter: &RoadFilter, roads: &mut Vec<Road>) {
+    let Some(geometry) = &f.geometry else {
+        return;
+    };
+    let road_type = f.properties.as_ref().map_or(
+        RoadType::from_highway("unclassified"),
+        SimpleProps::road_type,
+    );
+    if !filter.accepts(road_type) {
+        return;
+    }
+    for coords in road_lines(geometry) {
+        roads.push(Road {
+            coords: project_points(&coords),
+            road_type,
+        });
+    }
+}
+
+/// 解析单个 GeoJSON Feature 的字节并追加道路（流式解析使用）
+pub fn parse_road_feature(
+    json: &[u8],
+    filter: &RoadFilter,
+    roads: &mut Vec<Road>,
+) -> Result<(), String> {
+    let feature: SimpleFeature =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON feature: {}", e))?;
+    push_feature_roads(feature, filter, roads);
+    Ok(())
+}
+
+// --- 二进制格式头部（可选） ---
+//
+// 带头部的数据以 [BIN_MAGIC, version, layer, ...] 开头；魔数为负数，
+// 不可能与旧格式首位的要素数量混淆，因此无头部的旧数据仍按原样解析
+
+/// 二进制头部魔数（"MP" = 0x4D50 取负）
+pub const BIN_MAGIC: f64 = -19_792.0;
+/// 当前二进制格式版本
+pub const BIN_VERSION: u32 = 1;
+const BIN_HEADER_LEN: usize = 3;
+
+/// 二进制数据的图层类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinLayer {
+    Roads = 0,
+    Polygons = 1,
+    Pois = 2,
+    Routes = 3,
+    Container = 4,
+}
+
+impl BinLayer {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "roads" => Some(BinLayer::Roads),
+            "polygons" | "water" | "parks" => Some(BinLayer::Polygons),
+            "pois" => Some(BinLayer::Pois),
+            "routes" => Some(BinLayer::Routes),
+            "container" => Some(BinLayer::Container),
+            _ => None,
         }
     }
-    time_end("parse_roads_obj: Total");
-    Ok(roads)
 }
 
-/// 解析道路 (从二进制 TypedArray)
-pub fn parse_roads_bin(data: &[f64]) -> Result<Vec<Road>, String> {
+/// 二进制头部校验错误
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BinHeaderError {
+    #[error("binary header has invalid magic {0} (expected {BIN_MAGIC})")]
+    BadMagic(f64),
+    #[error("binary header is truncated")]
+    Truncated,
+    #[error("unsupported binary format version {0} (this build reads version {BIN_VERSION})")]
+    UnsupportedVersion(f64),
+    #[error("binary layer mismatch: expected {expected:?}, found layer id {found}")]
+    LayerMismatch { expected: BinLayer, found: f64 },
+}
+
+/// 校验并去掉可选的二进制头部，返回数据主体
+pub fn strip_bin_header<T: Copy + Into<f64>>(
+    data: &[T],
+    expected: BinLayer,
+) -> Result<&[T], BinHeaderError> {
+    let Some(&first) = data.first() else {
+        return Ok(data);
+    };
+    let first: f64 = first.into();
+    if first >= 0.0 {
+        return Ok(data); // 无头部的旧格式
+    }
+    if first != BIN_MAGIC {
+        return Err(BinHeaderError::BadMagic(first));
+    }
+    if data.len() < BIN_HEADER_LEN {
+        return Err(BinHeaderError::Truncated);
+    }
+    let version: f64 = data[1].into();
+    if version != f64::from(BIN_VERSION) {
+        return Err(BinHeaderError::UnsupportedVersion(version));
+    }
+    let layer: f64 = data[2].into();
+    if layer != expected as u32 as f64 {
+        return Err(BinHeaderError::LayerMismatch {
+            expected,
+            found: layer,
+        });
+    }
+    Ok(&data[BIN_HEADER_LEN..])
+}
+
+/// 就地去掉 Vec 中的可选二进制头部，错误信息带上图层名
+pub fn strip_bin_header_vec(
+    data: &mut Vec<f64>,
+    expected: BinLayer,
+    layer: &str,
+) -> Result<(), String> {
+    let body_len = strip_bin_header(data, expected)
+        .map_err(|e| format!("Invalid {} binary: {}", layer, e))?
+        .len();
+    data.drain(..data.len() - body_len);
+    Ok(())
+}
+
+/// 为数据主体加上二进制头部
+pub fn with_bin_header(layer: BinLayer, body: &[f64]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(body.len() + BIN_HEADER_LEN);
+    out.extend([BIN_MAGIC, f64::from(BIN_VERSION), layer as u32 as f64]);
+    out.extend_from_slice(body);
+    out
+}
+
+/// 解析道路 (从二进制 TypedArray，支持 Float64Array 与 Float32Array 布局)
+///
+/// 提供 `viewport` 时剔除视口外的道路，并在视口边界处断开跨越的道路
+pub fn parse_roads_bin<T: Copy + Into<f64>>(
+    data: &[T],
+    filter: &RoadFilter,
+    viewport: Option<&Viewport>,
+) -> Result<Vec<Road>, String> {
+    let data = strip_bin_header(data, BinLayer::Roads).map_err(|e| e.to_string())?;
     if data.is_empty() {
         return Ok(vec![]);
     }
 
-    let road_count = data[0] as usize;
+    let road_count = data[0].into() as usize;
     let mut roads = Vec::with_capacity(road_count);
     let mut offset = 1;
 
@@ -85,35 +257,59 @@ pub fn parse_roads_bin(data: &[f64]) -> Result<Vec<Road>, String> {
         if offset + 2 > data.len() {
             break;
         }
-        let type_val = data[offset] as u32;
-        let point_count = data[offset + 1] as usize;
+        let type_val = data[offset].into() as u32;
+        let point_count = data[offset + 1].into() as usize;
         offset += 2;
 
         if offset + point_count * 2 > data.len() {
             break;
         }
+        // 被过滤的道路直接跳过坐标，不做投影
+        let road_type = RoadType::from_u32(type_val);
+        if !filter.accepts(road_type) {
+            offset += point_count * 2;
+            continue;
+        }
         let mut coords = Vec::with_capacity(point_count);
         for _ in 0..point_count {
-            coords.push((data[offset], data[offset + 1]));
+            coords.push((data[offset].into(), data[offset + 1].into()));
             offset += 2;
         }
+        drop_invalid_lonlat(&mut coords);
+        if coords.len() < 2 {
+            continue;
+        }
 
-        let road_type = RoadType::from_u32(type_val);
-        roads.push(Road {
-            coords: project_points(&coords),
-            road_type,
-        });
+        match viewport {
+            Some(view) => {
+                view.wrap_lons(&mut coords);
+                roads.extend(view.clip_polyline(&coords).iter().map(|part| Road {
+                    coords: project_points(part),
+                    road_type,
+                }));
+            }
+            None => roads.push(Road {
+                coords: project_points(&coords),
+                road_type,
+            }),
+        }
     }
     Ok(roads)
 }
 
-/// 解析多边形 (从二进制 TypedArray)
-pub fn parse_polygons_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
+/// 解析多边形 (从二进制 TypedArray，支持 Float64Array 与 Float32Array 布局)
+///
+/// 提供 `viewport` 时剔除视口外的多边形，并将跨越视口的多边形裁剪到视口内
+pub fn parse_polygons_bin<T: Copy + Into<f64>>(
+    data: &[T],
+    viewport: Option<&Viewport>,
+) -> Result<Vec<PolyFeature>, String> {
+    let data = strip_bin_header(data, BinLayer::Polygons).map_err(|e| e.to_string())?;
     if data.is_empty() {
         return Ok(vec![]);
     }
 
-    let poly_count = data[0] as usize;
+    let poly_count = data[0].into() as usize;
     let mut polys = Vec::with_capacity(poly_count);
     let mut offset = 1;
 
@@ -121,8 +317,8 @@ pub fn parse_polygons_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
         if offset + 2 > data.len() {
             break;
         }
-        let exterior_count = data[offset] as usize;
-        let interior_ring_count = data[offset + 1] as usize;
+        let exterior_count = data[offset].into() as usize;
+        let interior_ring_count = data[offset + 1].into() as usize;
         offset += 2;
 
         // Exterior
@@ -131,7 +327,7 @@ pub fn parse_polygons_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
         }
         let mut exterior = Vec::with_capacity(exterior_count);
         for _ in 0..exterior_count {
-            exterior.push((data[offset], data[offset + 1]));
+            exterior.push((data[offset].into(), data[offset + 1].into()));
             offset += 2;
         }
 
@@ -141,7 +337,7 @@ pub fn parse_polygons_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
             if offset + 1 > data.len() {
                 break;
             }
-            let ring_point_count = data[offset] as usize;
+            let ring_point_count = data[offset].into() as usize;
             offset += 1;
 
             if offset + ring_point_count * 2 > data.len() {
@@ -149,20 +345,83 @@ pub fn parse_polygons_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
             }
             let mut ring = Vec::with_capacity(ring_point_count);
             for _ in 0..ring_point_count {
-                ring.push((data[offset], data[offset + 1]));
+                ring.push((data[offset].into(), data[offset + 1].into()));
                 offset += 2;
             }
+            drop_invalid_lonlat(&mut ring);
+            if ring.len() < 3 {
+                continue;
+            }
+            if let Some(view) = viewport {
+                view.wrap_lons(&mut ring);
+                ring = view.clip_ring(&ring);
+                if ring.is_empty() {
+                    continue;
+                }
+            }
             interiors.push(project_points(&ring));
         }
 
-        polys.push(PolyFeature {
+        drop_invalid_lonlat(&mut exterior);
+        if exterior.len() < 3 {
+            continue;
+        }
+        if let Some(view) = viewport {
+            view.wrap_lons(&mut exterior);
+            exterior = view.clip_ring(&exterior);
+            if exterior.is_empty() {
+                continue;
+            }
+        }
+        let mut poly = PolyFeature {
             exterior: project_points(&exterior),
             interiors,
-        });
+        };
+        poly.normalize_winding();
+        polys.push(poly);
     }
     Ok(polys)
 }
 
+/// 提取道路几何中的所有线段（MultiLineString 的每个成员各成一段，
+/// GeometryCollection 递归展开）
+fn road_lines(geometry: &SimpleGeometry) -> Vec<Vec<(f64, f64)>> {
+    match geometry.geom_type.as_str() {
+        "LineString" => parse_coords_val(&geometry.coordinates)
+            .into_iter()
+            .collect(),
+        "MultiLineString" => geometry
+            .coordinates
+            .as_array()
+            .map(|lines| lines.iter().filter_map(parse_coords_val).collect())
+            .unwrap_or_default(),
+        "GeometryCollection" => geometry.geometries.iter().flat_map(road_lines).collect(),
+        _ => vec![],
+    }
+}
+
+/// 提取多边形几何中的所有多边形（未投影；GeometryCollection 递归展开）
+fn polygon_parts(geometry: &SimpleGeometry) -> Vec<PolyFeature> {
+    match geometry.geom_type.as_str() {
+        "Polygon" => parse_polygon_rings(&geometry.coordinates)
+            .into_iter()
+            .collect(),
+        "GeometryCollection" => geometry.geometries.iter().flat_map(polygon_parts).collect(),
+        _ => vec![],
+    }
+}
+
+/// 解析 Polygon 的坐标数组（第一个环为外圈，其余为内圈）
+fn parse_polygon_rings(val: &serde_json::Value) -> Option<PolyFeature> {
+    let rings = val.as_array()?;
+    let exterior = parse_coords_val(rings.first()?)?;
+    let interiors = rings[1..].iter().filter_map(parse_coords_val).collect();
+    Some(PolyFeature {
+        exterior,
+        interiors,
+    })
+}
+
 fn parse_coords_val(val: &serde_json::Value) -> Option<Vec<(f64, f64)>> {
     let arr = val.as_array()?;
     let mut coords = Vec::with_capacity(arr.len());
@@ -172,33 +431,76 @@ fn parse_coords_val(val: &serde_json::Value) -> Option<Vec<(f64, f64)>> {
             coords.push((pair[0].as_f64()?, pair[1].as_f64()?));
         }
     }
+    drop_invalid_lonlat(&mut coords);
     Some(coords)
 }
 
+#[cfg(js_host)]
 pub fn parse_polygons_js(js_val: JsValue) -> Result<Vec<PolyFeature>, String> {
     let collection: SimpleFC = serde_wasm_bindgen::from_value(js_val).map_err(|e| e.to_string())?;
+    Ok(polygons_from_collection(collection))
+}
+
+/// 解析多边形（从 GeoJSON 字节，例如解压后的响应体）
+pub fn parse_polygons_slice(json: &[u8]) -> Result<Vec<PolyFeature>, String> {
+    let collection: SimpleFC =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON: {}", e))?;
+    Ok(polygons_from_collection(collection))
+}
+
+fn polygons_from_collection(collection: SimpleFC) -> Vec<PolyFeature> {
     let mut polys = Vec::with_capacity(collection.features.len());
     for f in collection.features {
-        if f.geometry.geom_type == "Polygon" {
-            if let Some(rings) = f.geometry.coordinates.as_array() {
-                if !rings.is_empty() {
-                    if let Some(exterior) = parse_coords_val(&rings[0]) {
-                        let mut interiors = Vec::new();
-                        for i in 1..rings.len() {
-                            if let Some(ring) = parse_coords_val(&rings[i]) {
-                                interiors.push(project_points(&ring));
-                            }
-                        }
-                        polys.push(PolyFeature {
-                            exterior: project_points(&exterior),
-                            interiors,
-                        });
-                    }
-                }
+        push_feature_polygons(&f, &mut polys);
+    }
+    polys
+}
+
+fn push_feature_polygons(f: &SimpleFeature, polys: &mut Vec<PolyFeature>) {
+    let Some(geometry) = &f.geometry else {
+        return;
+    };
+    for poly in polygon_parts(geometry) {
+        polys.push(PolyFeature {
+            exterior: project_points(&poly.exterior),
+            interiors: poly.interiors.iter().map(|r| project_points(r)).collect(),
+        });
+    }
+}
+
+/// 按标签规则将混合 GeoJSON 拆分为道路 / 水体 / 公园（坐标保持 WGS84 经纬度）
+///
+/// 仅保留 `highway`、`leisure`、`landuse`、`natural`、`waterway` 标签，规则中的其它键不会命中
+pub fn split_geojson_layers(json: &[u8], rules: &[StyleRule]) -> Result<OsmLayers, String> {
+    let collection: SimpleFC =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON: {}", e))?;
+    let mut layers = OsmLayers::default();
+    for f in &collection.features {
+        let (Some(geometry), Some(props)) = (&f.geometry, &f.properties) else {
+            continue;
+        };
+        match style::classify(rules, |k| props.tag(k)) {
+            Some(StyleLayer::Roads) => {
+                let road_type = props.road_type();
+                layers.roads.extend(
+                    road_lines(geometry)
+                        .into_iter()
+                        .map(|coords| Road { coords, road_type }),
+                );
             }
+            Some(layer) => layers.polygons_mut(layer).extend(polygon_parts(geometry)),
+            None => {}
         }
     }
-    Ok(polys)
+    Ok(layers)
+}
+
+/// 解析单个 GeoJSON Feature 的字节并追加多边形（流式解析使用）
+pub fn parse_polygon_feature(json: &[u8], polys: &mut Vec<PolyFeature>) -> Result<(), String> {
+    let feature: SimpleFeature =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON feature: {}", e))?;
+    push_feature_polygons(&feature, polys);
+    Ok(())
 }
 
 /// 解析道路（从 GeoJSON 字符串）
@@ -212,3 +514,1197 @@ pub fn parse_roads(_: &str) -> Result<Vec<Road>, String> {
 pub fn parse_polygons(_: &str) -> Result<Vec<PolyFeature>, String> {
     Ok(vec![])
 }
+
+/// 道路二进制中每条道路坐标数据所在的下标区间（区间内为 x0, y0, x1, y1, ...）
+pub fn road_bin_coord_ranges<T: Copy + Into<f64>>(data: &[T]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if data.is_empty() {
+        return ranges;
+    }
+
+    let road_count = data[0].into() as usize;
+    let mut offset = 1;
+    for _ in 0..road_count {
+        if offset + 2 > data.len() {
+            break;
+        }
+        let point_count = data[offset + 1].into() as usize;
+        offset += 2;
+        if offset + point_count * 2 > data.len() {
+            break;
+        }
+        ranges.push(offset..offset + point_count * 2);
+        offset += point_count * 2;
+    }
+    ranges
+}
+
+/// 将道路二进制按每块至多 `max_roads` 条切分
+///
+/// 返回各块的道路数与其记录（含 type / n 头部）所在的下标区间，
+/// 每块前补上道路数即为合法的道路二进制
+pub fn road_bin_chunks(data: &[f64], max_roads: usize) -> Vec<(usize, Range<usize>)> {
+    road_bin_coord_ranges(data)
+        .chunks(max_roads.max(1))
+        .map(|roads| (roads.len(), roads[0].start - 2..roads[roads.len() - 1].end))
+        .collect()
+}
+
+/// 路线二进制 [count, n, x, y, ...] 中每条路线坐标数据所在的下标区间
+pub fn route_bin_coord_ranges<T: Copy + Into<f64>>(data: &[T]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if data.is_empty() {
+        return ranges;
+    }
+
+    let route_count = data[0].into() as usize;
+    let mut offset = 1;
+    for _ in 0..route_count {
+        if offset + 1 > data.len() {
+            break;
+        }
+        let point_count = data[offset].into() as usize;
+        offset += 1;
+        if offset + point_count * 2 > data.len() {
+            break;
+        }
+        ranges.push(offset..offset + point_count * 2);
+        offset += point_count * 2;
+    }
+    ranges
+}
+
+/// 将路线序列化为二进制格式 [count, n, x, y, ...]
+pub fn routes_to_bin(routes: &[Vec<(f64, f64)>]) -> Vec<f64> {
+    let mut out = vec![routes.len() as f64];
+    for route in routes {
+        out.push(route.len() as f64);
+        out.extend(route.iter().flat_map(|&(x, y)| [x, y]));
+    }
+    out
+}
+
+/// 多边形二进制中每个环（外圈与内圈）坐标数据所在的下标区间
+pub fn polygon_bin_coord_ranges<T: Copy + Into<f64>>(data: &[T]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if data.is_empty() {
+        return ranges;
+    }
+
+    let poly_count = data[0].into() as usize;
+    let mut offset = 1;
+    'polys: for _ in 0..poly_count {
+        if offset + 2 > data.len() {
+            break;
+        }
+        let exterior_count = data[offset].into() as usize;
+        let interior_ring_count = data[offset + 1].into() as usize;
+        offset += 2;
+        if offset + exterior_count * 2 > data.len() {
+            break;
+        }
+        ranges.push(offset..offset + exterior_count * 2);
+        offset += exterior_count * 2;
+
+        for _ in 0..interior_ring_count {
+            if offset + 1 > data.len() {
+                break 'polys;
+            }
+            let ring_point_count = data[offset].into() as usize;
+            offset += 1;
+            if offset + ring_point_count * 2 > data.len() {
+                break 'polys;
+            }
+            ranges.push(offset..offset + ring_point_count * 2);
+            offset += ring_point_count * 2;
+        }
+    }
+    ranges
+}
+
+/// 去掉 NaN / Inf 与超出范围的经纬度，并记入渲染警告
+fn drop_invalid_lonlat(coords: &mut Vec<(f64, f64)>) {
+    let len = coords.len();
+    coords.retain(|&(lon, lat)| coord_in_range(lon, lat, Crs::Wgs84));
+    warnings::invalid_coordinates(None, len - coords.len());
+}
+
+/// POI 二进制 [count, x, y, ...] 中坐标数据所在的下标区间
+pub fn poi_bin_coord_range<T: Copy + Into<f64>>(data: &[T]) -> Range<usize> {
+    let count = data.first().map_or(0, |&c| c.into() as usize);
+    let end = 1usize
+        .saturating_add(count.saturating_mul(2))
+        .min(data.len());
+    end.min(1)..end
+}
+
+/// 按声明的数量校正 POI 二进制 [count, x, y, ...]，返回与声明不符的数值个数
+///
+/// 数量须为非负整数，否则整组数据作废；声明的 POI 多于实际数据时只保留完整的坐标对，
+/// 超出声明数量的尾部数据一并去掉
+pub fn truncate_poi_bin(data: &mut Vec<f64>) -> usize {
+    let Some(&declared) = data.first() else {
+        return 0;
+    };
+    let available = (data.len() - 1) / 2;
+    let (points, mismatched) = if declared >= 0.0 && declared.fract() == 0.0 {
+        let declared = declared.min(usize::MAX as f64) as usize;
+        let points = declared.min(available);
+        let missing = (declared - points).saturating_mul(2);
+        (points, missing.saturating_add(data.len() - 1 - points * 2))
+    } else {
+        (0, data.len() - 1)
+    };
+    if mismatched > 0 {
+        data.truncate(1 + points * 2);
+        data[0] = points as f64;
+    }
+    mismatched
+}
+
+/// 去掉二进制几何中的无效坐标（NaN / Inf、超出 CRS 范围），返回去掉的坐标数
+///
+/// 点数不足的道路、路线（< 2）与环（< 3）整体去掉，外圈被去掉的多边形连同内圈一并去掉；
+/// 末尾不完整的记录同时被丢弃。声明为 `wgs84` 而多数坐标超出经纬度范围时只去掉非有限值，
+/// 留给 `validate_crs` 报告 CRS 不符
+pub fn drop_invalid_coords(data: &mut Vec<f64>, layer: BinLayer, crs: Crs) -> usize {
+    let ranges = match layer {
+        BinLayer::Roads => road_bin_coord_ranges(data),
+        BinLayer::Polygons => polygon_bin_coord_ranges(data),
+        BinLayer::Routes => route_bin_coord_ranges(data),
+        BinLayer::Pois => vec![poi_bin_coord_range(data)],
+        BinLayer::Container => return 0,
+    };
+    let (mut total, mut non_finite, mut out_of_range) = (0usize, 0usize, 0usize);
+    for p in ranges.iter().flat_map(|r| data[r.clone()].chunks_exact(2)) {
+        total += 1;
+        if !(p[0].is_finite() && p[1].is_finite()) {
+            non_finite += 1;
+        } else if !coord_in_range(p[0], p[1], crs) {
+            out_of_range += 1;
+        }
+    }
+    let check_range = out_of_range * 2 <= total - non_finite;
+    let dropped = non_finite + if check_range { out_of_range } else { 0 };
+    if dropped == 0 {
+        return 0;
+    }
+
+    let keep = |x: f64, y: f64| {
+        if check_range {
+            coord_in_range(x, y, crs)
+        } else {
+            x.is_finite() && y.is_finite()
+        }
+    };
+    let valid = |r: &Range<usize>| -> Vec<(f64, f64)> {
+        data[r.clone()]
+            .chunks_exact(2)
+            .map(|p| (p[0], p[1]))
+            .filter(|&(x, y)| keep(x, y))
+            .collect()
+    };
+    *data = match layer {
+        BinLayer::Roads => {
+            let roads = ranges.iter().filter_map(|r| {
+                let coords = valid(r);
+                (coords.len() >= 2).then(|| Road {
+                    coords,
+                    road_type: RoadType::from_u32(data[r.start - 2] as u32),
+                })
+            });
+            roads_to_bin(&roads.collect::<Vec<_>>())
+        }
+        BinLayer::Routes => {
+            let routes = ranges.iter().map(&valid).filter(|route| route.len() >= 2);
+            routes_to_bin(&routes.collect::<Vec<_>>())
+        }
+        BinLayer::Pois => {
+            let points = valid(&ranges[0]);
+            let mut out = vec![points.len() as f64];
+            out.extend(points.iter().flat_map(|&(x, y)| [x, y]));
+            out
+        }
+        _ => {
+            // 多边形：每个外圈区间之后紧跟其内圈区间
+            let mut polys: Vec<PolyFeature> = Vec::new();
+            let mut rings = ranges.iter();
+            while let Some(r) = rings.next() {
+                let exterior = valid(r);
+                let hole_count = data[r.start - 1] as usize;
+                let holes: Vec<_> = rings.by_ref().take(hole_count).collect();
+                if exterior.len() < 3 {
+                    continue;
+                }
+                let interiors = holes
+                    .iter()
+                    .map(|h| valid(h))
+                    .filter(|ring| ring.len() >= 3)
+                    .collect();
+                polys.push(PolyFeature {
+                    exterior,
+                    interiors,
+                });
+            }
+            polygons_to_bin(&polys)
+        }
+    };
+    dropped
+}
+
+/// 按声明的 CRS 校验二进制几何，若为经纬度则原地投影为 Web Mercator
+pub fn prepare_bin_geometry(
+    data: &mut [f64],
+    ranges: &[Range<usize>],
+    crs: Crs,
+    center: (f64, f64),
+    layer: &str,
+) -> Result<(), String> {
+    let points = ranges
+        .iter()
+        .flat_map(|r| data[r.clone()].chunks_exact(2).map(|p| (p[0], p[1])));
+    validate_crs(points, crs, center, layer)?;
+
+    if crs == Crs::Wgs84 {
+        let mut slices = par::disjoint_slices_mut(data, ranges);
+        par::for_each_mut(&mut slices, |slice| {
+            for p in slice.chunks_exact_mut(2) {
+                let (x, y) = project_point(p[0], p[1]);
+                p[0] = x;
+                p[1] = y;
+            }
+        });
+    }
+    Ok(())
+}
+
+// --- OSM 原始要素（Overpass JSON 与 .osm XML 共用） ---
+
+type Tags = HashMap<String, String>;
+
+struct OsmNode {
+    lon: f64,
+    lat: f64,
+    tags: Tags,
+}
+
+struct OsmWay {
+    id: i64,
+    nodes: Vec<i64>,
+    // Overpass `out geom` 直接内联的坐标（为空时通过 nodes 查找）
+    geometry: Vec<(f64, f64)>,
+    tags: Tags,
+}
+
+struct OsmMember {
+    member_type: String,
+    reference: i64,
+    role: String,
+    geometry: Vec<(f64, f64)>,
+}
+
+struct OsmRelation {
+    members: Vec<OsmMember>,
+    tags: Tags,
+}
+
+/// 未经分类的 OSM 元素集合
+#[derive(Default)]
+struct OsmElements {
+    nodes: HashMap<i64, OsmNode>,
+    ways: Vec<OsmWay>,
+    relations: Vec<OsmRelation>,
+}
+
+/// 从 OSM 数据中分类出的图层（坐标为 WGS84 经纬度，尚未投影）
+#[derive(Debug, Default)]
+pub struct OsmLayers {
+    pub roads: Vec<Road>,
+    pub water: Vec<PolyFeature>,
+    pub parks: Vec<PolyFeature>,
+    pub pois: Vec<POI>,
+}
+
+impl OsmLayers {
+    /// 面图层对应的多边形集合（调用方需先排除道路）
+    fn polygons_mut(&mut self, layer: StyleLayer) -> &mut Vec<PolyFeature> {
+        match layer {
+            StyleLayer::Water => &mut self.water,
+            StyleLayer::Parks => &mut self.parks,
+            StyleLayer::Roads => unreachable!("roads have no polygon layer"),
+        }
+    }
+}
+
+/// 按标签判断面要素所属图层（与前端 presets 的查询条件一致）
+fn area_layer(tags: &Tags) -> Option<StyleLayer> {
+    style::classify(&style::DEFAULT_RULES, |k| tags.get(k).map(String::as_str))
+        .filter(|layer| *layer != StyleLayer::Roads)
+}
+
+fn is_closed(ring: &[(f64, f64)]) -> bool {
+    ring.len() >= 4 && ring.first() == ring.last()
+}
+
+/// 将首尾相接的线段拼接为闭合环，无法闭合的部分被丢弃
+fn assemble_rings(mut segments: Vec<Vec<(f64, f64)>>) -> Vec<Vec<(f64, f64)>> {
+    segments.retain(|s| s.len() >= 2);
+    let mut rings = Vec::new();
+    while let Some(mut ring) = segments.pop() {
+        while !is_closed(&ring) {
+            let end = ring[ring.len() - 1];
+            let Some(i) = segments
+                .iter()
+                .position(|s| s[0] == end || s[s.len() - 1] == end)
+            else {
+                break;
+            };
+            let mut next = segments.swap_remove(i);
+            if next[0] != end {
+                next.reverse();
+            }
+            ring.extend(next.into_iter().skip(1));
+        }
+        if is_closed(&ring) {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// 射线法判断点是否位于环内
+pub fn point_in_ring(p: (f64, f64), ring: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+        if (yi > p.1) != (yj > p.1) && p.0 < (xj - xi) * (p.1 - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl OsmElements {
+    /// 加入节点，跳过坐标无效的节点（引用它的路径随之少一个点）
+    fn insert_node(&mut self, id: i64, node: OsmNode) {
+        if coord_in_range(node.lon, node.lat, Crs::Wgs84) {
+            self.nodes.insert(id, node);
+        } else {
+            warnings::invalid_coordinates(None, 1);
+        }
+    }
+
+    fn way_coords(&self, way: &OsmWay) -> Vec<(f64, f64)> {
+        if !way.geometry.is_empty() {
+            return way.geometry.clone();
+        }
+        way.nodes
+            .iter()
+            .filter_map(|id| self.nodes.get(id).map(|n| (n.lon, n.lat)))
+            .collect()
+    }
+
+    /// 将元素分类为道路 / 水体 / 公园 / POI
+    fn into_layers(self) -> OsmLayers {
+        let mut layers = OsmLayers::default();
+        let way_index: HashMap<i64, usize> = self
+            .ways
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w.id, i))
+            .collect();
+
+        for way in &self.ways {
+            let coords = self.way_coords(way);
+            if coords.len() < 2 {
+                continue;
+            }
+            if let Some(highway) = way.tags.get("highway")
+                && way.tags.get("area").map(String::as_str) != Some("yes")
+            {
+                layers.roads.push(Road {
+                    coords,
+                    road_type: RoadType::from_highway(highway),
+                });
+            } else if let Some(layer) = area_layer(&way.tags)
+                && is_closed(&coords)
+            {
+                let poly = PolyFeature {
+                    exterior: coords,
+                    interiors: vec![],
+                };
+                layers.polygons_mut(layer).push(poly);
+            }
+        }
+
+        for relation in &self.relations {
+            if relation.tags.get("type").map(String::as_str) != Some("multipolygon") {
+                continue;
+            }
+            let Some(layer) = area_layer(&relation.tags) else {
+                continue;
+            };
+            let mut outers = Vec::new();
+            let mut inners = Vec::new();
+            for m in relation.members.iter().filter(|m| m.member_type == "way") {
+                let coords = if !m.geometry.is_empty() {
+                    m.geometry.clone()
+                } else if let Some(&i) = way_index.get(&m.reference) {
+                    self.way_coords(&self.ways[i])
+                } else {
+                    continue;
+                };
+                if m.role == "inner" {
+                    inners.push(coords);
+                } else {
+                    outers.push(coords);
+                }
+            }
+
+            let mut polys: Vec<PolyFeature> = assemble_rings(outers)
+                .into_iter()
+                .map(|exterior| PolyFeature {
+                    exterior,
+                    interiors: vec![],
+                })
+                .collect();
+            for inner in assemble_rings(inners) {
+                if let Some(poly) = polys
+                    .iter_mut()
+                    .find(|p| point_in_ring(inner[0], &p.exterior))
+                {
+                    poly.interiors.push(inner);
+                }
+            }
+            layers.polygons_mut(layer).extend(polys);
+        }
+
+        // 按节点 ID 排序，保证输出顺序稳定
+        let mut poi_nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.tags.contains_key("amenity"))
+            .collect();
+        poi_nodes.sort_by_key(|(id, _)| **id);
+        layers.pois = poi_nodes
+            .into_iter()
+            .map(|(_, n)| POI {
+                name: n.tags.get("name").cloned(),
+                category: n.tags.get("amenity").cloned(),
+                ..POI::new(n.lon, n.lat)
+            })
+            .collect();
+        layers.roads = merge::merge_roads(layers.roads);
+        layers
+    }
+}
+
+// --- Overpass JSON ---
+
+#[derive(Deserialize)]
+struct OverpassResponse {
+    elements: Vec<OverpassElement>,
+}
+
+#[derive(Deserialize)]
+struct OverpassLatLon {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize)]
+struct OverpassMember {
+    #[serde(rename = "type")]
+    member_type: String,
+    #[serde(rename = "ref")]
+    reference: i64,
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    geometry: Vec<OverpassLatLon>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum OverpassElement {
+    Node {
+        id: i64,
+        lat: Option<f64>,
+        lon: Option<f64>,
+        #[serde(default)]
+        tags: Tags,
+    },
+    Way {
+        id: i64,
+        #[serde(default)]
+        nodes: Vec<i64>,
+        #[serde(default)]
+        geometry: Vec<OverpassLatLon>,
+        #[serde(default)]
+        tags: Tags,
+    },
+    Relation {
+        #[serde(default)]
+        members: Vec<OverpassMember>,
+        #[serde(default)]
+        tags: Tags,
+    },
+    #[serde(other)]
+    Other,
+}
+
+fn latlon_coords(points: Vec<OverpassLatLon>) -> Vec<(f64, f64)> {
+    let mut coords = points.into_iter().map(|p| (p.lon, p.lat)).collect();
+    drop_invalid_lonlat(&mut coords);
+    coords
+}
+
+/// 解析 Overpass API 的原始 JSON 响应（`elements` 数组）
+///
+/// 支持 `out body` + `>` 递归（通过节点引用取坐标）以及 `out geom`（内联坐标）两种输出
+pub fn parse_overpass(json: &str) -> Result<OsmLayers, String> {
+    let response: OverpassResponse =
+        serde_json::from_str(json).map_err(|e| format!("Invalid Overpass JSON: {}", e))?;
+
+    let mut elements = OsmElements::default();
+    for element in response.elements {
+        match element {
+            OverpassElement::Node { id, lat, lon, tags } => {
+                if let (Some(lat), Some(lon)) = (lat, lon) {
+                    elements.insert_node(id, OsmNode { lon, lat, tags });
+                }
+            }
+            OverpassElement::Way {
+                id,
+                nodes,
+                geometry,
+                tags,
+            } => elements.ways.push(OsmWay {
+                id,
+                nodes,
+                geometry: latlon_coords(geometry),
+                tags,
+            }),
+            OverpassElement::Relation { members, tags } => elements.relations.push(OsmRelation {
+                members: members
+                    .into_iter()
+                    .map(|m| OsmMember {
+                        member_type: m.member_type,
+                        reference: m.reference,
+                        role: m.role,
+                        geometry: latlon_coords(m.geometry),
+                    })
+                    .collect(),
+                tags,
+            }),
+            OverpassElement::Other => {}
+        }
+    }
+    Ok(elements.into_layers())
+}
+
+/// 按内容识别并解析 OSM 数据：`<` 开头为 .osm XML，含 `elements` 的 JSON 为 Overpass 响应，
+/// 其余按混合 GeoJSON 以默认规则拆分图层（坐标均为 WGS84 经纬度）
+pub fn parse_osm_auto(input: &str) -> Result<OsmLayers, String> {
+    let trimmed = input.trim_start();
+    if trimmed.starts_with('<') {
+        return parse_osm_xml(trimmed);
+    }
+    #[derive(Deserialize)]
+    struct Probe {
+        elements: Option<serde::de::IgnoredAny>,
+    }
+    let probe: Probe =
+        serde_json::from_str(trimmed).map_err(|e| format!("Unrecognized input: {}", e))?;
+    if probe.elements.is_some() {
+        parse_overpass(trimmed)
+    } else {
+        split_geojson_layers(trimmed.as_bytes(), &style::DEFAULT_RULES)
+    }
+}
+
+// --- OSM XML (.osm) ---
+
+fn xml_tags(node: roxmltree::Node) -> Tags {
+    node.children()
+        .filter(|c| c.has_tag_name("tag"))
+        .filter_map(|c| Some((c.attribute("k")?.to_string(), c.attribute("v")?.to_string())))
+        .collect()
+}
+
+fn xml_attr<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Option<T> {
+    node.attribute(name)?.parse().ok()
+}
+
+/// 解析 openstreetmap.org「导出」得到的 .osm XML 文件
+pub fn parse_osm_xml(xml: &str) -> Result<OsmLayers, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid OSM XML: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("osm") {
+        return Err(format!(
+            "Invalid OSM XML: expected <osm> root, found <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut elements = OsmElements::default();
+    for el in root.children().filter(|n| n.is_element()) {
+        match el.tag_name().name() {
+            "node" => {
+                if let (Some(id), Some(lat), Some(lon)) =
+                    (xml_attr(el, "id"), xml_attr(el, "lat"), xml_attr(el, "lon"))
+                {
+                    elements.insert_node(
+                        id,
+                        OsmNode {
+                            lon,
+                            lat,
+                            tags: xml_tags(el),
+                        },
+                    );
+                }
+            }
+            "way" => {
+                let Some(id) = xml_attr(el, "id") else {
+                    continue;
+                };
+                elements.ways.push(OsmWay {
+                    id,
+                    nodes: el
+                        .children()
+                        .filter(|c| c.has_tag_name("nd"))
+                        .filter_map(|c| xml_attr(c, "ref"))
+                        .collect(),
+                    geometry: vec![],
+                    tags: xml_tags(el),
+                });
+            }
+            "relation" => elements.relations.push(OsmRelation {
+                members: el
+                    .children()
+                    .filter(|c| c.has_tag_name("member"))
+                    .filter_map(|c| {
+                        Some(OsmMember {
+                            member_type: c.attribute("type")?.to_string(),
+                            reference: xml_attr(c, "ref")?,
+                            role: c.attribute("role").unwrap_or_default().to_string(),
+                            geometry: vec![],
+                        })
+                    })
+                    .collect(),
+                tags: xml_tags(el),
+            }),
+            _ => {}
+        }
+    }
+    Ok(elements.into_layers())
+}
+
+/// 将道路序列化为二进制格式 [count, type, n, x, y, ...]
+pub fn roads_to_bin(roads: &[Road]) -> Vec<f64> {
+    let mut out = vec![roads.len() as f64];
+    for road in roads {
+        out.push(road.road_type.to_u32() as f64);
+        out.push(road.coords.len() as f64);
+        out.extend(road.coords.iter().flat_map(|&(x, y)| [x, y]));
+    }
+    out
+}
+
+/// 从二进制格式 [count, type, n, x, y, ...] 读取道路（不做投影）
+pub fn roads_from_bin(data: &[f64]) -> Result<Vec<Road>, String> {
+    let Some((&count, mut rest)) = data.split_first() else {
+        return Ok(vec![]);
+    };
+    let mut roads = Vec::with_capacity(count as usize);
+    for i in 0..count as usize {
+        let [road_type, n, tail @ ..] = rest else {
+            return Err(format!("Road {} header truncated", i));
+        };
+        let len = *n as usize * 2;
+        if tail.len() < len {
+            return Err(format!("Road {} coordinates truncated", i));
+        }
+        roads.push(Road {
+            coords: tail[..len].chunks_exact(2).map(|c| (c[0], c[1])).collect(),
+            road_type: RoadType::from_u32(*road_type as u32),
+        });
+        rest = &tail[len..];
+    }
+    Ok(roads)
+}
+
+/// 将多边形序列化为二进制格式 [count, ext_n, int_count, ext..., (ring_n, ring...)*]
+pub fn polygons_to_bin(polys: &[PolyFeature]) -> Vec<f64> {
+    let mut out = vec![polys.len() as f64];
+    for poly in polys {
+        out.push(poly.exterior.len() as f64);
+        out.push(poly.interiors.len() as f64);
+        out.extend(poly.exterior.iter().flat_map(|&(x, y)| [x, y]));
+        for ring in &poly.interiors {
+            out.push(ring.len() as f64);
+            out.extend(ring.iter().flat_map(|&(x, y)| [x, y]));
+        }
+    }
+    out
+}
+
+/// 解码多边形二进制（`polygons_to_bin` 的逆过程）
+pub fn polygons_from_bin(data: &[f64]) -> Result<Vec<PolyFeature>, String> {
+    let Some((&count, mut rest)) = data.split_first() else {
+        return Ok(vec![]);
+    };
+    let take_ring = |rest: &mut &[f64], n: usize, i: usize| -> Result<Vec<(f64, f64)>, String> {
+        if rest.len() < n * 2 {
+            return Err(format!("Polygon {} coordinates truncated", i));
+        }
+        let (ring, tail) = rest.split_at(n * 2);
+        *rest = tail;
+        Ok(ring.chunks_exact(2).map(|c| (c[0], c[1])).collect())
+    };
+    let mut polys = Vec::with_capacity(count as usize);
+    for i in 0..count as usize {
+        let [ext_n, int_count, tail @ ..] = rest else {
+            return Err(format!("Polygon {} header truncated", i));
+        };
+        rest = tail;
+        let exterior = take_ring(&mut rest, *ext_n as usize, i)?;
+        let mut interiors = Vec::with_capacity(*int_count as usize);
+        for _ in 0..*int_count as usize {
+            let Some((&n, tail)) = rest.split_first() else {
+                return Err(format!("Polygon {} ring header truncated", i));
+            };
+            rest = tail;
+            interiors.push(take_ring(&mut rest, n as usize, i)?);
+        }
+        polys.push(PolyFeature {
+            exterior,
+            interiors,
+        });
+    }
+    Ok(polys)
+}
+
+/// 从 GeoJSON FeatureCollection 读取 POI（WGS84 经纬度）
+///
+/// Point / MultiPoint 的每个点各为一个 POI，属性中的 `name`、`category` 为字符串或数字时保留；
+/// 无效坐标被跳过并记入渲染警告
+pub fn pois_from_features(collection: &PoiFeatureCollection) -> Vec<POI> {
+    let text = |value: &Option<serde_json::Value>| match value {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    let mut pois = Vec::new();
+    for f in &collection.features {
+        let points: Vec<&[f64]> = match &f.geometry {
+            Some(PoiGeometry::Point { coordinates }) => vec![coordinates],
+            Some(PoiGeometry::MultiPoint { coordinates }) => {
+                coordinates.iter().map(Vec::as_slice).collect()
+            }
+            _ => continue,
+        };
+        let (name, category) = f
+            .properties
+            .as_ref()
+            .map_or((None, None), |p| (text(&p.name), text(&p.category)));
+        let mut coords: Vec<(f64, f64)> = points
+            .iter()
+            .filter_map(|p| Some((*p.first()?, *p.get(1)?)))
+            .collect();
+        drop_invalid_lonlat(&mut coords);
+        pois.extend(coords.into_iter().map(|(x, y)| POI {
+            name: name.clone(),
+            category: category.clone(),
+            ..POI::new(x, y)
+        }));
+    }
+    pois
+}
+
+/// 解析 GeoJSON FeatureCollection 字节中的 POI
+pub fn parse_poi_geojson(json: &[u8]) -> Result<Vec<POI>, String> {
+    let collection: PoiFeatureCollection =
+        serde_json::from_slice(json).map_err(|e| format!("Invalid GeoJSON: {}", e))?;
+    Ok(pois_from_features(&collection))
+}
+
+/// 将 POI 序列化为二进制格式 [count, x1, y1, ...]
+pub fn pois_to_bin(pois: &[POI]) -> Vec<f64> {
+    let mut out = vec![pois.len() as f64];
+    out.extend(pois.iter().flat_map(|p| [p.x, p.y]));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_osm_auto_detects_format() {
+        let overpass = r#"{"elements":[{"type":"way","id":1,"tags":{"highway":"primary"},
+            "geometry":[{"lat":0.0,"lon":0.0},{"lat":0.001,"lon":0.001}]}]}"#;
+        assert_eq!(parse_osm_auto(overpass).unwrap().roads.len(), 1);
+
+        let geojson = r#"{"type":"FeatureCollection","features":[{"type":"Feature",
+            "properties":{"natural":"water"},"geometry":{"type":"Polygon",
+            "coordinates":[[[0,0],[1,0],[1,1],[0,0]]]}}]}"#;
+        assert_eq!(parse_osm_auto(geojson).unwrap().water.len(), 1);
+
+        let xml = r#"<osm version="0.6"><node id="1" lat="0" lon="0"/><node id="2" lat="0.001" lon="0.001"/>
+            <way id="3"><nd ref="1"/><nd ref="2"/><tag k="highway" v="residential"/></way></osm>"#;
+        assert_eq!(parse_osm_auto(xml).unwrap().roads.len(), 1);
+        assert!(parse_osm_auto("not data").is_err());
+    }
+
+    fn geometry(json: &str) -> SimpleGeometry {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_road_lines_keeps_every_multilinestring_member() {
+        let g = geometry(
+            r#"{"type":"MultiLineString","coordinates":[[[0,0],[1,1]],[[2,2],[3,3],[4,4]],[[5,5],[6,6]]]}"#,
+        );
+        let lines = road_lines(&g);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], vec![(2.0, 2.0), (3.0, 3.0), (4.0, 4.0)]);
+        assert_eq!(lines[2][1], (6.0, 6.0));
+    }
+
+    #[test]
+    fn test_geometry_collection_is_unpacked_recursively() {
+        let g = geometry(
+            r#"{"type":"GeometryCollection","geometries":[
+                {"type":"LineString","coordinates":[[0,0],[1,1]]},
+                {"type":"GeometryCollection","geometries":[
+                    {"type":"MultiLineString","coordinates":[[[2,2],[3,3]],[[4,4],[5,5]]]},
+                    {"type":"Polygon","coordinates":[[[0,0],[1,0],[1,1],[0,0]],[[0.2,0.2],[0.4,0.2],[0.2,0.4],[0.2,0.2]]]}
+                ]}
+            ]}"#,
+        );
+        assert_eq!(road_lines(&g).len(), 3);
+        let polys = polygon_parts(&g);
+        assert_eq!(polys.len(), 1);
+        assert_eq!(polys[0].exterior.len(), 4);
+        assert_eq!(polys[0].interiors.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_overpass_layers() {
+        let json = r#"{"elements":[
+            {"type":"node","id":1,"lat":0.0,"lon":0.0},
+            {"type":"node","id":2,"lat":0.0,"lon":1.0},
+            {"type":"node","id":3,"lat":1.0,"lon":1.0},
+            {"type":"node","id":4,"lat":0.5,"lon":0.5,"tags":{"amenity":"cafe"}},
+            {"type":"way","id":10,"nodes":[1,2,3],"tags":{"highway":"primary"}},
+            {"type":"way","id":11,"nodes":[1,2,3,1],"tags":{"leisure":"park"}},
+            {"type":"way","id":12,"nodes":[1,2]},
+            {"type":"way","id":13,"nodes":[2,3]},
+            {"type":"way","id":14,"nodes":[3,1]},
+            {"type":"relation","id":20,"members":[
+                {"type":"way","ref":12,"role":"outer"},
+                {"type":"way","ref":14,"role":"outer"},
+                {"type":"way","ref":13,"role":"outer"}
+            ],"tags":{"type":"multipolygon","natural":"water"}},
+            {"type":"area","id":3600000001}
+        ]}"#;
+        let layers = parse_overpass(json).unwrap();
+        assert_eq!(layers.roads.len(), 1);
+        assert_eq!(layers.roads[0].road_type, RoadType::Primary);
+        assert_eq!(layers.roads[0].coords[1], (1.0, 0.0));
+        assert_eq!(layers.parks.len(), 1);
+        assert_eq!(layers.water.len(), 1);
+        assert_eq!(layers.water[0].exterior.len(), 4);
+        assert_eq!(layers.pois.len(), 1);
+        assert_eq!((layers.pois[0].x, layers.pois[0].y), (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_parse_osm_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="openstreetmap-cgimap">
+ <bounds minlat="0" minlon="0" maxlat="1" maxlon="1"/>
+ <node id="1" lat="0" lon="0"/>
+ <node id="2" lat="0" lon="1"/>
+ <node id="3" lat="1" lon="1">
+  <tag k="amenity" v="school"/>
+ </node>
+ <way id="10">
+  <nd ref="1"/><nd ref="2"/><nd ref="3"/><nd ref="1"/>
+  <tag k="natural" v="water"/>
+ </way>
+ <way id="11">
+  <nd ref="1"/><nd ref="3"/>
+  <tag k="highway" v="residential"/>
+ </way>
+</osm>"#;
+        let layers = parse_osm_xml(xml).unwrap();
+        assert_eq!(layers.water.len(), 1);
+        assert_eq!(layers.roads.len(), 1);
+        assert_eq!(layers.roads[0].road_type, RoadType::Residential);
+        assert_eq!(layers.pois.len(), 1);
+        assert!(parse_osm_xml("<gpx/>").is_err());
+    }
+
+    #[test]
+    fn test_parse_road_feature_tolerates_null_members() {
+        let mut roads = Vec::new();
+        parse_road_feature(
+            br#"{"type":"Feature","properties":null,"geometry":null}"#,
+            &RoadFilter::default(),
+            &mut roads,
+        )
+        .unwrap();
+        parse_road_feature(
+            br#"{"type":"Feature","properties":null,"geometry":{"type":"LineString","coordinates":[[0,0],[1,1]]}}"#,
+            &RoadFilter::default(),
+            &mut roads,
+        )
+        .unwrap();
+        assert_eq!(roads.len(), 1);
+        assert_eq!(roads[0].road_type, RoadType::Residential);
+    }
+
+    #[test]
+    fn test_split_geojson_layers_by_tags() {
+        let json = r#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","properties":{"highway":"primary"},
+             "geometry":{"type":"LineString","coordinates":[[0,0],[1,1]]}},
+            {"type":"Feature","properties":{"natural":"water"},
+             "geometry":{"type":"Polygon","coordinates":[[[0,0],[1,0],[1,1],[0,0]]]}},
+            {"type":"Feature","properties":{"leisure":["park"]},
+             "geometry":{"type":"Polygon","coordinates":[[[0,0],[2,0],[2,2],[0,0]]]}},
+            {"type":"Feature","properties":{"building":"yes"},
+             "geometry":{"type":"Polygon","coordinates":[[[0,0],[3,0],[3,3],[0,0]]]}}
+        ]}"#;
+        let layers = split_geojson_layers(json.as_bytes(), &style::DEFAULT_RULES).unwrap();
+        assert_eq!(layers.roads.len(), 1);
+        assert_eq!(layers.roads[0].coords, vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(layers.water.len(), 1);
+        assert_eq!(layers.parks.len(), 1);
+    }
+
+    #[test]
+    fn test_road_filter_min_class() {
+        // motorway、secondary、residential 各一条
+        let data = [
+            3.0, 0.0, 2.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 0.0, 0.0, 1.0, 1.0, 4.0, 2.0, 0.0, 0.0,
+            1.0, 1.0,
+        ];
+        let filter: RoadFilter = serde_json::from_str(r#"{"min_class":"secondary"}"#).unwrap();
+        let roads = parse_roads_bin(&data, &filter, None).unwrap();
+        let types: Vec<_> = roads.iter().map(|r| r.road_type).collect();
+        assert_eq!(types, vec![RoadType::Motorway, RoadType::Secondary]);
+        assert_eq!(
+            parse_roads_bin(&data, &RoadFilter::default(), None)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let json = br#"{"features":[
+            {"properties":{"highway":"residential"},"geometry":{"type":"LineString","coordinates":[[0,0],[1,1]]}},
+            {"properties":{"highway":"trunk"},"geometry":{"type":"LineString","coordinates":[[0,0],[1,1]]}}
+        ]}"#;
+        let roads = parse_roads_slice(json, &filter).unwrap();
+        assert_eq!(roads.len(), 1);
+        assert_eq!(roads[0].road_type, RoadType::Primary);
+    }
+
+    #[test]
+    fn test_cull_roads_across_antimeridian() {
+        // 东侧、西侧与远离视口的道路各一条
+        let data = [
+            3.0, 0.0, 2.0, 175.0, 0.0, 176.0, 0.0, 0.0, 2.0, -176.0, 0.0, -175.0, 0.0, 0.0, 2.0,
+            0.0, 0.0, 1.0, 0.0,
+        ];
+        let bounds = crate::types::GeoBounds {
+            min_lon: 170.0,
+            min_lat: -10.0,
+            max_lon: -170.0,
+            max_lat: 10.0,
+        };
+        let view = Viewport::from_bounds(&bounds, crate::cull::DEFAULT_CULL_MARGIN);
+        let roads = parse_roads_bin(&data, &RoadFilter::default(), Some(&view)).unwrap();
+        assert_eq!(roads.len(), 2);
+        // 展开的经度在投影时归一化，西侧道路仍落在世界西端
+        assert!(roads[1].coords[0].0 < 0.0);
+    }
+
+    #[test]
+    fn test_bin_header_validation() {
+        let body = [1.0, 4.0, 2.0, 0.0, 0.0, 1.0, 1.0];
+        let headed = with_bin_header(BinLayer::Roads, &body);
+        assert_eq!(strip_bin_header(&headed, BinLayer::Roads), Ok(&body[..]));
+        // 无头部的旧格式原样通过
+        assert_eq!(strip_bin_header(&body, BinLayer::Roads), Ok(&body[..]));
+        assert_eq!(
+            strip_bin_header(&headed, BinLayer::Polygons),
+            Err(BinHeaderError::LayerMismatch {
+                expected: BinLayer::Polygons,
+                found: 0.0
+            })
+        );
+        assert_eq!(
+            strip_bin_header(&[BIN_MAGIC, 9.0, 0.0], BinLayer::Roads),
+            Err(BinHeaderError::UnsupportedVersion(9.0))
+        );
+        assert_eq!(
+            strip_bin_header(&[-1.0, 1.0, 0.0], BinLayer::Roads),
+            Err(BinHeaderError::BadMagic(-1.0))
+        );
+        assert!(parse_polygons_bin(&headed, None).is_err());
+        assert_eq!(
+            parse_roads_bin(&headed, &RoadFilter::default(), None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_road_bin_coord_ranges() {
+        // 2 条道路：type=1 两个点，type=3 一个点
+        let data = [2.0, 1.0, 2.0, 0.0, 0.0, 1.0, 1.0, 3.0, 1.0, 5.0, 5.0];
+        assert_eq!(road_bin_coord_ranges(&data), vec![3..7, 9..11]);
+        assert_eq!(road_bin_chunks(&data, 1), vec![(1, 1..7), (1, 7..11)]);
+        assert_eq!(road_bin_chunks(&data, 5), vec![(2, 1..11)]);
+    }
+
+    #[test]
+    fn test_parse_poi_geojson() {
+        let json = br#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","properties":{"name":"Cafe","category":"food"},
+             "geometry":{"type":"Point","coordinates":[2.35,48.85,35.0]}},
+            {"type":"Feature","properties":{"name":7},
+             "geometry":{"type":"MultiPoint","coordinates":[[1.0,2.0],[1.0,100.0]]}},
+            {"type":"Feature","properties":null,
+             "geometry":{"type":"LineString","coordinates":[[0.0,0.0],[1.0,1.0]]}},
+            {"type":"Feature","properties":null,"geometry":null}]}"#;
+        let pois = parse_poi_geojson(json).unwrap();
+        assert_eq!(pois.len(), 2);
+        assert_eq!((pois[0].x, pois[0].y), (2.35, 48.85));
+        assert_eq!(pois[0].name.as_deref(), Some("Cafe"));
+        assert_eq!(pois[0].category.as_deref(), Some("food"));
+        assert_eq!(pois[1].name.as_deref(), Some("7"));
+        assert_eq!(pois[1].category, None);
+
+        // 作为配置中的 pois 直接传入
+        let input: crate::types::PoiInput = serde_json::from_slice(json).unwrap();
+        assert_eq!(input.into_bin(), [2.0, 2.35, 48.85, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_truncate_poi_bin() {
+        let mut exact = vec![2.0, 1.0, 2.0, 3.0, 4.0];
+        assert_eq!(truncate_poi_bin(&mut exact), 0);
+        assert_eq!(exact, [2.0, 1.0, 2.0, 3.0, 4.0]);
+
+        // 声明 3 个、实际 1 个完整坐标对加半个
+        let mut short = vec![3.0, 1.0, 2.0, 3.0];
+        assert_eq!(truncate_poi_bin(&mut short), 5);
+        assert_eq!(short, [1.0, 1.0, 2.0]);
+
+        let mut trailing = vec![1.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(truncate_poi_bin(&mut trailing), 3);
+        assert_eq!(trailing, [1.0, 1.0, 2.0]);
+
+        for count in [f64::NAN, -1.0, 1.5, f64::INFINITY] {
+            let mut bad = vec![count, 1.0, 2.0];
+            assert_eq!(truncate_poi_bin(&mut bad), 2);
+            assert_eq!(bad, [0.0]);
+        }
+        assert_eq!(truncate_poi_bin(&mut Vec::new()), 0);
+    }
+
+    #[test]
+    fn test_drop_invalid_coords() {
+        // 第二条路线去掉 NaN 后只剩一个点，整条去掉
+        let mut routes = vec![2.0, 2.0, 1.0, 1.0, 2.0, 2.0, 2.0, f64::NAN, 0.0, 3.0, 3.0];
+        assert_eq!(
+            drop_invalid_coords(&mut routes, BinLayer::Routes, Crs::Wgs84),
+            1
+        );
+        assert_eq!(routes, [1.0, 2.0, 1.0, 1.0, 2.0, 2.0]);
+
+        // 超出纬度范围的点去掉；内圈不足 3 点时去掉内圈，外圈保留
+        let mut polys = vec![
+            1.0,
+            4.0,
+            1.0,
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+            1.0,
+            0.0,
+            95.0, //
+            3.0,
+            0.2,
+            0.2,
+            0.4,
+            f64::INFINITY,
+            0.4,
+            0.4,
+        ];
+        assert_eq!(
+            drop_invalid_coords(&mut polys, BinLayer::Polygons, Crs::Wgs84),
+            2
+        );
+        assert_eq!(polys, [1.0, 3.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0]);
+
+        // 多数坐标超出经纬度范围：保留给 CRS 校验报告，只去掉非有限值
+        let mut pois = vec![3.0, 1e6, 5e6, 2e6, 5e6, f64::NAN, 0.0];
+        assert_eq!(
+            drop_invalid_coords(&mut pois, BinLayer::Pois, Crs::Wgs84),
+            1
+        );
+        assert_eq!(pois, [2.0, 1e6, 5e6, 2e6, 5e6]);
+
+        let mut clean = vec![1.0, 5.0, 2.0, 0.0, 0.0, 1.0, 1.0];
+        assert_eq!(
+            drop_invalid_coords(&mut clean, BinLayer::Roads, Crs::Wgs84),
+            0
+        );
+        assert_eq!(clean, [1.0, 5.0, 2.0, 0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_polygons_bin_round_trip() {
+        let polys = vec![PolyFeature {
+            exterior: vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0)],
+            interiors: vec![vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0)]],
+        }];
+        let data = polygons_to_bin(&polys);
+        let decoded = polygons_from_bin(&data).unwrap();
+        assert_eq!(decoded[0].exterior, polys[0].exterior);
+        assert_eq!(decoded[0].interiors, polys[0].interiors);
+        assert!(polygons_from_bin(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_local_bin_keeps_precision() {
+        // 墨卡托绝对坐标直接转 f32 会丢失米级精度，相对原点存储则不会
+        let data = [
+            1.0,
+            1.0,
+            2.0,
+            12_958_123.456,
+            4_852_321.789,
+            12_958_124.0,
+            4_852_322.0,
+        ];
+        let ranges = road_bin_coord_ranges(&data);
+        let local = crate::types::LocalBin::new(&data, &ranges, (12_958_000.0, 4_852_000.0));
+        assert_eq!(local.count(), 1);
+        let world = local.to_world(&ranges);
+        for (a, b) in world.iter().zip(&data) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! 各图层绘制统计
+//!
+//! 记录每个图层的要素数、实际绘制数、被跳过数（细节层级、画布外、数据不完整等）、
+//! 处理的顶点数与最终 Path 数，随 `RenderResult` 返回，便于排查"河流为什么没画出来"。
+
+use serde::Serialize;
+use std::cell::RefCell;
+
+/// 单次或累计的绘制计数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct DrawStats {
+    /// 输入要素数
+    pub features: usize,
+    /// 实际绘制的要素数
+    pub drawn: usize,
+    /// 被跳过的要素数
+    pub culled: usize,
+    /// 处理的顶点数（简化前）
+    pub vertices: usize,
+    /// 最终填充或描边的 Path 数
+    pub paths: usize,
+}
+
+impl DrawStats {
+    pub fn add(&mut self, other: DrawStats) {
+        self.features += other.features;
+        self.drawn += other.drawn;
+        self.culled += other.culled;
+        self.vertices += other.vertices;
+        self.paths += other.paths;
+    }
+}
+
+/// 单个图层的绘制统计
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct LayerStats {
+    /// 图层名，如 "water"、"roads"、"extra_layers[0]"
+    pub layer: String,
+    #[serde(flatten)]
+    pub stats: DrawStats,
+}
+
+thread_local! {
+    // 按首次记录的顺序排列；绘制总在调用渲染接口的线程上进行，
+    // 线程局部存储使原生多线程调用方（如 HTTP 服务）的并发渲染互不干扰
+    static LAYERS: RefCell<Vec<LayerStats>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 渲染开始：清空上一次的统计
+pub fn begin() {
+    LAYERS.with(|layers| layers.borrow_mut().clear());
+}
+
+/// 累加图层统计，同名图层合并
+pub fn record(layer: &str, stats: DrawStats) {
+    LAYERS.with(|layers| {
+        let mut layers = layers.borrow_mut();
+        match layers.iter_mut().find(|l| l.layer == layer) {
+            Some(existing) => existing.stats.add(stats),
+            None => layers.push(LayerStats {
+                layer: layer.to_string(),
+                stats,
+            }),
+        }
+    });
+}
+
+/// 取出本次渲染的统计结果
+pub fn take() -> Vec<LayerStats> {
+    LAYERS.with(|layers| std::mem::take(&mut *layers.borrow_mut()))
+}
+
+/// 单独收集 `f` 执行期间记录的统计，不计入本次渲染
+///
+/// 用于缓存图层：首次绘制时保存统计，之后每次合成缓存时用 `record` 重放
+pub fn capture(f: impl FnOnce()) -> Vec<LayerStats> {
+    let outer = take();
+    f();
+    let captured = take();
+    LAYERS.with(|layers| *layers.borrow_mut() = outer);
+    captured
+}
@@ -0,0 +1,117 @@
+//! 渲染错误
+//!
+//! 前端按 `code` 区分错误类型而无需匹配错误文本，`field` 指出出错的配置字段或图层。
+
+use serde::Serialize;
+use std::fmt::Display;
+#[cfg(js_host)]
+use tsify::Tsify;
+
+/// 错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "snake_case")]
+pub enum RenderErrorCode {
+    /// 配置无法解析或取值无效
+    InvalidConfig,
+    /// 几何数据格式错误或坐标参考系不符
+    BadGeometry,
+    /// 画布尺寸为 0 或超出可分配的大小
+    CanvasTooLarge,
+    /// 字体缺失或无法解析
+    FontError,
+    /// PNG 编码失败
+    EncodeError,
+    /// 渲染被调用方取消
+    Cancelled,
+    /// 其他内部错误
+    Internal,
+}
+
+impl RenderErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RenderErrorCode::InvalidConfig => "invalid_config",
+            RenderErrorCode::BadGeometry => "bad_geometry",
+            RenderErrorCode::CanvasTooLarge => "canvas_too_large",
+            RenderErrorCode::FontError => "font_error",
+            RenderErrorCode::EncodeError => "encode_error",
+            RenderErrorCode::Cancelled => "cancelled",
+            RenderErrorCode::Internal => "internal",
+        }
+    }
+}
+
+/// 带错误码的渲染错误，序列化为 `{ code, message, field? }`
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[error("{message}")]
+pub struct RenderError {
+    pub code: RenderErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl RenderError {
+    pub fn new(code: RenderErrorCode, message: impl Into<String>) -> Self {
+        RenderError {
+            code,
+            message: message.into(),
+            field: None,
+        }
+    }
+
+    pub fn invalid_config(message: impl Into<String>) -> Self {
+        Self::new(RenderErrorCode::InvalidConfig, message)
+    }
+
+    pub fn bad_geometry(message: impl Into<String>) -> Self {
+        Self::new(RenderErrorCode::BadGeometry, message)
+    }
+
+    pub fn font(message: impl Into<String>) -> Self {
+        Self::new(RenderErrorCode::FontError, message)
+    }
+
+    pub fn encode(message: impl Into<String>) -> Self {
+        Self::new(RenderErrorCode::EncodeError, message)
+    }
+
+    /// 标注出错的配置字段或图层
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// 配置反序列化失败：从 serde 的错误信息中提取字段名（如 "missing field `theme`"）
+    pub fn config_parse(context: &str, e: impl Display) -> Self {
+        let message = e.to_string();
+        let field = message
+            .split_once("field `")
+            .and_then(|(_, rest)| rest.split_once('`'))
+            .map(|(name, _)| name.to_string());
+        RenderError {
+            field,
+            ..Self::invalid_config(format!("{}: {}", context, message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse_extracts_field() {
+        let e = serde_json::from_str::<crate::types::Center>(r#"{"lat": 1.0}"#).unwrap_err();
+        let err = RenderError::config_parse("Config JSON parse failed", e);
+        assert_eq!(err.code, RenderErrorCode::InvalidConfig);
+        assert_eq!(err.field.as_deref(), Some("lon"));
+
+        let json =
+            serde_json::to_value(RenderError::bad_geometry("x").with_field("water")).unwrap();
+        assert_eq!(json["code"], "bad_geometry");
+        assert_eq!(json["field"], "water");
+    }
+}
@@ -0,0 +1,501 @@
+//! FlatGeobuf 读取
+//!
+//! 通过文件自带的打包 Hilbert R 树索引，只解码与请求范围相交的要素；
+//! 无索引的文件会逐个要素按外包框过滤。支持 EPSG:4326 与 EPSG:3857 数据。
+
+use crate::projection::{project_point, unproject_point};
+use crate::types::{GeoBounds, PolyFeature, Road, RoadType};
+
+const MAGIC: [u8; 3] = *b"fgb";
+const NODE_ITEM_LEN: usize = 40;
+
+// --- 最小 FlatBuffers 表读取器 ---
+
+#[derive(Clone, Copy)]
+struct FbTable<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buf.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn read_u64(buf: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(buf.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+fn read_f64(buf: &[u8], pos: usize) -> Option<f64> {
+    Some(f64::from_le_bytes(buf.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+impl<'a> FbTable<'a> {
+    /// 读取 FlatBuffer 根表
+    fn root(buf: &'a [u8]) -> Option<Self> {
+        let pos = read_u32(buf, 0)? as usize;
+        Some(FbTable { buf, pos })
+    }
+
+    /// 字段在缓冲区中的绝对位置（字段缺省时返回 None）
+    fn field(&self, id: usize) -> Option<usize> {
+        let soffset = read_u32(self.buf, self.pos)? as i32;
+        let vtable = (self.pos as i64 - i64::from(soffset)) as usize;
+        let vtable_len = read_u16(self.buf, vtable)? as usize;
+        let entry = 4 + id * 2;
+        if entry + 2 > vtable_len {
+            return None;
+        }
+        match read_u16(self.buf, vtable + entry)? {
+            0 => None,
+            off => Some(self.pos + off as usize),
+        }
+    }
+
+    fn u8_or(&self, id: usize, default: u8) -> u8 {
+        self.field(id)
+            .and_then(|p| self.buf.get(p).copied())
+            .unwrap_or(default)
+    }
+
+    fn u16_or(&self, id: usize, default: u16) -> u16 {
+        self.field(id)
+            .and_then(|p| read_u16(self.buf, p))
+            .unwrap_or(default)
+    }
+
+    fn u64_or(&self, id: usize, default: u64) -> u64 {
+        self.field(id)
+            .and_then(|p| read_u64(self.buf, p))
+            .unwrap_or(default)
+    }
+
+    fn i32_or(&self, id: usize, default: i32) -> i32 {
+        self.field(id)
+            .and_then(|p| read_u32(self.buf, p))
+            .map(|v| v as i32)
+            .unwrap_or(default)
+    }
+
+    /// 引用字段（表 / 向量 / 字符串）指向的位置
+    fn indirect(&self, id: usize) -> Option<usize> {
+        let p = self.field(id)?;
+        Some(p + read_u32(self.buf, p)? as usize)
+    }
+
+    fn table(&self, id: usize) -> Option<FbTable<'a>> {
+        Some(FbTable {
+            buf: self.buf,
+            pos: self.indirect(id)?,
+        })
+    }
+
+    /// 向量字段：返回 (首元素位置, 元素个数)
+    fn vector(&self, id: usize) -> Option<(usize, usize)> {
+        let p = self.indirect(id)?;
+        Some((p + 4, read_u32(self.buf, p)? as usize))
+    }
+
+    fn bytes(&self, id: usize) -> Option<&'a [u8]> {
+        let (start, len) = self.vector(id)?;
+        self.buf.get(start..start + len)
+    }
+
+    fn string(&self, id: usize) -> Option<&'a str> {
+        std::str::from_utf8(self.bytes(id)?).ok()
+    }
+
+    fn tables(&self, id: usize) -> Vec<FbTable<'a>> {
+        let Some((start, len)) = self.vector(id) else {
+            return vec![];
+        };
+        (0..len)
+            .filter_map(|i| {
+                let p = start + i * 4;
+                Some(FbTable {
+                    buf: self.buf,
+                    pos: p + read_u32(self.buf, p)? as usize,
+                })
+            })
+            .collect()
+    }
+
+    fn u32s(&self, id: usize) -> Vec<u32> {
+        let Some((start, len)) = self.vector(id) else {
+            return vec![];
+        };
+        (0..len)
+            .filter_map(|i| read_u32(self.buf, start + i * 4))
+            .collect()
+    }
+
+    fn f64s(&self, id: usize) -> Vec<f64> {
+        let Some((start, len)) = self.vector(id) else {
+            return vec![];
+        };
+        (0..len)
+            .filter_map(|i| read_f64(self.buf, start + i * 8))
+            .collect()
+    }
+}
+
+// --- 头部与属性 ---
+
+struct Column {
+    name: String,
+    column_type: u8,
+}
+
+struct Header {
+    geometry_type: u8,
+    columns: Vec<Column>,
+    features_count: u64,
+    index_node_size: u16,
+    mercator: bool,
+}
+
+fn parse_header(buf: &[u8]) -> Result<Header, String> {
+    let t = FbTable::root(buf).ok_or("FlatGeobuf: malformed header")?;
+    let columns = t
+        .tables(7)
+        .into_iter()
+        .map(|c| Column {
+            name: c.string(0).unwrap_or_default().to_string(),
+            column_type: c.u8_or(1, 0),
+        })
+        .collect();
+    let crs_code = t.table(10).map(|crs| crs.i32_or(1, 0)).unwrap_or(0);
+    let mercator = match crs_code {
+        0 | 4326 => false,
+        3857 | 900913 => true,
+        code => {
+            return Err(format!(
+                "FlatGeobuf: unsupported CRS EPSG:{}, expected 4326 or 3857",
+                code
+            ));
+        }
+    };
+    Ok(Header {
+        geometry_type: t.u8_or(2, 0),
+        columns,
+        features_count: t.u64_or(8, 0),
+        index_node_size: t.u16_or(9, 16),
+        mercator,
+    })
+}
+
+/// 从属性字节中读取指定列的字符串值
+fn string_property<'a>(props: &'a [u8], columns: &[Column], name: &str) -> Option<&'a str> {
+    let mut pos = 0;
+    while pos + 2 <= props.len() {
+        let col = columns.get(read_u16(props, pos)? as usize)?;
+        pos += 2;
+        let size = match col.column_type {
+            0..=2 => 1,
+            3 | 4 => 2,
+            5 | 6 | 9 => 4,
+            7 | 8 | 10 => 8,
+            _ => {
+                let len = read_u32(props, pos)? as usize;
+                pos += 4;
+                len
+            }
+        };
+        let value = props.get(pos..pos + size)?;
+        if col.name == name && col.column_type == 11 {
+            return std::str::from_utf8(value).ok();
+        }
+        pos += size;
+    }
+    None
+}
+
+// --- 空间索引 ---
+
+/// 打包 Hilbert R 树每一层节点的下标区间（第 0 层为叶子）
+fn level_bounds(num_items: usize, node_size: usize) -> Vec<(usize, usize)> {
+    let mut level_num_nodes = vec![num_items];
+    let mut n = num_items;
+    let mut num_nodes = n;
+    loop {
+        n = n.div_ceil(node_size);
+        num_nodes += n;
+        level_num_nodes.push(n);
+        if n <= 1 {
+            break;
+        }
+    }
+    let mut end = num_nodes;
+    level_num_nodes
+        .into_iter()
+        .map(|size| {
+            end -= size;
+            (end, end + size)
+        })
+        .collect()
+}
+
+fn index_size(num_items: usize, node_size: usize) -> usize {
+    // 叶子层位于数组末尾，其结束下标即节点总数
+    level_bounds(num_items, node_size)[0].1 * NODE_ITEM_LEN
+}
+
+/// 在索引中查找与 bbox 相交的要素，返回其在要素区内的字节偏移
+fn search_index(index: &[u8], num_items: usize, node_size: usize, bbox: [f64; 4]) -> Vec<u64> {
+    let levels = level_bounds(num_items, node_size);
+    let leaf_start = levels[0].0;
+    let mut results = Vec::new();
+    let mut queue = vec![(0usize, levels.len() - 1)];
+    while let Some((node, level)) = queue.pop() {
+        let end = (node + node_size).min(levels[level].1);
+        for pos in node..end {
+            let base = pos * NODE_ITEM_LEN;
+            let (Some(min_x), Some(min_y), Some(max_x), Some(max_y), Some(offset)) = (
+                read_f64(index, base),
+                read_f64(index, base + 8),
+                read_f64(index, base + 16),
+                read_f64(index, base + 24),
+                read_u64(index, base + 32),
+            ) else {
+                continue;
+            };
+            if max_x < bbox[0] || max_y < bbox[1] || min_x > bbox[2] || min_y > bbox[3] {
+                continue;
+            }
+            if pos >= leaf_start {
+                results.push(offset);
+            } else if level > 0 {
+                queue.push((offset as usize, level - 1));
+            }
+        }
+    }
+    results.sort_unstable();
+    results
+}
+
+// --- 几何 ---
+
+/// FlatGeobuf 解析出的线或面（经纬度）
+enum Shape {
+    Line(Vec<(f64, f64)>),
+    Polygon(PolyFeature),
+}
+
+fn split_parts(
+    geom: &FbTable,
+    to_lonlat: &impl Fn(f64, f64) -> (f64, f64),
+) -> Vec<Vec<(f64, f64)>> {
+    let xy = geom.f64s(1);
+    let points: Vec<(f64, f64)> = xy.chunks_exact(2).map(|p| to_lonlat(p[0], p[1])).collect();
+    let ends = geom.u32s(0);
+    if ends.is_empty() {
+        return vec![points];
+    }
+    let mut start = 0;
+    ends.into_iter()
+        .filter_map(|end| {
+            let part = points.get(start..end as usize)?.to_vec();
+            start = end as usize;
+            Some(part)
+        })
+        .collect()
+}
+
+fn collect_shapes(
+    geom: &FbTable,
+    geometry_type: u8,
+    to_lonlat: &impl Fn(f64, f64) -> (f64, f64),
+    out: &mut Vec<Shape>,
+) {
+    let geometry_type = match geometry_type {
+        0 => geom.u8_or(6, 0),
+        t => t,
+    };
+    match geometry_type {
+        2 | 5 => out.extend(split_parts(geom, to_lonlat).into_iter().map(Shape::Line)),
+        3 => {
+            let mut rings = split_parts(geom, to_lonlat).into_iter();
+            if let Some(exterior) = rings.next() {
+                out.push(Shape::Polygon(PolyFeature {
+                    exterior,
+                    interiors: rings.collect(),
+                }));
+            }
+        }
+        6 => {
+            for part in geom.tables(7) {
+                collect_shapes(&part, 3, to_lonlat, out);
+            }
+        }
+        7 => {
+            for part in geom.tables(7) {
+                collect_shapes(&part, 0, to_lonlat, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn intersects(points: &[(f64, f64)], bounds: &GeoBounds) -> bool {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for &(x, y) in points {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    !(max_x < bounds.min_lon
+        || max_y < bounds.min_lat
+        || min_x > bounds.max_lon
+        || min_y > bounds.max_lat)
+}
+
+/// 读取 FlatGeobuf 中的道路与多边形（经纬度坐标）
+///
+/// 提供 `bounds` 时只返回与之相交的要素；道路类型取自 `highway` 字符串列
+pub fn read_flatgeobuf(
+    data: &[u8],
+    bounds: Option<&GeoBounds>,
+) -> Result<(Vec<Road>, Vec<PolyFeature>), String> {
+    if data.len() < 12 || data[..3] != MAGIC {
+        return Err("FlatGeobuf: missing 'fgb' magic bytes".to_string());
+    }
+    let header_len = read_u32(data, 8).ok_or("FlatGeobuf: truncated header")? as usize;
+    let header_buf = data
+        .get(12..12 + header_len)
+        .ok_or("FlatGeobuf: truncated header")?;
+    let header = parse_header(header_buf)?;
+
+    let num_items = header.features_count as usize;
+    let node_size = header.index_node_size as usize;
+    let index_start = 12 + header_len;
+    let has_index = node_size >= 2 && num_items > 0;
+    let features_start = if has_index {
+        index_start + index_size(num_items, node_size)
+    } else {
+        index_start
+    };
+
+    let offsets: Option<Vec<u64>> = match bounds {
+        Some(b) if has_index => {
+            let index = data
+                .get(index_start..features_start)
+                .ok_or("FlatGeobuf: truncated spatial index")?;
+            let bbox = if header.mercator {
+                let (min_x, min_y) = project_point(b.min_lon, b.min_lat);
+                let (max_x, max_y) = project_point(b.max_lon, b.max_lat);
+                [min_x, min_y, max_x, max_y]
+            } else {
+                [b.min_lon, b.min_lat, b.max_lon, b.max_lat]
+            };
+            Some(search_index(index, num_items, node_size, bbox))
+        }
+        _ => None,
+    };
+
+    let to_lonlat = |x: f64, y: f64| {
+        if header.mercator {
+            unproject_point(x, y)
+        } else {
+            (x, y)
+        }
+    };
+
+    let mut roads = Vec::new();
+    let mut polys = Vec::new();
+    let mut decode = |pos: usize| -> Result<usize, String> {
+        let len = read_u32(data, pos).ok_or("FlatGeobuf: truncated feature")? as usize;
+        let buf = data
+            .get(pos + 4..pos + 4 + len)
+            .ok_or("FlatGeobuf: truncated feature")?;
+        let feature = FbTable::root(buf).ok_or("FlatGeobuf: malformed feature")?;
+        let highway = feature
+            .bytes(1)
+            .and_then(|props| string_property(props, &header.columns, "highway"))
+            .unwrap_or("unclassified");
+
+        let mut shapes = Vec::new();
+        if let Some(geom) = feature.table(0) {
+            collect_shapes(&geom, header.geometry_type, &to_lonlat, &mut shapes);
+        }
+        for shape in shapes {
+            match shape {
+                Shape::Line(coords)
+                    if coords.len() >= 2 && bounds.is_none_or(|b| intersects(&coords, b)) =>
+                {
+                    roads.push(Road {
+                        coords,
+                        road_type: RoadType::from_highway(highway),
+                    });
+                }
+                Shape::Polygon(poly)
+                    if poly.exterior.len() >= 3
+                        && bounds.is_none_or(|b| intersects(&poly.exterior, b)) =>
+                {
+                    polys.push(poly);
+                }
+                _ => {}
+            }
+        }
+        Ok(pos + 4 + len)
+    };
+
+    match offsets {
+        Some(offsets) => {
+            for offset in offsets {
+                decode(features_start + offset as usize)?;
+            }
+        }
+        None => {
+            let mut pos = features_start;
+            while pos + 4 <= data.len() {
+                pos = decode(pos)?;
+            }
+        }
+    }
+    Ok((roads, polys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_bounds_and_index_size() {
+        // 5 个要素、节点容量 2：叶子 5 + 3 + 2 + 1 = 11 个节点
+        let levels = level_bounds(5, 2);
+        assert_eq!(levels, vec![(6, 11), (3, 6), (1, 3), (0, 1)]);
+        assert_eq!(index_size(5, 2), 11 * NODE_ITEM_LEN);
+    }
+
+    #[test]
+    fn test_search_index_filters_leaves() {
+        // 2 个要素、节点容量 16：根节点 + 2 个叶子
+        let mut index = Vec::new();
+        let mut node = |b: [f64; 4], offset: u64| {
+            for v in b {
+                index.extend_from_slice(&v.to_le_bytes());
+            }
+            index.extend_from_slice(&offset.to_le_bytes());
+        };
+        node([0.0, 0.0, 10.0, 10.0], 1);
+        node([0.0, 0.0, 1.0, 1.0], 0);
+        node([9.0, 9.0, 10.0, 10.0], 128);
+        assert_eq!(
+            search_index(&index, 2, 16, [8.0, 8.0, 12.0, 12.0]),
+            vec![128]
+        );
+        assert_eq!(
+            search_index(&index, 2, 16, [-1.0, -1.0, 20.0, 20.0]),
+            vec![0, 128]
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_flatgeobuf() {
+        assert!(read_flatgeobuf(b"not a flatgeobuf file", None).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! 字体注册表
+//!
+//! 前端可在运行时通过 `register_font` 注册字体（例如经 HTTP 懒加载），
+//! 配置中的 `font` 字段按名称选用。关闭 `embedded-font` feature 时不内嵌 Roboto，
+//! 核心 WASM 体积更小，但渲染前必须至少注册一个字体。
+
+use fontdue::{Font, FontSettings};
+use std::sync::{Arc, Mutex};
+
+/// 未指定字体名时使用的注册名
+pub const DEFAULT_FONT: &str = "default";
+
+#[cfg(feature = "embedded-font")]
+static ROBOTO_REGULAR: &[u8] = include_bytes!("../fonts/Roboto-Regular.ttf");
+
+static FONTS: Mutex<Vec<(String, Arc<[u8]>)>> = Mutex::new(Vec::new());
+
+/// 注册（或替换）字体，字体数据无法解析时返回错误
+pub fn register(name: &str, data: Vec<u8>) -> Result<(), String> {
+    Font::from_bytes(data.as_slice(), FontSettings::default())
+        .map_err(|e| format!("Failed to load font '{}': {}", name, e))?;
+    let mut fonts = FONTS.lock().map_err(|e| e.to_string())?;
+    let data: Arc<[u8]> = data.into();
+    match fonts.iter_mut().find(|(n, _)| n == name) {
+        Some(entry) => entry.1 = data,
+        None => fonts.push((name.to_string(), data)),
+    }
+    Ok(())
+}
+
+/// 按名称查找字体
+///
+/// 未指定名称时依次尝试注册名 `default` 与内嵌的 Roboto
+pub fn resolve(name: Option<&str>) -> Result<Arc<[u8]>, String> {
+    let lookup = |name: &str| {
+        FONTS.lock().ok().and_then(|fonts| {
+            fonts
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, d)| d.clone())
+        })
+    };
+    match name {
+        Some(name) => lookup(name).ok_or_else(|| format!("Font '{}' is not registered", name)),
+        None => lookup(DEFAULT_FONT)
+            .or_else(embedded)
+            .ok_or_else(|| "No font available: call register_font first".to_string()),
+    }
+}
+
+#[cfg(feature = "embedded-font")]
+fn embedded() -> Option<Arc<[u8]>> {
+    Some(ROBOTO_REGULAR.into())
+}
+
+#[cfg(not(feature = "embedded-font"))]
+fn embedded() -> Option<Arc<[u8]>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_resolve() {
+        assert!(register("broken", vec![1, 2, 3]).is_err());
+        assert!(resolve(Some("missing")).is_err());
+
+        let roboto = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fonts/Roboto-Regular.ttf"
+        ))
+        .unwrap();
+        register("roboto", roboto.clone()).unwrap();
+        assert_eq!(&*resolve(Some("roboto")).unwrap(), roboto.as_slice());
+    }
+}
@@ -0,0 +1,70 @@
+//! GPX 解析
+//!
+//! 轨迹（trk/trkseg/trkpt）与路线（rte/rtept）转换为路线叠加层，航点（wpt）转换为 POI。
+
+use crate::types::{Overlay, POI};
+
+fn lonlat(node: roxmltree::Node) -> Option<(f64, f64)> {
+    Some((
+        node.attribute("lon")?.trim().parse().ok()?,
+        node.attribute("lat")?.trim().parse().ok()?,
+    ))
+}
+
+fn points(parent: roxmltree::Node, name: &str) -> Vec<(f64, f64)> {
+    parent
+        .children()
+        .filter(|c| c.tag_name().name() == name)
+        .filter_map(lonlat)
+        .collect()
+}
+
+/// 解析 GPX 字节
+pub fn parse_gpx(data: &[u8]) -> Result<Overlay, String> {
+    let text = std::str::from_utf8(data).map_err(|e| format!("GPX is not valid UTF-8: {}", e))?;
+    let doc = roxmltree::Document::parse(text).map_err(|e| format!("Invalid GPX: {}", e))?;
+    if doc.root_element().tag_name().name() != "gpx" {
+        return Err("Invalid GPX: expected <gpx> root element".to_string());
+    }
+
+    let mut overlay = Overlay::default();
+    for node in doc.root_element().descendants().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            "wpt" => {
+                if let Some((x, y)) = lonlat(node) {
+                    overlay.pois.push(POI::new(x, y));
+                }
+            }
+            "trkseg" => overlay.routes.push(points(node, "trkpt")),
+            "rte" => overlay.routes.push(points(node, "rtept")),
+            _ => {}
+        }
+    }
+    overlay.routes.retain(|r| r.len() >= 2);
+    Ok(overlay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpx_tracks_routes_and_waypoints() {
+        let gpx = br#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="48.85" lon="2.35"><name>Start</name></wpt>
+  <trk><trkseg>
+    <trkpt lat="48.85" lon="2.35"><ele>35</ele></trkpt>
+    <trkpt lat="48.86" lon="2.36"/>
+  </trkseg><trkseg><trkpt lat="1" lon="1"/></trkseg></trk>
+  <rte><rtept lat="1" lon="2"/><rtept lat="3" lon="4"/></rte>
+</gpx>"#;
+        let overlay = parse_gpx(gpx).unwrap();
+        assert_eq!(overlay.pois.len(), 1);
+        assert_eq!((overlay.pois[0].x, overlay.pois[0].y), (2.35, 48.85));
+        // 单点轨迹段被丢弃
+        assert_eq!(overlay.routes.len(), 2);
+        assert_eq!(overlay.routes[0], vec![(2.35, 48.85), (2.36, 48.86)]);
+        assert_eq!(overlay.routes[1], vec![(2.0, 1.0), (4.0, 3.0)]);
+    }
+}
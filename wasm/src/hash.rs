@@ -0,0 +1,54 @@
+//! 渲染输入的确定性哈希
+//!
+//! 使用 128 位 FNV-1a：实现简单、跨平台与跨版本结果稳定（不同于 std 的随机化 Hasher），
+//! 用作前端 / Service Worker 的 PNG 缓存键，不用于安全场景。
+
+const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+pub struct Fnv128(u128);
+
+impl Fnv128 {
+    pub fn new() -> Self {
+        Fnv128(OFFSET_BASIS)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u128::from(b);
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    /// 写入带长度前缀的字段，避免相邻字段拼接产生歧义
+    pub fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    pub fn finish_hex(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_vector_and_framing() {
+        // FNV-1a 128 的空输入即偏移基数
+        assert_eq!(
+            Fnv128::new().finish_hex(),
+            "6c62272e07bb014262b821756295c58d"
+        );
+
+        let hash = |fields: &[&str]| {
+            let mut h = Fnv128::new();
+            fields.iter().for_each(|f| h.write_field(f.as_bytes()));
+            h.finish_hex()
+        };
+        assert_eq!(hash(&["ab", "c"]), hash(&["ab", "c"]));
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+}
@@ -0,0 +1,74 @@
+//! 图片注册表
+//!
+//! 标志等叠加图片通过 `register_image` 以字节注册（PNG；JPEG 等其他格式取决于构建时
+//! image crate 启用的解码器），注册时解码为预乘 RGBA 并缓存，配置中的 `logo.image` 按名称选用。
+
+use std::sync::{Arc, Mutex};
+use tiny_skia::{ColorU8, Pixmap};
+
+static IMAGES: Mutex<Vec<(String, Arc<Pixmap>)>> = Mutex::new(Vec::new());
+
+/// 解码图片字节
+pub fn decode(data: &[u8]) -> Result<Pixmap, String> {
+    let rgba = image::load_from_memory(data)
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut pixmap = Pixmap::new(width, height).ok_or("Image has no pixels")?;
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(rgba.pixels()) {
+        let [r, g, b, a] = src.0;
+        *dst = ColorU8::from_rgba(r, g, b, a).premultiply();
+    }
+    Ok(pixmap)
+}
+
+/// 注册（或替换）图片，无法解码时返回错误
+pub fn register(name: &str, data: &[u8]) -> Result<(), String> {
+    let pixmap = Arc::new(decode(data).map_err(|e| format!("Image '{}': {}", name, e))?);
+    let mut images = IMAGES.lock().map_err(|e| e.to_string())?;
+    match images.iter_mut().find(|(n, _)| n == name) {
+        Some(entry) => entry.1 = pixmap,
+        None => images.push((name.to_string(), pixmap)),
+    }
+    Ok(())
+}
+
+/// 按名称查找图片
+pub fn resolve(name: &str) -> Result<Arc<Pixmap>, String> {
+    IMAGES
+        .lock()
+        .ok()
+        .and_then(|images| {
+            images
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, p)| p.clone())
+        })
+        .ok_or_else(|| format!("Image '{}' is not registered", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_resolve_image() {
+        assert!(register("broken", &[1, 2, 3]).is_err());
+        assert!(resolve("missing").is_err());
+
+        // 2×1：不透明红色 + 半透明白色
+        let rgba = image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 255, 255, 255, 255, 128]);
+        let mut png = std::io::Cursor::new(Vec::new());
+        rgba.unwrap()
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        register("logo", png.get_ref()).unwrap();
+
+        let logo = resolve("logo").unwrap();
+        assert_eq!((logo.width(), logo.height()), (2, 1));
+        let [red, white] = [logo.pixels()[0], logo.pixels()[1]];
+        assert_eq!((red.red(), red.alpha()), (255, 255));
+        // 预乘后颜色分量不超过 alpha
+        assert_eq!((white.red(), white.alpha()), (128, 128));
+    }
+}
@@ -0,0 +1,332 @@
+//! 要素空间索引
+//!
+//! 解析后的要素是静态的，因此使用 STR（Sort-Tile-Recursive）批量构建的 R-tree：
+//! 一次排序分组即可得到紧凑的节点，之后只读查询。用于视口剔除、
+//! 点击命中测试、最近要素查询以及标注碰撞检测。
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// 轴对齐包围盒
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Aabb {
+    pub fn from_point(x: f64, y: f64) -> Self {
+        Aabb {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    /// 点集的包围盒，空集返回 None
+    pub fn from_points(points: &[(f64, f64)]) -> Option<Self> {
+        let (&(x, y), rest) = points.split_first()?;
+        Some(rest.iter().fold(Self::from_point(x, y), |b, &(x, y)| {
+            b.union(&Self::from_point(x, y))
+        }))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    /// 四周各扩展 `margin`
+    pub fn expand(&self, margin: f64) -> Aabb {
+        Aabb {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    /// 点 (x, y) 到包围盒的距离，在盒内为 0
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.min_x - x).max(x - self.max_x).max(0.0);
+        let dy = (self.min_y - y).max(y - self.max_y).max(0.0);
+        dx.hypot(dy)
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+}
+
+/// 可放入堆中的距离（按 total_cmp 排序）
+#[derive(PartialEq)]
+struct Dist(f64);
+
+impl Eq for Dist {}
+
+impl PartialOrd for Dist {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Dist {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 最近邻搜索队列中的节点或要素（`nodes` / `items` 的下标）
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Entry {
+    Node(usize),
+    Item(usize),
+}
+
+/// 每个节点的最大子节点数
+const NODE_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone)]
+struct Node {
+    bbox: Aabb,
+    /// 叶节点为 `items` 的下标区间，内部节点为 `nodes` 的下标区间
+    start: usize,
+    end: usize,
+    leaf: bool,
+}
+
+/// 静态 R-tree
+#[derive(Debug, Clone)]
+pub struct RTree<T> {
+    items: Vec<(Aabb, T)>,
+    nodes: Vec<Node>,
+}
+
+impl<T> RTree<T> {
+    /// STR 批量构建
+    pub fn new(mut items: Vec<(Aabb, T)>) -> Self {
+        str_sort(&mut items, |(b, _)| *b);
+        let mut level: Vec<Node> = (0..items.len())
+            .step_by(NODE_CAPACITY)
+            .map(|start| {
+                let end = (start + NODE_CAPACITY).min(items.len());
+                Node {
+                    bbox: union_all(items[start..end].iter().map(|(b, _)| b)),
+                    start,
+                    end,
+                    leaf: true,
+                }
+            })
+            .collect();
+
+        // 逐层向上打包，直到只剩根节点；各层依次追加到 nodes，根节点位于末尾
+        let mut nodes = Vec::new();
+        while level.len() > 1 {
+            str_sort(&mut level, |n| n.bbox);
+            let base = nodes.len();
+            let count = level.len();
+            nodes.append(&mut level);
+            level = (0..count)
+                .step_by(NODE_CAPACITY)
+                .map(|i| {
+                    let (start, end) = (base + i, base + (i + NODE_CAPACITY).min(count));
+                    Node {
+                        bbox: union_all(nodes[start..end].iter().map(|n| &n.bbox)),
+                        start,
+                        end,
+                        leaf: false,
+                    }
+                })
+                .collect();
+        }
+        nodes.append(&mut level);
+        RTree { items, nodes }
+    }
+
+    /// 包围盒与 `area` 相交的所有要素
+    pub fn query(&self, area: &Aabb) -> Vec<&T> {
+        let mut out = Vec::new();
+        let Some(root) = self.nodes.len().checked_sub(1) else {
+            return out;
+        };
+        let mut stack = vec![root];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            if !node.bbox.intersects(area) {
+                continue;
+            }
+            if node.leaf {
+                out.extend(
+                    self.items[node.start..node.end]
+                        .iter()
+                        .filter(|(b, _)| b.intersects(area))
+                        .map(|(_, item)| item),
+                );
+            } else {
+                stack.extend(node.start..node.end);
+            }
+        }
+        out
+    }
+
+    /// 包围盒与以 (x, y) 为中心、半径 `radius` 的正方形相交的要素
+    pub fn query_point(&self, x: f64, y: f64, radius: f64) -> Vec<&T> {
+        self.query(&Aabb::from_point(x, y).expand(radius))
+    }
+
+    /// 距 (x, y) 最近的要素及其距离
+    ///
+    /// `distance` 计算点到要素几何的精确距离（不小于到其包围盒的距离），返回 None 的要素被跳过。
+    /// 按包围盒距离由近及远展开节点，比已找到的最近距离更远的节点不再访问
+    pub fn nearest(
+        &self,
+        x: f64,
+        y: f64,
+        mut distance: impl FnMut(&T) -> Option<f64>,
+    ) -> Option<(&T, f64)> {
+        let root = self.nodes.len().checked_sub(1)?;
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((
+            Dist(self.nodes[root].bbox.distance(x, y)),
+            Entry::Node(root),
+        )));
+        let mut best: Option<(&T, f64)> = None;
+        while let Some(Reverse((Dist(bound), entry))) = queue.pop() {
+            if best.is_some_and(|(_, d)| d <= bound) {
+                break;
+            }
+            match entry {
+                Entry::Node(i) => {
+                    let node = &self.nodes[i];
+                    for j in node.start..node.end {
+                        let (bbox, entry) = if node.leaf {
+                            (&self.items[j].0, Entry::Item(j))
+                        } else {
+                            (&self.nodes[j].bbox, Entry::Node(j))
+                        };
+                        queue.push(Reverse((Dist(bbox.distance(x, y)), entry)));
+                    }
+                }
+                Entry::Item(j) => {
+                    let item = &self.items[j].1;
+                    if let Some(d) = distance(item)
+                        && best.is_none_or(|(_, b)| d < b)
+                    {
+                        best = Some((item, d));
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+fn union_all<'a>(mut boxes: impl Iterator<Item = &'a Aabb>) -> Aabb {
+    let first = *boxes.next().expect("node must not be empty");
+    boxes.fold(first, |acc, b| acc.union(b))
+}
+
+/// STR 排序：按中心 x 分为 √(n / M) 个竖条，每条内部再按中心 y 排序
+fn str_sort<E>(entries: &mut [E], bbox: impl Fn(&E) -> Aabb) {
+    let leaf_count = entries.len().div_ceil(NODE_CAPACITY);
+    let slices = (leaf_count as f64).sqrt().ceil().max(1.0) as usize;
+    let slice_len = NODE_CAPACITY * leaf_count.div_ceil(slices);
+
+    let center_x = |e: &E| bbox(e).center().0;
+    let center_y = |e: &E| bbox(e).center().1;
+    entries.sort_by(|a, b| center_x(a).total_cmp(&center_x(b)));
+    for strip in entries.chunks_mut(slice_len.max(1)) {
+        strip.sort_by(|a, b| center_y(a).total_cmp(&center_y(b)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_matches_linear_scan() {
+        let items: Vec<(Aabb, usize)> = (0..1000)
+            .map(|i| {
+                let (x, y) = ((i % 40) as f64 * 10.0, (i / 40) as f64 * 10.0);
+                (Aabb::from_point(x, y).expand(3.0), i)
+            })
+            .collect();
+        let tree = RTree::new(items.clone());
+
+        let area = Aabb {
+            min_x: 52.0,
+            min_y: 95.0,
+            max_x: 131.0,
+            max_y: 160.0,
+        };
+        let mut found: Vec<usize> = tree.query(&area).into_iter().copied().collect();
+        found.sort();
+        let expected: Vec<usize> = items
+            .iter()
+            .filter(|(b, _)| b.intersects(&area))
+            .map(|&(_, i)| i)
+            .collect();
+        assert_eq!(found, expected);
+
+        assert_eq!(tree.query_point(200.0, 100.0, 1.0), vec![&420]);
+        assert!(RTree::<usize>::new(vec![]).query(&area).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_matches_linear_scan() {
+        // 伪随机线段端点构成的包围盒，精确距离取到盒中心的距离
+        let mut seed = 7u32;
+        let mut rand = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) as f64 / (1u32 << 24) as f64 * 1000.0
+        };
+        let items: Vec<(Aabb, (f64, f64))> = (0..2000)
+            .map(|_| {
+                let (x, y) = (rand(), rand());
+                (Aabb::from_point(x, y).expand(rand() / 100.0), (x, y))
+            })
+            .collect();
+        let tree = RTree::new(items.clone());
+        let dist = |x: f64, y: f64, c: &(f64, f64)| (c.0 - x).hypot(c.1 - y);
+
+        for (x, y) in [(500.0, 500.0), (-50.0, 20.0), (999.0, 1.0), (123.4, 876.5)] {
+            let (found, d) = tree.nearest(x, y, |c| Some(dist(x, y, c))).unwrap();
+            let expected = items
+                .iter()
+                .map(|(_, c)| dist(x, y, c))
+                .min_by(f64::total_cmp)
+                .unwrap();
+            assert_eq!(d, expected);
+            assert_eq!(dist(x, y, found), expected);
+        }
+
+        // 被跳过的要素不参与比较
+        let (found, _) = tree
+            .nearest(0.0, 0.0, |c| (c.0 > 500.0).then(|| dist(0.0, 0.0, c)))
+            .unwrap();
+        assert!(found.0 > 500.0);
+        assert!(
+            RTree::<usize>::new(vec![])
+                .nearest(0.0, 0.0, |_| Some(0.0))
+                .is_none()
+        );
+    }
+}
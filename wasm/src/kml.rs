@@ -0,0 +1,216 @@
+//! KML / KMZ 导入
+//!
+//! 将地标（Point）转换为 POI，将路径（LineString、gx:Track）转换为路线叠加层，
+//! 主要面向 Google My Maps 导出的文件。KMZ 为包含 KML 的 ZIP 压缩包。
+
+use crate::types::{Overlay, POI};
+
+/// 解析 `<coordinates>` 文本：以空白分隔的 "lon,lat[,alt]" 元组
+fn parse_coordinates(text: &str) -> Vec<(f64, f64)> {
+    text.split_whitespace()
+        .filter_map(|tuple| {
+            let mut parts = tuple.split(',');
+            let lon = parts.next()?.trim().parse().ok()?;
+            let lat = parts.next()?.trim().parse().ok()?;
+            Some((lon, lat))
+        })
+        .collect()
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|c| c.tag_name().name() == name)
+        .and_then(|c| c.text())
+}
+
+/// 解析 KML 文本
+pub fn parse_kml(xml: &str) -> Result<Overlay, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid KML: {}", e))?;
+    if doc.root_element().tag_name().name() != "kml" {
+        return Err("Invalid KML: expected <kml> root element".to_string());
+    }
+
+    let mut overlay = Overlay::default();
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            "Point" => {
+                if let Some(&(x, y)) = child_text(node, "coordinates")
+                    .map(parse_coordinates)
+                    .as_deref()
+                    .and_then(<[_]>::first)
+                {
+                    overlay.pois.push(POI::new(x, y));
+                }
+            }
+            "LineString" => {
+                let coords = child_text(node, "coordinates")
+                    .map(parse_coordinates)
+                    .unwrap_or_default();
+                if coords.len() >= 2 {
+                    overlay.routes.push(coords);
+                }
+            }
+            // gx:Track 使用 <gx:coord>lon lat alt</gx:coord>
+            "Track" => {
+                let coords: Vec<(f64, f64)> = node
+                    .children()
+                    .filter(|c| c.tag_name().name() == "coord")
+                    .filter_map(|c| {
+                        let mut parts = c.text()?.split_whitespace();
+                        Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+                    })
+                    .collect();
+                if coords.len() >= 2 {
+                    overlay.routes.push(coords);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(overlay)
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<usize> {
+    Some(u16::from_le_bytes(buf.get(pos..pos + 2)?.try_into().ok()?) as usize)
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<usize> {
+    Some(u32::from_le_bytes(buf.get(pos..pos + 4)?.try_into().ok()?) as usize)
+}
+
+/// 从 ZIP 中取出主 KML 文件（优先 doc.kml，否则取第一个 .kml）
+fn extract_kml_from_zip(data: &[u8]) -> Result<Vec<u8>, String> {
+    const EOCD_SIG: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+    const CDIR_SIG: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+
+    let eocd = data
+        .windows(4)
+        .rposition(|w| w == EOCD_SIG)
+        .ok_or("KMZ: end of central directory not found")?;
+    let entry_count = read_u16(data, eocd + 10).ok_or("KMZ: truncated archive")?;
+    let mut pos = read_u32(data, eocd + 16).ok_or("KMZ: truncated archive")?;
+
+    let mut chosen: Option<(usize, usize, usize)> = None;
+    for _ in 0..entry_count {
+        if data.get(pos..pos + 4) != Some(&CDIR_SIG[..]) {
+            return Err("KMZ: corrupt central directory".to_string());
+        }
+        let (Some(method), Some(comp_size), Some(name_len), Some(extra_len), Some(comment_len)) = (
+            read_u16(data, pos + 10),
+            read_u32(data, pos + 20),
+            read_u16(data, pos + 28),
+            read_u16(data, pos + 30),
+            read_u16(data, pos + 32),
+        ) else {
+            return Err("KMZ: truncated central directory".to_string());
+        };
+        let local_offset = read_u32(data, pos + 42).ok_or("KMZ: truncated central directory")?;
+        let name = data
+            .get(pos + 46..pos + 46 + name_len)
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default()
+            .to_lowercase();
+        if name.ends_with(".kml") && (chosen.is_none() || name == "doc.kml") {
+            chosen = Some((method, comp_size, local_offset));
+        }
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+
+    let (method, comp_size, local) = chosen.ok_or("KMZ: archive contains no .kml file")?;
+    let (Some(name_len), Some(extra_len)) =
+        (read_u16(data, local + 26), read_u16(data, local + 28))
+    else {
+        return Err("KMZ: truncated local header".to_string());
+    };
+    let start = local + 30 + name_len + extra_len;
+    let compressed = data
+        .get(start..start + comp_size)
+        .ok_or("KMZ: truncated file data")?;
+    match method {
+        0 => Ok(compressed.to_vec()),
+        8 => miniz_oxide::inflate::decompress_to_vec(compressed)
+            .map_err(|e| format!("KMZ: failed to inflate doc.kml: {:?}", e)),
+        m => Err(format!("KMZ: unsupported compression method {}", m)),
+    }
+}
+
+/// 解析 KML 或 KMZ 字节（按 ZIP 魔数自动识别）
+pub fn parse_kml_bytes(data: &[u8]) -> Result<Overlay, String> {
+    let owned;
+    let kml = if data.starts_with(b"PK\x03\x04") {
+        owned = extract_kml_from_zip(data)?;
+        &owned[..]
+    } else {
+        data
+    };
+    let text = std::str::from_utf8(kml).map_err(|e| format!("KML is not valid UTF-8: {}", e))?;
+    parse_kml(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">
+  <Document>
+    <Placemark><name>Cafe</name><Point><coordinates>116.39,39.91,0</coordinates></Point></Placemark>
+    <Placemark>
+      <LineString><coordinates>
+        116.30,39.90,0 116.31,39.91,0
+        116.32,39.92,0
+      </coordinates></LineString>
+    </Placemark>
+    <Placemark><gx:Track><gx:coord>1 2 0</gx:coord><gx:coord>3 4 0</gx:coord></gx:Track></Placemark>
+  </Document>
+</kml>"#;
+
+    #[test]
+    fn test_parse_kml_points_and_paths() {
+        let overlay = parse_kml(SAMPLE).unwrap();
+        assert_eq!(overlay.pois.len(), 1);
+        assert_eq!((overlay.pois[0].x, overlay.pois[0].y), (116.39, 39.91));
+        assert_eq!(overlay.routes.len(), 2);
+        assert_eq!(overlay.routes[0].len(), 3);
+        assert_eq!(overlay.routes[1], vec![(1.0, 2.0), (3.0, 4.0)]);
+    }
+
+    #[test]
+    fn test_parse_kmz_stored_entry() {
+        // 手工构造仅含一个未压缩 doc.kml 的 ZIP
+        let name = b"doc.kml";
+        let body = SAMPLE.as_bytes();
+        let mut zip = Vec::new();
+        zip.extend_from_slice(b"PK\x03\x04");
+        zip.extend_from_slice(&[0; 14]); // version, flags, method=0, time, date, crc
+        zip.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(body);
+
+        let cdir = zip.len();
+        zip.extend_from_slice(b"PK\x01\x02");
+        zip.extend_from_slice(&[0; 16]); // versions, flags, method=0, time, date, crc
+        zip.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0; 12]); // extra, comment, disk, attrs
+        zip.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+        zip.extend_from_slice(name);
+        let cdir_len = zip.len() - cdir;
+
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&1u16.to_le_bytes());
+        zip.extend_from_slice(&(cdir_len as u32).to_le_bytes());
+        zip.extend_from_slice(&(cdir as u32).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+
+        let overlay = parse_kml_bytes(&zip).unwrap();
+        assert_eq!(overlay.pois.len(), 1);
+        assert_eq!(overlay.routes.len(), 2);
+    }
+}
@@ -1,497 +1,2449 @@
-mod data_processor;
-mod projection;
-mod renderer;
-mod types;
+// JS 绑定仅在 JS 宿主上编译；其余目标上只有渲染核心对外可见，
+// 仅被 JS 导出使用的解析器与辅助函数保留编译以便运行单元测试
+#![cfg_attr(not(js_host), allow(dead_code))]
+
+mod capabilities;
+mod collage;
+#[cfg(feature = "component")]
+mod component;
+mod compression;
+mod container;
+mod cull;
+pub mod data_processor;
+pub mod draw_stats;
+pub mod error;
+mod fgb;
+pub mod fonts;
+mod gpx;
+mod hash;
+mod images;
+mod index;
+mod kml;
+mod memory;
+mod merge;
+mod mvt;
+mod paper;
+mod par;
+mod profile;
+pub mod projection;
+mod quantized;
+pub mod renderer;
+mod simd;
+mod simplify;
+mod solar;
+mod stream;
+mod style;
+pub mod types;
 mod utils;
+mod validate;
+pub mod warnings;
 
-use crate::utils::{log, time, time_end};
-use data_processor::{parse_polygons, parse_roads};
-use projection::{calculate_bounds, project_points_mut};
+/// 原生与 WASI 目标的日志设置（JS 宿主上使用 `set_log_handler` / `set_log_level` 导出）
+#[cfg(not(js_host))]
+pub mod logging {
+    pub use crate::utils::{LogLevel, LogSink, set_log_level, set_log_sink};
+}
+
+/// JS 端在渲染前调用 `await initThreadPool(navigator.hardwareConcurrency)` 创建 Web Worker 线程池
+#[cfg(all(js_host, feature = "threads"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+use crate::utils::{debug, log, warn};
+use data_processor::{BinLayer, parse_polygons, parse_roads};
+use error::{RenderError, RenderErrorCode};
+use profile::{time, time_end};
+use projection::{Projector, project_points_mut, resolve_view};
 use renderer::MapRenderer;
 use serde::Deserialize;
-use types::{RenderRequest, RenderResult};
+use std::ops::RangeBounds;
+use types::{RenderConfig, RenderRequest, RenderResult};
+#[cfg(js_host)]
 use wasm_bindgen::prelude::*;
 
+/// 旧版 JSON 请求：渲染配置 + GeoJSON 字符串
 #[derive(Deserialize)]
 struct JsonRenderRequest {
-    center: types::Center,
-    radius: f64,
+    #[serde(flatten)]
+    config: RenderConfig,
     roads: String,
     water: String,
     parks: String,
-    pois: Option<String>, // POI 数据（JSON 字符串格式）
-    theme: types::Theme,
-    width: u32,
-    height: u32,
-    display_city: String,
-    display_country: String,
+    // 旧版为 JSON 字符串（已不再解析），也接受与配置相同的 POI 格式
+    #[serde(default)]
+    pois: Option<serde_json::Value>,
 }
 
-// 嵌入 Roboto 字体（需要将字体文件放到 fonts/ 目录）
-const ROBOTO_REGULAR: &[u8] = include_bytes!("../fonts/Roboto-Regular.ttf");
+// 测地线圆裁剪的多边形边数
+const CIRCLE_CLIP_SEGMENTS: usize = 256;
+
+// 输出 PNG 的 DPI 元数据
+const PNG_DPI: u32 = 300;
 
 /// 初始化 panic hook
+#[cfg(js_host)]
 #[wasm_bindgen(start)]
 pub fn init_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-/// 主渲染函数 (JSON 版本)
+/// 注册日志回调 `(level, message) => void`，传入 `undefined` 恢复输出到 console
+///
+/// 便于 Node / 服务端等宿主收集诊断信息
+#[cfg(js_host)]
 #[wasm_bindgen]
+pub fn set_log_handler(
+    #[wasm_bindgen(
+        unchecked_param_type = "((level: string, message: string) => void) | undefined"
+    )]
+    handler: Option<js_sys::Function>,
+) {
+    utils::set_log_handler(handler);
+}
+
+/// 设置日志级别："debug" / "info" / "warn" / "error" / "off"
+///
+/// 调试构建默认 debug，发布构建默认 warn
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    let level = serde_json::from_value(serde_json::Value::String(level.to_string()))
+        .map_err(|e| JsValue::from_str(&format!("Invalid log level: {}", e)))?;
+    utils::set_log_level(level);
+    Ok(())
+}
+
+/// 主渲染函数 (JSON 版本)
+#[cfg_attr(js_host, wasm_bindgen)]
 pub fn render_map(request_json: &str) -> RenderResult {
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
     // 1. 解析请求 (使用旧版平铺结构)
     let json_req: JsonRenderRequest = match serde_json::from_str(request_json) {
         Ok(req) => req,
-        Err(e) => return RenderResult::error(format!("Failed to parse JSON request: {}", e)),
+        Err(e) => return RenderError::config_parse("Failed to parse JSON request", e).into(),
     };
 
     // 2. 将 JSON 字符串解析为结构化数据 (由于 JSON 接口仍传递字符串)
     time("render_map: parse_roads");
     let roads = match parse_roads(&json_req.roads) {
         Ok(r) => r,
-        Err(e) => return RenderResult::error(format!("Failed to parse roads: {}", e)),
+        Err(e) => {
+            let message = format!("Failed to parse roads: {}", e);
+            return RenderError::bad_geometry(message)
+                .with_field("roads")
+                .into();
+        }
     };
     time_end("render_map: parse_roads");
 
     time("render_map: parse_water");
     let water = match parse_polygons(&json_req.water) {
         Ok(w) => w,
-        Err(e) => return RenderResult::error(format!("Failed to parse water: {}", e)),
+        Err(e) => {
+            let message = format!("Failed to parse water: {}", e);
+            return RenderError::bad_geometry(message)
+                .with_field("water")
+                .into();
+        }
     };
     time_end("render_map: parse_water");
 
     time("render_map: parse_parks");
     let parks = match parse_polygons(&json_req.parks) {
         Ok(p) => p,
-        Err(e) => return RenderResult::error(format!("Failed to parse parks: {}", e)),
-    };
-    time_end("render_map: parse_parks");
-    time("render_map: parse_pois");
-    let pois = if let Some(pois_json) = &json_req.pois {
-        match parse_pois_json(pois_json) {
-            Ok(p) => p,
-            Err(e) => {
-                log(&format!("Warning: Failed to parse POIs: {}", e));
-                vec![] // Fallback to empty POI list
-            }
+        Err(e) => {
+            let message = format!("Failed to parse parks: {}", e);
+            return RenderError::bad_geometry(message)
+                .with_field("parks")
+                .into();
         }
-    } else {
-        vec![]
     };
-    time_end("render_map: parse_pois");
+    time_end("render_map: parse_parks");
+    let mut config = json_req.config;
+    match json_req.pois {
+        Some(serde_json::Value::String(_)) | None => {}
+        Some(value) => match serde_json::from_value(value) {
+            Ok(pois) => config.pois = Some(pois),
+            Err(e) => warn(&format!("Failed to parse POIs: {}", e)),
+        },
+    }
 
     let request = RenderRequest {
-        center: json_req.center,
-        radius: json_req.radius,
+        config,
         roads,
         water,
         parks,
-        pois,
-        theme: json_req.theme,
-        width: json_req.width,
-        height: json_req.height,
-        display_city: json_req.display_city,
-        display_country: json_req.display_country,
-        text_position: None, // Default to None which maps to Top/Default in internal logic usually
         needs_projection: false,
-        // Backwards-compatible defaults for dynamic road width scaling
-        selected_size_height: 3508,
-        frontend_scale: 2.0,
-        road_width_boost: 1.0,
     };
-
     render_map_internal(request)
 }
 
-#[derive(Deserialize)]
-pub struct BinaryRenderConfig {
-    pub center: types::Center,
-    pub radius: f64,
-    pub theme: types::Theme,
-    pub width: u32,
-    pub height: u32,
-    pub display_city: String,
-    pub display_country: String,
-    pub text_position: Option<types::TextPosition>,
-    // dynamic scaling params (optional)
-    #[serde(default = "types::default_selected_size_height")]
-    pub selected_size_height: u32,
-    #[serde(default = "types::default_frontend_scale")]
-    pub frontend_scale: f32,
-    #[serde(default = "types::default_road_width_boost")]
-    pub road_width_boost: f32,
-    // POI 数据（可选）
-    #[serde(default)]
-    pub pois: Option<Vec<f64>>, // [poi_count, x1, y1, x2, y2, ...]
+/// 渲染配置构建器
+///
+/// 替代手写配置 JSON：`new(lat, lon).radius(..).theme(..).size(..)`，
+/// 未设置的字段取与 JSON 配置相同的缺省值，`build` / `to_json` 时统一校验
+#[cfg_attr(js_host, wasm_bindgen)]
+pub struct RenderRequestBuilder {
+    config: RenderConfig,
+    has_theme: bool,
+}
+
+impl RenderRequestBuilder {
+    pub fn theme(mut self, theme: types::Theme) -> Self {
+        self.config.theme = theme;
+        self.has_theme = true;
+        self
+    }
+
+    pub fn text_position(mut self, position: types::TextPosition) -> Self {
+        self.config.text_position = Some(position);
+        self
+    }
+
+    pub fn png_compression(mut self, compression: types::PngCompression) -> Self {
+        self.config.png_compression = compression;
+        self
+    }
+
+    /// 校验并生成配置（规则同 `validate_request`，返回第一个问题）
+    pub fn build(self) -> Result<RenderConfig, String> {
+        if !self.has_theme {
+            return Err("Theme is required".to_string());
+        }
+        match validate::validate_config(&self.config).into_iter().next() {
+            Some(e) => Err(e.to_string()),
+            None => Ok(self.config),
+        }
+    }
+}
+
+#[cfg_attr(js_host, wasm_bindgen)]
+impl RenderRequestBuilder {
+    #[cfg_attr(js_host, wasm_bindgen(constructor))]
+    pub fn new(lat: f64, lon: f64) -> RenderRequestBuilder {
+        // 仅含必填字段的最小配置，其余字段由 serde 缺省值填充
+        let minimal = serde_json::json!({
+            "center": { "lat": lat, "lon": lon },
+            "radius": 0.0,
+            "theme": types::Theme::default(),
+            "width": 0,
+            "height": 0,
+            "display_city": "",
+            "display_country": "",
+            "text_position": null,
+        });
+        let config = serde_json::from_value(minimal).expect("minimal config must deserialize");
+        RenderRequestBuilder {
+            config,
+            has_theme: false,
+        }
+    }
+
+    /// 半径（米）
+    pub fn radius(mut self, radius: f64) -> RenderRequestBuilder {
+        self.config.radius = radius;
+        self
+    }
+
+    /// 输出尺寸（像素）
+    pub fn size(mut self, width: u32, height: u32) -> RenderRequestBuilder {
+        self.config.width = width;
+        self.config.height = height;
+        self
+    }
+
+    /// 海报上的城市与国家文字
+    pub fn text(mut self, city: String, country: String) -> RenderRequestBuilder {
+        self.config.display_city = city;
+        self.config.display_country = country;
+        self
+    }
+
+    /// 主题（JS 对象，字段与配置中的 theme 相同）
+    #[cfg(js_host)]
+    #[wasm_bindgen(js_name = theme)]
+    pub fn theme_js(self, theme: JsValue) -> Result<RenderRequestBuilder, JsValue> {
+        let theme = serde_wasm_bindgen::from_value(theme)
+            .map_err(|e| JsValue::from_str(&format!("Invalid theme: {}", e)))?;
+        Ok(self.theme(theme))
+    }
+
+    /// 文字位置："top" / "center" / "bottom"
+    #[cfg(js_host)]
+    #[wasm_bindgen(js_name = text_position)]
+    pub fn text_position_js(self, position: String) -> Result<RenderRequestBuilder, JsValue> {
+        let position = serde_json::from_value(serde_json::Value::String(position))
+            .map_err(|e| JsValue::from_str(&format!("Invalid text position: {}", e)))?;
+        Ok(self.text_position(position))
+    }
+
+    /// PNG 压缩级别："fast" / "default" / "best"
+    #[cfg(js_host)]
+    #[wasm_bindgen(js_name = png_compression)]
+    pub fn png_compression_js(self, compression: String) -> Result<RenderRequestBuilder, JsValue> {
+        let compression = serde_json::from_value(serde_json::Value::String(compression))
+            .map_err(|e| JsValue::from_str(&format!("Invalid PNG compression: {}", e)))?;
+        Ok(self.png_compression(compression))
+    }
+
+    /// 画布四周的内边距百分比
+    pub fn padding_pct(mut self, padding_pct: f64) -> RenderRequestBuilder {
+        self.config.padding_pct = padding_pct;
+        self
+    }
+
+    /// 地图四边相同的内嵌边距百分比，边距内填充背景色
+    pub fn margin_pct(mut self, margin_pct: f64) -> RenderRequestBuilder {
+        self.config.margin_pct = Some(types::MarginPct::All(margin_pct));
+        self
+    }
+
+    /// 道路宽度增强倍率
+    pub fn road_width_boost(mut self, boost: f32) -> RenderRequestBuilder {
+        self.config.road_width_boost = boost;
+        self
+    }
+
+    /// 几何简化容差（输出像素）
+    pub fn simplify_tolerance_px(mut self, tolerance_px: f32) -> RenderRequestBuilder {
+        self.config.simplify_tolerance_px = tolerance_px;
+        self
+    }
+
+    /// 细节层级阈值（输出像素，0 表示绘制全部细节）
+    pub fn lod_min_px(mut self, min_px: f32) -> RenderRequestBuilder {
+        self.config.lod_min_px = min_px;
+        self
+    }
+
+    pub fn anti_alias(mut self, anti_alias: bool) -> RenderRequestBuilder {
+        self.config.anti_alias = anti_alias;
+        self
+    }
+
+    /// 是否裁剪为测地线圆
+    pub fn clip_circle(mut self, clip_circle: bool) -> RenderRequestBuilder {
+        self.config.clip_circle = clip_circle;
+        self
+    }
+
+    /// 已注册字体的名称（见 `register_font`）
+    pub fn font(mut self, name: String) -> RenderRequestBuilder {
+        self.config.font = Some(name);
+        self
+    }
+
+    /// 校验并生成配置 JSON，可直接传给 `render_map_binary` 等接口
+    #[cfg(js_host)]
+    pub fn to_json(self) -> Result<String, JsValue> {
+        let config = self
+            .build()
+            .map_err(|e| JsValue::from_str(&format!("Invalid render config: {}", e)))?;
+        serde_json::to_string(&config)
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+    }
 }
 
 /// 主渲染函数 (二进制直读版本)
+///
+/// 道路分片、水体与公园均可传入 Float64Array 或 Float32Array（布局相同）
+#[cfg(js_host)]
 #[wasm_bindgen]
 pub fn render_map_binary(
     roads_shards: JsValue,
-    water_bin: &[f64],
-    parks_bin: &[f64],
+    water_bin: JsValue,
+    parks_bin: JsValue,
     config_json: &str,
 ) -> RenderResult {
-    render_map_binary_internal(
-        roads_shards,
-        water_bin,
-        parks_bin,
-        config_json,
-        ROBOTO_REGULAR,
-    )
+    let (layers, roads) = binary_layers(roads_shards, water_bin, parks_bin);
+    render_map_binary_internal(layers, roads, ConfigSource::Json(config_json), None)
+}
+
+/// 主渲染函数 (二进制直读 + JS 对象配置版本)
+///
+/// 配置直接以 JS 对象传入（经 serde_wasm_bindgen 反序列化），省去 JSON.stringify / 解析往返
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn render_map_binary_js(
+    roads_shards: JsValue,
+    water_bin: JsValue,
+    parks_bin: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "RenderConfig")] config: JsValue,
+) -> RenderResult {
+    let (layers, roads) = binary_layers(roads_shards, water_bin, parks_bin);
+    render_map_binary_internal(layers, roads, ConfigSource::Js(config), None)
 }
 
 /// 主渲染函数 (带自定义字体版本)
+#[cfg(js_host)]
 #[wasm_bindgen]
 pub fn render_map_binary_with_font(
     roads_shards: JsValue,
-    water_bin: &[f64],
-    parks_bin: &[f64],
+    water_bin: JsValue,
+    parks_bin: JsValue,
     config_json: &str,
     font_data: &[u8],
 ) -> RenderResult {
-    render_map_binary_internal(roads_shards, water_bin, parks_bin, config_json, font_data)
+    let (layers, roads) = binary_layers(roads_shards, water_bin, parks_bin);
+    let config = ConfigSource::Json(config_json);
+    render_map_binary_internal(layers, roads, config, Some(font_data))
 }
 
-fn render_map_binary_internal(
+/// 同一份几何按多个配置（通常仅主题不同）依次渲染，用于主题画廊预览
+///
+/// `configs_json` 为配置数组；几何只上传、投影一次，按第一个配置的中心点与 CRS 处理，
+/// 返回与配置一一对应的渲染结果
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn render_map_variants(
     roads_shards: JsValue,
-    water_bin: &[f64],
-    parks_bin: &[f64],
+    water_bin: JsValue,
+    parks_bin: JsValue,
+    configs_json: &str,
+) -> Result<Vec<RenderResult>, JsValue> {
+    let configs: Vec<RenderConfig> = serde_json::from_str(configs_json).map_err(|e| {
+        JsValue::from_str(&RenderError::config_parse("Configs JSON parse failed", e).to_string())
+    })?;
+    let (layers, roads) = binary_layers(roads_shards, water_bin, parks_bin);
+    render_variants(layers, roads, configs).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn render_variants(
+    layers: container::ContainerLayers,
+    road_shards: RoadShards,
+    configs: Vec<RenderConfig>,
+) -> Result<Vec<RenderResult>, RenderError> {
+    let mut configs = configs.into_iter();
+    let Some(first) = configs.next() else {
+        return Ok(Vec::new());
+    };
+    let mut session = PosterSession::prepare(layers, road_shards, first)?;
+    let mut results = vec![session.render()];
+    for config in configs {
+        results.push(match session.replace_config(config) {
+            Ok(()) => session.render(),
+            Err(e) => RenderError::invalid_config(e).into(),
+        });
+    }
+    Ok(results)
+}
+
+/// 主渲染函数 (多图层容器版本)
+///
+/// 所有图层打包在单个 Float64Array / Float32Array 容器中（见 `pack_layers`），
+/// 容器中的 pois / routes 分段优先于配置里的同名字段
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn render_map_container(container: JsValue, config_json: &str) -> RenderResult {
+    render_map_container_inner(container, config_json, None)
+}
+
+/// 主渲染函数 (多图层容器 + 自定义字体版本)
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn render_map_container_with_font(
+    container: JsValue,
     config_json: &str,
     font_data: &[u8],
 ) -> RenderResult {
-    let config: BinaryRenderConfig = match serde_json::from_str(config_json) {
+    render_map_container_inner(container, config_json, Some(font_data))
+}
+
+#[cfg(js_host)]
+fn render_map_container_inner(
+    container: JsValue,
+    config_json: &str,
+    font_data: Option<&[u8]>,
+) -> RenderResult {
+    let Some(data) = typed_array_to_vec(&container) else {
+        let message = "Container must be a Float64Array or Float32Array";
+        return RenderError::bad_geometry(message)
+            .with_field("container")
+            .into();
+    };
+    match container::parse_container(&data) {
+        Ok(mut layers) => {
+            let roads = RoadShards::Owned(std::mem::take(&mut layers.roads));
+            render_map_binary_internal(layers, roads, ConfigSource::Json(config_json), font_data)
+        }
+        Err(e) => RenderError::bad_geometry(e).with_field("container").into(),
+    }
+}
+
+/// 将各图层的扁平数据打包为多图层容器
+///
+/// `layers` 为对象，键取 "roads"、"water"、"parks"、"pois"、"routes"、"buildings"；
+/// "roads" 可以是单个数组或分片数组
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn pack_layers(layers: JsValue) -> Result<js_sys::Float64Array, JsValue> {
+    let object = layers
+        .dyn_into::<js_sys::Object>()
+        .map_err(|_| JsValue::from_str("Error packing layers: expected an object"))?;
+
+    let mut sections = Vec::new();
+    for entry in js_sys::Object::entries(&object).iter() {
+        let entry = js_sys::Array::from(&entry);
+        let name = entry.get(0).as_string().unwrap_or_default();
+        let tag = container::SectionTag::from_name(&name).ok_or_else(|| {
+            JsValue::from_str(&format!("Error packing layers: unknown layer '{}'", name))
+        })?;
+        let value = entry.get(1);
+        let shards = if tag == container::SectionTag::Roads {
+            collect_road_shards(&value)
+        } else {
+            typed_array_to_vec(&value).into_iter().collect()
+        };
+        sections.extend(shards.into_iter().map(|data| (tag, data)));
+    }
+
+    let refs: Vec<_> = sections.iter().map(|(t, d)| (*t, d.as_slice())).collect();
+    Ok(js_sys::Float64Array::from(
+        container::build_container(&refs).as_slice(),
+    ))
+}
+
+/// 将旧的三参数输入转换为容器图层
+///
+/// 道路分片保留为 JS 引用，渲染时逐片拷入复用的缓冲区
+#[cfg(js_host)]
+fn binary_layers(
+    roads_shards: JsValue,
+    water_bin: JsValue,
+    parks_bin: JsValue,
+) -> (container::ContainerLayers, RoadShards) {
+    let layers = container::ContainerLayers {
+        water: typed_array_to_vec(&water_bin).unwrap_or_default(),
+        parks: typed_array_to_vec(&parks_bin).unwrap_or_default(),
+        ..Default::default()
+    };
+    let shards = if js_sys::Array::is_array(&roads_shards) {
+        js_sys::Array::from(&roads_shards).iter().collect()
+    } else {
+        vec![roads_shards]
+    };
+    (layers, RoadShards::Js(shards))
+}
+
+/// 道路分片来源
+enum RoadShards {
+    /// 已在 WASM 内存中（容器解析结果）
+    Owned(Vec<Vec<f64>>),
+    /// 尚未拷贝的 JS 类型化数组
+    #[cfg(js_host)]
+    Js(Vec<JsValue>),
+}
+
+impl RoadShards {
+    fn len(&self) -> usize {
+        match self {
+            RoadShards::Owned(v) => v.len(),
+            #[cfg(js_host)]
+            RoadShards::Js(v) => v.len(),
+        }
+    }
+
+    /// 将第 `index` 片载入 `buf`（覆盖原内容），不是数组时返回 false
+    ///
+    /// 自有数据直接移出；JS 数组拷入复用的缓冲区，同一时刻 WASM 内存中只保留一片
+    fn load(&mut self, index: usize, buf: &mut Vec<f64>) -> bool {
+        match self {
+            RoadShards::Owned(v) => {
+                *buf = std::mem::take(&mut v[index]);
+                true
+            }
+            #[cfg(js_host)]
+            RoadShards::Js(v) => read_typed_array_into(&v[index], buf),
+        }
+    }
+}
+
+/// 渲染配置来源
+enum ConfigSource<'a> {
+    /// JSON 字符串
+    Json(&'a str),
+    /// JS 对象
+    #[cfg(js_host)]
+    Js(JsValue),
+    /// 已解析的配置（JSON / MessagePack 请求）
+    Parsed(Box<RenderConfig>),
+}
+
+impl ConfigSource<'_> {
+    fn parse(self) -> Result<RenderConfig, RenderError> {
+        match self {
+            ConfigSource::Json(json) => serde_json::from_str(json)
+                .map_err(|e| RenderError::config_parse("Config JSON parse failed", e)),
+            #[cfg(js_host)]
+            ConfigSource::Js(value) => serde_wasm_bindgen::from_value(value)
+                .map_err(|e| RenderError::config_parse("Config parse failed", e)),
+            ConfigSource::Parsed(config) => Ok(*config),
+        }
+    }
+}
+
+fn render_map_binary_internal(
+    layers: container::ContainerLayers,
+    mut road_shards: RoadShards,
+    config: ConfigSource,
+    font_data: Option<&[u8]>,
+) -> RenderResult {
+    memory::begin_render();
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
+    let config = match config.parse() {
         Ok(c) => c,
-        Err(e) => return RenderResult::error(format!("Config JSON parse failed: {}", e)),
+        Err(e) => return e.into(),
+    };
+    let scene = match BinaryScene::new(layers, config) {
+        Ok(s) => s,
+        Err(e) => return e.into(),
+    };
+
+    scene.render(font_data, |draw| {
+        scene.load_road_shards(&mut road_shards, draw)
+    })
+}
+
+/// 多城市拼贴：2–4 个城市并排或堆叠在同一张画布上，共用主题并合并标题
+///
+/// `containers` 为与 `panels` 一一对应的多图层容器（见 `pack_layers`），
+/// `config_json` 为 `CollageConfig`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn render_collage(containers: js_sys::Array, config_json: &str) -> RenderResult {
+    let config = match serde_json::from_str(config_json) {
+        Ok(c) => c,
+        Err(e) => return RenderError::config_parse("Collage JSON parse failed", e).into(),
+    };
+    let mut panels = Vec::new();
+    for (i, container) in containers.iter().enumerate() {
+        let Some(data) = typed_array_to_vec(&container) else {
+            let message = "Container must be a Float64Array or Float32Array";
+            return RenderError::bad_geometry(message)
+                .with_field(format!("containers[{}]", i))
+                .into();
+        };
+        panels.push(data);
+    }
+    match render_collage_internal(panels, config, None) {
+        Ok(result) => result,
+        Err(e) => e.into(),
+    }
+}
+
+/// 逐格渲染并合成拼贴；分格的错误字段加上 `panels[i].` 前缀
+fn render_collage_internal(
+    containers: Vec<Vec<f64>>,
+    config: types::CollageConfig,
+    font_data: Option<&[u8]>,
+) -> Result<RenderResult, RenderError> {
+    memory::begin_render();
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
+    if containers.len() != config.panels.len() {
+        let message = format!(
+            "Expected {} containers, one per panel, got {}",
+            config.panels.len(),
+            containers.len()
+        );
+        return Err(RenderError::invalid_config(message).with_field("containers"));
+    }
+    let layout = collage::layout(&config)
+        .map_err(|e| RenderError::invalid_config(e).with_field("panels"))?;
+    validate::check_canvas_size(config.width, config.height)?;
+    let registered;
+    let font_data = match font_data {
+        Some(data) => data,
+        None => {
+            registered = fonts::resolve(config.font.as_deref())
+                .map_err(|e| RenderError::font(e).with_field("font"))?;
+            &registered
+        }
     };
 
-    // 1. 计算边界框
-    let bounds = calculate_bounds(
-        config.center.lat,
-        config.center.lon,
-        config.radius,
+    // 拼贴画布本身不绘制地图，边界仅作占位
+    let placeholder = types::BoundingBox::new(0.0, 1.0, 0.0, 1.0);
+    let mut canvas = MapRenderer::new(
         config.width,
         config.height,
+        config.theme.clone(),
+        placeholder,
+        types::TextPosition::Bottom,
+    )
+    .ok_or_else(|| canvas_error(config.width, config.height))?;
+    canvas.draw_background();
+
+    for (i, (data, rect)) in containers.into_iter().zip(&layout.panels).enumerate() {
+        let in_panel = |mut e: RenderError| {
+            let field = e.field.take().map_or(String::new(), |f| format!(".{}", f));
+            e.with_field(format!("panels[{}]{}", i, field))
+        };
+        let panel_config = config
+            .panel_config(i, rect.width, rect.height)
+            .map_err(|e| in_panel(RenderError::invalid_config(e)))?;
+        let mut layers = container::parse_container(&data)
+            .map_err(|e| RenderError::bad_geometry(e).with_field(format!("containers[{}]", i)))?;
+        let mut roads = RoadShards::Owned(std::mem::take(&mut layers.roads));
+        let scene = BinaryScene::new(layers, panel_config).map_err(in_panel)?;
+        scene.record_memory();
+
+        let mut renderer = scene.create_renderer().map_err(in_panel)?;
+        renderer.draw_background();
+        scene.draw_water(&mut renderer);
+        scene.draw_parks(&mut renderer);
+        scene
+            .draw_roads(&mut renderer, |draw| {
+                scene.load_road_shards(&mut roads, draw)
+            })
+            .map_err(|e| in_panel(RenderError::bad_geometry(e).with_field("roads")))?;
+        scene
+            .draw_overlays(&mut renderer, font_data)
+            .map_err(in_panel)?;
+        canvas.composite_panel(renderer, rect.x, rect.y);
+    }
+
+    if let Some(title) = layout.title {
+        canvas
+            .draw_title_block(
+                &config.title(),
+                &config.subtitle,
+                title.y,
+                title.height,
+                font_data,
+            )
+            .map_err(|e| RenderError::font(format!("Failed to draw text: {}", e)))?;
+    }
+    if let Some(watermark) = &config.watermark {
+        canvas
+            .draw_watermark(watermark, font_data)
+            .map_err(|e| RenderError::font(e).with_field("watermark"))?;
+    }
+
+    time("render_collage: encode_png");
+    let png_data = canvas
+        .encode_png(PNG_DPI, config.png_compression)
+        .map_err(|e| RenderError::encode(format!("PNG encoding failed: {}", e)))?;
+    time_end("render_collage: encode_png");
+    Ok(RenderResult::success(config.width, config.height, png_data))
+}
+
+/// 画布通过尺寸检查却仍无法创建
+fn canvas_error(width: u32, height: u32) -> RenderError {
+    let message = format!(
+        "Failed to create {}x{} canvas ({} MiB)",
+        width,
+        height,
+        validate::canvas_bytes(width, height) >> 20
     );
+    RenderError::new(RenderErrorCode::CanvasTooLarge, message).with_field("width")
+}
 
-    // 2. 统计元素总数
-    let water_count = if water_bin.is_empty() {
-        0
-    } else {
-        water_bin[0] as usize
-    };
-    let parks_count = if parks_bin.is_empty() {
-        0
-    } else {
-        parks_bin[0] as usize
-    };
-    let poi_count = config
-        .pois
-        .as_ref()
-        .map(|p| if p.is_empty() { 0 } else { p[0] as usize })
-        .unwrap_or(0);
-
-    let mut total_roads = 0usize;
-    let mut road_type_counts = [0usize; 6];
-
-    if js_sys::Array::is_array(&roads_shards) {
-        let shards_array = js_sys::Array::from(&roads_shards);
-        for shard_val in shards_array.iter() {
-            if let Some(shard_typed) = shard_val.dyn_ref::<js_sys::Float64Array>() {
-                let vec = shard_typed.to_vec();
-                if !vec.is_empty() {
-                    let road_count = vec[0] as usize;
-                    total_roads += road_count;
-
-                    let mut offset = 1;
-                    for _ in 0..road_count {
-                        if offset + 2 <= vec.len() {
-                            let type_val = vec[offset] as usize;
-                            let point_count = vec[offset + 1] as usize;
-                            if type_val < 6 {
-                                road_type_counts[type_val] += 1;
-                            }
-                            offset += 2 + point_count * 2;
-                        }
+/// 去掉无效坐标，并将去掉的数量记入渲染警告
+fn drop_invalid_coords(data: &mut Vec<f64>, layer: BinLayer, crs: types::Crs, field: &str) {
+    let dropped = data_processor::drop_invalid_coords(data, layer, crs);
+    warnings::invalid_coordinates(Some(field), dropped);
+}
+
+/// 按声明的数量校正 POI 二进制，并将不符的数值个数记入渲染警告
+fn truncate_poi_bin(data: &mut Vec<f64>, field: &str) {
+    let mismatched = data_processor::truncate_poi_bin(data);
+    warnings::malformed_data(field, mismatched);
+}
+
+/// 已投影的 (pois, routes)
+type Overlays = (Option<Vec<f64>>, Option<Vec<f64>>);
+
+/// 去除 pois / routes 的头部并按各自的 CRS 投影
+fn prepare_overlays(
+    config: &RenderConfig,
+    center_3857: (f64, f64),
+    mut pois: Option<Vec<f64>>,
+    mut routes: Option<Vec<f64>>,
+) -> Result<Overlays, String> {
+    if let Some(data) = routes.as_mut() {
+        data_processor::strip_bin_header_vec(data, BinLayer::Routes, "routes")?;
+        drop_invalid_coords(data, BinLayer::Routes, config.route_crs, "routes");
+        let ranges = data_processor::route_bin_coord_ranges(data);
+        data_processor::prepare_bin_geometry(
+            data,
+            &ranges,
+            config.route_crs,
+            center_3857,
+            "routes",
+        )?;
+    }
+    if let Some(data) = pois.as_mut() {
+        data_processor::strip_bin_header_vec(data, BinLayer::Pois, "pois")?;
+        truncate_poi_bin(data, "pois");
+        drop_invalid_coords(data, BinLayer::Pois, config.poi_crs, "pois");
+        if !data.is_empty() && data[0] as usize > 0 {
+            let poi_range = data_processor::poi_bin_coord_range(data);
+            data_processor::prepare_bin_geometry(
+                data,
+                &[poi_range],
+                config.poi_crs,
+                center_3857,
+                "pois",
+            )?;
+        }
+    }
+    Ok((pois, routes))
+}
+
+/// 校验并投影自定义图层，按 `z` 稳定排序
+fn prepare_extra_layers(
+    config: &RenderConfig,
+    center_3857: (f64, f64),
+) -> Result<Vec<types::ExtraLayer>, RenderError> {
+    let mut layers = config.extra_layers.clone();
+    for (i, layer) in layers.iter_mut().enumerate() {
+        let field = format!("extra_layers[{}]", i);
+        let bin_layer = match layer.kind {
+            types::ExtraLayerKind::Line => BinLayer::Routes,
+            types::ExtraLayerKind::Polygon => BinLayer::Polygons,
+            types::ExtraLayerKind::Point => BinLayer::Pois,
+        };
+        let data = &mut layer.data;
+        data_processor::strip_bin_header_vec(data, bin_layer, &field)
+            .and_then(|_| {
+                if bin_layer == BinLayer::Pois {
+                    truncate_poi_bin(data, &field);
+                }
+                drop_invalid_coords(data, bin_layer, layer.crs, &field);
+                let ranges = match layer.kind {
+                    types::ExtraLayerKind::Line => data_processor::route_bin_coord_ranges(data),
+                    types::ExtraLayerKind::Polygon => {
+                        data_processor::polygon_bin_coord_ranges(data)
+                    }
+                    types::ExtraLayerKind::Point => {
+                        vec![data_processor::poi_bin_coord_range(data)]
                     }
+                };
+                data_processor::prepare_bin_geometry(data, &ranges, layer.crs, center_3857, &field)
+            })
+            .map_err(|e| RenderError::bad_geometry(e).with_field(&field))?;
+    }
+    layers.sort_by_key(|layer| layer.z);
+    Ok(layers)
+}
+
+/// 预处理后的几何（已解析、投影），可导出缓存以便重复渲染同一城市
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PreparedScene {
+    version: u32,
+    roads: Vec<types::LocalBin>,
+    water: types::LocalBin,
+    parks: types::LocalBin,
+    buildings: types::LocalBin,
+    pois: Option<Vec<f64>>,
+    routes: Option<Vec<f64>>,
+}
+
+/// 导出格式版本，结构变化时递增
+const PREPARED_SCENE_VERSION: u32 = 1;
+
+/// 已校验并投影的二进制场景（道路分片除外）
+///
+/// 主题与文字只影响绘制阶段，修改后可直接重新渲染而无需重新投影
+struct BinaryScene {
+    config: RenderConfig,
+    bounds: types::BoundingBox,
+    projector: Projector,
+    center_3857: (f64, f64),
+    // 投影后以视口中心为原点的 f32 局部坐标
+    water: types::LocalBin,
+    parks: types::LocalBin,
+    buildings: types::LocalBin,
+    // 已投影的 POI / 路线
+    pois: Option<Vec<f64>>,
+    routes: Option<Vec<f64>>,
+    // 已投影、按 z 排序的自定义图层
+    extra_layers: Vec<types::ExtraLayer>,
+}
+
+impl BinaryScene {
+    /// 计算边界框并按声明的 CRS 校验、投影除道路以外的图层
+    fn new(
+        layers: container::ContainerLayers,
+        mut config: RenderConfig,
+    ) -> Result<Self, RenderError> {
+        validate::clamp_config(&mut config)?;
+        let container::ContainerLayers {
+            mut water,
+            mut parks,
+            mut buildings,
+            pois,
+            routes,
+            ..
+        } = layers;
+
+        // 1. 计算边界框
+        let (bounds, projector) =
+            resolve_view(&config.view()).map_err(RenderError::invalid_config)?;
+
+        // 2. 按声明的 CRS 校验几何，必要时在 WASM 内投影
+        let center_3857 = projection::project_point(config.center.lon, config.center.lat);
+        let polygon_layers = [
+            (&mut water, "water"),
+            (&mut parks, "parks"),
+            (&mut buildings, "buildings"),
+        ];
+        for (data, layer) in polygon_layers {
+            data_processor::strip_bin_header_vec(data, BinLayer::Polygons, layer)
+                .and_then(|_| {
+                    drop_invalid_coords(data, BinLayer::Polygons, config.geometry_crs(), layer);
+                    let ranges = data_processor::polygon_bin_coord_ranges(data);
+                    data_processor::prepare_bin_geometry(
+                        data,
+                        &ranges,
+                        config.geometry_crs(),
+                        center_3857,
+                        layer,
+                    )
+                })
+                .map_err(|e| RenderError::bad_geometry(e).with_field(layer))?;
+        }
+        let [water, parks, buildings] = [water, parks, buildings].map(|data| {
+            let ranges = data_processor::polygon_bin_coord_ranges(&data);
+            types::LocalBin::new(&data, &ranges, center_3857)
+        });
+
+        // 容器中的 pois / routes 优先于配置
+        let routes = routes.or_else(|| config.routes.clone());
+        let pois = pois.or_else(|| config.pois.clone().map(types::PoiInput::into_bin));
+        let (pois, routes) = prepare_overlays(&config, center_3857, pois, routes)
+            .map_err(RenderError::bad_geometry)?;
+        let extra_layers = prepare_extra_layers(&config, center_3857)?;
+
+        Ok(BinaryScene {
+            config,
+            bounds,
+            projector,
+            center_3857,
+            water,
+            parks,
+            buildings,
+            pois,
+            routes,
+            extra_layers,
+        })
+    }
+
+    /// 由导出的预处理几何重建场景，跳过解析与投影
+    ///
+    /// 边界按新配置重新计算，因此可换用不同尺寸；配置中的 pois / routes 优先于导出数据
+    fn from_prepared(prepared: PreparedScene, mut config: RenderConfig) -> Result<Self, String> {
+        validate::clamp_config(&mut config).map_err(|e| e.to_string())?;
+        let (bounds, projector) = resolve_view(&config.view())?;
+        let center_3857 = projection::project_point(config.center.lon, config.center.lat);
+        let pois = config.pois.clone().map(types::PoiInput::into_bin);
+        let (pois, routes) = match (pois, config.routes.clone()) {
+            (None, None) => (prepared.pois, prepared.routes),
+            (pois, routes) => {
+                let (pois, routes) = prepare_overlays(&config, center_3857, pois, routes)?;
+                (pois.or(prepared.pois), routes.or(prepared.routes))
+            }
+        };
+        let extra_layers = prepare_extra_layers(&config, center_3857).map_err(|e| e.to_string())?;
+        Ok(BinaryScene {
+            config,
+            bounds,
+            projector,
+            center_3857,
+            water: prepared.water,
+            parks: prepared.parks,
+            buildings: prepared.buildings,
+            pois,
+            routes,
+            extra_layers,
+        })
+    }
+
+    /// 去除道路分片的头部并投影
+    fn prepare_road_shard(&self, shard: &mut Vec<f64>) -> Result<(), String> {
+        data_processor::strip_bin_header_vec(shard, BinLayer::Roads, "roads")?;
+        let crs = self.config.geometry_crs();
+        drop_invalid_coords(shard, BinLayer::Roads, crs, "roads");
+        let ranges = data_processor::road_bin_coord_ranges(shard);
+        data_processor::prepare_bin_geometry(shard, &ranges, crs, self.center_3857, "roads")
+    }
+
+    /// 逐片载入、投影并绘制道路，避免同时持有所有分片的副本
+    fn load_road_shards(
+        &self,
+        road_shards: &mut RoadShards,
+        draw: &mut dyn FnMut(ShardRef),
+    ) -> Result<(), String> {
+        let mut shard = Vec::new();
+        for i in 0..road_shards.len() {
+            if !road_shards.load(i, &mut shard) {
+                continue;
+            }
+            self.prepare_road_shard(&mut shard)?;
+            draw(ShardRef::World(&shard));
+        }
+        Ok(())
+    }
+
+    /// 绘制并编码场景
+    ///
+    /// `for_each_road_shard` 对每个已投影的道路分片调用传入的绘制回调
+    fn render<F>(&self, font_data: Option<&[u8]>, for_each_road_shard: F) -> RenderResult
+    where
+        F: FnOnce(&mut dyn FnMut(ShardRef)) -> Result<(), String>,
+    {
+        self.log_counts();
+        self.record_memory();
+        let mut renderer = match self.create_renderer() {
+            Ok(r) => r,
+            Err(e) => return e.into(),
+        };
+
+        // 5. 绘制
+        time("render_map_bin: draw_background");
+        renderer.draw_background();
+        time_end("render_map_bin: draw_background");
+
+        self.draw_water(&mut renderer);
+        self.draw_parks(&mut renderer);
+        if let Err(e) = self.draw_roads(&mut renderer, for_each_road_shard) {
+            return RenderError::bad_geometry(e).with_field("roads").into();
+        }
+        self.finish(renderer, font_data)
+    }
+
+    /// 统计元素总数
+    fn log_counts(&self) {
+        let count = |data: &[f64]| data.first().map_or(0, |&c| c as usize);
+        log(&format!(
+            "[Render] Elements: {} water polygons, {} parks, {} POIs",
+            self.water.count(),
+            self.parks.count(),
+            self.pois.as_deref().map_or(0, count)
+        ));
+    }
+
+    /// 记录除道路外各图层几何的字节数（道路在绘制时由 `RoadStats` 记录）
+    fn record_memory(&self) {
+        let bytes = |data: Option<&Vec<f64>>| data.map_or(0, |d| d.len() * 8);
+        memory::record_layers(|l| {
+            l.water += self.water.data.len() * 4;
+            l.parks += self.parks.data.len() * 4;
+            l.buildings += self.buildings.data.len() * 4;
+            l.pois += bytes(self.pois.as_ref());
+            l.routes += bytes(self.routes.as_ref());
+        });
+    }
+
+    /// 以当前配置创建渲染器
+    fn create_renderer(&self) -> Result<MapRenderer, RenderError> {
+        let config = &self.config;
+        let text_pos = config.text_position.unwrap_or(types::TextPosition::Top);
+        validate::check_canvas_size(config.width, config.height)?;
+        let mut renderer = MapRenderer::new(
+            config.width,
+            config.height,
+            config.theme.clone(),
+            self.bounds,
+            text_pos,
+        )
+        .ok_or_else(|| canvas_error(config.width, config.height))?;
+        renderer.set_projector(self.projector);
+        renderer.set_simplify_tolerance(config.simplify_tolerance_px);
+        renderer.set_lod_min_px(config.lod_min_px);
+        renderer.set_anti_alias(config.anti_alias);
+        Ok(renderer)
+    }
+
+    /// 绘制水体及其上下的自定义图层
+    fn draw_water(&self, renderer: &mut MapRenderer) {
+        self.draw_extra_layers(renderer, ..types::LAYER_Z_WATER);
+        let water_color = renderer.get_theme().water.clone();
+        time("render_map_bin: draw_water");
+        renderer.draw_polygons_local(&self.water, &water_color);
+        time_end("render_map_bin: draw_water");
+        draw_stats::record("water", renderer.take_draw_stats());
+        self.draw_extra_layers(renderer, types::LAYER_Z_WATER..types::LAYER_Z_PARKS);
+    }
+
+    /// 绘制公园与建筑
+    fn draw_parks(&self, renderer: &mut MapRenderer) {
+        let parks_color = renderer.get_theme().parks.clone();
+        time("render_map_bin: draw_parks");
+        renderer.draw_polygons_local(&self.parks, &parks_color);
+        time_end("render_map_bin: draw_parks");
+        draw_stats::record("parks", renderer.take_draw_stats());
+
+        if !self.buildings.is_empty() {
+            let theme = renderer.get_theme();
+            let buildings_color = theme
+                .buildings
+                .clone()
+                .unwrap_or_else(|| theme.parks.clone());
+            time("render_map_bin: draw_buildings");
+            renderer.draw_polygons_local(&self.buildings, &buildings_color);
+            time_end("render_map_bin: draw_buildings");
+            draw_stats::record("buildings", renderer.take_draw_stats());
+        }
+        self.draw_extra_layers(renderer, types::LAYER_Z_PARKS..types::LAYER_Z_ROADS);
+    }
+
+    /// 绘制 `z` 落在 `z_range` 内的自定义图层
+    fn draw_extra_layers(&self, renderer: &mut MapRenderer, z_range: impl RangeBounds<i32>) {
+        let layers = self.extra_layers.iter().enumerate();
+        for (i, layer) in layers.filter(|(_, l)| z_range.contains(&l.z)) {
+            match layer.kind {
+                types::ExtraLayerKind::Line => {
+                    renderer.draw_lines_bin(&layer.data, &layer.color, layer.width)
+                }
+                types::ExtraLayerKind::Polygon => {
+                    renderer.draw_polygons_bin(&layer.data, &layer.color)
+                }
+                types::ExtraLayerKind::Point => {
+                    renderer.draw_points_bin(&layer.data, &layer.color, layer.width)
                 }
             }
+            draw_stats::record(&format!("extra_layers[{}]", i), renderer.take_draw_stats());
         }
-    } else if let Some(shard_typed) = roads_shards.dyn_ref::<js_sys::Float64Array>() {
-        let vec = shard_typed.to_vec();
-        if !vec.is_empty() {
-            let road_count = vec[0] as usize;
-            total_roads = road_count;
-
-            let mut offset = 1;
-            for _ in 0..road_count {
-                if offset + 2 <= vec.len() {
-                    let type_val = vec[offset] as usize;
-                    let point_count = vec[offset + 1] as usize;
-                    if type_val < 6 {
-                        road_type_counts[type_val] += 1;
-                    }
-                    offset += 2 + point_count * 2;
+    }
+
+    /// 采样大圆弧并按路线格式绘制
+    fn draw_arcs(&self, renderer: &mut MapRenderer, arcs: &types::GreatCircleArcs) {
+        let mut points: Vec<(f64, f64)> = arcs.points.iter().map(|p| (p.lon, p.lat)).collect();
+        let len = points.len();
+        points.retain(|&(lon, lat)| projection::coord_in_range(lon, lat, types::Crs::Wgs84));
+        warnings::invalid_coordinates(Some("arcs.points"), len - points.len());
+
+        let lines = projection::great_circle_path(&points, self.center_3857.0);
+        let data = data_processor::routes_to_bin(&lines);
+        let theme = &self.config.theme;
+        let color = arcs
+            .color
+            .as_ref()
+            .or(theme.route_color.as_ref())
+            .unwrap_or(&theme.text);
+        renderer.draw_dashed_lines_bin(&data, color, arcs.width, &arcs.dash);
+        draw_stats::record("arcs", renderer.take_draw_stats());
+    }
+
+    fn draw_roads<F>(
+        &self,
+        renderer: &mut MapRenderer,
+        for_each_road_shard: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce(&mut dyn FnMut(ShardRef)) -> Result<(), String>,
+    {
+        time("render_map_bin: draw_roads");
+        let road_width_scale = self.config.road_width_scale();
+        let mut stats = RoadStats::default();
+        for_each_road_shard(&mut |shard: ShardRef| {
+            stats.draw_shard(renderer, shard, road_width_scale)
+        })?;
+        time_end("render_map_bin: draw_roads");
+        stats.log();
+        draw_stats::record("roads", renderer.take_draw_stats());
+        Ok(())
+    }
+
+    /// 绘制叠加层、遮罩、渐变与文字并编码为 PNG
+    ///
+    /// `font_data` 为空时按配置中的 `font` 从字体注册表选用
+    fn finish(&self, mut renderer: MapRenderer, font_data: Option<&[u8]>) -> RenderResult {
+        let config = &self.config;
+        let registered;
+        let font_data = match font_data {
+            Some(data) => data,
+            None => match fonts::resolve(config.font.as_deref()) {
+                Ok(font) => {
+                    registered = font;
+                    &registered
+                }
+                Err(e) => return RenderError::font(e).with_field("font").into(),
+            },
+        };
+        if let Err(e) = self.draw_overlays(&mut renderer, font_data) {
+            return e.into();
+        }
+
+        // 天空色渐变在面板、宝丽来版式中只绘制在地图区域内
+        let sky = config
+            .solar
+            .as_ref()
+            .is_some_and(|s| s.style == types::SolarStyle::Sky);
+        if sky && config.layout != types::PosterLayout::Overlay {
+            let margins = config.map_margins().map(|m| m.fractions());
+            time("render_map_bin: draw_gradients");
+            renderer.draw_gradients_in(margins.unwrap_or_default());
+            time_end("render_map_bin: draw_gradients");
+        }
+        match config.layout {
+            types::PosterLayout::Overlay => {
+                time("render_map_bin: draw_gradients");
+                renderer.draw_gradients();
+                time_end("render_map_bin: draw_gradients");
+            }
+            types::PosterLayout::Panel => {
+                let color = config.text_panel_color.as_ref().unwrap_or(&config.theme.bg);
+                renderer.fill_text_panel(config.text_panel_height_pct(), color);
+            }
+            types::PosterLayout::Polaroid => {
+                let margins = config.map_margins().map(|m| m.fractions());
+                renderer.draw_polaroid_frame(margins.unwrap_or_default());
+            }
+        }
+
+        // 4. 绘制文字 (使用传入的字体数据)
+        if let Err(e) = renderer.draw_text(
+            &config.display_city,
+            &config.display_country,
+            config.center.lat,
+            config.center.lon,
+            font_data,
+        ) {
+            return RenderError::font(format!("Failed to draw text: {}", e)).into();
+        }
+
+        if let Some(logo) = &config.logo {
+            match images::resolve(&logo.image) {
+                Ok(image) => renderer.draw_logo(&image, logo),
+                Err(e) => {
+                    return RenderError::invalid_config(e)
+                        .with_field("logo.image")
+                        .into();
+                }
+            }
+        }
+
+        if let Some(watermark) = &config.watermark {
+            time("render_map_bin: draw_watermark");
+            if let Err(e) = renderer.draw_watermark(watermark, font_data) {
+                return RenderError::font(e).with_field("watermark").into();
+            }
+            time_end("render_map_bin: draw_watermark");
+        }
+
+        // 5. 编码为 PNG
+        time("render_map_bin: encode_png");
+        let png_data = match renderer.encode_png(PNG_DPI, config.png_compression) {
+            Ok(data) => data,
+            Err(e) => return RenderError::encode(format!("PNG encoding failed: {}", e)).into(),
+        };
+        time_end("render_map_bin: encode_png");
+
+        RenderResult::success(config.width, config.height, png_data)
+    }
+
+    /// 绘制道路之上的叠加层、标记、遮罩与边距（不含渐变与文字）
+    fn draw_overlays(
+        &self,
+        renderer: &mut MapRenderer,
+        font_data: &[u8],
+    ) -> Result<(), RenderError> {
+        let config = &self.config;
+        self.draw_extra_layers(renderer, types::LAYER_Z_ROADS..types::LAYER_Z_ROUTES);
+
+        // 绘制路线叠加层
+        if let Some(routes) = &self.routes {
+            time("render_map_bin: draw_routes");
+            renderer.draw_routes_bin(routes, config.route_width);
+            time_end("render_map_bin: draw_routes");
+            draw_stats::record("routes", renderer.take_draw_stats());
+        }
+        if let Some(arcs) = &config.arcs {
+            self.draw_arcs(renderer, arcs);
+        }
+        self.draw_extra_layers(renderer, types::LAYER_Z_ROUTES..types::LAYER_Z_POIS);
+
+        // 绘制 POI
+        if let Some(pois) = &self.pois
+            && !pois.is_empty()
+            && pois[0] as usize > 0
+        {
+            renderer
+                .set_poi_labels(config.poi_labels, font_data)
+                .map_err(|e| RenderError::font(e).with_field("font"))?;
+            renderer.set_poi_halo(config.poi_halo.clone());
+            time("render_map_bin: draw_pois");
+            renderer.draw_pois_bin(pois);
+            time_end("render_map_bin: draw_pois");
+            draw_stats::record("pois", renderer.take_draw_stats());
+        }
+        self.draw_extra_layers(renderer, types::LAYER_Z_POIS..);
+
+        if let Some(overlay) = &config.solar {
+            let sun = solar::subsolar_point(overlay.timestamp_ms);
+            match overlay.style {
+                types::SolarStyle::Terminator => {
+                    time("render_map_bin: draw_night_tint");
+                    let color = overlay.night_color.as_deref();
+                    renderer.draw_night_tint(
+                        sun,
+                        color.unwrap_or(renderer::DEFAULT_NIGHT_COLOR),
+                        overlay.night_opacity,
+                    );
+                    time_end("render_map_bin: draw_night_tint");
+                }
+                types::SolarStyle::Sky => {
+                    let altitude = solar::altitude(sun, config.center.lon, config.center.lat);
+                    renderer.set_gradient_color(solar::sky_color(altitude));
                 }
             }
         }
+
+        if let Some(marker) = &config.home_marker {
+            renderer.draw_home_marker(self.center_3857, marker);
+        }
+
+        if let Some(region) = &config.region {
+            time("render_map_bin: apply_region_mask");
+            apply_region_mask(renderer, region.clone(), config.region_outside_opacity);
+            time_end("render_map_bin: apply_region_mask");
+        }
+
+        if config.clip_circle {
+            let circle = types::PolyFeature {
+                exterior: projection::geodesic_circle(
+                    config.center.lat,
+                    config.center.lon,
+                    config.radius,
+                    CIRCLE_CLIP_SEGMENTS,
+                ),
+                interiors: vec![],
+            };
+            apply_region_mask(renderer, vec![circle], config.region_outside_opacity);
+        }
+
+        let margins = config.map_margins().map(|m| m.fractions());
+        if let Some(margins) = margins {
+            renderer.fill_margins(margins);
+        }
+        if config.corner_radius > 0.0 {
+            renderer.round_map_corners(margins.unwrap_or_default(), config.corner_radius);
+        }
+        Ok(())
+    }
+}
+
+/// 跨分片累计的道路绘制统计
+#[derive(Default)]
+struct RoadStats {
+    total_roads: usize,
+    type_counts: [usize; 6],
+    timings: [f64; 6],
+}
+
+/// 待绘制的道路分片
+#[derive(Clone, Copy)]
+enum ShardRef<'a> {
+    /// f64 绝对坐标（流式渲染的临时分片）
+    World(&'a [f64]),
+    /// f32 局部坐标（会话中常驻的分片）
+    Local(&'a types::LocalBin),
+}
+
+impl RoadStats {
+    fn draw_shard(&mut self, renderer: &mut MapRenderer, shard: ShardRef, road_width_scale: f32) {
+        memory::record_layers(|l| {
+            l.roads += match shard {
+                ShardRef::World(data) => data.len() * 8,
+                ShardRef::Local(local) => local.data.len() * 4,
+            }
+        });
+        let timings = match shard {
+            ShardRef::World(data) => {
+                self.count(data);
+                renderer.draw_roads_bin_scaled(data, road_width_scale)
+            }
+            ShardRef::Local(local) => {
+                self.count(&local.data);
+                renderer.draw_roads_local(local, road_width_scale)
+            }
+        };
+        for (total, t) in self.timings.iter_mut().zip(timings) {
+            *total += t;
+        }
+    }
+
+    fn count<T: Copy + Into<f64>>(&mut self, shard: &[T]) {
+        if shard.is_empty() {
+            return;
+        }
+        let road_count = shard[0].into() as usize;
+        self.total_roads += road_count;
+
+        let mut offset = 1;
+        for _ in 0..road_count {
+            if offset + 2 <= shard.len() {
+                let type_val = shard[offset].into() as usize;
+                let point_count = shard[offset + 1].into() as usize;
+                if type_val < 6 {
+                    self.type_counts[type_val] += 1;
+                }
+                offset += 2 + point_count * 2;
+            }
+        }
+    }
+
+    fn log(&self) {
+        let (counts, timings) = (&self.type_counts, &self.timings);
+        log(&format!("[Render] Elements: {} roads", self.total_roads));
+        log(&format!(
+            "[Render] Roads by type: Motorway={}, Primary={}, Secondary={}, Tertiary={}, Residential={}, Default={}",
+            counts[0], counts[1], counts[2], counts[3], counts[4], counts[5]
+        ));
+
+        let names = [
+            "motorway",
+            "primary",
+            "secondary",
+            "tertiary",
+            "residential",
+            "default",
+        ];
+        for (name, ms) in names.iter().zip(timings) {
+            profile::record(&format!("draw_roads: {}", name), *ms);
+        }
+
+        debug("render_map_bin: draw_roads breakdown:");
+        debug(&format!("  Motorway: {:.2}ms", timings[0]));
+        debug(&format!("  Primary: {:.2}ms", timings[1]));
+        debug(&format!("  Secondary: {:.2}ms", timings[2]));
+        debug(&format!("  Tertiary: {:.2}ms", timings[3]));
+        debug(&format!("  Residential: {:.2}ms", timings[4]));
+        debug(&format!("  Default: {:.2}ms", timings[5]));
+    }
+}
+
+/// 异步渲染时每块最多描边的道路数
+const ASYNC_ROADS_PER_CHUNK: usize = 20_000;
+
+/// 主渲染函数 (异步分块版本)
+///
+/// 参数与 `render_map_binary` 相同。道路按分片描边，超大分片再切为每块至多
+/// `ASYNC_ROADS_PER_CHUNK` 条，每块之间 await 一个微任务，使单线程 Worker
+/// 能及时响应取消 / 进度消息。`on_progress(progress)` 在每块之后以 0..1 的进度调用，
+/// 返回 `false` 时中止渲染
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub async fn render_map_async(
+    roads_shards: JsValue,
+    water_bin: JsValue,
+    parks_bin: JsValue,
+    config_json: String,
+    on_progress: Option<js_sys::Function>,
+) -> RenderResult {
+    memory::begin_render();
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
+    let config: RenderConfig = match serde_json::from_str(&config_json) {
+        Ok(c) => c,
+        Err(e) => return RenderError::config_parse("Config JSON parse failed", e).into(),
+    };
+    let (layers, mut road_shards) = binary_layers(roads_shards, water_bin, parks_bin);
+    let scene = match BinaryScene::new(layers, config) {
+        Ok(s) => s,
+        Err(e) => return e.into(),
+    };
+
+    scene.log_counts();
+    scene.record_memory();
+    let mut renderer = match scene.create_renderer() {
+        Ok(r) => r,
+        Err(e) => return e.into(),
+    };
+    renderer.draw_background();
+    scene.draw_water(&mut renderer);
+    scene.draw_parks(&mut renderer);
+    yield_now().await;
+
+    time("render_map_async: draw_roads");
+    let road_width_scale = scene.config.road_width_scale();
+    let mut stats = RoadStats::default();
+    let (mut shard, mut chunk) = (Vec::new(), Vec::new());
+    let shard_count = road_shards.len();
+    for i in 0..shard_count {
+        if !road_shards.load(i, &mut shard) {
+            continue;
+        }
+        if let Err(e) = scene.prepare_road_shard(&mut shard) {
+            return RenderError::bad_geometry(e).with_field("roads").into();
+        }
+        let chunks = data_processor::road_bin_chunks(&shard, ASYNC_ROADS_PER_CHUNK);
+        for (j, (count, range)) in chunks.iter().enumerate() {
+            chunk.clear();
+            chunk.push(*count as f64);
+            chunk.extend_from_slice(&shard[range.clone()]);
+            stats.draw_shard(&mut renderer, ShardRef::World(&chunk), road_width_scale);
+
+            let progress = (i as f64 + (j + 1) as f64 / chunks.len() as f64) / shard_count as f64;
+            if let Some(callback) = &on_progress
+                && callback
+                    .call1(&JsValue::NULL, &JsValue::from_f64(progress))
+                    .is_ok_and(|ret| ret == JsValue::FALSE)
+            {
+                return RenderError::new(RenderErrorCode::Cancelled, "Render cancelled").into();
+            }
+            yield_now().await;
+        }
+    }
+    time_end("render_map_async: draw_roads");
+    stats.log();
+    draw_stats::record("roads", renderer.take_draw_stats());
+
+    scene.finish(renderer, None)
+}
+
+/// 让出执行权：等待一个已完成的 Promise（微任务）
+#[cfg(js_host)]
+async fn yield_now() {
+    let _ =
+        wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&JsValue::UNDEFINED)).await;
+}
+
+/// 可复用的海报渲染会话
+///
+/// 构造时一次性上传并投影全部几何，之后修改主题或文字只需重新绘制，
+/// 无需再次传输和解析数据。水体、公园与道路分别缓存为图层，
+/// 仅在相关配色变化时重绘，其余情况直接合成
+#[cfg_attr(js_host, wasm_bindgen)]
+pub struct PosterSession {
+    scene: BinaryScene,
+    roads: Vec<types::LocalBin>,
+    font: Option<Vec<u8>>,
+    water_layer: Option<CachedLayer>,
+    parks_layer: Option<CachedLayer>,
+    roads_layer: Option<CachedLayer>,
+    // 首次命中测试时构建
+    feature_index: std::cell::OnceCell<index::RTree<IndexedFeature>>,
+    // 准备场景时产生的警告（无效坐标、被夹取的参数），每次渲染时重新报告
+    prepare_warnings: Vec<warnings::RenderWarning>,
+}
+
+/// 空间索引中的要素
+struct IndexedFeature {
+    hit: FeatureHit,
+    geometry: IndexedGeometry,
+}
+
+enum IndexedGeometry {
+    Line(Vec<(f64, f64)>),
+    Area(types::PolyFeature),
+}
+
+/// 命中测试结果
+#[derive(serde::Serialize)]
+struct FeatureHit {
+    /// "roads" / "water" / "parks" / "buildings"
+    layer: &'static str,
+    /// 要素在该图层中的序号（道路按分片顺序连续编号）
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    road_type: Option<types::RoadType>,
+}
+
+impl IndexedFeature {
+    /// 点 `p` 到要素的距离是否不超过 `radius`（面要素内部视为命中）
+    fn hit_test(&self, p: (f64, f64), radius: f64) -> bool {
+        let near_line = |line: &[(f64, f64)]| match line {
+            [] => false,
+            [only] => simplify::segment_dist_sq(p, *only, *only) <= radius * radius,
+            _ => line
+                .windows(2)
+                .any(|w| simplify::segment_dist_sq(p, w[0], w[1]) <= radius * radius),
+        };
+        match &self.geometry {
+            IndexedGeometry::Line(line) => near_line(line),
+            IndexedGeometry::Area(poly) => {
+                self.contains(p)
+                    || std::iter::once(&poly.exterior)
+                        .chain(&poly.interiors)
+                        .any(|r| near_line(r))
+            }
+        }
+    }
+
+    /// 点 `p` 是否在面要素内部（奇偶规则，洞内不算）；线要素恒为 false
+    fn contains(&self, p: (f64, f64)) -> bool {
+        let IndexedGeometry::Area(poly) = &self.geometry else {
+            return false;
+        };
+        std::iter::once(&poly.exterior)
+            .chain(&poly.interiors)
+            .filter(|r| !r.is_empty() && data_processor::point_in_ring(p, r))
+            .count()
+            % 2
+            == 1
+    }
+
+    /// 线要素上距 `p` 最近的点；面要素返回 None
+    fn closest_point(&self, p: (f64, f64)) -> Option<(f64, f64)> {
+        let IndexedGeometry::Line(line) = &self.geometry else {
+            return None;
+        };
+        let dist_sq = |c: (f64, f64)| (c.0 - p.0).powi(2) + (c.1 - p.1).powi(2);
+        match line.as_slice() {
+            [] => None,
+            [only] => Some(*only),
+            _ => line
+                .windows(2)
+                .map(|w| simplify::closest_on_segment(p, w[0], w[1]))
+                .min_by(|a, b| dist_sq(*a).total_cmp(&dist_sq(*b))),
+        }
+    }
+}
+
+/// 最近道路查询结果
+#[derive(serde::Serialize)]
+struct NearestRoad {
+    /// 道路序号（与命中测试结果中的 index 一致）
+    index: usize,
+    road_type: types::RoadType,
+    /// 道路上距查询点最近的位置（经纬度）
+    lon: f64,
+    lat: f64,
+    /// 查询点到该位置的地面距离（米）
+    distance_m: f64,
+}
+
+impl PosterSession {
+    /// 输出像素 -> 管线内部的 Web Mercator 坐标
+    fn screen_to_web_mercator(&self, x: f64, y: f64) -> (f64, f64) {
+        let (config, bounds) = (&self.scene.config, &self.scene.bounds);
+        let wx = bounds.min_x + x * bounds.width() / config.width as f64;
+        let wy = bounds.max_y - y * bounds.height() / config.height as f64;
+        let (lon, lat) = self.scene.projector.inverse(wx, wy);
+        projection::project_point(lon, lat)
+    }
+
+    /// 空间索引，首次使用时构建
+    fn index(&self) -> &index::RTree<IndexedFeature> {
+        self.feature_index.get_or_init(|| self.build_index())
+    }
+
+    fn build_index(&self) -> index::RTree<IndexedFeature> {
+        let mut items = Vec::new();
+        let mut push = |hit: FeatureHit, geometry: IndexedGeometry| {
+            let points = match &geometry {
+                IndexedGeometry::Line(line) => line,
+                IndexedGeometry::Area(poly) => &poly.exterior,
+            };
+            if let Some(bbox) = index::Aabb::from_points(points) {
+                items.push((bbox, IndexedFeature { hit, geometry }));
+            }
+        };
+
+        let roads = self.roads.iter().flat_map(|shard| {
+            let world = shard.to_world(&data_processor::road_bin_coord_ranges(&shard.data));
+            data_processor::roads_from_bin(&world).unwrap_or_default()
+        });
+        for (i, road) in roads.enumerate() {
+            let hit = FeatureHit {
+                layer: "roads",
+                index: i,
+                road_type: Some(road.road_type),
+            };
+            push(hit, IndexedGeometry::Line(road.coords));
+        }
+
+        let scene = &self.scene;
+        let areas = [
+            ("water", &scene.water),
+            ("parks", &scene.parks),
+            ("buildings", &scene.buildings),
+        ];
+        for (layer, data) in areas {
+            let world = data.to_world(&data_processor::polygon_bin_coord_ranges(&data.data));
+            let polys = data_processor::polygons_from_bin(&world).unwrap_or_default();
+            for (i, poly) in polys.into_iter().enumerate() {
+                let hit = FeatureHit {
+                    layer,
+                    index: i,
+                    road_type: None,
+                };
+                push(hit, IndexedGeometry::Area(poly));
+            }
+        }
+        index::RTree::new(items)
+    }
+}
+
+/// 按绘制顺序从上到下排序（道路在最上层），同层按序号
+fn sort_by_draw_order(mut hits: Vec<&FeatureHit>) -> Vec<&FeatureHit> {
+    let draw_order = |layer: &str| {
+        ["water", "parks", "buildings", "roads"]
+            .iter()
+            .position(|&l| l == layer)
+    };
+    hits.sort_by_key(|h| (std::cmp::Reverse(draw_order(h.layer)), h.index));
+    hits
+}
+
+/// 缓存的图层像素，`key` 为影响该图层的主题字段
+struct CachedLayer {
+    key: String,
+    pixmap: tiny_skia::Pixmap,
+    /// 绘制该图层时记录的统计，每次合成时重放
+    stats: Vec<draw_stats::LayerStats>,
+}
+
+/// 键不变时直接合成缓存图层，否则先在透明图层上重绘
+///
+/// 无论是否命中缓存，图层的绘制统计都会计入本次渲染
+fn composite_cached(
+    slot: &mut Option<CachedLayer>,
+    key: String,
+    renderer: &mut MapRenderer,
+    draw: impl FnOnce(&mut MapRenderer),
+) -> Result<(), String> {
+    if slot.as_ref().is_none_or(|layer| layer.key != key) {
+        let mut pixmap = None;
+        let stats = draw_stats::capture(|| pixmap = renderer.render_layer(draw));
+        let pixmap = pixmap.ok_or_else(|| "Failed to create layer".to_string())?;
+        *slot = Some(CachedLayer { key, pixmap, stats });
+    }
+    if let Some(layer) = slot {
+        renderer.composite_layer(&layer.pixmap);
+        for l in &layer.stats {
+            draw_stats::record(&l.layer, l.stats);
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(js_host, wasm_bindgen)]
+impl PosterSession {
+    /// 参数与 `render_map_binary` 相同
+    #[cfg(js_host)]
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        roads_shards: JsValue,
+        water_bin: JsValue,
+        parks_bin: JsValue,
+        config_json: &str,
+    ) -> Result<PosterSession, JsValue> {
+        let (layers, roads) = binary_layers(roads_shards, water_bin, parks_bin);
+        Self::create(layers, roads, config_json)
+    }
+
+    /// 从多图层容器创建会话（见 `pack_layers`）
+    #[cfg(js_host)]
+    pub fn from_container(container: JsValue, config_json: &str) -> Result<PosterSession, JsValue> {
+        let data = typed_array_to_vec(&container)
+            .ok_or_else(|| JsValue::from_str("Container must be a Float64Array or Float32Array"))?;
+        let mut layers = container::parse_container(&data).map_err(|e| JsValue::from_str(&e))?;
+        let roads = RoadShards::Owned(std::mem::take(&mut layers.roads));
+        Self::create(layers, roads, config_json)
+    }
+
+    /// 由 `export_prepared_scene` 导出的数据创建会话，跳过解析与投影
+    ///
+    /// `config_json` 可使用不同的尺寸与主题；中心点应与导出时相同
+    #[cfg(js_host)]
+    pub fn import_prepared_scene(
+        bytes: &[u8],
+        config_json: &str,
+    ) -> Result<PosterSession, JsValue> {
+        let config: RenderConfig = serde_json::from_str(config_json)
+            .map_err(|e| JsValue::from_str(&format!("Config JSON parse failed: {}", e)))?;
+        Self::from_prepared(bytes, config).map_err(|e| JsValue::from_str(&e))
+    }
+
+    fn from_prepared(bytes: &[u8], config: RenderConfig) -> Result<PosterSession, String> {
+        warnings::begin();
+        let mut prepared: PreparedScene = rmp_serde::from_slice(bytes)
+            .map_err(|e| format!("Prepared scene decode failed: {}", e))?;
+        if prepared.version != PREPARED_SCENE_VERSION {
+            return Err(format!(
+                "Unsupported prepared scene version {} (expected {})",
+                prepared.version, PREPARED_SCENE_VERSION
+            ));
+        }
+        let roads = std::mem::take(&mut prepared.roads);
+        let scene = BinaryScene::from_prepared(prepared, config)?;
+        Ok(Self::with_scene(scene, roads))
+    }
+
+    /// 导出已解析、投影的几何（MessagePack），可存入 IndexedDB 供 `import_prepared_scene` 使用
+    #[cfg(js_host)]
+    pub fn export_prepared_scene(&self) -> Result<Vec<u8>, JsValue> {
+        self.prepared_bytes().map_err(|e| JsValue::from_str(&e))
+    }
+
+    fn prepared_bytes(&self) -> Result<Vec<u8>, String> {
+        let prepared = PreparedScene {
+            roads: self.roads.clone(),
+            ..self.prepared_layers()
+        };
+        rmp_serde::to_vec(&prepared).map_err(|e| format!("Prepared scene encode failed: {}", e))
+    }
+
+    /// 除道路外的预处理几何
+    fn prepared_layers(&self) -> PreparedScene {
+        let scene = &self.scene;
+        PreparedScene {
+            version: PREPARED_SCENE_VERSION,
+            roads: Vec::new(),
+            water: scene.water.clone(),
+            parks: scene.parks.clone(),
+            buildings: scene.buildings.clone(),
+            pois: scene.pois.clone(),
+            routes: scene.routes.clone(),
+        }
+    }
+
+    /// 以新配置替换场景（复用已投影的几何），并清空图层缓存
+    fn replace_config(&mut self, config: RenderConfig) -> Result<(), String> {
+        warnings::begin();
+        self.scene = BinaryScene::from_prepared(self.prepared_layers(), config)?;
+        // 新配置重新产生的警告替换旧的同类警告，道路等未重新处理的图层保留原警告
+        let fresh = warnings::take();
+        self.prepare_warnings.retain(|w| {
+            w.code != warnings::WarningCode::Clamped
+                && !fresh.iter().any(|f| f.code == w.code && f.field == w.field)
+        });
+        self.prepare_warnings.extend(fresh);
+        self.water_layer = None;
+        self.parks_layer = None;
+        self.roads_layer = None;
+        self.feature_index = std::cell::OnceCell::new();
+        Ok(())
+    }
+
+    #[cfg(js_host)]
+    fn create(
+        layers: container::ContainerLayers,
+        road_shards: RoadShards,
+        config_json: &str,
+    ) -> Result<PosterSession, JsValue> {
+        let config: RenderConfig = serde_json::from_str(config_json)
+            .map_err(|e| JsValue::from_str(&format!("Config JSON parse failed: {}", e)))?;
+        Self::prepare(layers, road_shards, config).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// 投影全部几何并保存道路分片
+    fn prepare(
+        layers: container::ContainerLayers,
+        mut road_shards: RoadShards,
+        config: RenderConfig,
+    ) -> Result<PosterSession, RenderError> {
+        warnings::begin();
+        let scene = BinaryScene::new(layers, config)?;
+
+        let mut roads = Vec::with_capacity(road_shards.len());
+        for i in 0..road_shards.len() {
+            let mut shard = Vec::new();
+            if road_shards.load(i, &mut shard) {
+                scene
+                    .prepare_road_shard(&mut shard)
+                    .map_err(|e| RenderError::bad_geometry(e).with_field("roads"))?;
+                let ranges = data_processor::road_bin_coord_ranges(&shard);
+                roads.push(types::LocalBin::new(&shard, &ranges, scene.center_3857));
+            }
+        }
+        Ok(Self::with_scene(scene, roads))
+    }
+
+    fn with_scene(scene: BinaryScene, roads: Vec<types::LocalBin>) -> PosterSession {
+        PosterSession {
+            scene,
+            roads,
+            font: None,
+            water_layer: None,
+            parks_layer: None,
+            roads_layer: None,
+            feature_index: std::cell::OnceCell::new(),
+            prepare_warnings: warnings::take(),
+        }
+    }
+
+    /// 替换主题（JSON，与配置中的 theme 字段格式相同）
+    #[cfg(js_host)]
+    pub fn set_theme(&mut self, theme_json: &str) -> Result<(), JsValue> {
+        self.scene.config.theme = serde_json::from_str(theme_json)
+            .map_err(|e| JsValue::from_str(&format!("Theme JSON parse failed: {}", e)))?;
+        Ok(())
+    }
+
+    /// 替换城市与国家文字
+    pub fn set_text(&mut self, city: String, country: String) {
+        self.scene.config.display_city = city;
+        self.scene.config.display_country = country;
+    }
+
+    /// 设置文字位置（"top" / "bottom" 等，与配置中的 text_position 相同）
+    #[cfg(js_host)]
+    pub fn set_text_position(&mut self, position: String) -> Result<(), JsValue> {
+        let position = serde_json::from_value(serde_json::Value::String(position))
+            .map_err(|e| JsValue::from_str(&format!("Invalid text position: {}", e)))?;
+        self.scene.config.text_position = Some(position);
+        Ok(())
+    }
+
+    /// 使用自定义字体（传入空数组恢复默认字体）
+    pub fn set_font(&mut self, font_data: Vec<u8>) {
+        self.font = (!font_data.is_empty()).then_some(font_data);
+    }
+
+    /// 查询屏幕像素 (x, y) 处的要素，按绘制顺序从上到下返回
+    ///
+    /// `radius_px` 为命中半径（输出像素，默认 4）；返回 `{ layer, index, road_type? }` 数组
+    #[cfg(js_host)]
+    pub fn query_features_at(
+        &self,
+        x: f64,
+        y: f64,
+        radius_px: Option<f64>,
+    ) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.features_at(x, y, radius_px.unwrap_or(4.0)))
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+    }
+
+    /// `query_features_at` 的实现，返回命中的要素
+    fn features_at(&self, x: f64, y: f64, radius_px: f64) -> Vec<&FeatureHit> {
+        let radius_px = radius_px.max(0.0);
+        let p = self.screen_to_web_mercator(x, y);
+        let edge = self.screen_to_web_mercator(x + radius_px, y);
+        let radius = (edge.0 - p.0).hypot(edge.1 - p.1);
+
+        let hits = self
+            .index()
+            .query_point(p.0, p.1, radius)
+            .into_iter()
+            .filter(|f| f.hit_test(p, radius))
+            .map(|f| &f.hit)
+            .collect();
+        sort_by_draw_order(hits)
+    }
+
+    /// 查询经纬度 (lon, lat) 所在的面要素（水体、公园、建筑），按绘制顺序从上到下返回
+    ///
+    /// 返回 `{ layer, index }` 数组，点不在任何面要素内时为空数组
+    #[cfg(js_host)]
+    pub fn contains_point(&self, lon: f64, lat: f64) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.areas_containing(lon, lat))
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+    }
+
+    /// `contains_point` 的实现
+    fn areas_containing(&self, lon: f64, lat: f64) -> Vec<&FeatureHit> {
+        let p = projection::project_point(lon, lat);
+        let hits = self
+            .index()
+            .query_point(p.0, p.1, 0.0)
+            .into_iter()
+            .filter(|f| f.contains(p))
+            .map(|f| &f.hit)
+            .collect();
+        sort_by_draw_order(hits)
+    }
+
+    /// 查询距经纬度 (lon, lat) 最近的道路，用于将标记吸附到街道上
+    ///
+    /// `max_distance_m` 为最大地面距离（米，默认不限）；返回
+    /// `{ index, road_type, lon, lat, distance_m }`，其中 lon / lat 为道路上的最近点，
+    /// 范围内没有道路时返回 undefined
+    #[cfg(js_host)]
+    pub fn nearest_road(
+        &self,
+        lon: f64,
+        lat: f64,
+        max_distance_m: Option<f64>,
+    ) -> Result<JsValue, JsValue> {
+        let nearest = self.nearest_road_to(lon, lat, max_distance_m.unwrap_or(f64::INFINITY));
+        serde_wasm_bindgen::to_value(&nearest)
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+    }
+
+    /// `nearest_road` 的实现
+    fn nearest_road_to(&self, lon: f64, lat: f64, max_distance_m: f64) -> Option<NearestRoad> {
+        let p = projection::project_point(lon, lat);
+        let (feature, dist) = self.index().nearest(p.0, p.1, |f| {
+            f.closest_point(p).map(|c| (c.0 - p.0).hypot(c.1 - p.1))
+        })?;
+        // Web Mercator 距离按纬度缩放为地面距离（与 RadiusMode::Ground 一致）
+        let distance_m = dist * lat.to_radians().cos();
+        if distance_m > max_distance_m {
+            return None;
+        }
+        let (x, y) = feature.closest_point(p)?;
+        let (lon, lat) = projection::unproject_point(x, y);
+        Some(NearestRoad {
+            index: feature.hit.index,
+            road_type: feature.hit.road_type?,
+            lon,
+            lat,
+            distance_m,
+        })
+    }
+
+    /// 以当前主题与文字重新渲染
+    pub fn render(&mut self) -> RenderResult {
+        let PosterSession {
+            scene,
+            roads,
+            font,
+            water_layer,
+            parks_layer,
+            roads_layer,
+            prepare_warnings,
+            ..
+        } = self;
+        memory::begin_render();
+        profile::begin();
+        draw_stats::begin();
+        warnings::begin();
+        warnings::extend(prepare_warnings);
+        scene.record_memory();
+        let mut renderer = match scene.create_renderer() {
+            Ok(r) => r,
+            Err(e) => return e.into(),
+        };
+        renderer.draw_background();
+
+        let theme = &scene.config.theme;
+        let water_key = theme.water.clone();
+        let parks_key = format!("{}|{:?}", theme.parks, theme.buildings);
+        let roads_key = [
+            &theme.road_motorway,
+            &theme.road_primary,
+            &theme.road_secondary,
+            &theme.road_tertiary,
+            &theme.road_residential,
+            &theme.road_default,
+        ]
+        .map(String::as_str)
+        .join("|");
+
+        let mut drawn = Ok(());
+        let composited = composite_cached(water_layer, water_key, &mut renderer, |r| {
+            scene.draw_water(r)
+        })
+        .and_then(|_| {
+            composite_cached(parks_layer, parks_key, &mut renderer, |r| {
+                scene.draw_parks(r)
+            })
+        })
+        .and_then(|_| {
+            composite_cached(roads_layer, roads_key, &mut renderer, |r| {
+                drawn = scene.draw_roads(r, |draw| {
+                    roads.iter().for_each(|shard| draw(ShardRef::Local(shard)));
+                    Ok(())
+                });
+            })
+        })
+        .and(drawn);
+        if let Err(e) = composited {
+            return RenderResult::error(e);
+        }
+
+        scene.finish(renderer, font.as_deref())
+    }
+}
+
+/// 主渲染函数 (MessagePack 版本)
+#[cfg_attr(js_host, wasm_bindgen)]
+pub fn render_map_msgpack(request_bin: &[u8]) -> RenderResult {
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
+    time("render_map: msgpack_parse");
+    let request: RenderRequest = match rmp_serde::from_slice(request_bin) {
+        Ok(req) => req,
+        Err(e) => {
+            return RenderError::config_parse("Failed to parse MessagePack request", e).into();
+        }
+    };
+    time_end("render_map: msgpack_parse");
+
+    render_map_internal(request)
+}
+
+/// 渲染带几何数据的请求（Rust 接口，原生与 wasm32 目标均可用）
+pub fn render_request(request: RenderRequest) -> RenderResult {
+    profile::begin();
+    draw_stats::begin();
+    warnings::begin();
+    render_map_internal(request)
+}
+
+/// 渲染二进制几何（Rust 接口，格式同 `render_map_binary`）
+///
+/// `font_data` 为空时按配置中的 `font` 从字体注册表选用
+pub fn render_binary(
+    roads_shards: Vec<Vec<f64>>,
+    water: Vec<f64>,
+    parks: Vec<f64>,
+    config: RenderConfig,
+    font_data: Option<&[u8]>,
+) -> RenderResult {
+    let layers = container::ContainerLayers {
+        water,
+        parks,
+        ..Default::default()
+    };
+    let roads = RoadShards::Owned(roads_shards);
+    render_map_binary_internal(
+        layers,
+        roads,
+        ConfigSource::Parsed(Box::new(config)),
+        font_data,
+    )
+}
+
+/// JSON / MessagePack 请求转为二进制图层后走与 `render_map_binary` 相同的管线
+fn render_map_internal(request: RenderRequest) -> RenderResult {
+    let RenderRequest {
+        mut config,
+        roads,
+        water,
+        parks,
+        needs_projection,
+    } = request;
+
+    // 确定几何数据的坐标参考系；请求中的 POI 与其它几何使用同一坐标系
+    let crs = match resolve_request_crs(config.crs, needs_projection) {
+        Ok(c) => c,
+        Err(e) => return RenderError::invalid_config(e).with_field("crs").into(),
+    };
+    config.crs = Some(crs);
+    config.poi_crs = crs;
+
+    let layers = container::ContainerLayers {
+        water: data_processor::polygons_to_bin(&water),
+        parks: data_processor::polygons_to_bin(&parks),
+        ..Default::default()
+    };
+    let roads = RoadShards::Owned(vec![data_processor::roads_to_bin(&roads)]);
+    render_map_binary_internal(layers, roads, ConfigSource::Parsed(Box::new(config)), None)
+}
+
+/// 确定请求中几何数据的坐标参考系
+///
+/// 显式的 `crs` 优先；未声明时沿用 `needs_projection` 的含义
+fn resolve_request_crs(
+    crs: Option<types::Crs>,
+    needs_projection: bool,
+) -> Result<types::Crs, String> {
+    match (crs, needs_projection) {
+        (Some(types::Crs::Epsg3857), true) => Err(
+            "crs 'epsg3857' conflicts with needs_projection=true; declare only one of them"
+                .to_string(),
+        ),
+        (Some(crs), _) => Ok(crs),
+        (None, true) => Ok(types::Crs::Wgs84),
+        (None, false) => Ok(types::Crs::Epsg3857),
+    }
+}
+
+/// 解析可选的道路等级过滤配置
+#[cfg(js_host)]
+fn parse_road_filter(json: Option<String>) -> Result<types::RoadFilter, JsValue> {
+    json.map_or(Ok(types::RoadFilter::default()), |j| {
+        serde_json::from_str(&j)
+            .map_err(|e| JsValue::from_str(&format!("Error parsing road filter: {}", e)))
+    })
+}
+
+/// 解析可选的剔除范围（经纬度 `GeoBounds` JSON），并按默认边距扩展
+#[cfg(js_host)]
+fn parse_cull_viewport(json: Option<String>) -> Result<Option<cull::Viewport>, JsValue> {
+    json.map(|j| {
+        serde_json::from_str::<types::GeoBounds>(&j)
+            .map(|b| cull::Viewport::from_bounds(&b, cull::DEFAULT_CULL_MARGIN))
+            .map_err(|e| JsValue::from_str(&format!("Error parsing cull bounds: {}", e)))
+    })
+    .transpose()
+}
+
+/// 将 JS 传入的道路分片（单个 Float64Array / Float32Array 或其数组）收集为 Vec
+#[cfg(js_host)]
+fn collect_road_shards(roads_shards: &JsValue) -> Vec<Vec<f64>> {
+    if js_sys::Array::is_array(roads_shards) {
+        js_sys::Array::from(roads_shards)
+            .iter()
+            .filter_map(|v| typed_array_to_vec(&v))
+            .collect()
+    } else {
+        typed_array_to_vec(roads_shards).into_iter().collect()
+    }
+}
+
+/// 读取 Float64Array 或 Float32Array（同样的布局），兼容普通数字数组，其它类型返回 None
+#[cfg(js_host)]
+fn typed_array_to_vec(value: &JsValue) -> Option<Vec<f64>> {
+    let mut buf = Vec::new();
+    read_typed_array_into(value, &mut buf).then_some(buf)
+}
+
+/// 将类型化数组读入 `buf`（覆盖原内容并复用其容量），类型不支持时返回 false
+///
+/// Float64Array 通过 `copy_to` 直接写入缓冲区，不产生中间 Vec
+#[cfg(js_host)]
+fn read_typed_array_into(value: &JsValue, buf: &mut Vec<f64>) -> bool {
+    buf.clear();
+    if let Some(a) = value.dyn_ref::<js_sys::Float64Array>() {
+        buf.resize(a.length() as usize, 0.0);
+        a.copy_to(buf);
+    } else if let Some(a) = value.dyn_ref::<js_sys::Float32Array>() {
+        buf.extend(a.to_vec().into_iter().map(f64::from));
+    } else if let Some(a) = value.dyn_ref::<js_sys::Array>() {
+        let values: Option<Vec<f64>> = a.iter().map(|v| v.as_f64()).collect();
+        match values {
+            Some(values) => buf.extend(values),
+            None => return false,
+        }
+    } else {
+        return false;
+    }
+    true
+}
+
+/// 投影区域多边形（输入为 WGS84 经纬度）并裁剪区域外的内容
+fn apply_region_mask(
+    renderer: &mut MapRenderer,
+    mut region: Vec<types::PolyFeature>,
+    outside_opacity: f32,
+) {
+    for poly in region.iter_mut() {
+        project_points_mut(&mut poly.exterior);
+        for interior in poly.interiors.iter_mut() {
+            project_points_mut(interior);
+        }
     }
+    renderer.apply_region_mask(&region, outside_opacity);
+}
 
-    log(&format!(
-        "[Render] Elements: {} roads, {} water polygons, {} parks, {} POIs",
-        total_roads, water_count, parks_count, poi_count
-    ));
-    log(&format!(
-        "[Render] Roads by type: Motorway={}, Primary={}, Secondary={}, Tertiary={}, Residential={}, Default={}",
-        road_type_counts[0],
-        road_type_counts[1],
-        road_type_counts[2],
-        road_type_counts[3],
-        road_type_counts[4],
-        road_type_counts[5]
-    ));
-
-    // 3. 创建渲染器
-    let text_pos = config.text_position.unwrap_or(types::TextPosition::Top);
-    let mut renderer =
-        match MapRenderer::new(config.width, config.height, config.theme, bounds, text_pos) {
-            Some(r) => r,
-            None => return RenderResult::error("Failed to create renderer".to_string()),
-        };
-
-    // 4. 绘制
-    time("render_map_bin: draw_background");
-    renderer.draw_background();
-    time_end("render_map_bin: draw_background");
-
-    let water_color = renderer.get_theme().water.clone();
-    let parks_color = renderer.get_theme().parks.clone();
+/// 计算视图的比例尺与近似缩放级别
+///
+/// 参数与渲染配置中的 center / radius / radius_mode / bounds / width / height 字段一致，
+/// 前端可据此选择 OSM 数据的缩放级别，或在海报上显示 "1:25,000"
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn compute_scale(view_json: &str) -> Result<types::ScaleInfo, JsValue> {
+    let view: types::ViewConfig = serde_json::from_str(view_json)
+        .map_err(|e| JsValue::from_str(&format!("View JSON parse failed: {}", e)))?;
+    let (bounds, projector) = resolve_view(&view).map_err(|e| JsValue::from_str(&e))?;
+    let (mpp, zoom, denominator) = projection::compute_scale(
+        &bounds,
+        view.center.lon,
+        view.center.lat,
+        view.width,
+        PNG_DPI,
+        projector,
+    );
+    Ok(types::ScaleInfo::new(mpp, zoom, denominator))
+}
 
-    time("render_map_bin: draw_water");
-    renderer.draw_polygons_bin(water_bin, &water_color);
-    time_end("render_map_bin: draw_water");
+/// 渲染视图的坐标变换（与渲染时使用的边界完全一致）
+///
+/// 屏幕坐标以输出 PNG 的像素为单位，原点在左上角，前端可用于在预览图上
+/// 点击添加 POI 等交互
+#[cfg_attr(js_host, wasm_bindgen)]
+pub struct MapView {
+    bounds: types::BoundingBox,
+    projector: Projector,
+    width: u32,
+    height: u32,
+}
 
-    time("render_map_bin: draw_parks");
-    renderer.draw_polygons_bin(parks_bin, &parks_color);
-    time_end("render_map_bin: draw_parks");
+#[cfg_attr(js_host, wasm_bindgen)]
+impl MapView {
+    #[cfg(js_host)]
+    #[wasm_bindgen(constructor)]
+    pub fn new(view_json: &str) -> Result<MapView, JsValue> {
+        Self::from_json(view_json).map_err(|e| JsValue::from_str(&e))
+    }
 
-    time("render_map_bin: draw_roads");
+    fn from_json(view_json: &str) -> Result<MapView, String> {
+        let view: types::ViewConfig = serde_json::from_str(view_json)
+            .map_err(|e| format!("View JSON parse failed: {}", e))?;
+        let (bounds, projector) = resolve_view(&view)?;
+        Ok(MapView {
+            bounds,
+            projector,
+            width: view.width,
+            height: view.height,
+        })
+    }
 
-    let road_width_scale = types::calculate_road_width_scale(
-        config.selected_size_height as f32,
-        config.frontend_scale,
-        config.road_width_boost,
-    );
+    /// 投影边界 [min_x, min_y, max_x, max_y]（输出投影平面坐标，米）
+    pub fn get_bounds(&self) -> Vec<f64> {
+        vec![
+            self.bounds.min_x,
+            self.bounds.min_y,
+            self.bounds.max_x,
+            self.bounds.max_y,
+        ]
+    }
 
-    let mut total_timings = [0.0; 6];
+    /// 投影平面坐标 -> 屏幕像素的仿射变换 [a, b, c, d, e, f]
+    ///
+    /// 与 Canvas `setTransform` 的参数顺序一致：
+    /// `sx = a * x + c * y + e`，`sy = b * x + d * y + f`
+    pub fn get_transform(&self) -> Vec<f64> {
+        let (kx, ky) = self.factors();
+        vec![
+            kx,
+            0.0,
+            0.0,
+            -ky,
+            -self.bounds.min_x * kx,
+            self.bounds.max_y * ky,
+        ]
+    }
 
-    if js_sys::Array::is_array(&roads_shards) {
-        let shards_array = js_sys::Array::from(&roads_shards);
-        for shard_val in shards_array.iter() {
-            if let Some(shard_typed) = shard_val.dyn_ref::<js_sys::Float64Array>() {
-                let timings =
-                    renderer.draw_roads_bin_scaled(&shard_typed.to_vec(), road_width_scale);
-                for i in 0..6 {
-                    total_timings[i] += timings[i];
-                }
-            }
-        }
-    } else if let Some(shard_typed) = roads_shards.dyn_ref::<js_sys::Float64Array>() {
-        total_timings = renderer.draw_roads_bin_scaled(&shard_typed.to_vec(), road_width_scale);
-    }
-
-    time_end("render_map_bin: draw_roads");
-
-    log("render_map_bin: draw_roads breakdown:");
-    log(&format!("  Motorway: {:.2}ms", total_timings[0]));
-    log(&format!("  Primary: {:.2}ms", total_timings[1]));
-    log(&format!("  Secondary: {:.2}ms", total_timings[2]));
-    log(&format!("  Tertiary: {:.2}ms", total_timings[3]));
-    log(&format!("  Residential: {:.2}ms", total_timings[4]));
-    log(&format!("  Default: {:.2}ms", total_timings[5]));
-
-    // 投影并绘制 POI
-    if let Some(pois_data) = &config.pois {
-        if !pois_data.is_empty() && pois_data[0] as usize > 0 {
-            let mut projected_pois = pois_data.clone();
-            let poi_count = projected_pois[0] as usize;
-            for i in 0..poi_count {
-                let offset = 1 + i * 2;
-                let (proj_lon, proj_lat) = projection::project_point(
-                    projected_pois[offset],     // lon
-                    projected_pois[offset + 1], // lat
-                );
-                projected_pois[offset] = proj_lon;
-                projected_pois[offset + 1] = proj_lat;
-            }
+    /// 屏幕像素 -> 经纬度 [lon, lat]
+    pub fn screen_to_lonlat(&self, x: f64, y: f64) -> Vec<f64> {
+        let (kx, ky) = self.factors();
+        let wx = self.bounds.min_x + x / kx;
+        let wy = self.bounds.max_y - y / ky;
+        let (lon, lat) = self.projector.inverse(wx, wy);
+        vec![lon, lat]
+    }
 
-            time("render_map_bin: draw_pois");
-            renderer.draw_pois_bin(&projected_pois);
-            time_end("render_map_bin: draw_pois");
+    /// 经纬度 -> 屏幕像素 [x, y]
+    pub fn lonlat_to_screen(&self, lon: f64, lat: f64) -> Vec<f64> {
+        let (kx, ky) = self.factors();
+        let (mut wx, wy) = self.projector.forward(lon, lat);
+        if self.projector == Projector::WebMercator {
+            let center_x = (self.bounds.min_x + self.bounds.max_x) / 2.0;
+            wx = projection::wrap_x(wx, center_x);
         }
+        vec![(wx - self.bounds.min_x) * kx, (self.bounds.max_y - wy) * ky]
     }
 
-    time("render_map_bin: draw_gradients");
-    renderer.draw_gradients();
-    time_end("render_map_bin: draw_gradients");
-
-    // 4. 绘制文字 (使用传入的字体数据)
-    if let Err(e) = renderer.draw_text(
-        &config.display_city,
-        &config.display_country,
-        config.center.lat,
-        config.center.lon,
-        font_data,
-    ) {
-        return RenderResult::error(format!("Failed to draw text: {}", e));
+    fn factors(&self) -> (f64, f64) {
+        (
+            self.width as f64 / self.bounds.width(),
+            self.height as f64 / self.bounds.height(),
+        )
     }
+}
 
-    // 5. 编码为 PNG
-    time("render_map_bin: encode_png");
-    let png_data = match renderer.encode_png(300) {
-        Ok(data) => data,
-        Err(e) => return RenderResult::error(format!("PNG encoding failed: {}", e)),
-    };
-    time_end("render_map_bin: encode_png");
+/// 内存使用统计
+///
+/// 返回 `{ heap_bytes, allocated_bytes, peak_bytes, layers: { roads, water, parks, buildings, pois, routes } }`，
+/// 其中 peak_bytes 与 layers 为最近一次渲染的数据
+/// 注册字体，配置中的 `font` 字段按名称选用
+///
+/// 名称为 "default" 的字体在未指定 `font` 时优先于内嵌字体；
+/// 以 `--no-default-features` 构建（不内嵌 Roboto）时必须先注册
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn register_font(name: &str, font_data: Vec<u8>) -> Result<(), JsValue> {
+    fonts::register(name, font_data)
+        .map_err(|e| JsValue::from_str(&format!("Error registering font: {}", e)))
+}
 
-    RenderResult::success(config.width, config.height, png_data)
+/// 注册叠加图片（PNG 字节），配置中的 `logo.image` 按名称选用
+///
+/// 注册时即解码并缓存，多次渲染不重复解码
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn register_image(name: &str, image_data: &[u8]) -> Result<(), JsValue> {
+    images::register(name, image_data)
+        .map_err(|e| JsValue::from_str(&format!("Error registering image: {}", e)))
 }
 
-/// 主渲染函数 (MessagePack 版本)
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn render_map_msgpack(request_bin: &[u8]) -> RenderResult {
-    time("render_map: msgpack_parse");
-    let request: RenderRequest = match rmp_serde::from_slice(request_bin) {
-        Ok(req) => req,
-        Err(e) => {
-            return RenderResult::error(format!("Failed to parse MessagePack request: {}", e));
-        }
-    };
-    time_end("render_map: msgpack_parse");
+pub fn get_memory_stats() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&memory::stats())
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}
 
-    render_map_internal(request)
+/// 获取版本信息
+#[cfg_attr(js_host, wasm_bindgen)]
+pub fn get_version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
 }
 
-fn render_map_internal(mut request: RenderRequest) -> RenderResult {
-    // 2. 检查并执行投影（可选）
-    if request.needs_projection {
-        time("render_map: projection_pass");
-        for road in request.roads.iter_mut() {
-            project_points_mut(&mut road.coords);
-        }
-        for poly in request.water.iter_mut() {
-            project_points_mut(&mut poly.exterior);
-            for interior in poly.interiors.iter_mut() {
-                project_points_mut(interior);
-            }
-        }
-        for poly in request.parks.iter_mut() {
-            project_points_mut(&mut poly.exterior);
-            for interior in poly.interiors.iter_mut() {
-                project_points_mut(interior);
-            }
-        }
-        // 投影 POI 点
-        for poi in request.pois.iter_mut() {
-            let mut coords = vec![(poi.x, poi.y)];
-            project_points_mut(&mut coords);
-            poi.x = coords[0].0;
-            poi.y = coords[0].1;
-        }
-        time_end("render_map: projection_pass");
-    }
+/// 获取当前构建的能力描述：输出格式、投影、画布上限、二进制格式版本与启用的 feature
+#[cfg(js_host)]
+#[wasm_bindgen(unchecked_return_type = "Capabilities")]
+pub fn get_capabilities() -> JsValue {
+    serde_wasm_bindgen::to_value(&capabilities::capabilities()).unwrap_or(JsValue::NULL)
+}
 
-    // 3. 计算边界框
-    let bounds = calculate_bounds(
-        request.center.lat,
-        request.center.lon,
-        request.radius,
-        request.width,
-        request.height,
-    );
+/// 获取纸张尺寸预设（A 系列、英寸与厘米海报尺寸、方形），按 `dpi`（缺省 300）换算为像素宽高
+/// 与对应的 `selected_size_height`
+#[cfg(js_host)]
+#[wasm_bindgen(unchecked_return_type = "PaperPreset[]")]
+pub fn get_paper_presets(dpi: Option<f64>) -> Result<JsValue, JsValue> {
+    let presets = paper::paper_presets(dpi.unwrap_or(paper::DEFAULT_DPI))
+        .map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&presets)
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}
 
-    // 4. 创建渲染器
-    let text_pos = request.text_position.unwrap_or(types::TextPosition::Top);
-    let mut renderer = match MapRenderer::new(
-        request.width,
-        request.height,
-        request.theme,
-        bounds,
-        text_pos,
-    ) {
-        Some(r) => r,
-        None => return RenderResult::error("Failed to create renderer".to_string()),
-    };
+/// 预检渲染请求（不绘制）
+///
+/// 检查尺寸、中心点、半径范围、颜色格式与道路分片长度（`shard_lengths` 为各分片的元素数），
+/// 返回全部问题组成的 `RenderError` 数组，无问题时为空数组
+#[cfg(js_host)]
+#[wasm_bindgen(unchecked_return_type = "RenderError[]")]
+pub fn validate_request(config_json: &str, shard_lengths: Vec<u32>) -> JsValue {
+    let errors = validate_request_inner(config_json, &shard_lengths);
+    serde_wasm_bindgen::to_value(&errors).unwrap_or(JsValue::NULL)
+}
 
-    // 5. 按顺序绘制图层
-    time("render_map: draw_background");
-    renderer.draw_background();
-    time_end("render_map: draw_background");
-
-    time("render_map: draw_water");
-    renderer.draw_water(&request.water);
-    time_end("render_map: draw_water");
-
-    time("render_map: draw_parks");
-    renderer.draw_parks(&request.parks);
-    time_end("render_map: draw_parks");
-
-    time("render_map: draw_roads");
-    // 计算动态道路线宽缩放因子并调用缩放绘制方法
-    let road_width_scale = types::calculate_road_width_scale(
-        request.selected_size_height as f32,
-        request.frontend_scale,
-        request.road_width_boost,
-    );
-    renderer.draw_roads_scaled(&request.roads, road_width_scale);
-    time_end("render_map: draw_roads");
-
-    // 绘制 POI
-    if !request.pois.is_empty() {
-        time("render_map: draw_pois");
-        renderer.draw_pois(&request.pois);
-        time_end("render_map: draw_pois");
-    }
-
-    time("render_map: draw_gradients");
-    renderer.draw_gradients();
-    time_end("render_map: draw_gradients");
-
-    // 6. 绘制文字
-    if let Err(e) = renderer.draw_text(
-        &request.display_city,
-        &request.display_country,
-        request.center.lat,
-        request.center.lon,
-        ROBOTO_REGULAR,
-    ) {
-        return RenderResult::error(format!("Failed to draw text: {}", e));
-    }
-
-    // 7. 编码为 PNG
-    time("render_map: encode_png");
-    let png_data = match renderer.encode_png(300) {
-        Ok(data) => data,
-        Err(e) => return RenderResult::error(format!("PNG encoding failed: {}", e)),
+fn validate_request_inner(config_json: &str, shard_lengths: &[u32]) -> Vec<RenderError> {
+    let mut errors = match serde_json::from_str::<RenderConfig>(config_json) {
+        Ok(config) => validate::validate_config(&config),
+        Err(e) => vec![RenderError::config_parse("Config JSON parse failed", e)],
     };
-    time_end("render_map: encode_png");
-
-    RenderResult::success(request.width, request.height, png_data)
+    errors.extend(validate::validate_shard_lengths(shard_lengths));
+    errors
 }
 
-/// 获取版本信息
+/// 计算渲染输入的稳定哈希（32 位十六进制字符串），用作 PNG 缓存键
+///
+/// 覆盖解析后的完整配置（缺省字段已填充，写法不同但等价的配置哈希相同）、
+/// 按顺序的几何分片摘要（由调用方计算，如分片的 SHA-256）、所用字体与 WASM 版本
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn get_version() -> String {
-    env!("CARGO_PKG_VERSION").to_string()
+pub fn hash_request(config_json: &str, shard_digests: Vec<String>) -> Result<String, JsValue> {
+    hash_request_inner(config_json, &shard_digests).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn hash_request_inner(config_json: &str, shard_digests: &[String]) -> Result<String, RenderError> {
+    let config: RenderConfig = serde_json::from_str(config_json)
+        .map_err(|e| RenderError::config_parse("Config JSON parse failed", e))?;
+    let canonical = serde_json::to_vec(&config)
+        .map_err(|e| RenderError::invalid_config(format!("Config serialize failed: {}", e)))?;
+
+    let mut hasher = hash::Fnv128::new();
+    hasher.write_field(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.write_field(&canonical);
+    // 同名字体可被重新注册，按字体内容计入
+    let font = fonts::resolve(config.font.as_deref()).unwrap_or_else(|_| Vec::new().into());
+    hasher.write_field(&font);
+    hasher.write(&(shard_digests.len() as u64).to_le_bytes());
+    for digest in shard_digests {
+        hasher.write_field(digest.as_bytes());
+    }
+    Ok(hasher.finish_hex())
+}
+
+/// 获取默认主题（JSON），可作为主题编辑器的初始值
+#[cfg_attr(js_host, wasm_bindgen)]
+pub fn get_default_theme() -> String {
+    serde_json::to_string(&types::Theme::default()).unwrap_or_default()
 }
 
-fn parse_pois_json(_pois_json: &str) -> Result<Vec<types::POI>, String> {
-    // POI JSON 格式：扁平数组 [poi_count, x1, y1, x2, y2, ...]
-    // 为了简单起见，直接返回空 POI 列表，因为 POI 数据应该已经是二进制格式通过 config 传递
-    Ok(vec![])
+/// 获取主题的 JSON Schema，列出全部字段、是否必填及默认值
+#[cfg_attr(js_host, wasm_bindgen)]
+pub fn get_theme_schema() -> String {
+    types::Theme::json_schema().to_string()
 }
 
+#[cfg(js_host)]
 #[wasm_bindgen]
 pub fn parse_roads_to_bin(geojson_str: &str) -> Result<JsValue, JsValue> {
     let roads = parse_roads(geojson_str)
@@ -500,14 +2452,20 @@ pub fn parse_roads_to_bin(geojson_str: &str) -> Result<JsValue, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+/// 解析道路 GeoJSON 对象
+///
+/// `road_filter` 为可选的 JSON，例如 `{"min_class":"secondary"}`，低等级道路在解析时即被丢弃
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn parse_roads_val(geojson: JsValue) -> Result<JsValue, JsValue> {
-    let roads = data_processor::parse_roads_js(geojson)
+pub fn parse_roads_val(geojson: JsValue, road_filter: Option<String>) -> Result<JsValue, JsValue> {
+    let filter = parse_road_filter(road_filter)?;
+    let roads = data_processor::parse_roads_js(geojson, &filter)
         .map_err(|e| JsValue::from_str(&format!("Error parsing roads object: {}", e)))?;
     serde_wasm_bindgen::to_value(&roads)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+#[cfg(js_host)]
 #[wasm_bindgen]
 pub fn parse_polygons_to_bin(geojson_str: &str) -> Result<JsValue, JsValue> {
     let polys = parse_polygons(geojson_str)
@@ -516,6 +2474,7 @@ pub fn parse_polygons_to_bin(geojson_str: &str) -> Result<JsValue, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+#[cfg(js_host)]
 #[wasm_bindgen]
 pub fn parse_polygons_val(geojson: JsValue) -> Result<JsValue, JsValue> {
     let polys = data_processor::parse_polygons_js(geojson)
@@ -524,34 +2483,486 @@ pub fn parse_polygons_val(geojson: JsValue) -> Result<JsValue, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+/// `cull_bounds` 为可选的经纬度范围 JSON（`GeoBounds`），视口外的道路在投影前即被剔除
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn parse_roads_typed(data: &[f64]) -> Result<JsValue, JsValue> {
-    let roads = data_processor::parse_roads_bin(data)
+pub fn parse_roads_typed(
+    data: &[f64],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<JsValue, JsValue> {
+    parse_roads_typed_internal(data, road_filter, cull_bounds)
+}
+
+/// `parse_roads_typed` 的 Float32Array 版本
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_roads_typed_f32(
+    data: &[f32],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<JsValue, JsValue> {
+    parse_roads_typed_internal(data, road_filter, cull_bounds)
+}
+
+#[cfg(js_host)]
+fn parse_roads_typed_internal<T: Copy + Into<f64>>(
+    data: &[T],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let filter = parse_road_filter(road_filter)?;
+    let viewport = parse_cull_viewport(cull_bounds)?;
+    let roads = data_processor::parse_roads_bin(data, &filter, viewport.as_ref())
         .map_err(|e| JsValue::from_str(&format!("Error parsing roads binary: {}", e)))?;
     serde_wasm_bindgen::to_value(&roads)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+/// `cull_bounds` 含义同 `parse_roads_typed`，跨越视口的多边形会被裁剪
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_polygons_typed(data: &[f64], cull_bounds: Option<String>) -> Result<JsValue, JsValue> {
+    parse_polygons_typed_internal(data, cull_bounds)
+}
+
+/// `parse_polygons_typed` 的 Float32Array 版本
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn parse_polygons_typed(data: &[f64]) -> Result<JsValue, JsValue> {
-    let polys = data_processor::parse_polygons_bin(data)
+pub fn parse_polygons_typed_f32(
+    data: &[f32],
+    cull_bounds: Option<String>,
+) -> Result<JsValue, JsValue> {
+    parse_polygons_typed_internal(data, cull_bounds)
+}
+
+#[cfg(js_host)]
+fn parse_polygons_typed_internal<T: Copy + Into<f64>>(
+    data: &[T],
+    cull_bounds: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let viewport = parse_cull_viewport(cull_bounds)?;
+    let polys = data_processor::parse_polygons_bin(data, viewport.as_ref())
         .map_err(|e| JsValue::from_str(&format!("Error parsing polygons binary: {}", e)))?;
     serde_wasm_bindgen::to_value(&polys)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
 }
 
+/// 直接解析 Overpass API 的原始 JSON，跳过前端的 osmtogeojson 转换
+///
+/// 返回 `{ roads, water, parks, pois }`，均为 Float64Array：
+/// 道路 / 水体 / 公园已投影为 Web Mercator，可直接传给 `render_map_binary`；
+/// POI 保持经纬度，对应配置中默认的 `poi_crs: "wgs84"`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_overpass(json: &str) -> Result<JsValue, JsValue> {
+    let layers = data_processor::parse_overpass(json)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing Overpass JSON: {}", e)))?;
+    osm_layers_to_js(layers)
+}
+
+/// 按标签规则将混合 GeoJSON 拆分为各图层，返回格式同 `parse_overpass`
+///
+/// `rules_json` 为规则数组 `[{ key, values, layer: "roads" | "water" | "parks" }]`，
+/// 按顺序匹配；缺省时使用与前端 presets 一致的默认规则
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_geojson_layers(json: &str, rules_json: Option<String>) -> Result<JsValue, JsValue> {
+    let custom_rules: Option<Vec<style::StyleRule>> = rules_json
+        .map(|r| serde_json::from_str(&r))
+        .transpose()
+        .map_err(|e| JsValue::from_str(&format!("Error parsing style rules: {}", e)))?;
+    let rules = custom_rules.as_deref().unwrap_or(&style::DEFAULT_RULES);
+    let layers = data_processor::split_geojson_layers(json.as_bytes(), rules)
+        .map_err(|e| JsValue::from_str(&format!("Error splitting GeoJSON layers: {}", e)))?;
+    osm_layers_to_js(layers)
+}
+
+/// 解析 openstreetmap.org 导出的 .osm XML，返回格式同 `parse_overpass`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_osm_xml(xml: &str) -> Result<JsValue, JsValue> {
+    let layers = data_processor::parse_osm_xml(xml)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing OSM XML: {}", e)))?;
+    osm_layers_to_js(layers)
+}
+
+/// 解码一组 Mapbox Vector Tile，返回格式同 `parse_overpass`
+///
+/// `tiles` 为 `{ z, x, y, data: Uint8Array }` 对象数组
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_mvt_tiles(tiles: js_sys::Array) -> Result<JsValue, JsValue> {
+    let get_u32 = |obj: &JsValue, key: &str| -> Result<u32, JsValue> {
+        js_sys::Reflect::get(obj, &JsValue::from_str(key))?
+            .as_f64()
+            .map(|v| v as u32)
+            .ok_or_else(|| JsValue::from_str(&format!("MVT tile is missing numeric '{}'", key)))
+    };
+
+    let mut owned = Vec::with_capacity(tiles.length() as usize);
+    for tile in tiles.iter() {
+        let data = js_sys::Reflect::get(&tile, &JsValue::from_str("data"))?;
+        let data = data
+            .dyn_ref::<js_sys::Uint8Array>()
+            .ok_or_else(|| JsValue::from_str("MVT tile 'data' must be a Uint8Array"))?
+            .to_vec();
+        owned.push((
+            get_u32(&tile, "z")?,
+            get_u32(&tile, "x")?,
+            get_u32(&tile, "y")?,
+            data,
+        ));
+    }
+    let tiles: Vec<mvt::MvtTile> = owned
+        .iter()
+        .map(|(z, x, y, data)| mvt::MvtTile {
+            z: *z,
+            x: *x,
+            y: *y,
+            data,
+        })
+        .collect();
+
+    let layers = mvt::decode_tiles(&tiles)
+        .map_err(|e| JsValue::from_str(&format!("Error decoding vector tiles: {}", e)))?;
+    osm_layers_to_js(layers)
+}
+
+/// 读取 FlatGeobuf 文件，利用其空间索引只解码与 `bounds_json` 相交的要素
+///
+/// `bounds_json` 为 `GeoBounds` JSON，传空字符串时读取全部要素；
+/// 线要素输出为 `roads`，面要素输出到 `polygon_layer` 指定的 `water` 或 `parks`，
+/// 其余字段格式同 `parse_overpass`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_flatgeobuf(
+    data: &[u8],
+    bounds_json: &str,
+    polygon_layer: &str,
+) -> Result<JsValue, JsValue> {
+    let bounds: Option<types::GeoBounds> = if bounds_json.trim().is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(bounds_json)
+                .map_err(|e| JsValue::from_str(&format!("Failed to parse bounds: {}", e)))?,
+        )
+    };
+    let (roads, polys) = fgb::read_flatgeobuf(data, bounds.as_ref())
+        .map_err(|e| JsValue::from_str(&format!("Error reading FlatGeobuf: {}", e)))?;
+
+    let mut layers = data_processor::OsmLayers {
+        roads,
+        ..Default::default()
+    };
+    match polygon_layer {
+        "water" => layers.water = polys,
+        "parks" => layers.parks = polys,
+        other => {
+            return Err(JsValue::from_str(&format!(
+                "Unknown polygon_layer '{}', expected 'water' or 'parks'",
+                other
+            )));
+        }
+    }
+    osm_layers_to_js(layers)
+}
+
+/// 导入 KML 或 KMZ（如 Google My Maps 导出），按 ZIP 魔数自动识别
+///
+/// 返回 `{ pois, routes }`，均为 WGS84 经纬度的 Float64Array，
+/// 可直接作为配置中的 `pois` 与 `routes` 传给 `render_map_binary`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_kml(data: &[u8]) -> Result<JsValue, JsValue> {
+    let overlay = kml::parse_kml_bytes(data)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing KML: {}", e)))?;
+    overlay_to_js(&overlay)
+}
+
+/// 解析 GPX 文件：轨迹与路线输出为 `routes`，航点输出为 `pois`
+///
+/// 返回格式同 `parse_kml`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_gpx(data: &[u8]) -> Result<JsValue, JsValue> {
+    let overlay = gpx::parse_gpx(data)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing GPX: {}", e)))?;
+    overlay_to_js(&overlay)
+}
+
+/// 解析 GeoJSON FeatureCollection 中的 Point / MultiPoint 要素
+///
+/// 返回格式同 `parse_kml`，`routes` 为空
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_poi_geojson(data: &[u8]) -> Result<JsValue, JsValue> {
+    let pois = data_processor::parse_poi_geojson(data)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing POI GeoJSON: {}", e)))?;
+    overlay_to_js(&types::Overlay {
+        pois,
+        routes: Vec::new(),
+    })
+}
+
+/// 将 POI 与路线打包为 `{ pois, routes }` JS 对象
+#[cfg(js_host)]
+fn overlay_to_js(overlay: &types::Overlay) -> Result<JsValue, JsValue> {
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &obj,
+        &JsValue::from_str("pois"),
+        &js_sys::Float64Array::from(data_processor::pois_to_bin(&overlay.pois).as_slice()),
+    )?;
+    js_sys::Reflect::set(
+        &obj,
+        &JsValue::from_str("routes"),
+        &js_sys::Float64Array::from(data_processor::routes_to_bin(&overlay.routes).as_slice()),
+    )?;
+    Ok(obj.into())
+}
+
+/// 将 OSM 图层投影并打包为 JS 对象
+#[cfg(js_host)]
+fn osm_layers_to_js(mut layers: data_processor::OsmLayers) -> Result<JsValue, JsValue> {
+    for road in layers.roads.iter_mut() {
+        project_points_mut(&mut road.coords);
+    }
+    for poly in layers.water.iter_mut().chain(layers.parks.iter_mut()) {
+        project_points_mut(&mut poly.exterior);
+        for ring in poly.interiors.iter_mut() {
+            project_points_mut(ring);
+        }
+    }
+
+    let obj = js_sys::Object::new();
+    let fields = [
+        ("roads", data_processor::roads_to_bin(&layers.roads)),
+        ("water", data_processor::polygons_to_bin(&layers.water)),
+        ("parks", data_processor::polygons_to_bin(&layers.parks)),
+        ("pois", data_processor::pois_to_bin(&layers.pois)),
+    ];
+    for (key, data) in fields {
+        js_sys::Reflect::set(
+            &obj,
+            &JsValue::from_str(key),
+            &js_sys::Float64Array::from(data.as_slice()),
+        )?;
+    }
+    Ok(obj.into())
+}
+
+/// 解压 gzip / zlib 载荷（按魔数识别，未压缩的数据原样返回）
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn decompress_payload(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    compression::maybe_decompress(data)
+        .map(|bytes| bytes.into_owned())
+        .map_err(|e| JsValue::from_str(&format!("Decompression failed: {}", e)))
+}
+
+/// 将（可能压缩的）二进制分片字节解码为 Float64Array
+///
+/// 输入为小端 f64 序列，可直接传给 `render_map_binary` 的道路 / 多边形参数
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn decode_bin_shard(data: &[u8]) -> Result<js_sys::Float64Array, JsValue> {
+    let values = compression::decode_f64_le(data)
+        .map_err(|e| JsValue::from_str(&format!("Error decoding binary shard: {}", e)))?;
+    Ok(js_sys::Float64Array::from(values.as_slice()))
+}
+
+/// 为扁平二进制数据加上版本化头部 `[magic, version, layer, ...]`
+///
+/// `layer` 取 "roads"、"polygons"（或 "water" / "parks"）、"pois"、"routes"；
+/// 渲染与解析入口会校验头部，不匹配时返回结构化错误而不是渲染乱码
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn add_bin_header(data: &[f64], layer: &str) -> Result<js_sys::Float64Array, JsValue> {
+    let layer = BinLayer::from_name(layer).ok_or_else(|| {
+        JsValue::from_str(&format!("Error adding header: unknown layer '{}'", layer))
+    })?;
+    let out = data_processor::with_bin_header(layer, data);
+    Ok(js_sys::Float64Array::from(out.as_slice()))
+}
+
+/// 道路去重并合并首尾相接的分段，输入输出均为 `[count, type, n, x, y, ...]`
+///
+/// Overpass / OSM XML 导入时已自动执行；此函数用于前端自行生成的道路二进制
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn merge_roads_bin(data: &[f64]) -> Result<js_sys::Float64Array, JsValue> {
+    let body = data_processor::strip_bin_header(data, BinLayer::Roads)
+        .map_err(|e| JsValue::from_str(&format!("Error merging roads: {}", e)))?;
+    let roads = data_processor::roads_from_bin(body)
+        .map_err(|e| JsValue::from_str(&format!("Error merging roads: {}", e)))?;
+    let merged = data_processor::roads_to_bin(&merge::merge_roads(roads));
+    Ok(js_sys::Float64Array::from(merged.as_slice()))
+}
+
+/// 解析（可能压缩的）道路 GeoJSON 字节
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_roads_bytes(data: &[u8], road_filter: Option<String>) -> Result<JsValue, JsValue> {
+    let filter = parse_road_filter(road_filter)?;
+    let json = compression::maybe_decompress(data)
+        .map_err(|e| JsValue::from_str(&format!("Decompression failed: {}", e)))?;
+    let roads = data_processor::parse_roads_slice(&json, &filter)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing roads: {}", e)))?;
+    serde_wasm_bindgen::to_value(&roads)
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}
+
+/// 解析（可能压缩的）多边形 GeoJSON 字节
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn parse_polygons_bytes(data: &[u8]) -> Result<JsValue, JsValue> {
+    let json = compression::maybe_decompress(data)
+        .map_err(|e| JsValue::from_str(&format!("Decompression failed: {}", e)))?;
+    let polys = data_processor::parse_polygons_slice(&json)
+        .map_err(|e| JsValue::from_str(&format!("Error parsing polygons: {}", e)))?;
+    serde_wasm_bindgen::to_value(&polys)
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}
+
+/// 分块解析大型 GeoJSON 响应，边接收边解析，避免缓冲整个字符串
+///
+/// `kind` 为 `"roads"` 或 `"polygons"`；`finish()` 返回与
+/// `process_roads_bin_wasm` / `process_polygons_bin_wasm` 相同格式的 Float64Array
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub struct GeometryStreamParser {
+    splitter: stream::FeatureSplitter,
+    polygons: bool,
+    road_filter: types::RoadFilter,
+    roads: Vec<types::Road>,
+    polys: Vec<types::PolyFeature>,
+}
+
+#[cfg(js_host)]
+#[wasm_bindgen]
+impl GeometryStreamParser {
+    #[wasm_bindgen(constructor)]
+    pub fn new(kind: &str, road_filter: Option<String>) -> Result<GeometryStreamParser, JsValue> {
+        let polygons = match kind {
+            "roads" => false,
+            "polygons" => true,
+            other => {
+                return Err(JsValue::from_str(&format!(
+                    "Unknown stream kind '{}', expected 'roads' or 'polygons'",
+                    other
+                )));
+            }
+        };
+        Ok(GeometryStreamParser {
+            splitter: stream::FeatureSplitter::new(),
+            polygons,
+            road_filter: parse_road_filter(road_filter)?,
+            roads: Vec::new(),
+            polys: Vec::new(),
+        })
+    }
+
+    /// 推入一段响应字节（可在任意位置切分）
+    pub fn push_chunk(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let (roads, polys) = (&mut self.roads, &mut self.polys);
+        let (polygons, filter) = (self.polygons, &self.road_filter);
+        self.splitter
+            .push(bytes, |feature| {
+                if polygons {
+                    data_processor::parse_polygon_feature(feature, polys)
+                } else {
+                    data_processor::parse_road_feature(feature, filter, roads)
+                }
+            })
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// 已解析的 Feature 数量
+    pub fn get_feature_count(&self) -> usize {
+        self.splitter.feature_count()
+    }
+
+    /// 结束输入并返回投影后的二进制几何
+    pub fn finish(self) -> Result<js_sys::Float64Array, JsValue> {
+        self.splitter.finish().map_err(|e| JsValue::from_str(&e))?;
+        let data = if self.polygons {
+            data_processor::polygons_to_bin(&self.polys)
+        } else {
+            data_processor::roads_to_bin(&self.roads)
+        };
+        Ok(js_sys::Float64Array::from(data.as_slice()))
+    }
+}
+
+/// 将投影后的道路二进制编码为量化差分格式（Uint32Array）
+///
+/// `scale` 为每米的量化单位数，例如 10 表示 0.1 米精度
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn encode_roads_quantized(data: &[f64], scale: u32) -> Result<Vec<u32>, JsValue> {
+    quantized::encode(data, quantized::QuantizedLayer::Roads, scale)
+        .map_err(|e| JsValue::from_str(&format!("Error encoding roads: {}", e)))
+}
+
+/// 将投影后的多边形二进制编码为量化差分格式（Uint32Array）
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn encode_polygons_quantized(data: &[f64], scale: u32) -> Result<Vec<u32>, JsValue> {
+    quantized::encode(data, quantized::QuantizedLayer::Polygons, scale)
+        .map_err(|e| JsValue::from_str(&format!("Error encoding polygons: {}", e)))
+}
+
+/// 将量化差分格式解码回扁平 Float64Array，可直接传给 `render_map_binary`
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn decode_quantized(data: &[u32]) -> Result<js_sys::Float64Array, JsValue> {
+    let (_, values) = quantized::decode(data)
+        .map_err(|e| JsValue::from_str(&format!("Error decoding quantized data: {}", e)))?;
+    Ok(js_sys::Float64Array::from(values.as_slice()))
+}
+
 /// 极速处理：接收二进制，在 WASM 内部投影并返回新的二进制（Float64Array）
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn process_roads_bin_wasm(
+    data: &[f64],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
+    process_roads_bin_internal(data, road_filter, cull_bounds)
+}
+
+/// `process_roads_bin_wasm` 的 Float32Array 输入版本（输出仍为 Float64Array）
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn process_roads_bin_wasm(data: &[f64]) -> Result<js_sys::Float64Array, JsValue> {
+pub fn process_roads_bin_wasm_f32(
+    data: &[f32],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
+    process_roads_bin_internal(data, road_filter, cull_bounds)
+}
+
+#[cfg(js_host)]
+fn process_roads_bin_internal<T: Copy + Into<f64>>(
+    data: &[T],
+    road_filter: Option<String>,
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
     if data.is_empty() {
         return Ok(js_sys::Float64Array::new(&JsValue::NULL));
     }
 
-    let roads = data_processor::parse_roads_bin(data)
+    let filter = parse_road_filter(road_filter)?;
+    let viewport = parse_cull_viewport(cull_bounds)?;
+    let roads = data_processor::parse_roads_bin(data, &filter, viewport.as_ref())
         .map_err(|e| JsValue::from_str(&format!("Error parsing roads binary: {}", e)))?;
 
     // 预计算总长度，直接分配 Float64Array，避免中间 Vec 分配和复制
-    let total_len: usize = 1 + roads.iter()
+    let total_len: usize = 1 + roads
+        .iter()
         .map(|r| 2usize + r.coords.len() * 2)
         .sum::<usize>();
 
@@ -576,17 +2987,45 @@ pub fn process_roads_bin_wasm(data: &[f64]) -> Result<js_sys::Float64Array, JsVa
     Ok(array)
 }
 
+#[cfg(js_host)]
+#[wasm_bindgen]
+pub fn process_polygons_bin_wasm(
+    data: &[f64],
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
+    process_polygons_bin_internal(data, cull_bounds)
+}
+
+/// `process_polygons_bin_wasm` 的 Float32Array 输入版本（输出仍为 Float64Array）
+#[cfg(js_host)]
 #[wasm_bindgen]
-pub fn process_polygons_bin_wasm(data: &[f64]) -> Result<js_sys::Float64Array, JsValue> {
-    let polys = data_processor::parse_polygons_bin(data)
+pub fn process_polygons_bin_wasm_f32(
+    data: &[f32],
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
+    process_polygons_bin_internal(data, cull_bounds)
+}
+
+#[cfg(js_host)]
+fn process_polygons_bin_internal<T: Copy + Into<f64>>(
+    data: &[T],
+    cull_bounds: Option<String>,
+) -> Result<js_sys::Float64Array, JsValue> {
+    let viewport = parse_cull_viewport(cull_bounds)?;
+    let polys = data_processor::parse_polygons_bin(data, viewport.as_ref())
         .map_err(|e| JsValue::from_str(&format!("Error parsing polygons binary: {}", e)))?;
 
     // 预计算总长度，直接分配 Float64Array，避免中间 Vec 分配和复制
-    let total_len: usize = 1 + polys.iter()
+    let total_len: usize = 1 + polys
+        .iter()
         .map(|p| {
-            2usize + p.exterior.len() * 2 + 1 + p.interiors.iter()
-                .map(|r| 1usize + r.len() * 2)
-                .sum::<usize>()
+            2usize
+                + p.exterior.len() * 2
+                + 1
+                + p.interiors
+                    .iter()
+                    .map(|r| 1usize + r.len() * 2)
+                    .sum::<usize>()
         })
         .sum::<usize>();
 
@@ -622,7 +3061,7 @@ pub fn process_polygons_bin_wasm(data: &[f64]) -> Result<js_sys::Float64Array, J
 }
 
 /// 测试函数
-#[wasm_bindgen]
+#[cfg_attr(js_host, wasm_bindgen)]
 pub fn hello_wasm(name: &str) -> String {
     format!("Hello, {}! Map Poster WASM v{}", name, get_version())
 }
@@ -636,4 +3075,529 @@ mod tests {
         let version = get_version();
         assert!(!version.is_empty());
     }
+
+    #[test]
+    fn test_validate_request_collects_errors() {
+        let errors = validate_request_inner(r#"{"center":{"lat":0,"lon":0}}"#, &[2]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code, RenderErrorCode::InvalidConfig);
+        assert_eq!(errors[1].field.as_deref(), Some("roads[0]"));
+    }
+
+    #[test]
+    fn test_hash_request_is_stable() {
+        let config = RenderRequestBuilder::new(48.85, 2.35)
+            .radius(5000.0)
+            .size(800, 1000)
+            .theme(types::Theme::default())
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+        let digests = vec!["a".to_string(), "b".to_string()];
+        let hash = hash_request_inner(&json, &digests).unwrap();
+        assert_eq!(hash.len(), 32);
+        assert_eq!(hash, hash_request_inner(&json, &digests).unwrap());
+
+        // 缺省字段显式写出与省略时哈希相同
+        let mut minimal = serde_json::to_value(&config).unwrap();
+        minimal.as_object_mut().unwrap().remove("lod_min_px");
+        assert_eq!(
+            hash,
+            hash_request_inner(&minimal.to_string(), &digests).unwrap()
+        );
+
+        let swapped = vec!["b".to_string(), "a".to_string()];
+        assert_ne!(hash, hash_request_inner(&json, &swapped).unwrap());
+        let resized = json.replace("\"width\":800", "\"width\":801");
+        assert_ne!(hash, hash_request_inner(&resized, &digests).unwrap());
+    }
+
+    #[test]
+    fn test_theme_schema_matches_default() {
+        let theme: types::Theme = serde_json::from_str(&get_default_theme()).unwrap();
+        assert_eq!(theme.bg, types::Theme::default().bg);
+
+        let schema: serde_json::Value = serde_json::from_str(&get_theme_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        assert_eq!(properties.len(), 14);
+        assert_eq!(properties["water"]["default"], "#D1EAF0");
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&"road_motorway".into()));
+        assert!(!required.contains(&"route_color".into()));
+
+        // 颜色格式与 parse_hex_color 接受的写法一致
+        let pattern = properties["bg"]["pattern"].as_str().unwrap();
+        let pattern = regex_lite::Regex::new(pattern).unwrap();
+        for color in ["#abc", "#ABCD", "#1a2b3c", "#11223344", "1a2b3c", " #fff "] {
+            assert!(pattern.is_match(color), "{}", color);
+            assert!(utils::parse_hex_color(color).is_ok(), "{}", color);
+        }
+        for color in ["#12345", "#1234567", "red", "#ggg", ""] {
+            assert!(!pattern.is_match(color), "{}", color);
+            assert!(utils::parse_hex_color(color).is_err(), "{}", color);
+        }
+    }
+
+    #[test]
+    fn test_road_width_scales_with_output_height() {
+        let config = |height: u32| {
+            RenderRequestBuilder::new(48.85, 2.35)
+                .radius(5000.0)
+                .size(height * 3 / 4, height)
+                .theme(types::Theme::default())
+                .build()
+                .unwrap()
+        };
+        // 参考高度下为基础线宽；缺省 selected_size_height 时按实际输出高度缩放
+        assert_eq!(config(4800).road_width_scale(), 1.0);
+        assert_eq!(config(2400).road_width_scale(), 0.5);
+
+        let mut boosted = config(2400);
+        boosted.road_width_boost = 2.0;
+        boosted.selected_size_height = Some(9600);
+        assert_eq!(boosted.road_width_scale(), 4.0);
+        assert_eq!(types::RoadType::Motorway.get_width_scaled(4.0), 4.8);
+    }
+
+    #[test]
+    fn test_margins_frame_inner_map() {
+        let config = |width: u32, height: u32| {
+            RenderRequestBuilder::new(48.85, 2.35)
+                .radius(5000.0)
+                .size(width, height)
+                .theme(types::Theme::default())
+        };
+        let (inner, _) = resolve_view(&config(800, 600).build().unwrap().view()).unwrap();
+        let mut framed = config(1000, 1000).margin_pct(10.0).build().unwrap();
+        framed.margin_pct = Some(types::MarginPct::Sides(types::Margins {
+            top: 10.0,
+            right: 10.0,
+            bottom: 30.0,
+            left: 10.0,
+        }));
+        let (full, _) = resolve_view(&framed.view()).unwrap();
+        // 边距内的 800×600 区域与无边距的 800×600 画布范围一致
+        let px = full.width() / 1000.0;
+        assert!((px - inner.width() / 800.0).abs() < 1e-9);
+        assert!((full.min_x + 100.0 * px - inner.min_x).abs() < 1e-6);
+        assert!((full.max_y - 100.0 * px - inner.max_y).abs() < 1e-6);
+        assert!((full.min_y + 300.0 * px - inner.min_y).abs() < 1e-6);
+
+        // 面板版式在底部边距上加上文字面板
+        framed.layout = types::PosterLayout::Panel;
+        let sides = framed.map_margins().unwrap().sides();
+        assert_eq!((sides.top, sides.bottom), (10.0, 50.0));
+        // 底部边距 30 + 面板 20 超过上限：校验报错，渲染时压缩面板，地图与面板之间只隔底部边距
+        let errors = validate::validate_config(&framed);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field.as_deref(), Some("text_panel_pct"));
+        let map_bottom = framed.map_margins().unwrap().fractions().bottom * 100.0;
+        assert_eq!(map_bottom, types::MAX_MARGIN_PCT);
+        assert_eq!(framed.text_panel_height_pct(), map_bottom - 30.0);
+        framed.text_panel_pct = 10.0;
+        assert!(validate::validate_config(&framed).is_empty());
+        assert_eq!(framed.text_panel_height_pct(), 10.0);
+
+        // 宝丽来：左右与顶部边框像素宽度相同
+        framed.layout = types::PosterLayout::Polaroid;
+        framed.height = 1250;
+        let sides = framed.map_margins().unwrap().sides();
+        assert_eq!(sides.left * 1000.0, sides.top * 1250.0);
+        assert_eq!(sides.bottom, types::POLAROID_BOTTOM_PCT);
+
+        framed.margin_pct = Some(types::MarginPct::All(60.0));
+        assert_eq!(validate::validate_config(&framed).len(), 1);
+    }
+
+    #[test]
+    fn test_render_request_builder() {
+        let theme = types::Theme {
+            bg: "#FFFFFF".to_string(),
+            ..Default::default()
+        };
+        let config = RenderRequestBuilder::new(48.8566, 2.3522)
+            .radius(5000.0)
+            .size(800, 1000)
+            .text("Paris".to_string(), "France".to_string())
+            .theme(theme.clone())
+            .anti_alias(false)
+            .build()
+            .unwrap();
+        assert_eq!(config.radius, 5000.0);
+        assert_eq!((config.width, config.height), (800, 1000));
+        assert!(!config.anti_alias);
+        assert_eq!(config.lod_min_px, types::default_lod_min_px());
+
+        // 序列化结果可被 JSON 配置接口原样解析
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: RenderConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.display_city, "Paris");
+
+        let missing_theme = RenderRequestBuilder::new(0.0, 0.0).radius(1.0).size(1, 1);
+        assert!(missing_theme.build().is_err());
+        let bad_center = RenderRequestBuilder::new(95.0, 0.0)
+            .radius(1.0)
+            .size(1, 1)
+            .theme(theme);
+        assert!(bad_center.build().is_err());
+    }
+
+    #[test]
+    fn test_json_request_shares_render_config() {
+        let mut config = serde_json::to_value(
+            RenderRequestBuilder::new(0.0, 0.0)
+                .radius(1000.0)
+                .size(10, 10)
+                .theme(types::Theme::default())
+                .text_position(types::TextPosition::Top)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let fields = config.as_object_mut().unwrap();
+        fields.insert("roads".into(), "[]".into());
+        fields.insert("water".into(), "[]".into());
+        fields.insert("parks".into(), "[]".into());
+        fields.insert("pois".into(), serde_json::json!([{ "x": 1.0, "y": 2.0 }]));
+
+        // 旧 JSON 请求与二进制接口共用同一份配置：文字位置不再被丢弃，POI 可为对象数组
+        let request: JsonRenderRequest = serde_json::from_value(config).unwrap();
+        assert!(request.config.text_position.is_some());
+        let pois: types::PoiInput = serde_json::from_value(request.pois.unwrap()).unwrap();
+        assert_eq!(pois.into_bin(), vec![1.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_extra_layers_follow_z_order() {
+        let square = |color: &str, z: i32| types::ExtraLayer {
+            kind: types::ExtraLayerKind::Polygon,
+            data: vec![
+                1.0, 4.0, 0.0, -100.0, -100.0, 100.0, -100.0, 100.0, 100.0, -100.0, 100.0,
+            ],
+            color: color.to_string(),
+            width: 1.0,
+            z,
+            crs: types::Crs::Epsg3857,
+        };
+        let center_red = |z: i32| {
+            let mut config = RenderRequestBuilder::new(0.0, 0.0)
+                .radius(1000.0)
+                .size(40, 40)
+                .theme(types::Theme {
+                    water: "#0000ff".to_string(),
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            config.crs = Some(types::Crs::Epsg3857);
+            config.extra_layers = vec![square("#ff0000", z)];
+            let layers = container::ContainerLayers {
+                water: square("#000000", 0).data,
+                ..Default::default()
+            };
+            let scene = BinaryScene::new(layers, config).unwrap();
+            let mut renderer = scene.create_renderer().unwrap();
+            let layer = renderer.render_layer(|r| scene.draw_water(r)).unwrap();
+            let center = layer.pixel(layer.width() / 2, layer.height() / 2).unwrap();
+            center.red() == 255
+        };
+
+        // 低于水体层级时被水体覆盖，默认层级绘制在水体之上
+        assert!(!center_red(types::LAYER_Z_WATER - 1));
+        assert!(center_red(types::LAYER_Z_WATER));
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_render_binary_native() {
+        let mut config = RenderRequestBuilder::new(0.0, 0.0)
+            .radius(1000.0)
+            .size(40, 40)
+            .theme(types::Theme::default())
+            .build()
+            .unwrap();
+        config.crs = Some(types::Crs::Epsg3857);
+        // 声明 3 个 POI 但只有 1 个坐标对
+        config.pois = Some(types::PoiInput::Flat(vec![3.0, 0.0, 0.0]));
+        // 第二条道路只有一个有限坐标，整条被跳过并计入警告
+        let roads = vec![vec![
+            2.0,
+            0.0,
+            2.0,
+            -500.0,
+            0.0,
+            500.0,
+            0.0,
+            0.0,
+            2.0,
+            f64::NAN,
+            0.0,
+            100.0,
+            f64::INFINITY,
+        ]];
+        let result = render_binary(roads, Vec::new(), Vec::new(), config, None);
+        assert!(result.is_success(), "{:?}", result.error_info());
+        assert!(!result.timings().is_empty());
+        let layers: Vec<&str> = result.stats().iter().map(|l| l.layer.as_str()).collect();
+        assert_eq!(layers, ["water", "parks", "roads", "pois"]);
+        assert_eq!(result.stats()[2].stats.features, 1);
+        assert_eq!(result.stats()[2].stats.drawn, 1);
+        assert_eq!(result.stats()[3].stats.drawn, 1);
+        assert_eq!(result.get_warning_count(), 2);
+        let reported: Vec<_> = result
+            .warnings()
+            .iter()
+            .map(|w| (w.code, w.field.as_deref(), w.count))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                (warnings::WarningCode::MalformedData, Some("pois"), 4),
+                (warnings::WarningCode::InvalidCoordinates, Some("roads"), 2),
+            ]
+        );
+        let len = result.get_data_len();
+        let png = result.into_data().unwrap();
+        assert_eq!(png.len(), len);
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_sky_gradient_in_panel_layout() {
+        let render = |solar: Option<types::SolarOverlay>| {
+            let mut config = RenderRequestBuilder::new(0.0, 0.0)
+                .radius(1000.0)
+                .size(100, 100)
+                .theme(types::Theme::default())
+                .build()
+                .unwrap();
+            config.crs = Some(types::Crs::Epsg3857);
+            config.layout = types::PosterLayout::Panel;
+            config.solar = solar;
+            let result = render_binary(Vec::new(), Vec::new(), Vec::new(), config, None);
+            assert!(result.is_success(), "{:?}", result.error_info());
+            let png = result.into_data().unwrap();
+            let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+            let mut buf = vec![0; reader.output_buffer_size()];
+            reader.next_frame(&mut buf).unwrap();
+            buf
+        };
+        let plain = render(None);
+        // 2024-06-21T12:00:00Z：太阳接近 (0, 0) 的天顶，天空色为白昼蓝
+        let sky = render(Some(types::SolarOverlay {
+            timestamp_ms: 1_718_971_200_000.0,
+            style: types::SolarStyle::Sky,
+            night_color: None,
+            night_opacity: types::default_night_opacity(),
+        }));
+        let rgb = |buf: &[u8], x: usize, y: usize| {
+            let i = (y * 100 + x) * 4;
+            [buf[i], buf[i + 1], buf[i + 2]]
+        };
+
+        // 地图区域顶部叠加天空色，中部不受影响
+        assert_ne!(rgb(&sky, 50, 0), rgb(&plain, 50, 0));
+        assert_eq!(rgb(&sky, 50, 40), rgb(&plain, 50, 40));
+        // 文字面板之上的地图底部也有渐变，面板本身保持原色
+        let panel_top = 100 - types::default_text_panel_pct() as usize;
+        assert_ne!(rgb(&sky, 50, panel_top - 1), rgb(&plain, 50, panel_top - 1));
+        assert_eq!(rgb(&sky, 1, 99), rgb(&plain, 1, 99));
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_render_collage() {
+        let roads = [1.0, 1.0, 2.0, 2.34, 48.85, 2.36, 48.85];
+        let container = container::build_container(&[(container::SectionTag::Roads, &roads)]);
+        let mut config: types::CollageConfig = serde_json::from_value(serde_json::json!({
+            "panels": [
+                { "center": { "lat": 48.85, "lon": 2.35 }, "radius": 1000, "display_city": "Paris",
+                  "crs": "wgs84" },
+                { "center": { "lat": 35.68, "lon": 139.69 }, "radius": 1000, "display_city": "Tokyo",
+                  "crs": "wgs84" },
+            ],
+            "width": 60,
+            "height": 40,
+            "theme": types::Theme::default(),
+            "gap": 4,
+        }))
+        .unwrap();
+        assert_eq!(config.title(), "Paris & Tokyo");
+
+        let containers = vec![container.clone(), container.clone()];
+        let result = render_collage_internal(containers, config.clone(), None).unwrap();
+        assert!(result.is_success(), "{:?}", result.error_info());
+        assert_eq!((result.get_width(), result.get_height()), (60, 40));
+        let roads = result.stats().iter().find(|l| l.layer == "roads").unwrap();
+        assert_eq!(roads.stats.features, 2);
+
+        // 分格配置的错误带上分格前缀
+        config.panels[1]["crs"] = serde_json::json!("epsg3857");
+        let containers = vec![container.clone(), container];
+        let Err(error) = render_collage_internal(containers, config, None) else {
+            panic!("degree coordinates declared as epsg3857 should fail");
+        };
+        assert_eq!(error.field.as_deref(), Some("panels[1].roads"));
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_render_variants_per_theme() {
+        let config = |water: &str| {
+            let mut config = RenderRequestBuilder::new(0.0, 0.0)
+                .radius(1000.0)
+                .size(40, 40)
+                .theme(types::Theme {
+                    water: water.to_string(),
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            config.crs = Some(types::Crs::Epsg3857);
+            config
+        };
+        let layers = container::ContainerLayers {
+            water: vec![
+                1.0, 4.0, 0.0, -100.0, -100.0, 100.0, -100.0, 100.0, 100.0, -100.0, 100.0,
+            ],
+            ..Default::default()
+        };
+        let roads = RoadShards::Owned(vec![vec![1.0, 0.0, 2.0, -500.0, 0.0, 500.0, 0.0]]);
+        let results =
+            render_variants(layers, roads, vec![config("#0000ff"), config("#ff0000")]).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(RenderResult::is_success));
+        assert_ne!(results[0].get_data(), results[1].get_data());
+    }
+
+    #[test]
+    fn test_map_view_screen_roundtrip() {
+        let view = MapView::from_json(
+            r#"{"center":{"lat":48.8566,"lon":2.3522},"radius":5000,"width":800,"height":1000}"#,
+        )
+        .unwrap();
+
+        // 画布中心应对应中心点
+        let center = view.screen_to_lonlat(400.0, 500.0);
+        assert!((center[0] - 2.3522).abs() < 1e-9);
+        assert!((center[1] - 48.8566).abs() < 1e-9);
+
+        let screen = view.lonlat_to_screen(2.36, 48.86);
+        let back = view.screen_to_lonlat(screen[0], screen[1]);
+        assert!((back[0] - 2.36).abs() < 1e-9);
+        assert!((back[1] - 48.86).abs() < 1e-9);
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_session_replays_cached_layer_stats() {
+        let mut config = RenderRequestBuilder::new(0.0, 0.0)
+            .radius(1000.0)
+            .size(40, 40)
+            .theme(types::Theme::default())
+            .build()
+            .unwrap();
+        config.crs = Some(types::Crs::Epsg3857);
+        let layers = container::ContainerLayers {
+            water: vec![
+                1.0, 4.0, 0.0, -100.0, -100.0, 100.0, -100.0, 100.0, 100.0, -100.0, 100.0,
+            ],
+            ..Default::default()
+        };
+        let roads = RoadShards::Owned(vec![vec![1.0, 0.0, 2.0, -500.0, 0.0, 500.0, 0.0]]);
+        let mut session = PosterSession::prepare(layers, roads, config).unwrap();
+
+        let stats = |result: &RenderResult| -> Vec<(String, draw_stats::DrawStats)> {
+            assert!(result.is_success());
+            result
+                .stats()
+                .iter()
+                .map(|l| (l.layer.clone(), l.stats))
+                .collect()
+        };
+        let first = stats(&session.render());
+        // 第二次渲染直接合成缓存的水体 / 公园 / 道路图层
+        let second = stats(&session.render());
+        assert!(
+            first
+                .iter()
+                .any(|(layer, s)| layer == "water" && s.drawn == 1)
+        );
+        assert!(
+            first
+                .iter()
+                .any(|(layer, s)| layer == "roads" && s.drawn == 1)
+        );
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_session_spatial_queries() {
+        let mut config = RenderRequestBuilder::new(0.0, 0.0)
+            .radius(1000.0)
+            .size(40, 40)
+            .theme(types::Theme::default())
+            .build()
+            .unwrap();
+        config.crs = Some(types::Crs::Epsg3857);
+        let layers = container::ContainerLayers {
+            water: vec![
+                1.0, 4.0, 0.0, -100.0, -100.0, 100.0, -100.0, 100.0, 100.0, -100.0, 100.0,
+            ],
+            ..Default::default()
+        };
+        let roads = RoadShards::Owned(vec![vec![1.0, 0.0, 2.0, -500.0, 0.0, 500.0, 0.0]]);
+        let session = PosterSession::prepare(layers, roads, config).unwrap();
+
+        let inside = session.areas_containing(0.0, 0.0);
+        assert_eq!(inside.len(), 1);
+        assert_eq!(inside[0].layer, "water");
+        let (lon, lat) = projection::unproject_point(300.0, 300.0);
+        assert!(session.areas_containing(lon, lat).is_empty());
+
+        // 道路沿赤道，最近点在正南方 50 米处
+        let (lon, lat) = projection::unproject_point(100.0, 50.0);
+        let road = session.nearest_road_to(lon, lat, f64::INFINITY).unwrap();
+        assert_eq!(road.index, 0);
+        assert!((road.lon - lon).abs() < 1e-9);
+        assert!(road.lat.abs() < 1e-9);
+        assert!((road.distance_m - 50.0).abs() < 1e-6);
+        assert!(session.nearest_road_to(lon, lat, 10.0).is_none());
+    }
+
+    #[test]
+    fn test_prepared_scene_roundtrip() {
+        let config = |width: u32| {
+            format!(
+                r##"{{"center":{{"lat":0.0,"lon":0.0}},"radius":1000,"width":{},"height":400,
+                "display_city":"A","display_country":"B","text_position":null,
+                "theme":{{"bg":"#fff","text":"#000","gradient_color":"#fff","poi_color":"#f00",
+                "water":"#00f","parks":"#0f0","road_motorway":"#111","road_primary":"#222",
+                "road_secondary":"#333","road_tertiary":"#444","road_residential":"#555",
+                "road_default":"#666"}}}}"##,
+                width
+            )
+        };
+        let water = vec![
+            1.0, 4.0, 0.0, -100.0, -100.0, 100.0, -100.0, 100.0, 100.0, -100.0, -100.0,
+        ];
+        let roads = vec![1.0, 0.0, 2.0, -500.0, 0.0, 500.0, 0.0];
+        let layers = container::ContainerLayers {
+            water,
+            ..Default::default()
+        };
+        let config_400: RenderConfig = serde_json::from_str(&config(400)).unwrap();
+        let session =
+            PosterSession::prepare(layers, RoadShards::Owned(vec![roads]), config_400).unwrap();
+        let bytes = session.prepared_bytes().unwrap();
+
+        let config: RenderConfig = serde_json::from_str(&config(800)).unwrap();
+        let imported = PosterSession::from_prepared(&bytes, config).unwrap();
+        assert_eq!(imported.roads[0].data, session.roads[0].data);
+        assert_eq!(imported.scene.water.data, session.scene.water.data);
+        assert_eq!(imported.scene.config.width, 800);
+        assert!(imported.scene.bounds.width() > session.scene.bounds.width());
+    }
 }
@@ -0,0 +1,146 @@
+//! 内存使用统计
+//!
+//! 通过包装系统分配器统计当前与峰值分配量，并记录最近一次渲染各图层几何的字节数，
+//! 前端可据此在低内存手机上提前提示 OOM 风险。
+
+use serde::Serialize;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// 统计分配量的全局分配器
+///
+/// 仅在 wasm32 且启用 `alloc-stats` feature 时安装：作为 rlib 被原生程序链接时不与其自带的
+/// 全局分配器冲突，也不让每次分配都承担原子计数的开销。未安装时分配量统计为 0
+#[cfg(all(target_arch = "wasm32", feature = "alloc-stats"))]
+mod counting {
+    use super::{ALLOCATED, PEAK};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::Ordering;
+
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { System.alloc(layout) };
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { System.alloc_zeroed(layout) };
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) };
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+            if !new_ptr.is_null() {
+                ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+                add(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn add(size: usize) {
+        let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+/// 各图层几何占用的字节数
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct LayerBytes {
+    pub roads: usize,
+    pub water: usize,
+    pub parks: usize,
+    pub buildings: usize,
+    pub pois: usize,
+    pub routes: usize,
+}
+
+thread_local! {
+    // 与 warnings / draw_stats 相同，按调用渲染接口的线程隔离
+    static LAYER_BYTES: Cell<LayerBytes> = const {
+        Cell::new(LayerBytes {
+            roads: 0,
+            water: 0,
+            parks: 0,
+            buildings: 0,
+            pois: 0,
+            routes: 0,
+        })
+    };
+}
+
+/// 内存统计快照
+#[derive(Debug, Serialize)]
+pub struct MemoryStats {
+    /// WASM 线性内存大小（非 wasm32 目标为 0）
+    pub heap_bytes: usize,
+    /// 当前已分配字节数（未安装统计分配器时为 0）
+    pub allocated_bytes: usize,
+    /// 最近一次渲染开始以来的峰值分配字节数（未安装统计分配器时为 0）
+    pub peak_bytes: usize,
+    /// 最近一次渲染各图层几何的字节数
+    pub layers: LayerBytes,
+}
+
+/// 渲染开始：峰值重置为当前分配量，清空图层统计
+pub fn begin_render() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+    LAYER_BYTES.set(LayerBytes::default());
+}
+
+/// 累加图层字节数
+pub fn record_layers(f: impl FnOnce(&mut LayerBytes)) {
+    let mut layers = LAYER_BYTES.get();
+    f(&mut layers);
+    LAYER_BYTES.set(layers);
+}
+
+pub fn stats() -> MemoryStats {
+    #[cfg(target_arch = "wasm32")]
+    let heap_bytes = core::arch::wasm32::memory_size(0) * 65536;
+    #[cfg(not(target_arch = "wasm32"))]
+    let heap_bytes = 0;
+
+    MemoryStats {
+        heap_bytes,
+        allocated_bytes: ALLOCATED.load(Ordering::Relaxed),
+        peak_bytes: PEAK.load(Ordering::Relaxed),
+        layers: LAYER_BYTES.get(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_bytes_reset_per_render() {
+        begin_render();
+        record_layers(|l| l.roads += 128);
+        record_layers(|l| l.water += 64);
+        let layers = stats().layers;
+        assert_eq!((layers.roads, layers.water), (128, 64));
+
+        begin_render();
+        assert_eq!(stats().layers.roads, 0);
+    }
+}
@@ -0,0 +1,139 @@
+//! 道路去重与折线合并
+//!
+//! Overpass 响应中常有重复的 way，或同一条路被拆成首尾相接的多段。
+//! 先去掉正向或反向完全相同的折线，再把同一等级、在度为 2 的端点处相接的线段
+//! 拼成一条折线：路径数量更少，接缝处也不会因重复描边出现深色圆点。
+//! 交叉口（三条及以上线段共用端点）保持断开，避免任意选择延伸方向。
+
+use crate::simplify::{PointKey, point_key as key};
+use crate::types::Road;
+use std::collections::{HashMap, HashSet};
+
+/// 去掉重复道路（同等级、坐标序列正向或反向相同）
+pub fn dedup_roads(roads: Vec<Road>) -> Vec<Road> {
+    let mut seen = HashSet::with_capacity(roads.len());
+    roads
+        .into_iter()
+        .filter(|road| {
+            let forward: Vec<PointKey> = road.coords.iter().map(|&p| key(p)).collect();
+            let reverse: Vec<PointKey> = forward.iter().rev().copied().collect();
+            seen.insert((road.road_type, forward.min(reverse)))
+        })
+        .collect()
+}
+
+/// 去重后将首尾相接的同等级道路合并为单条折线
+pub fn merge_roads(roads: Vec<Road>) -> Vec<Road> {
+    let roads = dedup_roads(roads);
+
+    // (等级, 端点) -> 以该点为端点的道路下标
+    let mut ends: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, road) in roads.iter().enumerate() {
+        if let (Some(&first), Some(&last)) = (road.coords.first(), road.coords.last())
+            && road.coords.len() >= 2
+        {
+            ends.entry((road.road_type, key(first)))
+                .or_default()
+                .push(i);
+            ends.entry((road.road_type, key(last))).or_default().push(i);
+        }
+    }
+
+    // 仅当端点恰好连接两条道路时继续延伸
+    let next = |from: usize, end: (f64, f64), used: &[bool]| -> Option<usize> {
+        let list = ends.get(&(roads[from].road_type, key(end)))?;
+        let [a, b] = list[..] else {
+            return None;
+        };
+        let other = if a == from { b } else { a };
+        (other != from && !used[other]).then_some(other)
+    };
+
+    let mut used = vec![false; roads.len()];
+    let mut merged = Vec::with_capacity(roads.len());
+    for start in 0..roads.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut line = roads[start].coords.clone();
+        if line.len() >= 2 {
+            // 先向尾端延伸，再反转后向首端延伸，最后恢复原方向
+            for _ in 0..2 {
+                let mut current = start;
+                while let Some(&end) = line.last()
+                    && let Some(j) = next(current, end, &used)
+                {
+                    used[j] = true;
+                    let segment = &roads[j].coords;
+                    if key(segment[0]) == key(end) {
+                        line.extend_from_slice(&segment[1..]);
+                    } else {
+                        line.extend(segment.iter().rev().skip(1));
+                    }
+                    current = j;
+                }
+                line.reverse();
+            }
+        }
+        merged.push(Road {
+            coords: line,
+            road_type: roads[start].road_type,
+        });
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RoadType;
+
+    fn road(coords: &[(f64, f64)], road_type: RoadType) -> Road {
+        Road {
+            coords: coords.to_vec(),
+            road_type,
+        }
+    }
+
+    #[test]
+    fn test_dedup_and_merge_split_ways() {
+        let roads = vec![
+            road(&[(1.0, 0.0), (2.0, 0.0)], RoadType::Primary),
+            road(&[(0.0, 0.0), (1.0, 0.0)], RoadType::Primary),
+            // 反向重复
+            road(&[(1.0, 0.0), (0.0, 0.0)], RoadType::Primary),
+            // 反向相接
+            road(&[(3.0, 0.0), (2.0, 0.0)], RoadType::Primary),
+            // 等级不同，不合并
+            road(&[(3.0, 0.0), (4.0, 0.0)], RoadType::Residential),
+        ];
+        let merged = merge_roads(roads);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged[0].coords,
+            vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]
+        );
+        assert_eq!(merged[1].road_type, RoadType::Residential);
+    }
+
+    #[test]
+    fn test_junctions_and_loops_stay_split() {
+        // 三条道路共用 (0, 0)：不合并
+        let star = vec![
+            road(&[(0.0, 0.0), (1.0, 0.0)], RoadType::Tertiary),
+            road(&[(0.0, 0.0), (0.0, 1.0)], RoadType::Tertiary),
+            road(&[(0.0, 0.0), (-1.0, 0.0)], RoadType::Tertiary),
+        ];
+        assert_eq!(merge_roads(star).len(), 3);
+
+        // 两段组成的环只合并一次，不会无限延伸
+        let ring = vec![
+            road(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], RoadType::Tertiary),
+            road(&[(1.0, 1.0), (0.0, 1.0), (0.0, 0.0)], RoadType::Tertiary),
+        ];
+        let merged = merge_roads(ring);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].coords.len(), 5);
+    }
+}
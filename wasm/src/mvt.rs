@@ -0,0 +1,434 @@
+//! Mapbox Vector Tile (MVT) 解码
+//!
+//! 仅实现渲染所需的子集：读取道路 / 水体 / 绿地图层的线与面几何，
+//! 兼容 OpenMapTiles（transportation / water / park / landuse / landcover）
+//! 与 Mapbox Streets（road / water / landuse）两套常见图层命名。
+
+use crate::data_processor::OsmLayers;
+use crate::projection::{WORLD_WIDTH, unproject_point};
+use crate::types::{PolyFeature, Road, RoadType};
+
+/// 单个瓦片的输入：protobuf 字节与瓦片坐标
+pub struct MvtTile<'a> {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+    pub data: &'a [u8],
+}
+
+// --- 最小 protobuf 读取器 ---
+
+struct PbReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+enum PbValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> PbReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        PbReader { buf, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .buf
+                .get(self.pos)
+                .ok_or("MVT: unexpected end of varint")?;
+            self.pos += 1;
+            result |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err("MVT: varint too long".to_string())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&e| e <= self.buf.len())
+            .ok_or("MVT: field length exceeds buffer")?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// 读取下一个字段，返回 (字段号, 值)；缓冲区结束时返回 None
+    fn next_field(&mut self) -> Result<Option<(u64, PbValue<'a>)>, String> {
+        if self.pos >= self.buf.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => PbValue::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                PbValue::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                PbValue::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                PbValue::Fixed
+            }
+            wire => return Err(format!("MVT: unsupported wire type {}", wire)),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+/// 读取 packed（或单个非 packed）的 uint32 字段并追加到 out
+fn read_packed(value: PbValue, out: &mut Vec<u32>) -> Result<(), String> {
+    match value {
+        PbValue::Varint(v) => out.push(v as u32),
+        PbValue::Bytes(bytes) => {
+            let mut r = PbReader::new(bytes);
+            while r.pos < bytes.len() {
+                out.push(r.varint()? as u32);
+            }
+        }
+        PbValue::Fixed => {}
+    }
+    Ok(())
+}
+
+fn zigzag(v: u32) -> i32 {
+    ((v >> 1) as i32) ^ -((v & 1) as i32)
+}
+
+// --- 图层解码 ---
+
+struct RawFeature {
+    geom_type: u64,
+    tags: Vec<u32>,
+    geometry: Vec<u32>,
+}
+
+struct RawLayer<'a> {
+    name: &'a str,
+    extent: u32,
+    keys: Vec<&'a str>,
+    // 仅保留字符串值；其它类型的值以 None 占位以维持下标
+    values: Vec<Option<&'a str>>,
+    features: Vec<RawFeature>,
+}
+
+impl RawLayer<'_> {
+    fn string_tag(&self, feature: &RawFeature, key: &str) -> Option<&str> {
+        feature.tags.chunks_exact(2).find_map(|kv| {
+            if self.keys.get(kv[0] as usize) == Some(&key) {
+                self.values.get(kv[1] as usize).copied().flatten()
+            } else {
+                None
+            }
+        })
+    }
+}
+
+fn decode_value(bytes: &[u8]) -> Result<Option<&str>, String> {
+    let mut r = PbReader::new(bytes);
+    let mut result = None;
+    while let Some((field, value)) = r.next_field()? {
+        if field == 1
+            && let PbValue::Bytes(b) = value
+        {
+            result = std::str::from_utf8(b).ok();
+        }
+    }
+    Ok(result)
+}
+
+fn decode_feature(bytes: &[u8]) -> Result<RawFeature, String> {
+    let mut r = PbReader::new(bytes);
+    let mut feature = RawFeature {
+        geom_type: 0,
+        tags: vec![],
+        geometry: vec![],
+    };
+    while let Some((field, value)) = r.next_field()? {
+        match field {
+            2 => read_packed(value, &mut feature.tags)?,
+            3 => {
+                if let PbValue::Varint(v) = value {
+                    feature.geom_type = v;
+                }
+            }
+            4 => read_packed(value, &mut feature.geometry)?,
+            _ => {}
+        }
+    }
+    Ok(feature)
+}
+
+fn decode_layer(bytes: &[u8]) -> Result<RawLayer<'_>, String> {
+    let mut r = PbReader::new(bytes);
+    let mut layer = RawLayer {
+        name: "",
+        extent: 4096,
+        keys: vec![],
+        values: vec![],
+        features: vec![],
+    };
+    while let Some((field, value)) = r.next_field()? {
+        match (field, value) {
+            (1, PbValue::Bytes(b)) => layer.name = std::str::from_utf8(b).unwrap_or(""),
+            (2, PbValue::Bytes(b)) => layer.features.push(decode_feature(b)?),
+            (3, PbValue::Bytes(b)) => layer.keys.push(std::str::from_utf8(b).unwrap_or("")),
+            (4, PbValue::Bytes(b)) => layer.values.push(decode_value(b)?),
+            (5, PbValue::Varint(v)) if v > 0 => layer.extent = v as u32,
+            _ => {}
+        }
+    }
+    Ok(layer)
+}
+
+/// 解码几何命令流，返回瓦片坐标下的路径（ClosePath 会补上闭合点）
+fn decode_geometry(cmds: &[u32]) -> Vec<Vec<(i32, i32)>> {
+    let mut paths: Vec<Vec<(i32, i32)>> = Vec::new();
+    let (mut cx, mut cy) = (0i32, 0i32);
+    let mut i = 0;
+    while i < cmds.len() {
+        let cmd = cmds[i] & 0x7;
+        let count = (cmds[i] >> 3) as usize;
+        i += 1;
+        match cmd {
+            1 | 2 => {
+                for _ in 0..count {
+                    if i + 1 >= cmds.len() {
+                        return paths;
+                    }
+                    cx = cx.wrapping_add(zigzag(cmds[i]));
+                    cy = cy.wrapping_add(zigzag(cmds[i + 1]));
+                    i += 2;
+                    if cmd == 1 || paths.is_empty() {
+                        paths.push(vec![(cx, cy)]);
+                    } else if let Some(path) = paths.last_mut() {
+                        path.push((cx, cy));
+                    }
+                }
+            }
+            7 => {
+                if let Some(path) = paths.last_mut()
+                    && let Some(&first) = path.first()
+                {
+                    path.push(first);
+                }
+            }
+            _ => return paths,
+        }
+    }
+    paths
+}
+
+/// 瓦片坐标下的有向面积（y 轴向下时外圈为正）
+fn signed_area(ring: &[(i32, i32)]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0].0 as f64 * w[1].1 as f64 - w[1].0 as f64 * w[0].1 as f64)
+        .sum::<f64>()
+        / 2.0
+}
+
+#[derive(Clone, Copy)]
+enum MvtLayerKind {
+    Roads,
+    Water,
+    Parks,
+}
+
+/// 按图层名与要素 class 判断所属图层
+fn classify(layer: &RawLayer, feature: &RawFeature) -> Option<MvtLayerKind> {
+    let class = layer
+        .string_tag(feature, "class")
+        .or_else(|| layer.string_tag(feature, "kind"));
+    match layer.name {
+        "transportation" | "road" | "roads" => Some(MvtLayerKind::Roads),
+        "water" => Some(MvtLayerKind::Water),
+        "park" => Some(MvtLayerKind::Parks),
+        "landuse" | "landcover"
+            if matches!(
+                class,
+                Some("park" | "garden" | "nature_reserve" | "national_park")
+            ) =>
+        {
+            Some(MvtLayerKind::Parks)
+        }
+        _ => None,
+    }
+}
+
+/// 将矢量瓦片的道路 class 映射为 OSM highway 取值
+fn road_type(layer: &RawLayer, feature: &RawFeature) -> RoadType {
+    let class = layer
+        .string_tag(feature, "class")
+        .or_else(|| layer.string_tag(feature, "highway"))
+        .or_else(|| layer.string_tag(feature, "kind"))
+        .unwrap_or("unclassified");
+    match class {
+        "minor" | "street" | "street_limited" => RoadType::Residential,
+        other => RoadType::from_highway(other),
+    }
+}
+
+/// 解码一组 MVT 瓦片，输出经纬度坐标的图层
+pub fn decode_tiles(tiles: &[MvtTile]) -> Result<OsmLayers, String> {
+    let mut layers = OsmLayers::default();
+    for tile in tiles {
+        let tile_count = f64::from(2u32.checked_pow(tile.z).ok_or("MVT: zoom too large")?);
+        let tile_size = WORLD_WIDTH / tile_count;
+
+        let mut r = PbReader::new(tile.data);
+        while let Some((field, value)) = r.next_field()? {
+            let (3, PbValue::Bytes(bytes)) = (field, value) else {
+                continue;
+            };
+            let layer = decode_layer(bytes)?;
+            let extent = f64::from(layer.extent);
+            let to_lonlat = |(px, py): (i32, i32)| {
+                let x =
+                    (f64::from(tile.x) + f64::from(px) / extent) * tile_size - WORLD_WIDTH / 2.0;
+                let y =
+                    WORLD_WIDTH / 2.0 - (f64::from(tile.y) + f64::from(py) / extent) * tile_size;
+                unproject_point(x, y)
+            };
+
+            for feature in &layer.features {
+                let Some(kind) = classify(&layer, feature) else {
+                    continue;
+                };
+                let paths = decode_geometry(&feature.geometry);
+                match (kind, feature.geom_type) {
+                    (MvtLayerKind::Roads, 2) => {
+                        let road_type = road_type(&layer, feature);
+                        for path in paths.into_iter().filter(|p| p.len() >= 2) {
+                            layers.roads.push(Road {
+                                coords: path.into_iter().map(to_lonlat).collect(),
+                                road_type,
+                            });
+                        }
+                    }
+                    (MvtLayerKind::Water | MvtLayerKind::Parks, 3) => {
+                        let mut polys: Vec<PolyFeature> = Vec::new();
+                        for ring in paths.into_iter().filter(|p| p.len() >= 4) {
+                            let area = signed_area(&ring);
+                            let coords = ring.into_iter().map(to_lonlat).collect();
+                            if area > 0.0 {
+                                polys.push(PolyFeature {
+                                    exterior: coords,
+                                    interiors: vec![],
+                                });
+                            } else if let Some(poly) = polys.last_mut() {
+                                poly.interiors.push(coords);
+                            }
+                        }
+                        match kind {
+                            MvtLayerKind::Water => layers.water.extend(polys),
+                            _ => layers.parks.extend(polys),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut v: u64, out: &mut Vec<u8>) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn bytes_field(field: u64, data: &[u8], out: &mut Vec<u8>) {
+        varint((field << 3) | 2, out);
+        varint(data.len() as u64, out);
+        out.extend_from_slice(data);
+    }
+
+    fn packed_field(field: u64, vals: &[u32], out: &mut Vec<u8>) {
+        let mut packed = Vec::new();
+        for &v in vals {
+            varint(u64::from(v), &mut packed);
+        }
+        bytes_field(field, &packed, out);
+    }
+
+    fn zz(v: i32) -> u32 {
+        ((v << 1) ^ (v >> 31)) as u32
+    }
+
+    fn feature(geom_type: u64, tags: &[u32], geometry: &[u32]) -> Vec<u8> {
+        let mut f = Vec::new();
+        packed_field(2, tags, &mut f);
+        varint(3 << 3, &mut f);
+        varint(geom_type, &mut f);
+        packed_field(4, geometry, &mut f);
+        f
+    }
+
+    #[test]
+    fn test_decode_road_and_water_tile() {
+        let mut road_layer = Vec::new();
+        bytes_field(1, b"transportation", &mut road_layer);
+        // LineString: MoveTo(0,0) LineTo(4096,0)
+        let line = [(1 << 3) | 1, zz(0), zz(0), (1 << 3) | 2, zz(4096), zz(0)];
+        bytes_field(2, &feature(2, &[0, 0], &line), &mut road_layer);
+        bytes_field(3, b"class", &mut road_layer);
+        let mut value = Vec::new();
+        bytes_field(1, b"primary", &mut value);
+        bytes_field(4, &value, &mut road_layer);
+
+        let mut water_layer = Vec::new();
+        bytes_field(1, b"water", &mut water_layer);
+        // 顺时针（屏幕坐标）外圈
+        let ring = [
+            (1 << 3) | 1,
+            zz(0),
+            zz(0),
+            (3 << 3) | 2,
+            zz(10),
+            zz(0),
+            zz(0),
+            zz(10),
+            zz(-10),
+            zz(0),
+            (1 << 3) | 7,
+        ];
+        bytes_field(2, &feature(3, &[], &ring), &mut water_layer);
+
+        let mut tile = Vec::new();
+        bytes_field(3, &road_layer, &mut tile);
+        bytes_field(3, &water_layer, &mut tile);
+
+        let layers = decode_tiles(&[MvtTile {
+            z: 0,
+            x: 0,
+            y: 0,
+            data: &tile,
+        }])
+        .unwrap();
+        assert_eq!(layers.roads.len(), 1);
+        assert_eq!(layers.roads[0].road_type, RoadType::Primary);
+        let (lon0, lat0) = layers.roads[0].coords[0];
+        assert!((lon0.abs() - 180.0).abs() < 1e-9);
+        assert!((lat0 - 85.051_128_78).abs() < 1e-6);
+        assert_eq!(layers.water.len(), 1);
+        assert_eq!(layers.water[0].exterior.len(), 5);
+    }
+}
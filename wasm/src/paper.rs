@@ -0,0 +1,109 @@
+//! 纸张尺寸预设
+//!
+//! 按打印 DPI 将常见纸张尺寸换算为像素宽高与对应的 `selected_size_height`，
+//! 各前端共用同一份数值，不再各自推算。尺寸均为竖版（宽 ≤ 高）。
+
+use serde::Serialize;
+
+/// 缺省打印 DPI
+pub const DEFAULT_DPI: f64 = 300.0;
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// 纸张尺寸预设
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct PaperPreset {
+    /// 标识，如 "a4"、"50x70cm"
+    pub id: &'static str,
+    /// 显示名称
+    pub name: &'static str,
+    /// 纸张宽度（毫米）
+    pub width_mm: f64,
+    /// 纸张高度（毫米）
+    pub height_mm: f64,
+    /// 换算所用的 DPI
+    pub dpi: f64,
+    /// 输出宽度（像素），即 `width`
+    pub width: u32,
+    /// 输出高度（像素），即 `height`
+    pub height: u32,
+    /// 道路线宽参考高度，与输出高度一致
+    pub selected_size_height: u32,
+    /// 是否在当前构建的画布上限以内
+    pub fits: bool,
+}
+
+/// (id, 名称, 宽 mm, 高 mm)
+const PAPERS: [(&str, &str, f64, f64); 8] = [
+    ("a4", "A4", 210.0, 297.0),
+    ("a3", "A3", 297.0, 420.0),
+    ("a2", "A2", 420.0, 594.0),
+    (
+        "18x24in",
+        "18 × 24 in",
+        18.0 * MM_PER_INCH,
+        24.0 * MM_PER_INCH,
+    ),
+    ("50x70cm", "50 × 70 cm", 500.0, 700.0),
+    ("30x30cm", "30 × 30 cm", 300.0, 300.0),
+    (
+        "12x12in",
+        "12 × 12 in",
+        12.0 * MM_PER_INCH,
+        12.0 * MM_PER_INCH,
+    ),
+    ("50x50cm", "50 × 50 cm", 500.0, 500.0),
+];
+
+/// 毫米按 DPI 换算为像素（四舍五入，至少 1）
+fn mm_to_px(mm: f64, dpi: f64) -> u32 {
+    ((mm / MM_PER_INCH * dpi).round() as u32).max(1)
+}
+
+/// 按 DPI 列出全部纸张预设
+pub fn paper_presets(dpi: f64) -> Result<Vec<PaperPreset>, String> {
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(format!("DPI must be positive, got {}", dpi));
+    }
+    Ok(PAPERS
+        .iter()
+        .map(|&(id, name, width_mm, height_mm)| {
+            let width = mm_to_px(width_mm, dpi);
+            let height = mm_to_px(height_mm, dpi);
+            PaperPreset {
+                id,
+                name,
+                width_mm,
+                height_mm,
+                dpi,
+                width,
+                height,
+                selected_size_height: height,
+                fits: crate::validate::check_canvas_size(width, height).is_ok(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paper_presets() {
+        let presets = paper_presets(DEFAULT_DPI).unwrap();
+        let a4 = &presets[0];
+        assert_eq!((a4.width, a4.height), (2480, 3508));
+        assert_eq!(a4.selected_size_height, a4.height);
+        assert!(a4.fits);
+        let inches = presets.iter().find(|p| p.id == "18x24in").unwrap();
+        assert_eq!((inches.width, inches.height), (5400, 7200));
+        assert!(presets.iter().all(|p| p.width <= p.height));
+
+        let low = paper_presets(150.0).unwrap();
+        assert_eq!((low[0].width, low[0].height), (1240, 1754));
+        assert!(paper_presets(0.0).is_err());
+        assert!(paper_presets(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! 可选的多线程执行
+//!
+//! 启用 `threads` feature 时由 rayon 在线程池中并行执行，否则退化为普通的串行循环。
+//! JS 宿主上的线程池由 wasm-bindgen-rayon 以 Web Worker 提供：需以 nightly 工具链、
+//! atomics / bulk-memory 目标特性与 `-Z build-std=std,panic_abort` 编译，页面需跨源隔离
+//! （COOP / COEP），并在渲染前调用导出的 `initThreadPool`。
+
+use std::ops::Range;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+/// 按固定大小分块处理可变切片，回调参数为（块序号, 块）
+pub fn for_each_chunk_mut<T: Send>(
+    data: &mut [T],
+    chunk_size: usize,
+    f: impl Fn(usize, &mut [T]) + Sync + Send,
+) {
+    #[cfg(feature = "threads")]
+    data.par_chunks_mut(chunk_size)
+        .enumerate()
+        .for_each(|(i, chunk)| f(i, chunk));
+    #[cfg(not(feature = "threads"))]
+    data.chunks_mut(chunk_size)
+        .enumerate()
+        .for_each(|(i, chunk)| f(i, chunk));
+}
+
+/// 逐个处理可变元素
+pub fn for_each_mut<T: Send>(items: &mut [T], f: impl Fn(&mut T) + Sync + Send) {
+    #[cfg(feature = "threads")]
+    items.par_iter_mut().for_each(f);
+    #[cfg(not(feature = "threads"))]
+    items.iter_mut().for_each(f);
+}
+
+/// 对区间内每个下标求值并按顺序收集结果
+pub fn map_range<R: Send>(range: Range<usize>, f: impl Fn(usize) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "threads")]
+    return range.into_par_iter().map(f).collect();
+    #[cfg(not(feature = "threads"))]
+    range.map(f).collect()
+}
+
+/// 将升序且互不重叠的区间切分为互不别名的可变子切片，便于并行处理
+pub fn disjoint_slices_mut<'a, T>(data: &'a mut [T], ranges: &[Range<usize>]) -> Vec<&'a mut [T]> {
+    let mut slices = Vec::with_capacity(ranges.len());
+    let mut rest = data;
+    let mut consumed = 0;
+    for r in ranges {
+        let (_, tail) = std::mem::take(&mut rest).split_at_mut(r.start - consumed);
+        let (slice, tail) = tail.split_at_mut(r.len());
+        slices.push(slice);
+        rest = tail;
+        consumed = r.end;
+    }
+    slices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_helpers_match_serial_results() {
+        let mut data: Vec<u32> = (0..10).collect();
+        for_each_chunk_mut(&mut data, 3, |i, chunk| {
+            chunk.iter_mut().for_each(|v| *v += i as u32 * 100)
+        });
+        assert_eq!(data[..4], [0, 1, 2, 103]);
+
+        let mut slices = disjoint_slices_mut(&mut data, &[1..3, 5..6]);
+        for_each_mut(&mut slices, |s| s.iter_mut().for_each(|v| *v = 0));
+        assert_eq!(data[..7], [0, 0, 0, 103, 104, 0, 206]);
+
+        assert_eq!(map_range(0..4, |i| i * i), vec![0, 1, 4, 9]);
+    }
+}
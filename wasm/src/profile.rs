@@ -0,0 +1,137 @@
+//! 渲染阶段计时
+//!
+//! 取代 console.time / timeEnd：各阶段耗时记录在内存中，随 `RenderResult` 返回，
+//! 前端可通过 `get_timings()` 采集遥测数据而无需解析控制台输出。
+
+use serde::Serialize;
+use std::cell::RefCell;
+
+/// 单个阶段的累计耗时
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct StageTiming {
+    pub stage: String,
+    pub ms: f64,
+}
+
+struct Profile {
+    /// 已开始但尚未结束的阶段及其开始时间
+    open: Vec<(String, f64)>,
+    /// 按首次结束的顺序排列
+    stages: Vec<StageTiming>,
+}
+
+thread_local! {
+    // 与 draw_stats / warnings 相同：计时只在调用渲染接口的线程上进行，
+    // 原生多线程调用方（如 HTTP 服务）的并发渲染各自计时、互不覆盖
+    static PROFILE: RefCell<Profile> = const {
+        RefCell::new(Profile {
+            open: Vec::new(),
+            stages: Vec::new(),
+        })
+    };
+}
+
+/// 当前时间（毫秒）
+pub fn now() -> f64 {
+    #[cfg(js_host)]
+    return crate::utils::performance_now();
+
+    #[cfg(not(js_host))]
+    {
+        static START: std::sync::LazyLock<std::time::Instant> =
+            std::sync::LazyLock::new(std::time::Instant::now);
+        START.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+/// 渲染开始：清空上一次的计时
+pub fn begin() {
+    PROFILE.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        profile.open.clear();
+        profile.stages.clear();
+    });
+}
+
+/// 开始计时一个阶段
+pub fn time(stage: &str) {
+    let start = now();
+    PROFILE.with(|profile| profile.borrow_mut().open.push((stage.to_string(), start)));
+}
+
+/// 结束计时一个阶段；未开始的阶段忽略
+pub fn time_end(stage: &str) {
+    let end = now();
+    let start = PROFILE.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        let i = profile.open.iter().rposition(|(s, _)| s == stage)?;
+        Some(profile.open.remove(i).1)
+    });
+    if let Some(start) = start {
+        record(stage, end - start);
+    }
+}
+
+/// 累加阶段耗时，同名阶段合并
+pub fn record(stage: &str, ms: f64) {
+    PROFILE.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        match profile.stages.iter_mut().find(|t| t.stage == stage) {
+            Some(timing) => timing.ms += ms,
+            None => profile.stages.push(StageTiming {
+                stage: stage.to_string(),
+                ms,
+            }),
+        }
+    });
+}
+
+/// 取出本次渲染的计时结果
+pub fn take() -> Vec<StageTiming> {
+    PROFILE.with(|profile| {
+        let mut profile = profile.borrow_mut();
+        profile.open.clear();
+        std::mem::take(&mut profile.stages)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_accumulate_in_order() {
+        begin();
+        time("parse");
+        time_end("parse");
+        record("draw", 2.0);
+        record("draw", 3.0);
+        time_end("never_started");
+
+        let timings = take();
+        let stages: Vec<&str> = timings.iter().map(|t| t.stage.as_str()).collect();
+        assert_eq!(stages, ["parse", "draw"]);
+        assert!(timings[0].ms >= 0.0);
+        assert_eq!(timings[1].ms, 5.0);
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_renders_do_not_share_timings() {
+        begin();
+        record("main", 1.0);
+        let other = std::thread::spawn(|| {
+            begin();
+            record("other", 2.0);
+            take()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].stage, "other");
+        let timings = take();
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].stage, "main");
+    }
+}
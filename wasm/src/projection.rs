@@ -1,12 +1,35 @@
-use crate::types::BoundingBox;
+use crate::types::{BoundingBox, Crs, GeoBounds, MapProjection, Margins, RadiusMode, ViewConfig};
 use std::f64::consts::PI;
 
+/// WGS84 椭球长半轴（米），Web Mercator 使用该值作为球半径
+pub const EARTH_RADIUS: f64 = 6378137.0;
+
+/// Web Mercator 平面上一整圈经度对应的宽度（米）
+pub const WORLD_WIDTH: f64 = 2.0 * PI * EARTH_RADIUS;
+
+/// 将经度归一化到 [-180, 180) 区间
+pub fn normalize_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// 将投影 x 坐标平移整数个世界宽度，使其落在距 `center_x` 最近的一侧
+///
+/// 用于跨越 ±180° 经线的区域：经线两侧的点分别投影到世界的两端，
+/// 以视口中心为参考展开后才能连续绘制
+#[inline]
+pub fn wrap_x(x: f64, center_x: f64) -> f64 {
+    let dx = x - center_x;
+    if dx.abs() <= WORLD_WIDTH / 2.0 {
+        x
+    } else {
+        x - (dx / WORLD_WIDTH).round() * WORLD_WIDTH
+    }
+}
+
 /// Web Mercator 投影（EPSG:3857）
-/// 将经纬度（WGS84）转换为平面坐标（米）
+/// 将经纬度（WGS84）转换为平面坐标（米），经度会先归一化到 [-180, 180)
 pub fn project_point(lon: f64, lat: f64) -> (f64, f64) {
-    const EARTH_RADIUS: f64 = 6378137.0;
-
-    let lon_rad = lon * (PI / 180.0);
+    let lon_rad = normalize_lon(lon) * (PI / 180.0);
     let lat_rad = lat * (PI / 180.0);
 
     let x = lon_rad * EARTH_RADIUS;
@@ -15,13 +38,23 @@ pub fn project_point(lon: f64, lat: f64) -> (f64, f64) {
     (x, y)
 }
 
+/// Web Mercator 逆投影：平面坐标（米）-> 经纬度 (lon, lat)
+pub fn unproject_point(x: f64, y: f64) -> (f64, f64) {
+    let lon = normalize_lon((x / EARTH_RADIUS).to_degrees());
+    let lat = (y / EARTH_RADIUS).sinh().atan().to_degrees();
+    (lon, lat)
+}
+
 /// 批量投影坐标点（原地修改）
 pub fn project_points_mut(coords: &mut [(f64, f64)]) {
-    for coord in coords.iter_mut() {
-        *coord = project_point(coord.0, coord.1);
-    }
+    crate::par::for_each_chunk_mut(coords, PROJECT_CHUNK, |_, chunk| {
+        crate::simd::project_in_place(chunk);
+    });
 }
 
+/// 并行投影时每个任务处理的坐标点数
+pub const PROJECT_CHUNK: usize = 4096;
+
 /// 批量投影坐标点
 pub fn project_points(coords: &[(f64, f64)]) -> Vec<(f64, f64)> {
     coords
@@ -30,44 +63,549 @@ pub fn project_points(coords: &[(f64, f64)]) -> Vec<(f64, f64)> {
         .collect()
 }
 
+fn looks_like_degrees(x: f64, y: f64) -> bool {
+    x.abs() <= 540.0 && y.abs() <= 90.0
+}
+
+/// 坐标是否可用：须为有限值，且落在声明的坐标参考系的合理范围内
+///
+/// 经度允许 ±540° 以容纳跨越反子午线的数据；Web Mercator 允许一个世界宽度以外的环绕
+pub fn coord_in_range(x: f64, y: f64, crs: Crs) -> bool {
+    match crs {
+        Crs::Wgs84 => looks_like_degrees(x, y),
+        Crs::Epsg3857 => x.abs() <= 1.5 * WORLD_WIDTH && y.abs() <= WORLD_WIDTH,
+    }
+}
+
+/// 按声明的坐标参考系校验几何坐标，拒绝明显不匹配的数据
+///
+/// - `wgs84`：任意坐标超出经纬度范围即报错（通常是已投影的数据）
+/// - `epsg3857`：所有坐标都落在经纬度范围内、而地图中心远离原点时报错
+///   （通常是忘记投影的经纬度数据）
+///
+/// `center` 为视图中心的 Web Mercator 坐标
+pub fn validate_crs(
+    points: impl IntoIterator<Item = (f64, f64)>,
+    crs: Crs,
+    center: (f64, f64),
+    layer: &str,
+) -> Result<(), String> {
+    match crs {
+        Crs::Wgs84 => {
+            for (x, y) in points {
+                if !looks_like_degrees(x, y) {
+                    return Err(format!(
+                        "{}: declared crs 'wgs84' but found coordinate ({}, {}) outside the lon/lat range; is the data already 'epsg3857'?",
+                        layer, x, y
+                    ));
+                }
+            }
+        }
+        Crs::Epsg3857 => {
+            let mut count = 0usize;
+            for (x, y) in points {
+                if !looks_like_degrees(x, y) {
+                    return Ok(());
+                }
+                count += 1;
+            }
+            if count > 0 && !looks_like_degrees(center.0, center.1) {
+                return Err(format!(
+                    "{}: declared crs 'epsg3857' but all {} coordinates look like lon/lat degrees; is the data 'wgs84'?",
+                    layer, count
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Web Mercator 可表示的最大纬度
+const MAX_MERCATOR_LAT: f64 = 85.051_128_78;
+
+/// 将半径换算为 Web Mercator 平面上的米数
+///
+/// Mercator 在纬度 φ 处的比例因子为 1/cos(φ)，因此地面距离需要除以 cos(φ)
+/// 才能得到投影平面上的等效长度
+pub fn mercator_radius(radius: f64, center_lat: f64, mode: RadiusMode) -> f64 {
+    match mode {
+        RadiusMode::Mercator => radius,
+        RadiusMode::Ground => {
+            let lat = center_lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT);
+            radius / lat.to_radians().cos()
+        }
+    }
+}
+
+// ── 可选输出投影 ─────────────────────────────────────────────────────────────
+
+/// WGS84 扁率
+const WGS84_F: f64 = 1.0 / 298.257223563;
+/// WGS84 第一偏心率平方
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+/// UTM 中央经线比例因子
+const UTM_K0: f64 = 0.9996;
+/// UTM 东偏移（米）
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+/// UTM 南半球北偏移（米）
+const UTM_FALSE_NORTHING: f64 = 10_000_000.0;
+
+/// 已解析的输出投影
+///
+/// 管线内部的几何数据始终是 Web Mercator 坐标（由 `project_point` 生成），
+/// 选择其他投影时由渲染器在绘制阶段通过 `reproject_web_mercator` 重投影
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projector {
+    WebMercator,
+    /// EPSG:4087 世界等距圆柱投影
+    Equirectangular,
+    /// WGS84 UTM 分带（zone 1..=60）
+    Utm {
+        zone: u8,
+        south: bool,
+    },
+}
+
+/// `MapProjection::Epsg` 支持的代码（区间以 "起-止" 表示），与 `Projector::from_config` 保持一致
+pub const SUPPORTED_EPSG: &[&str] = &["3857", "900913", "4087", "32601-32660", "32701-32760"];
+
+impl Projector {
+    pub fn from_config(projection: &MapProjection) -> Result<Self, String> {
+        match *projection {
+            MapProjection::WebMercator => Ok(Projector::WebMercator),
+            MapProjection::Utm { zone, south } => Self::utm(zone, south),
+            MapProjection::Epsg { code } => match code {
+                3857 | 900913 => Ok(Projector::WebMercator),
+                4087 => Ok(Projector::Equirectangular),
+                32601..=32660 => Self::utm((code - 32600) as u8, false),
+                32701..=32760 => Self::utm((code - 32700) as u8, true),
+                _ => Err(format!("Unsupported EPSG code: {}", code)),
+            },
+        }
+    }
+
+    fn utm(zone: u8, south: bool) -> Result<Self, String> {
+        if !(1..=60).contains(&zone) {
+            return Err(format!("Invalid UTM zone: {} (expected 1-60)", zone));
+        }
+        Ok(Projector::Utm { zone, south })
+    }
+
+    /// 经纬度 -> 投影平面坐标（米）
+    pub fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        match *self {
+            Projector::WebMercator => project_point(lon, lat),
+            Projector::Equirectangular => (
+                normalize_lon(lon).to_radians() * EARTH_RADIUS,
+                lat.to_radians() * EARTH_RADIUS,
+            ),
+            Projector::Utm { zone, south } => utm_forward(lon, lat, zone, south),
+        }
+    }
+
+    /// 投影平面坐标（米）-> 经纬度 (lon, lat)
+    pub fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        match *self {
+            Projector::WebMercator => unproject_point(x, y),
+            Projector::Equirectangular => (
+                normalize_lon((x / EARTH_RADIUS).to_degrees()),
+                (y / EARTH_RADIUS).to_degrees(),
+            ),
+            Projector::Utm { zone, south } => utm_inverse(x, y, zone, south),
+        }
+    }
+
+    /// 将管线内部的 Web Mercator 坐标重投影到当前投影
+    #[inline]
+    pub fn reproject_web_mercator(&self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Projector::WebMercator => (x, y),
+            _ => {
+                let (lon, lat) = unproject_point(x, y);
+                self.forward(lon, lat)
+            }
+        }
+    }
+
+    /// 指定位置沿纬线方向的点比例因子（投影距离 / 地面距离）
+    pub fn scale_factor(&self, lon: f64, lat: f64) -> f64 {
+        let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT);
+        match *self {
+            Projector::WebMercator | Projector::Equirectangular => 1.0 / lat.to_radians().cos(),
+            Projector::Utm { zone, .. } => {
+                let phi = lat.to_radians();
+                let ep2 = WGS84_E2 / (1.0 - WGS84_E2);
+                let c = ep2 * phi.cos().powi(2);
+                let a = phi.cos() * (lon - utm_central_meridian(zone)).to_radians();
+                UTM_K0 * (1.0 + (1.0 + c) * a * a / 2.0)
+            }
+        }
+    }
+
+    /// 按半径模式将半径换算为当前投影平面上的长度
+    pub fn plane_radius(&self, radius: f64, lon: f64, lat: f64, mode: RadiusMode) -> f64 {
+        match (self, mode) {
+            (Projector::WebMercator, _) => mercator_radius(radius, lat, mode),
+            (_, RadiusMode::Mercator) => radius,
+            (_, RadiusMode::Ground) => radius * self.scale_factor(lon, lat),
+        }
+    }
+}
+
+fn utm_central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// 子午线弧长（Snyder 3-21）
+fn meridian_arc(phi: f64) -> f64 {
+    let e2 = WGS84_E2;
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+    EARTH_RADIUS
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
+
+/// UTM 正算（Snyder 横轴墨卡托级数展开）
+fn utm_forward(lon: f64, lat: f64, zone: u8, south: bool) -> (f64, f64) {
+    let phi = lat.to_radians();
+    let dlon = normalize_lon(lon - utm_central_meridian(zone)).to_radians();
+    let ep2 = WGS84_E2 / (1.0 - WGS84_E2);
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    let n = EARTH_RADIUS / (1.0 - WGS84_E2 * sin_phi * sin_phi).sqrt();
+    let t = phi.tan().powi(2);
+    let c = ep2 * cos_phi * cos_phi;
+    let a = cos_phi * dlon;
+    let m = meridian_arc(phi);
+
+    let x = UTM_K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+        + UTM_FALSE_EASTING;
+    let mut y = UTM_K0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    if south {
+        y += UTM_FALSE_NORTHING;
+    }
+    (x, y)
+}
+
+/// UTM 反算（Snyder 横轴墨卡托级数展开）
+fn utm_inverse(x: f64, y: f64, zone: u8, south: bool) -> (f64, f64) {
+    let e2 = WGS84_E2;
+    let ep2 = e2 / (1.0 - e2);
+    let x = x - UTM_FALSE_EASTING;
+    let y = if south { y - UTM_FALSE_NORTHING } else { y };
+
+    let m = y / UTM_K0;
+    let mu =
+        m / (EARTH_RADIUS * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let (sin_phi1, cos_phi1) = phi1.sin_cos();
+    let n1 = EARTH_RADIUS / (1.0 - e2 * sin_phi1 * sin_phi1).sqrt();
+    let t1 = phi1.tan().powi(2);
+    let c1 = ep2 * cos_phi1 * cos_phi1;
+    let r1 = EARTH_RADIUS * (1.0 - e2) / (1.0 - e2 * sin_phi1 * sin_phi1).powf(1.5);
+    let d = x / (n1 * UTM_K0);
+
+    let phi = phi1
+        - (n1 * phi1.tan() / r1)
+            * (d * d / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1)
+                    * d.powi(6)
+                    / 720.0);
+    let lambda = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1) * d.powi(5)
+            / 120.0)
+        / cos_phi1;
+
+    (
+        normalize_lon(utm_central_meridian(zone) + lambda.to_degrees()),
+        phi.to_degrees(),
+    )
+}
+
+/// 地球平均半径（米），用于球面大地测量计算
+pub const MEAN_EARTH_RADIUS: f64 = 6371008.8;
+
+/// 生成以 (center_lat, center_lon) 为圆心、地面半径为 `radius` 米的测地线圆
+///
+/// 使用球面正解公式逐点计算方位角上的目标点，返回 WGS84 经纬度闭合环 (lon, lat)
+pub fn geodesic_circle(
+    center_lat: f64,
+    center_lon: f64,
+    radius: f64,
+    segments: usize,
+) -> Vec<(f64, f64)> {
+    let segments = segments.max(3);
+    let lat1 = center_lat.to_radians();
+    let lon1 = center_lon.to_radians();
+    let angular = radius / MEAN_EARTH_RADIUS;
+    let (sin_lat1, cos_lat1) = lat1.sin_cos();
+    let (sin_d, cos_d) = angular.sin_cos();
+
+    let mut ring = Vec::with_capacity(segments + 1);
+    for i in 0..segments {
+        let bearing = 2.0 * PI * i as f64 / segments as f64;
+        let lat2 = (sin_lat1 * cos_d + cos_lat1 * sin_d * bearing.cos()).asin();
+        let lon2 = lon1 + (bearing.sin() * sin_d * cos_lat1).atan2(cos_d - sin_lat1 * lat2.sin());
+        ring.push((lon2.to_degrees(), lat2.to_degrees()));
+    }
+    ring.push(ring[0]);
+    ring
+}
+
+/// 大圆弧采样的最大角步长（度）
+const ARC_STEP_DEG: f64 = 0.5;
+
+/// 两点间的大圆弧，按 `ARC_STEP_DEG` 均匀采样（含两端点），返回 WGS84 经纬度 (lon, lat)
+///
+/// 球面线性插值；两点重合或互为对跖点（大圆不唯一）时返回两端点连线
+pub fn great_circle_arc(from: (f64, f64), to: (f64, f64)) -> Vec<(f64, f64)> {
+    let unit = |(lon, lat): (f64, f64)| {
+        let (lon, lat) = (lon.to_radians(), lat.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (a, b) = (unit(from), unit(to));
+    let dot = (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]).clamp(-1.0, 1.0);
+    let angle = dot.acos();
+    let sin_angle = angle.sin();
+    if sin_angle < 1e-9 {
+        return vec![from, to];
+    }
+
+    let segments = (angle.to_degrees() / ARC_STEP_DEG).ceil().max(1.0) as usize;
+    (0..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+            let wa = ((1.0 - t) * angle).sin() / sin_angle;
+            let wb = (t * angle).sin() / sin_angle;
+            let v = [0, 1, 2].map(|k| wa * a[k] + wb * b[k]);
+            let lat = v[2].atan2(v[0].hypot(v[1]));
+            (v[1].atan2(v[0]).to_degrees(), lat.to_degrees())
+        })
+        .collect()
+}
+
+/// 依次以大圆弧连接各点并投影为 Web Mercator 折线
+///
+/// x 以 `center_x` 为参考展开；相邻点展开后相距超过半个世界（跨越视口对向经线）时断开，
+/// 避免横穿画布的连线
+pub fn great_circle_path(points: &[(f64, f64)], center_x: f64) -> Vec<Vec<(f64, f64)>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<(f64, f64)> = Vec::new();
+    for pair in points.windows(2) {
+        let arc = great_circle_arc(pair[0], pair[1]);
+        // 后续弧段的起点与上一段终点重合
+        let skip = usize::from(!line.is_empty());
+        for &(lon, lat) in &arc[skip..] {
+            let (x, y) = project_point(lon, lat);
+            let p = (wrap_x(x, center_x), y);
+            if let Some(&(last_x, _)) = line.last()
+                && (p.0 - last_x).abs() > WORLD_WIDTH / 2.0
+            {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(p);
+        }
+    }
+    lines.push(line);
+    lines.retain(|l| l.len() >= 2);
+    lines
+}
+
 /// 计算边界框（固定半径，确保所有尺寸看到相同的地理区域）
+/// Web Mercator 下的便捷版本，通用投影请使用 `bounds_around`
+#[allow(dead_code)]
 pub fn calculate_bounds(
     center_lat: f64,
     center_lon: f64,
     radius: f64,
     width: u32,
     height: u32,
+    padding_pct: f64,
 ) -> BoundingBox {
     // 投影中心点
     let (center_x, center_y) = project_point(center_lon, center_lat);
+    bounds_around(center_x, center_y, radius, width, height, padding_pct)
+}
 
-    // 计算纵横比
-    let aspect = width as f64 / height as f64;
+/// 内边距上限（百分比），避免留白吞掉整个画布
+const MAX_PADDING_PCT: f64 = 45.0;
+
+/// 内边距对应的半径放大倍数
+///
+/// `padding_pct` 为画布短边每一侧保留的留白百分比，半径需要放大
+/// 1 / (1 - 2p) 倍才能让请求的范围完整落在留白以内
+fn padding_scale(padding_pct: f64) -> f64 {
+    let p = padding_pct.clamp(0.0, MAX_PADDING_PCT) / 100.0;
+    1.0 / (1.0 - 2.0 * p)
+}
 
-    // 使用固定半径，不再根据宽高比调整
-    // 这样可以确保所有尺寸的海报都显示相同的地理区域
-    // 不同宽高比的画布会在边缘自然裁剪或留白
-    let half_x = radius;
-    let half_y = radius;
-
-    // 根据宽高比调整边界框，使其适配画布比例
-    // 但保持中心区域一致
-    let (final_half_x, final_half_y) = if aspect > 1.0 {
-        // 横向画布：保持高度，扩展宽度
-        (half_y * aspect, half_y)
+/// 以投影平面上的中心点与半径构建适配画布比例的边界框
+pub fn bounds_around(
+    center_x: f64,
+    center_y: f64,
+    radius: f64,
+    width: u32,
+    height: u32,
+    padding_pct: f64,
+) -> BoundingBox {
+    // 半径对应画布短边的一半，长边按宽高比从中心向两侧对称扩展：
+    // 任意比例（含正方形与 8:1 的横幅）下两个方向的米 / 像素一致，地图不变形，
+    // 且所有尺寸的海报在短边方向显示相同的地理范围。
+    // 有内边距时放大半径，保证请求范围四周留有 padding_pct 的边距
+    let half_short = radius * padding_scale(padding_pct);
+    let (w, h) = (width.max(1) as f64, height.max(1) as f64);
+    let short = w.min(h);
+    let (half_x, half_y) = (half_short * w / short, half_short * h / short);
+
+    BoundingBox::new(
+        center_x - half_x,
+        center_x + half_x,
+        center_y - half_y,
+        center_y + half_y,
+    )
+}
+
+/// 根据显式地理边界框计算投影边界（用于地图 UI 框选的场景）
+///
+/// 投影后的矩形会按画布宽高比向外扩展，保证整个框选区域完整可见且不变形。
+/// 与 GeoJSON 约定一致，`min_lon > max_lon` 表示区域跨越 ±180° 经线
+pub fn calculate_bounds_from_geo(
+    geo: &GeoBounds,
+    width: u32,
+    height: u32,
+    projector: Projector,
+    padding_pct: f64,
+) -> Result<BoundingBox, String> {
+    if geo.min_lat >= geo.max_lat || geo.min_lon == geo.max_lon {
+        return Err(format!(
+            "Invalid bounds: min ({}, {}) / max ({}, {}) do not describe an area",
+            geo.min_lon, geo.min_lat, geo.max_lon, geo.max_lat
+        ));
+    }
+
+    let (min_x, min_y, max_x, max_y) = if projector == Projector::WebMercator {
+        let (min_x, min_y) = project_point(geo.min_lon, geo.min_lat);
+        let (mut max_x, max_y) = project_point(geo.max_lon, geo.max_lat);
+        // 跨越 ±180° 时，将东侧边界展开到西侧边界的右边
+        if max_x <= min_x {
+            max_x += WORLD_WIDTH;
+        }
+        (min_x, min_y, max_x, max_y)
     } else {
-        // 纵向画布：保持宽度，扩展高度
-        (half_x, half_x / aspect)
+        // 非圆柱投影下经纬线不再平直，取四个角点投影后的外包矩形
+        let corners = [
+            projector.forward(geo.min_lon, geo.min_lat),
+            projector.forward(geo.min_lon, geo.max_lat),
+            projector.forward(geo.max_lon, geo.min_lat),
+            projector.forward(geo.max_lon, geo.max_lat),
+        ];
+        corners.iter().fold(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        )
     };
 
+    let center_x = (min_x + max_x) / 2.0;
+    let center_y = (min_y + max_y) / 2.0;
+    let mut half_x = (max_x - min_x) / 2.0;
+    let mut half_y = (max_y - min_y) / 2.0;
+
+    // 按画布比例扩展较短的一边
+    let aspect = width as f64 / height as f64;
+    if half_x / half_y > aspect {
+        half_y = half_x / aspect;
+    } else {
+        half_x = half_y * aspect;
+    }
+
+    let pad = padding_scale(padding_pct);
+    Ok(BoundingBox::new(
+        center_x - half_x * pad,
+        center_x + half_x * pad,
+        center_y - half_y * pad,
+        center_y + half_y * pad,
+    ))
+}
+
+/// 计算视图使用的投影与边界：优先使用显式地理边界，否则按 center + radius 计算
+pub fn resolve_view(view: &ViewConfig) -> Result<(BoundingBox, Projector), String> {
+    let projector = Projector::from_config(&view.projection)?;
+    // 有内嵌边距时先按边距内的矩形计算边界，再向外扩展到整张画布
+    let margins = view.margin_pct.map(|m| m.fractions()).unwrap_or_default();
+    let inner = |side: u32, a: f64, b: f64| ((side as f64 * (1.0 - a - b)).round() as u32).max(1);
+    let width = inner(view.width, margins.left, margins.right);
+    let height = inner(view.height, margins.top, margins.bottom);
+    let bounds = match &view.bounds {
+        Some(geo) => calculate_bounds_from_geo(geo, width, height, projector, view.padding_pct)?,
+        None => {
+            let (lon, lat) = (view.center.lon, view.center.lat);
+            let (center_x, center_y) = projector.forward(lon, lat);
+            let radius = projector.plane_radius(view.radius, lon, lat, view.radius_mode);
+            bounds_around(center_x, center_y, radius, width, height, view.padding_pct)
+        }
+    };
+    Ok((expand_to_margins(bounds, margins), projector))
+}
+
+/// 将边距内矩形的边界按各边边距比例向外扩展为整张画布的边界
+fn expand_to_margins(inner: BoundingBox, m: Margins) -> BoundingBox {
+    let full_width = inner.width() / (1.0 - m.left - m.right);
+    let full_height = inner.height() / (1.0 - m.top - m.bottom);
     BoundingBox::new(
-        center_x - final_half_x,
-        center_x + final_half_x,
-        center_y - final_half_y,
-        center_y + final_half_y,
+        inner.min_x - m.left * full_width,
+        inner.max_x + m.right * full_width,
+        inner.min_y - m.bottom * full_height,
+        inner.max_y + m.top * full_height,
     )
 }
 
+/// 256px 瓦片在 0 级时赤道处每像素对应的 Mercator 米数
+const ZOOM0_METERS_PER_PIXEL: f64 = WORLD_WIDTH / 256.0;
+
+/// 根据投影边界与输出宽度计算比例尺
+///
+/// 返回 (地面米/像素, 近似瓦片缩放级别, 指定 DPI 下的比例尺分母)
+pub fn compute_scale(
+    bounds: &BoundingBox,
+    center_lon: f64,
+    center_lat: f64,
+    width_px: u32,
+    dpi: u32,
+    projector: Projector,
+) -> (f64, f64, f64) {
+    let plane_mpp = bounds.width() / width_px.max(1) as f64;
+    let ground_mpp = plane_mpp / projector.scale_factor(center_lon, center_lat);
+    // 瓦片级别以 Web Mercator 为准：该纬度处 0 级瓦片的地面米/像素为 Z0 × cos(φ)
+    let lat = center_lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT);
+    let zoom = (ZOOM0_METERS_PER_PIXEL * lat.to_radians().cos() / ground_mpp).log2();
+    // 1 英寸 = 0.0254 米
+    let denominator = ground_mpp * dpi as f64 / 0.0254;
+    (ground_mpp, zoom, denominator)
+}
+
 /// 计算补偿半径（用于数据获取，避免裁切后数据不足）
 #[allow(dead_code)]
 pub fn calculate_compensated_radius(radius: f64, width: u32, height: u32) -> f64 {
@@ -89,12 +627,260 @@ mod tests {
         assert!(y.abs() > 6000000.0 && y.abs() < 7000000.0);
     }
 
+    #[test]
+    fn test_unproject_point_roundtrip() {
+        let (x, y) = project_point(-73.9857, 40.7484);
+        let (lon, lat) = unproject_point(x, y);
+        assert!((lon - -73.9857).abs() < 1e-9);
+        assert!((lat - 40.7484).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_great_circle_arc() {
+        // 巴黎 -> 东京：端点保持不变，中途经过高纬度
+        let arc = great_circle_arc((2.35, 48.86), (139.69, 35.68));
+        assert!(arc.len() > 50);
+        let (first, last) = (arc[0], arc[arc.len() - 1]);
+        assert!((first.0 - 2.35).abs() < 1e-9 && (first.1 - 48.86).abs() < 1e-9);
+        assert!((last.0 - 139.69).abs() < 1e-9 && (last.1 - 35.68).abs() < 1e-9);
+        let max_lat = arc.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+        assert!(max_lat > 60.0, "{}", max_lat);
+
+        assert_eq!(great_circle_arc((1.0, 2.0), (1.0, 2.0)).len(), 2);
+        assert_eq!(great_circle_arc((0.0, 0.0), (180.0, 0.0)).len(), 2);
+    }
+
+    #[test]
+    fn test_great_circle_path_splits_at_far_meridian() {
+        // 视口中心在 0°，横跨 ±180° 的航线在对向经线处断开
+        let lines = great_circle_path(&[(170.0, 0.0), (-170.0, 0.0)], 0.0);
+        assert_eq!(lines.len(), 2);
+        // 以 180° 为中心时连续
+        let (center_x, _) = project_point(180.0, 0.0);
+        let lines = great_circle_path(&[(170.0, 0.0), (-170.0, 0.0), (-160.0, 10.0)], center_x);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].windows(2).all(|w| w[1].0 > w[0].0));
+    }
+
     #[test]
     fn test_calculate_bounds() {
-        let bounds = calculate_bounds(48.8566, 2.3522, 10000.0, 1200, 1600);
+        let bounds = calculate_bounds(48.8566, 2.3522, 10000.0, 1200, 1600, 0.0);
         assert!(bounds.width() > 0.0);
         assert!(bounds.height() > 0.0);
         // 纵向图，宽度应该小于高度
         assert!(bounds.width() < bounds.height());
     }
+
+    #[test]
+    fn test_bounds_around_aspect_ratios() {
+        let (cx, cy) = (1000.0, -500.0);
+        for (width, height, half_x, half_y) in [
+            (1000, 1000, 5000.0, 5000.0),
+            (3000, 1000, 15000.0, 5000.0),
+            (1000, 3000, 5000.0, 15000.0),
+        ] {
+            let b = bounds_around(cx, cy, 5000.0, width, height, 0.0);
+            // 以中心对称，短边一半等于半径
+            assert!((b.min_x + b.max_x - 2.0 * cx).abs() < 1e-9);
+            assert!((b.min_y + b.max_y - 2.0 * cy).abs() < 1e-9);
+            assert!(
+                (b.width() - 2.0 * half_x).abs() < 1e-9,
+                "{}x{}",
+                width,
+                height
+            );
+            assert!(
+                (b.height() - 2.0 * half_y).abs() < 1e-9,
+                "{}x{}",
+                width,
+                height
+            );
+            // 两个方向的米 / 像素一致
+            let ratio = b.width() / width as f64 / (b.height() / height as f64);
+            assert!((ratio - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_calculate_bounds_with_padding() {
+        // 短边每侧留白 10%，半径占短边的 80%
+        let plain = calculate_bounds(48.8566, 2.3522, 10000.0, 1200, 1600, 0.0);
+        let padded = calculate_bounds(48.8566, 2.3522, 10000.0, 1200, 1600, 10.0);
+        assert!((plain.width() - 20000.0).abs() < 1e-6);
+        assert!((padded.width() * 0.8 - 20000.0).abs() < 1e-6);
+        // 比例保持不变
+        let ratio = padded.width() / padded.height();
+        assert!((ratio - 1200.0 / 1600.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_bounds_from_geo() {
+        let geo = GeoBounds {
+            min_lon: 2.25,
+            min_lat: 48.80,
+            max_lon: 2.45,
+            max_lat: 48.90,
+        };
+        let bounds =
+            calculate_bounds_from_geo(&geo, 1000, 1000, Projector::WebMercator, 0.0).unwrap();
+        // 正方形画布，扩展后宽高一致
+        assert!((bounds.width() - bounds.height()).abs() < 1e-6);
+
+        // 框选区域必须完整包含在结果内
+        let (min_x, min_y) = project_point(geo.min_lon, geo.min_lat);
+        let (max_x, max_y) = project_point(geo.max_lon, geo.max_lat);
+        assert!(bounds.min_x <= min_x && bounds.max_x >= max_x);
+        assert!(bounds.min_y <= min_y && bounds.max_y >= max_y);
+    }
+
+    #[test]
+    fn test_calculate_bounds_from_geo_rejects_inverted() {
+        let geo = GeoBounds {
+            min_lon: 2.25,
+            min_lat: 48.90,
+            max_lon: 2.45,
+            max_lat: 48.80,
+        };
+        assert!(calculate_bounds_from_geo(&geo, 1000, 1000, Projector::WebMercator, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_mercator_radius_ground_mode() {
+        // 赤道附近比例因子接近 1
+        let r = mercator_radius(10000.0, 1.35, RadiusMode::Ground);
+        assert!((r - 10000.0).abs() < 5.0);
+
+        // 奥斯陆（约 60°N）比例因子约为 2
+        let r = mercator_radius(10000.0, 59.91, RadiusMode::Ground);
+        assert!((r - 20000.0).abs() < 100.0);
+
+        // 默认模式保持原值
+        assert_eq!(
+            mercator_radius(10000.0, 59.91, RadiusMode::Mercator),
+            10000.0
+        );
+    }
+
+    #[test]
+    fn test_geodesic_circle() {
+        let ring = geodesic_circle(59.91, 10.75, 10000.0, 64);
+        assert_eq!(ring.len(), 65);
+        assert_eq!(ring[0], ring[64]);
+
+        // 正北方向的点应位于中心以北约 0.0899°（10 km / 111.2 km）
+        let (lon, lat) = ring[0];
+        assert!((lon - 10.75).abs() < 1e-9);
+        assert!((lat - 59.91 - 0.0899).abs() < 1e-3);
+
+        // 正东方向的点经度差应随纬度放大（约 1/cos(60°) ≈ 2 倍）
+        let (lon, _) = ring[16];
+        assert!((lon - 10.75 - 0.0899 * 2.0).abs() < 5e-3);
+    }
+
+    #[test]
+    fn test_compute_scale() {
+        // 赤道处 0 级瓦片宽度的边界，输出 256px 时应为 0 级
+        let bounds = BoundingBox::new(-WORLD_WIDTH / 2.0, WORLD_WIDTH / 2.0, -1.0, 1.0);
+        let (mpp, zoom, _) = compute_scale(&bounds, 0.0, 0.0, 256, 300, Projector::WebMercator);
+        assert!(zoom.abs() < 1e-9);
+        assert!((mpp - ZOOM0_METERS_PER_PIXEL).abs() < 1e-6);
+
+        // 每像素 1 米、300 DPI 时比例尺约为 1:11811
+        let bounds = BoundingBox::new(0.0, 1000.0, 0.0, 1000.0);
+        let (mpp, _, denom) = compute_scale(&bounds, 0.0, 0.0, 1000, 300, Projector::WebMercator);
+        assert!((mpp - 1.0).abs() < 1e-9);
+        assert!((denom - 11811.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_utm_forward_central_meridian() {
+        // 31N 带中央经线 3°E 与赤道交点
+        let (x, y) = utm_forward(3.0, 0.0, 31, false);
+        assert!((x - 500_000.0).abs() < 1e-6);
+        assert!(y.abs() < 1e-6);
+
+        // 巴黎（31N）约为 E 452 km / N 5411 km
+        let (x, y) = utm_forward(2.3522, 48.8566, 31, false);
+        assert!((x - 452_000.0).abs() < 1_000.0);
+        assert!((y - 5_411_000.0).abs() < 1_000.0);
+    }
+
+    #[test]
+    fn test_utm_roundtrip() {
+        for &(lon, lat, zone, south) in &[
+            (2.3522, 48.8566, 31, false),
+            (151.2093, -33.8688, 56, true),
+            (-70.6693, -33.4489, 19, true),
+        ] {
+            let (x, y) = utm_forward(lon, lat, zone, south);
+            let (lon2, lat2) = utm_inverse(x, y, zone, south);
+            assert!((lon - lon2).abs() < 1e-7, "lon {} vs {}", lon, lon2);
+            assert!((lat - lat2).abs() < 1e-7, "lat {} vs {}", lat, lat2);
+        }
+    }
+
+    #[test]
+    fn test_projector_from_epsg() {
+        let p = Projector::from_config(&MapProjection::Epsg { code: 32756 }).unwrap();
+        assert_eq!(
+            p,
+            Projector::Utm {
+                zone: 56,
+                south: true
+            }
+        );
+        assert!(Projector::from_config(&MapProjection::Epsg { code: 2154 }).is_err());
+        assert!(
+            Projector::from_config(&MapProjection::Utm {
+                zone: 0,
+                south: false
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_crs() {
+        let center = project_point(2.3522, 48.8566);
+        let degrees = vec![(2.35, 48.85), (2.36, 48.86)];
+        let meters = vec![project_point(2.35, 48.85), project_point(2.36, 48.86)];
+
+        assert!(validate_crs(degrees.clone(), Crs::Wgs84, center, "roads").is_ok());
+        assert!(validate_crs(meters.clone(), Crs::Epsg3857, center, "roads").is_ok());
+        assert!(validate_crs(meters, Crs::Wgs84, center, "roads").is_err());
+        assert!(validate_crs(degrees, Crs::Epsg3857, center, "roads").is_err());
+    }
+
+    #[test]
+    fn test_normalize_lon() {
+        assert!((normalize_lon(190.0) - -170.0).abs() < 1e-9);
+        assert!((normalize_lon(-190.0) - 170.0).abs() < 1e-9);
+        assert!((normalize_lon(180.0) - -180.0).abs() < 1e-9);
+        assert!((normalize_lon(12.5) - 12.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wrap_x_across_antimeridian() {
+        // 以斐济苏瓦（178.44°E）为中心，西经 179.9° 的点应展开到中心东侧
+        let (center_x, _) = project_point(178.44, -18.14);
+        let (x, _) = project_point(-179.9, -18.14);
+        let wrapped = wrap_x(x, center_x);
+        assert!(wrapped > center_x);
+        assert!(wrapped - center_x < 300_000.0);
+    }
+
+    #[test]
+    fn test_calculate_bounds_from_geo_across_antimeridian() {
+        let geo = GeoBounds {
+            min_lon: 178.0,
+            min_lat: -18.5,
+            max_lon: -179.5,
+            max_lat: -17.5,
+        };
+        let bounds =
+            calculate_bounds_from_geo(&geo, 1000, 1000, Projector::WebMercator, 0.0).unwrap();
+        // 跨经线区域约 2.5° 宽，而不是绕地球大半圈
+        assert!(bounds.width() < 400_000.0);
+        assert!(bounds.max_x > WORLD_WIDTH / 2.0);
+    }
 }
@@ -0,0 +1,139 @@
+//! 量化差分编码的紧凑二进制几何格式
+//!
+//! 与扁平 f64 格式结构完全一致，只是坐标被量化为整数后与前一个点做差分，
+//! 再经 zigzag 编码存入 Uint32Array，体积约为 Float64Array 的 1/2，
+//! 配合 gzip 可再缩小数倍。布局：
+//!
+//! `[layer, scale, <与扁平格式相同的结构，坐标位置为 zigzag(dx), zigzag(dy)>]`
+//!
+//! - `layer`：0 = 道路，1 = 多边形
+//! - `scale`：每米的量化单位数（如 10 表示 0.1 米精度）
+//! - 差分基准在整个数据流中连续传递，首个点相对 (0, 0)
+
+use crate::data_processor::{polygon_bin_coord_ranges, road_bin_coord_ranges};
+use std::ops::Range;
+
+/// 量化数据的图层类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantizedLayer {
+    Roads = 0,
+    Polygons = 1,
+}
+
+/// 为保证 zigzag 结果不超出 u32，限制最大量化精度（Web Mercator 坐标绝对值 < 2.01e7 米）
+pub const MAX_SCALE: u32 = 100;
+
+fn zigzag(v: i64) -> u32 {
+    ((v << 1) ^ (v >> 63)) as u32
+}
+
+fn unzigzag(v: u32) -> i64 {
+    i64::from(v >> 1) ^ -i64::from(v & 1)
+}
+
+fn ranges_for(layer: QuantizedLayer, data: &[impl Copy + Into<f64>]) -> Vec<Range<usize>> {
+    match layer {
+        QuantizedLayer::Roads => road_bin_coord_ranges(data),
+        QuantizedLayer::Polygons => polygon_bin_coord_ranges(data),
+    }
+}
+
+/// 将扁平 f64 格式编码为量化差分格式
+pub fn encode(data: &[f64], layer: QuantizedLayer, scale: u32) -> Result<Vec<u32>, String> {
+    if scale == 0 || scale > MAX_SCALE {
+        return Err(format!("scale must be between 1 and {}", MAX_SCALE));
+    }
+    let mut out = Vec::with_capacity(data.len() + 2);
+    out.push(layer as u32);
+    out.push(scale);
+    // 结构字段（数量、类型）原样写入，坐标位置稍后覆盖
+    out.extend(data.iter().map(|&v| v as u32));
+
+    let (mut px, mut py) = (0i64, 0i64);
+    for range in ranges_for(layer, data) {
+        for i in range.step_by(2) {
+            let qx = (data[i] * f64::from(scale)).round() as i64;
+            let qy = (data[i + 1] * f64::from(scale)).round() as i64;
+            out[2 + i] = zigzag(qx - px);
+            out[2 + i + 1] = zigzag(qy - py);
+            (px, py) = (qx, qy);
+        }
+    }
+    Ok(out)
+}
+
+/// 将量化差分格式解码回扁平 f64 格式
+pub fn decode(data: &[u32]) -> Result<(QuantizedLayer, Vec<f64>), String> {
+    if data.len() < 2 {
+        return Err("quantized data is missing its header".to_string());
+    }
+    let layer = match data[0] {
+        0 => QuantizedLayer::Roads,
+        1 => QuantizedLayer::Polygons,
+        other => return Err(format!("unknown quantized layer type {}", other)),
+    };
+    let scale = data[1];
+    if scale == 0 {
+        return Err("quantized scale must be positive".to_string());
+    }
+
+    let body = &data[2..];
+    let mut out: Vec<f64> = body.iter().map(|&v| f64::from(v)).collect();
+    let (mut px, mut py) = (0i64, 0i64);
+    for range in ranges_for(layer, body) {
+        for i in range.step_by(2) {
+            px += unzigzag(body[i]);
+            py += unzigzag(body[i + 1]);
+            out[i] = px as f64 / f64::from(scale);
+            out[i + 1] = py as f64 / f64::from(scale);
+        }
+    }
+    Ok((layer, out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roads_roundtrip_within_precision() {
+        let roads = [
+            2.0,
+            1.0,
+            2.0,
+            12_958_000.04,
+            4_852_000.51,
+            12_958_010.0,
+            4_851_990.0,
+            4.0,
+            1.0,
+            -20_037_508.34,
+            -19_000_000.0,
+        ];
+        let encoded = encode(&roads, QuantizedLayer::Roads, 10).unwrap();
+        assert_eq!(encoded.len(), roads.len() + 2);
+        // 第二个点相对第一个点的差分很小
+        assert!(encoded[2 + 5] < 1000);
+
+        let (layer, decoded) = decode(&encoded).unwrap();
+        assert_eq!(layer, QuantizedLayer::Roads);
+        for (a, b) in roads.iter().zip(&decoded) {
+            assert!((a - b).abs() <= 0.05, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_polygons_roundtrip_and_invalid_input() {
+        // 1 个多边形：外圈 3 点 + 1 个 3 点内圈
+        let polys = [
+            1.0, 3.0, 1.0, 0.0, 0.0, 10.0, 0.0, 0.0, 10.0, 3.0, 1.0, 1.0, 2.0, 1.0, 1.0, 2.0,
+        ];
+        let encoded = encode(&polys, QuantizedLayer::Polygons, 1).unwrap();
+        let (layer, decoded) = decode(&encoded).unwrap();
+        assert_eq!(layer, QuantizedLayer::Polygons);
+        assert_eq!(decoded, polys);
+
+        assert!(encode(&polys, QuantizedLayer::Polygons, 0).is_err());
+        assert!(decode(&[7, 1]).is_err());
+    }
+}
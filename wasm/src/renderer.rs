@@ -1,14 +1,32 @@
 use fontdue::layout::{CoordinateSystem, Layout, TextStyle};
 use fontdue::{Font, FontSettings};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
 // [Road Casing] 新增 LineCap / LineJoin，用于道路圆头描边
 use tiny_skia::{
-    Color, FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform,
+    Color, ColorU8, FillRule, FilterQuality, LineCap, LineJoin, Mask, Paint, PathBuilder, Pixmap,
+    PixmapPaint, Rect, Stroke, StrokeDash, Transform,
 };
 
-use crate::types::{BoundingBox, PolyFeature, Road, RoadType, TextPosition, Theme};
-use crate::utils::{calculate_font_size, format_city_name, format_coordinates, parse_hex_color};
+use crate::cull::Viewport;
+use crate::draw_stats::DrawStats;
+use crate::index::Aabb;
+use crate::par;
+use crate::projection::{Projector, WORLD_WIDTH, wrap_x};
+use crate::simd::ScreenTransform;
+use crate::simplify::{PointKey, douglas_peucker, shared_vertices, visvalingam_ring};
+use crate::solar::{self, SubsolarPoint};
+use crate::types::{
+    BoundingBox, HaloStyle, HomeMarker, LocalBin, LogoOverlay, LogoPosition, Margins, MarkerStyle,
+    PngCompression, PoiHalo, PoiLabel, PolyFeature, RoadType, TextPosition, Theme, Watermark,
+    WatermarkStyle, orient_ring,
+};
+#[cfg(debug_assertions)]
+use crate::utils::debug;
+use crate::utils::{
+    calculate_font_size, dash_pattern, format_city_name, format_coordinates, parse_hex_color,
+    poi_label,
+};
 
 /// 地图渲染引擎
 pub struct MapRenderer {
@@ -21,12 +39,56 @@ pub struct MapRenderer {
     height: u32,
     x_factor: f64,
     y_factor: f64,
+    /// [跨经线] 视口中心的投影 x 坐标，用于展开跨越 ±180° 的坐标
+    center_x: f64,
+    /// 输出投影；非 Web Mercator 时在绘制阶段重投影输入坐标
+    projector: Projector,
     text_position: TextPosition,
+    /// 文字面板的顶部（画布像素），设置后文字在面板内居中（见 `fill_text_panel`）
+    text_panel_top: Option<f32>,
     /// [超采样] 内部渲染倍数。实际 Pixmap = width×render_scale × height×render_scale。
     /// 导出时通过 Box Filter 下采样回逻辑尺寸，所有边缘细节更平滑。
     render_scale: u32,
+    /// 几何简化容差（输出像素），绘制前换算为世界坐标米数
+    simplify_tolerance_px: f32,
+    /// 每个道路 Path 的最大线段数
+    path_chunk_segments: usize,
+    /// 细节层级阈值（输出像素）：外包框不足该尺寸的居住区道路与多边形不绘制
+    lod_min_px: f32,
+    /// 是否对所有路径启用抗锯齿
+    anti_alias: bool,
+    /// POI 标记内的编号与所用字体（见 `set_poi_labels`）
+    poi_labels: Option<(PoiLabel, Font)>,
+    /// POI 标记外的光环
+    poi_halo: Option<PoiHalo>,
+    /// 自上次 `take_draw_stats` 以来的绘制统计
+    draw_stats: DrawStats,
 }
 
+/// 道路 Path 默认线段数上限
+///
+/// 基准（test_bench_path_chunk_segments，20 万段随机交叉折线，原生 release）：
+/// 不切分约 2.9s，4096 段约 2.5s，256–64k 之间在 2.3–3.2s 波动，差异不大。
+/// 切分的主要收益是限制单个 Path 描边时的中间缓冲（与 Path 大小成正比），降低内存峰值
+pub const DEFAULT_PATH_CHUNK_SEGMENTS: usize = 4096;
+
+/// 宝丽来边框颜色、底边文字颜色与阴影（宽度为逻辑像素）
+const POLAROID_FRAME_COLOR: &str = "#FFFFFF";
+const POLAROID_INK_COLOR: &str = "#333333";
+const POLAROID_SHADOW_PX: f32 = 4.0;
+const POLAROID_SHADOW_OPACITY: f32 = 0.35;
+
+/// 夜侧缺省颜色
+pub const DEFAULT_NIGHT_COLOR: &str = "#0B1026";
+
+/// 夜色按此间距（内部像素）取样太阳高度角，再双线性放大到整个画布
+const NIGHT_CELL_PX: u32 = 8;
+
+/// 建路径前裁剪几何所用的画布外扩边距（内部像素）
+///
+/// 裁剪产生的边界落在画布之外，不会被最宽的道路描边或抗锯齿带入画面
+const CLIP_MARGIN_PX: f64 = 64.0;
+
 impl MapRenderer {
     /// 创建渲染器
     pub fn new(
@@ -38,8 +100,8 @@ impl MapRenderer {
     ) -> Option<Self> {
         // [超采样] 内部以 2× 分辨率创建画布；导出时再缩回逻辑尺寸
         let render_scale = 2u32;
-        let render_width = width * render_scale;
-        let render_height = height * render_scale;
+        let render_width = width.checked_mul(render_scale)?;
+        let render_height = height.checked_mul(render_scale)?;
 
         let pixmap = Pixmap::new(render_width, render_height)?;
 
@@ -56,12 +118,75 @@ impl MapRenderer {
             height,
             x_factor,
             y_factor,
+            center_x: (bounds.min_x + bounds.max_x) / 2.0,
+            projector: Projector::WebMercator,
             text_position,
+            text_panel_top: None,
             render_scale,
+            simplify_tolerance_px: crate::types::default_simplify_tolerance(),
+            path_chunk_segments: DEFAULT_PATH_CHUNK_SEGMENTS,
+            lod_min_px: crate::types::default_lod_min_px(),
+            anti_alias: true,
+            poi_labels: None,
+            poi_halo: None,
+            draw_stats: DrawStats::default(),
         })
     }
 
+    /// 设置输出投影（bounds 需已按该投影计算）
+    pub fn set_projector(&mut self, projector: Projector) {
+        self.projector = projector;
+    }
+
+    /// 设置几何简化容差（输出像素，0 表示不简化）
+    pub fn set_simplify_tolerance(&mut self, tolerance_px: f32) {
+        self.simplify_tolerance_px = tolerance_px.max(0.0);
+    }
+
+    /// 简化容差换算到世界坐标：容差像素 × 每输出像素米数
+    fn simplify_tolerance_world(&self) -> f64 {
+        self.simplify_tolerance_px as f64 * self.bounds.width() / self.width as f64
+    }
+
+    /// 设置细节层级阈值（输出像素，0 表示绘制全部细节）
+    pub fn set_lod_min_px(&mut self, min_px: f32) {
+        self.lod_min_px = min_px.max(0.0);
+    }
+
+    /// 启用或关闭抗锯齿（关闭后描边速度约提升一倍，适合快速预览）
+    pub fn set_anti_alias(&mut self, anti_alias: bool) {
+        self.anti_alias = anti_alias;
+    }
+
+    /// 细节层级阈值换算到世界坐标
+    fn lod_min_extent_world(&self) -> f64 {
+        self.lod_min_px as f64 * self.bounds.width() / self.width as f64
+    }
+
     /// 获取当前配色
+    /// 取出并清零自上次调用以来的绘制统计，调用方按图层归类
+    /// 在 POI 标记内绘制编号或字母，文字颜色按标记颜色自动取黑或白
+    pub fn set_poi_labels(&mut self, labels: PoiLabel, font_data: &[u8]) -> Result<(), String> {
+        self.poi_labels = match labels {
+            PoiLabel::None => None,
+            labels => Some((
+                labels,
+                Font::from_bytes(font_data, FontSettings::default())
+                    .map_err(|e| format!("Failed to load font: {}", e))?,
+            )),
+        };
+        Ok(())
+    }
+
+    /// 在 POI 标记外绘制同心光环
+    pub fn set_poi_halo(&mut self, halo: Option<PoiHalo>) {
+        self.poi_halo = halo;
+    }
+
+    pub fn take_draw_stats(&mut self) -> DrawStats {
+        std::mem::take(&mut self.draw_stats)
+    }
+
     pub fn get_theme(&self) -> &Theme {
         &self.theme
     }
@@ -97,63 +222,46 @@ impl MapRenderer {
 
     /// 绘制背景
     pub fn draw_background(&mut self) {
-        let color = parse_hex_color(&self.theme.bg);
+        let color = hex_color(&self.theme.bg);
         self.pixmap.fill(color);
     }
 
-    /// 绘制水体
-    pub fn draw_water(&mut self, water_features: &[PolyFeature]) {
-        if water_features.is_empty() {
-            return;
-        }
-        let color = parse_hex_color(&self.theme.water);
-        let mut pb = PathBuilder::new();
-        for feature in water_features {
-            self.add_poly_to_path(&mut pb, feature);
-        }
-
-        if let Some(path) = pb.finish() {
-            let mut paint = Paint::default();
-            paint.set_color(color);
-            paint.anti_alias = true;
-
-            self.pixmap.fill_path(
-                &path,
-                &paint,
-                FillRule::EvenOdd,
-                Transform::identity(),
-                None,
-            );
-        }
+    /// 在透明图层上执行 `draw`，返回该图层（与画布同尺寸）
+    ///
+    /// 图层可缓存后通过 `composite_layer` 叠加，效果与直接绘制在画布上相同
+    pub fn render_layer(&mut self, draw: impl FnOnce(&mut Self)) -> Option<Pixmap> {
+        let layer = Pixmap::new(self.render_width(), self.render_height())?;
+        let base = std::mem::replace(&mut self.pixmap, layer);
+        draw(self);
+        Some(std::mem::replace(&mut self.pixmap, base))
     }
 
-    /// 绘制公园
-    pub fn draw_parks(&mut self, park_features: &[PolyFeature]) {
-        if park_features.is_empty() {
-            return;
-        }
-        let color = parse_hex_color(&self.theme.parks);
-        let mut pb = PathBuilder::new();
-        for feature in park_features {
-            self.add_poly_to_path(&mut pb, feature);
-        }
-
-        if let Some(path) = pb.finish() {
-            let mut paint = Paint::default();
-            paint.set_color(color);
-            paint.anti_alias = true;
+    /// 将缓存图层叠加到画布（source-over）
+    pub fn composite_layer(&mut self, layer: &Pixmap) {
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            layer.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
 
-            self.pixmap.fill_path(
-                &path,
-                &paint,
-                FillRule::EvenOdd,
-                Transform::identity(),
-                None,
-            );
-        }
+    /// 将另一渲染器的画布叠加到 `(x, y)`（逻辑像素），用于拼贴分格
+    pub fn composite_panel(&mut self, panel: MapRenderer, x: u32, y: u32) {
+        let scale = self.render_scale as i32;
+        self.pixmap.draw_pixmap(
+            x as i32 * scale,
+            y as i32 * scale,
+            panel.pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
     }
 
-    /// 绘制道路 (二进制直读版 - 极致单次扫描优化)
+    // /// 绘制道路 (二进制直读版 - 极致单次扫描优化)
     // pub fn draw_roads_bin(&mut self, data: &[f64]) {
     //     // 【优化】委托给 scaled 版本，消除重复代码；scale_factor=1.0 等同于原无缩放行为
     //     self.draw_roads_bin_scaled(data, 1.0);
@@ -161,6 +269,21 @@ impl MapRenderer {
 
     /// 绘制道路 (二进制直读版) 使用动态缩放因子
     pub fn draw_roads_bin_scaled(&mut self, data: &[f64], scale_factor: f32) -> [f64; 6] {
+        self.draw_roads_bin_impl(data, (0.0, 0.0), scale_factor)
+    }
+
+    /// 绘制道路（f32 局部坐标版，见 `LocalBin`）
+    pub fn draw_roads_local(&mut self, roads: &LocalBin, scale_factor: f32) -> [f64; 6] {
+        self.draw_roads_bin_impl(&roads.data, roads.origin, scale_factor)
+    }
+
+    /// 二进制道路绘制，坐标为相对 `origin` 的偏移（f64 绝对坐标传入原点 0）
+    fn draw_roads_bin_impl<T: Copy + Into<f64> + Sync>(
+        &mut self,
+        data: &[T],
+        origin: (f64, f64),
+        scale_factor: f32,
+    ) -> [f64; 6] {
         if data.is_empty() {
             return [0.0; 6];
         }
@@ -171,55 +294,81 @@ impl MapRenderer {
         // 使道路宽度在 2× 画布上保持与逻辑分辨率一致的视觉比例
         let scale_factor = scale_factor * self.render_scale as f32;
 
-        let road_count = data[0] as usize;
-
-        // 准备 6 个路径构建器，对应 6 种道路类型
-        let mut pbs: Vec<PathBuilder> = (0..6).map(|_| PathBuilder::new()).collect();
-        let mut found = vec![false; 6];
+        let road_count = data[0].into() as usize;
+        let tolerance = self.simplify_tolerance_world();
+        let min_extent = self.lod_min_extent_world();
 
+        // 【优化】：单次遍历二进制数据，按类型记录每条道路坐标所在的区间
+        let mut by_type: [Vec<std::ops::Range<usize>>; 6] = Default::default();
+        let mut stats = DrawStats {
+            features: road_count,
+            ..Default::default()
+        };
         let mut curr_offset = 1;
-
-        // 【优化】：单次遍历二进制数据，按类型分发到不同的路径构建器
         for _ in 0..road_count {
             if curr_offset + 2 > data.len() {
                 break;
             }
-            let t = data[curr_offset] as usize;
-            let count = data[curr_offset + 1] as usize;
+            let t = data[curr_offset].into() as usize;
+            let count = data[curr_offset + 1].into() as usize;
             curr_offset += 2;
 
-            if t < 6 {
-                if curr_offset + count * 2 <= data.len() && count >= 2 {
-                    // 先收集屏幕坐标
-                    let screen_coords: Vec<(f32, f32)> = (0..count)
-                        .map(|i| {
-                            self.world_to_screen((
-                                data[curr_offset + i * 2],
-                                data[curr_offset + i * 2 + 1],
-                            ))
-                        })
-                        .collect();
-
-                    // 简化：epsilon = 0.5 屏幕像素，过滤掉亚像素级冗余点
-                    let simplified = simplify_screen_coords(&screen_coords, 0.5 * 0.5); // 传入 epsilon²
-
-                    let pb = &mut pbs[t];
-                    pb.move_to(simplified[0].0, simplified[0].1);
-                    for &(sx, sy) in &simplified[1..] {
+            if t < 6 && curr_offset + count * 2 <= data.len() && count >= 2 {
+                by_type[t].push(curr_offset..curr_offset + count * 2);
+                stats.vertices += count;
+            }
+            curr_offset += count * 2;
+        }
+
+        // 每种道路类型各自构建 Path（启用 threads 时并行），每满 path_chunk_segments 段
+        // 切分为新的 Path：tiny-skia 描边超大 Path 时耗时急剧上升
+        // [Z-order + Road Casing] Path 可在 Casing 与 Fill 两遍中复用
+        let this = &*self;
+        let chunk_segments = self.path_chunk_segments.max(1);
+        let built: Vec<(Vec<tiny_skia::Path>, usize)> = par::map_range(0..6, |t| {
+            let mut chunks = Vec::new();
+            let mut pb = PathBuilder::new();
+            let mut segments = 0;
+            let mut skipped = 0;
+            for range in &by_type[t] {
+                // 先在世界坐标上简化，过滤掉亚像素级冗余点，再做屏幕变换
+                let coords: Vec<(f64, f64)> = data[range.clone()]
+                    .chunks_exact(2)
+                    .map(|c| (c[0].into() + origin.0, c[1].into() + origin.1))
+                    .collect();
+                if t == RoadType::Residential as usize && below_lod(&coords, min_extent) {
+                    skipped += 1;
+                    continue;
+                }
+                let parts = this.line_to_screen(&douglas_peucker(&coords, tolerance));
+                if parts.is_empty() {
+                    skipped += 1;
+                    continue;
+                }
+                for screen in parts {
+                    pb.move_to(screen[0].0, screen[0].1);
+                    for &(sx, sy) in &screen[1..] {
                         pb.line_to(sx, sy);
                     }
-                    found[t] = true;
+                    segments += screen.len() - 1;
+                }
+                if segments >= chunk_segments {
+                    chunks.extend(std::mem::take(&mut pb).finish());
+                    segments = 0;
                 }
             }
-            curr_offset += count * 2;
+            chunks.extend(pb.finish());
+            (chunks, skipped)
+        });
+        let mut paths = Vec::with_capacity(6);
+        for (t, (chunks, skipped)) in built.into_iter().enumerate() {
+            stats.drawn += by_type[t].len() - skipped;
+            stats.paths += chunks.len();
+            paths.push(chunks);
         }
-
-        // [Z-order + Road Casing] 将 PathBuilder 转为可复用的 Path（tiny_skia::Path 实现了 Clone）
-        let paths: Vec<Option<tiny_skia::Path>> = pbs
-            .into_iter()
-            .enumerate()
-            .map(|(i, pb)| if found[i] { pb.finish() } else { None })
-            .collect();
+        // 类型无效、点数不足或数据截断的道路
+        stats.culled = stats.features.saturating_sub(stats.drawn);
+        self.draw_stats.add(stats);
 
         // [Z-order] 道路绘制顺序：低优先级 → 高优先级，确保主干道始终在最上层
         // 枚举 index：Motorway=0, Primary=1, Secondary=2, Tertiary=3, Residential=4, Default=5
@@ -234,14 +383,14 @@ impl MapRenderer {
                 continue;
             }
 
-            let Some(path) = &paths[t_idx] else {
+            if paths[t_idx].is_empty() {
                 continue;
-            };
+            }
 
-            let start = crate::utils::performance_now();
+            let start = crate::profile::now();
 
             let road_type = RoadType::from_u32(t_idx as u32);
-            let base_color = parse_hex_color(self.road_color_hex(road_type));
+            let base_color = hex_color(self.road_color_hex(road_type));
 
             // [Road Casing] Casing 宽度 = 道路宽 + 两侧各 1 逻辑像素（已含 render_scale 倍数）
             let casing_width =
@@ -260,7 +409,7 @@ impl MapRenderer {
 
             let mut paint = Paint::default();
             paint.set_color(casing_color);
-            paint.anti_alias = true;
+            paint.anti_alias = self.anti_alias;
 
             let stroke = Stroke {
                 width: casing_width,
@@ -268,25 +417,27 @@ impl MapRenderer {
                 line_join: LineJoin::Round, // [Road Casing] 圆角拐点，消除锐角处的尖刺
                 ..Default::default()
             };
-            self.pixmap
-                .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+            for path in &paths[t_idx] {
+                self.pixmap
+                    .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+            }
 
-            timings[t_idx] += crate::utils::performance_now() - start;
+            timings[t_idx] += crate::profile::now() - start;
         }
 
         // [Road Casing] 第二遍：按 Z 序绘制所有道路的「填充色」（Fill）
         for &t_idx in &DRAW_ORDER {
-            let Some(path) = &paths[t_idx] else {
+            if paths[t_idx].is_empty() {
                 continue;
-            };
+            }
 
-            let start = crate::utils::performance_now();
+            let start = crate::profile::now();
 
             let road_type = RoadType::from_u32(t_idx as u32);
 
             let mut paint = Paint::default();
-            paint.set_color(parse_hex_color(self.road_color_hex(road_type)));
-            paint.anti_alias = true;
+            paint.set_color(hex_color(self.road_color_hex(road_type)));
+            paint.anti_alias = self.anti_alias;
 
             let stroke = Stroke {
                 width: road_type.get_width_scaled(scale_factor),
@@ -294,446 +445,711 @@ impl MapRenderer {
                 line_join: LineJoin::Round,
                 ..Default::default()
             };
-            self.pixmap
-                .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+            for path in &paths[t_idx] {
+                self.pixmap
+                    .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+            }
 
-            timings[t_idx] += crate::utils::performance_now() - start;
+            timings[t_idx] += crate::profile::now() - start;
         }
 
         timings
     }
 
-    /// 绘制多边形 (二进制直读版)
+    /// 绘制多边形 (二进制直读版，f64 绝对坐标)
     pub fn draw_polygons_bin(&mut self, data: &[f64], color_hex: &str) {
+        self.draw_polygons_bin_impl(data, (0.0, 0.0), color_hex)
+    }
+
+    /// 绘制多边形 (二进制直读版，f32 局部坐标，见 `LocalBin`)
+    pub fn draw_polygons_local(&mut self, polys: &LocalBin, color_hex: &str) {
+        self.draw_polygons_bin_impl(&polys.data, polys.origin, color_hex)
+    }
+
+    fn draw_polygons_bin_impl<T: Copy + Into<f64>>(
+        &mut self,
+        data: &[T],
+        origin: (f64, f64),
+        color_hex: &str,
+    ) {
         if data.is_empty() {
-            // 【优化】console::log_1 每次调用都会跨越 JS/WASM 边界，仅在 debug 模式保留
+            // 【优化】日志经 JS 回调或 console 输出，每次调用都会跨越 JS/WASM 边界，仅在 debug 模式保留
             #[cfg(debug_assertions)]
-            web_sys::console::log_1(&format!("⚠️  多边形数据为空").into());
+            debug("⚠️  多边形数据为空");
             return;
         }
-        let poly_count = data[0] as usize;
+        let poly_count = data[0].into() as usize;
 
         if poly_count == 0 {
             #[cfg(debug_assertions)]
-            web_sys::console::log_1(&format!("⚠️  多边形数量为 0，颜色: {}", color_hex).into());
+            debug(&format!("⚠️  多边形数量为 0，颜色: {}", color_hex));
             return;
         }
 
         #[cfg(debug_assertions)]
-        web_sys::console::log_1(
-            &format!("🌊 开始绘制 {} 个多边形，颜色: {}", poly_count, color_hex).into(),
-        );
+        debug(&format!(
+            "🌊 开始绘制 {} 个多边形，颜色: {}",
+            poly_count, color_hex
+        ));
 
         let mut offset = 1;
-        let color = parse_hex_color(color_hex);
-
-        let mut pb = PathBuilder::new();
-        let mut found = false;
-
+        let color = hex_color(color_hex);
+
+        // 先解码全部多边形，以便找出相邻多边形共享的边界顶点
+        let ring_at = |offset: usize, count: usize| -> Vec<(f64, f64)> {
+            data[offset..offset + count * 2]
+                .chunks_exact(2)
+                .map(|c| (c[0].into() + origin.0, c[1].into() + origin.1))
+                .collect()
+        };
+        let mut polys = Vec::with_capacity(poly_count);
         for _idx in 0..poly_count {
             if offset + 2 > data.len() {
                 break;
             }
-            let ext_count = data[offset] as usize;
-            let int_ring_count = data[offset + 1] as usize;
+            let ext_count = data[offset].into() as usize;
+            let int_ring_count = data[offset + 1].into() as usize;
             offset += 2;
-
-            if offset + ext_count * 2 <= data.len() && ext_count >= 3 {
-                let (sx, sy) = self.world_to_screen((data[offset], data[offset + 1]));
-                pb.move_to(sx, sy);
-                for i in 1..ext_count {
-                    let (sx, sy) =
-                        self.world_to_screen((data[offset + i * 2], data[offset + i * 2 + 1]));
-                    pb.line_to(sx, sy);
-                }
-                pb.close();
-                found = true;
+            if offset + ext_count * 2 > data.len() {
+                break;
             }
+            let exterior = ring_at(offset, ext_count);
             offset += ext_count * 2;
 
+            let mut interiors = Vec::with_capacity(int_ring_count);
             for _ in 0..int_ring_count {
                 if offset + 1 > data.len() {
                     break;
                 }
-                let count = data[offset] as usize;
+                let count = data[offset].into() as usize;
                 offset += 1;
-                if offset + count * 2 <= data.len() && count >= 3 {
-                    let (sx, sy) = self.world_to_screen((data[offset], data[offset + 1]));
-                    pb.move_to(sx, sy);
-                    for i in 1..count {
-                        let (sx, sy) =
-                            self.world_to_screen((data[offset + i * 2], data[offset + i * 2 + 1]));
-                        pb.line_to(sx, sy);
-                    }
-                    pb.close();
+                if offset + count * 2 <= data.len() {
+                    interiors.push(ring_at(offset, count));
                 }
                 offset += count * 2;
             }
+            polys.push(PolyFeature {
+                exterior,
+                interiors,
+            });
+        }
+
+        let locked = shared_vertices(poly_rings(&polys));
+        let mut pb = PathBuilder::new();
+        let mut stats = DrawStats {
+            features: poly_count,
+            vertices: poly_rings(&polys).map(<[_]>::len).sum(),
+            ..Default::default()
+        };
+        for poly in &polys {
+            if self.add_poly_to_path(&mut pb, poly, &locked) {
+                stats.drawn += 1;
+            }
         }
+        stats.culled = poly_count - stats.drawn;
+        let found = stats.drawn > 0;
 
         if found {
             if let Some(path) = pb.finish() {
+                stats.paths = 1;
                 let mut paint = Paint::default();
                 paint.set_color(color);
-                paint.anti_alias = true;
+                paint.anti_alias = self.anti_alias;
                 self.pixmap.fill_path(
                     &path,
                     &paint,
-                    FillRule::EvenOdd,
+                    FillRule::Winding,
                     Transform::identity(),
                     None,
                 );
                 #[cfg(debug_assertions)]
-                web_sys::console::log_1(&format!("✅ 多边形绘制完成，颜色: {}", color_hex).into());
+                debug(&format!("✅ 多边形绘制完成，颜色: {}", color_hex));
             }
         } else {
             #[cfg(debug_assertions)]
-            web_sys::console::log_1(
-                &format!("⚠️  未找到有效的多边形数据，颜色: {}", color_hex).into(),
-            );
+            debug(&format!("⚠️  未找到有效的多边形数据，颜色: {}", color_hex));
         }
+        self.draw_stats.add(stats);
     }
 
-    /// 绘制道路
-    // pub fn draw_roads(&mut self, roads: &[Road]) {
-    //     // 【优化】委托给 scaled 版本，消除重复代码；scale_factor=1.0 等同于原无缩放行为
-    //     self.draw_roads_scaled(roads, 1.0);
-    // }
+    /// 绘制路线叠加层（二进制直读版本）
+    /// 数据格式：[route_count, n, x1, y1, ..., xn, yn, ...]，`width` 为逻辑像素线宽
+    pub fn draw_routes_bin(&mut self, data: &[f64], width: f32) {
+        let color_hex = self
+            .theme
+            .route_color
+            .clone()
+            .unwrap_or_else(|| self.theme.text.clone());
+        self.draw_lines_bin(data, &color_hex, width);
+    }
 
-    /// 绘制道路（使用动态缩放因子）
-    pub fn draw_roads_scaled(&mut self, roads: &[Road], scale_factor: f32) {
-        // [超采样] 将外部传入的缩放因子乘以内部超采样倍数，保持视觉比例一致
-        let scale_factor = scale_factor * self.render_scale as f32;
+    /// 以指定颜色与线宽（逻辑像素）绘制折线，数据格式同路线
+    pub fn draw_lines_bin(&mut self, data: &[f64], color_hex: &str, width: f32) {
+        self.draw_dashed_lines_bin(data, color_hex, width, &[]);
+    }
 
-        // 【优化】使用固定大小数组替代 HashMap，道路类型仅 6 种，无需哈希开销
-        let mut groups: [Vec<&Road>; 6] = [vec![], vec![], vec![], vec![], vec![], vec![]];
-        for road in roads {
-            let idx = road.road_type as usize;
-            if idx < 6 {
-                groups[idx].push(road);
-            }
+    /// 同 `draw_lines_bin`，`dash` 为虚线模式（逻辑像素，见 `dash_pattern`），为空或无效时画实线
+    pub fn draw_dashed_lines_bin(
+        &mut self,
+        data: &[f64],
+        color_hex: &str,
+        width: f32,
+        dash: &[f32],
+    ) {
+        if data.is_empty() {
+            return;
         }
 
-        // [Z-order + Road Casing] 将每种类型的 Road 列表预先构建为 Path
-        let mut paths: [Option<tiny_skia::Path>; 6] = Default::default();
-        for t_idx in 0..6usize {
-            let road_group = &groups[t_idx];
-            if road_group.is_empty() {
-                continue;
+        let route_count = data[0] as usize;
+        let mut pb = PathBuilder::new();
+        let mut found = false;
+        let mut offset = 1;
+        let mut stats = DrawStats {
+            features: route_count,
+            ..Default::default()
+        };
+
+        for _ in 0..route_count {
+            if offset + 1 > data.len() {
+                break;
             }
-            let mut pb = PathBuilder::new();
-            for road in road_group {
-                if road.coords.len() < 2 {
-                    continue;
-                }
-                let (x, y) = self.world_to_screen(road.coords[0]);
-                pb.move_to(x, y);
-                for &coord in &road.coords[1..] {
-                    let (x, y) = self.world_to_screen(coord);
-                    pb.line_to(x, y);
+            let count = data[offset] as usize;
+            offset += 1;
+            if offset + count * 2 > data.len() {
+                break;
+            }
+            stats.vertices += count;
+            let coords: Vec<(f64, f64)> = data[offset..offset + count * 2]
+                .chunks_exact(2)
+                .map(|c| (c[0], c[1]))
+                .collect();
+            let parts = self.line_to_screen(&coords);
+            if !parts.is_empty() {
+                stats.drawn += 1;
+                for screen in parts {
+                    pb.move_to(screen[0].0, screen[0].1);
+                    for &(sx, sy) in &screen[1..] {
+                        pb.line_to(sx, sy);
+                    }
                 }
+                found = true;
             }
-            paths[t_idx] = pb.finish();
+            offset += count * 2;
         }
 
-        // [Z-order] 低优先级 → 高优先级渲染顺序
-        const DRAW_ORDER: [usize; 6] = [5, 4, 3, 2, 1, 0];
-
-        // [Road Casing] 第一遍：所有道路的 Casing（加宽暗色描边）
-        for &t_idx in &DRAW_ORDER {
-            let Some(path) = &paths[t_idx] else {
-                continue;
-            };
-            let road_type = crate::types::RoadType::from_u32(t_idx as u32);
-            let base_color = parse_hex_color(self.road_color_hex(road_type));
-            let casing_width =
-                road_type.get_width_scaled(scale_factor) + 2.0 * self.render_scale as f32;
-            let mut casing_color = darken_color(base_color, 0.9);
-
-            // 把 alpha 降到 0.4，边缘隐约可见即可
-            casing_color = Color::from_rgba(
-                casing_color.red(),
-                casing_color.green(),
-                casing_color.blue(),
-                0.2,
-            )
-            .unwrap_or(casing_color);
-
+        stats.culled = route_count - stats.drawn;
+        if found && let Some(path) = pb.finish() {
+            stats.paths = 1;
             let mut paint = Paint::default();
-            paint.set_color(casing_color);
-            paint.anti_alias = true;
+            paint.set_color(hex_color(color_hex));
+            paint.anti_alias = self.anti_alias;
 
+            // [超采样] 线宽与虚线长度按内部渲染倍数放大
+            let scale = self.render_scale as f32;
             let stroke = Stroke {
-                width: casing_width,
+                width: width * scale,
                 line_cap: LineCap::Round,
                 line_join: LineJoin::Round,
+                dash: dash_pattern(dash).and_then(|pattern| {
+                    StrokeDash::new(pattern.iter().map(|d| d * scale).collect(), 0.0)
+                }),
                 ..Default::default()
             };
             self.pixmap
-                .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+                .stroke_path(&path, &paint, &stroke, Transform::identity(), None);
         }
+        self.draw_stats.add(stats);
+    }
 
-        // [Road Casing] 第二遍：所有道路的 Fill（正常颜色和宽度）
-        for &t_idx in &DRAW_ORDER {
-            let Some(path) = &paths[t_idx] else {
-                continue;
-            };
-            let road_type = crate::types::RoadType::from_u32(t_idx as u32);
-
+    /// 以指定颜色与半径（逻辑像素）绘制圆点，数据格式同 POI
+    ///
+    /// 与 POI 不同，不做数量限制与避让，逐点绘制
+    pub fn draw_points_bin(&mut self, data: &[f64], color_hex: &str, radius: f32) {
+        let count = data.first().map_or(0, |&c| c as usize);
+        let radius = radius * self.render_scale as f32;
+        let mut pb = PathBuilder::new();
+        let coords = data.get(1..).unwrap_or_default();
+        let mut stats = DrawStats {
+            features: count,
+            ..Default::default()
+        };
+        for point in coords.chunks_exact(2).take(count) {
+            let (x, y) = self.world_to_screen((point[0], point[1]));
+            pb.push_circle(x, y, radius);
+            stats.drawn += 1;
+        }
+        stats.vertices = stats.drawn;
+        stats.culled = count - stats.drawn;
+        if let Some(path) = pb.finish() {
+            stats.paths = 1;
             let mut paint = Paint::default();
-            paint.set_color(parse_hex_color(self.road_color_hex(road_type)));
-            paint.anti_alias = true;
+            paint.set_color(hex_color(color_hex));
+            paint.anti_alias = self.anti_alias;
+            self.pixmap.fill_path(
+                &path,
+                &paint,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+        self.draw_stats.add(stats);
+    }
 
-            let stroke = Stroke {
-                width: road_type.get_width_scaled(scale_factor),
-                line_cap: LineCap::Round,
-                line_join: LineJoin::Round,
-                ..Default::default()
-            };
-            self.pixmap
-                .stroke_path(path, &paint, &stroke, Transform::identity(), None);
+    /// 在世界坐标 `center` 处绘制中心标记
+    ///
+    /// 标记先以主题背景色描出一圈衬边，使其在密集的道路上依然清晰
+    pub fn draw_home_marker(&mut self, center: (f64, f64), marker: &HomeMarker) {
+        let (cx, cy) = self.world_to_screen(center);
+        let r = marker.size * self.render_scale as f32;
+        let halo = (r * 0.2).max(self.render_scale as f32);
+        let color = hex_color(marker.color.as_deref().unwrap_or(&self.theme.poi_color));
+        let bg = hex_color(&self.theme.bg);
+
+        let mut paint = Paint {
+            anti_alias: self.anti_alias,
+            ..Default::default()
+        };
+        let mut fill = |pixmap: &mut Pixmap, path: &tiny_skia::Path, color: Color, rule| {
+            paint.set_color(color);
+            pixmap.fill_path(path, &paint, rule, Transform::identity(), None);
+        };
+        let circle = |radius: f32| PathBuilder::from_circle(cx, cy, radius);
+
+        match marker.style {
+            MarkerStyle::Dot | MarkerStyle::Ring => {
+                if let Some(path) = circle(r + halo) {
+                    fill(&mut self.pixmap, &path, bg, FillRule::Winding);
+                }
+                let ring = if marker.style == MarkerStyle::Ring {
+                    // 环宽为半径的 40%，中间露出背景色
+                    let mut pb = PathBuilder::new();
+                    pb.push_circle(cx, cy, r);
+                    pb.push_circle(cx, cy, r * 0.6);
+                    pb.finish()
+                } else {
+                    circle(r)
+                };
+                if let Some(path) = ring {
+                    fill(&mut self.pixmap, &path, color, FillRule::EvenOdd);
+                }
+            }
+            MarkerStyle::Heart => {
+                let Some(path) = heart_path(cx, cy, r) else {
+                    return;
+                };
+                let mut outline = Paint::default();
+                outline.set_color(bg);
+                outline.anti_alias = self.anti_alias;
+                let stroke = Stroke {
+                    width: halo * 2.0,
+                    line_join: LineJoin::Round,
+                    ..Default::default()
+                };
+                self.pixmap
+                    .stroke_path(&path, &outline, &stroke, Transform::identity(), None);
+                fill(&mut self.pixmap, &path, color, FillRule::Winding);
+            }
         }
     }
 
-    /// 绘制 POI 圆点（使用 POI 结构体数组）
-    pub fn draw_pois(&mut self, pois: &[crate::types::POI]) {
+    /// 绘制 POI 圆点（二进制直读版本）
+    /// 数据格式：[poi_count, x1, y1, x2, y2, ...]
+    pub fn draw_pois_bin(&mut self, data: &[f64]) {
         // 【优化】委托给 scaled 版本，消除重复代码；scale_factor=1.0 等同于原无缩放行为
-        self.draw_pois_scaled(pois, 1.0);
+        self.draw_pois_bin_scaled(data, 1.0);
     }
 
-    /// 绘制 POI 圆点（使用 POI 结构体数组，带动态缩放因子）
-    pub fn draw_pois_scaled(&mut self, pois: &[crate::types::POI], scale_factor: f32) {
-        if pois.is_empty() {
+    /// 绘制 POI 圆点（二进制直读版本，带动态缩放因子）
+    /// 数据格式：[poi_count, x1, y1, x2, y2, ...]
+    pub fn draw_pois_bin_scaled(&mut self, data: &[f64], scale_factor: f32) {
+        if data.is_empty() || data[0] as usize == 0 {
             return;
         }
 
-        // [超采样] 缩放因子乘以内部渲染倍数，保持圆点视觉大小与逻辑尺寸一致
+        // [超采样] 缩放因子乘以内部渲染倍数，保持 POI 视觉大小一致
         let scale_factor = scale_factor * self.render_scale as f32;
 
+        // 只读取声明数量内完整的坐标对，不完整的数据不会越界
+        let coords = &data[crate::data_processor::poi_bin_coord_range(data)];
+        let poi_count = coords.len() / 2;
+
         // 使用主题中的 POI 专用颜色
-        let poi_color = parse_hex_color(&self.theme.poi_color);
+        let poi_color = hex_color(&self.theme.poi_color);
 
-        let poi_radius = 10.0 * scale_factor; // POI 圆点半径随分辨率缩放
-        let min_spacing = 8.0 * scale_factor; // POI 之间最小间距（像素）
-        const MAX_POIS: usize = 50; // 最多渲染 50 个 POI 点
+        let poi_radius = 8.0 * scale_factor; // POI 圆点半径随分辨率缩放
+        let min_spacing = 5.0 * scale_factor; // POI 之间最小间距随分辨率缩放
+        const MAX_POIS: usize = 50;
         let min_distance_sq = (poi_radius * 2.0 + min_spacing) * (poi_radius * 2.0 + min_spacing);
 
         // 【优化】空间网格替代 O(n²) 线性扫描，平均 O(1) 碰撞检测
         // cell_size = min_distance，只需检查 3×3 邻域即可覆盖所有可能碰撞的点
         let cell_size = ((poi_radius * 2.0 + min_spacing).ceil() as i32).max(1);
         let mut grid: HashMap<(i32, i32), Vec<(f32, f32)>> = HashMap::new();
-        let mut rendered_count = 0usize;
 
         // 【优化】批量构建路径，所有圆点一次 fill_path 完成，减少状态切换
         let mut pb = PathBuilder::new();
+        let mut rendered_count = 0usize;
+        // 已绘制 POI 的输入序号与屏幕坐标，用于编号
+        let mut placed = Vec::new();
 
         // [超采样] 边界检测使用实际画布像素尺寸
         let rw = self.render_width() as f32;
         let rh = self.render_height() as f32;
 
-        for poi in pois {
+        for (index, point) in coords.chunks_exact(2).enumerate() {
+            // 达到最大数量则停止
             if rendered_count >= MAX_POIS {
                 break;
             }
 
-            let (screen_x, screen_y) = self.world_to_screen((poi.x, poi.y));
+            let (screen_x, screen_y) = self.world_to_screen((point[0], point[1]));
 
             // 检查边界
-            if screen_x < 0.0 || screen_x > rw || screen_y < 0.0 || screen_y > rh {
-                continue;
-            }
-
-            let cx = (screen_x / cell_size as f32).floor() as i32;
-            let cy = (screen_y / cell_size as f32).floor() as i32;
-
-            // 精确距离检测：只检查 3×3 邻域（O(1) 平均复杂度）
-            let mut too_close = false;
-            'outer: for dy in -1..=1i32 {
-                for dx in -1..=1i32 {
-                    if let Some(pts) = grid.get(&(cx + dx, cy + dy)) {
-                        for &(rx, ry) in pts {
-                            let ddx = screen_x - rx;
-                            let ddy = screen_y - ry;
-                            if ddx * ddx + ddy * ddy < min_distance_sq {
-                                too_close = true;
-                                break 'outer;
+            if screen_x >= 0.0 && screen_x <= rw && screen_y >= 0.0 && screen_y <= rh {
+                let cx = (screen_x / cell_size as f32).floor() as i32;
+                let cy = (screen_y / cell_size as f32).floor() as i32;
+
+                // 精确距离检测：只检查 3×3 邻域（O(1) 平均复杂度）
+                let mut too_close = false;
+                'outer: for dy in -1..=1i32 {
+                    for dx in -1..=1i32 {
+                        if let Some(pts) = grid.get(&(cx + dx, cy + dy)) {
+                            for &(rx, ry) in pts {
+                                let ddx = screen_x - rx;
+                                let ddy = screen_y - ry;
+                                if ddx * ddx + ddy * ddy < min_distance_sq {
+                                    too_close = true;
+                                    break 'outer;
+                                }
                             }
                         }
                     }
                 }
-            }
 
-            if too_close {
-                continue;
+                if !too_close {
+                    grid.entry((cx, cy)).or_default().push((screen_x, screen_y));
+                    pb.push_circle(screen_x, screen_y, poi_radius);
+                    placed.push((index, screen_x, screen_y));
+                    rendered_count += 1;
+                }
             }
+        }
 
-            grid.entry((cx, cy)).or_default().push((screen_x, screen_y));
-            pb.push_circle(screen_x, screen_y, poi_radius);
-            rendered_count += 1;
+        // 超出数量上限、画布外或与已绘制点过近的 POI 计为跳过
+        let mut stats = DrawStats {
+            features: poi_count,
+            drawn: rendered_count,
+            culled: poi_count - rendered_count,
+            vertices: poi_count,
+            paths: 0,
+        };
+
+        if let Some(halo) = self.poi_halo.take() {
+            self.draw_poi_halos(&halo, &placed, poi_radius, scale_factor);
+            self.poi_halo = Some(halo);
         }
 
         // 一次性渲染所有圆点
-        if rendered_count > 0 {
-            if let Some(path) = pb.finish() {
-                let mut paint = Paint::default();
-                paint.set_color(poi_color);
-                paint.anti_alias = true;
+        if rendered_count > 0
+            && let Some(path) = pb.finish()
+        {
+            stats.paths = 1;
+            let mut paint = Paint::default();
+            paint.set_color(poi_color);
+            paint.anti_alias = self.anti_alias;
+            self.pixmap.fill_path(
+                &path,
+                &paint,
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
 
-                self.pixmap.fill_path(
-                    &path,
-                    &paint,
-                    FillRule::Winding,
-                    Transform::identity(),
-                    None,
-                );
+        if let Some((labels, font)) = self.poi_labels.take() {
+            let text_color = contrast_color(poi_color);
+            for &(index, x, y) in &placed {
+                if let Some(label) = poi_label(labels, index) {
+                    self.draw_label_in_circle(&font, &label, (x, y), poi_radius, text_color);
+                }
             }
+            self.poi_labels = Some((labels, font));
         }
-    }
 
-    /// 绘制 POI 圆点（二进制直读版本）
-    /// 数据格式：[poi_count, x1, y1, x2, y2, ...]
-    pub fn draw_pois_bin(&mut self, data: &[f64]) {
-        // 【优化】委托给 scaled 版本，消除重复代码；scale_factor=1.0 等同于原无缩放行为
-        self.draw_pois_bin_scaled(data, 1.0);
+        self.draw_stats.add(stats);
+
+        #[cfg(debug_assertions)]
+        debug(&format!(
+            "🔵 POI 采样完成: 原始 {} 个 → 采样后 {} 个，颜色: {}",
+            poi_count, rendered_count, &self.theme.poi_color
+        ));
     }
 
-    /// 绘制 POI 圆点（二进制直读版本，带动态缩放因子）
-    /// 数据格式：[poi_count, x1, y1, x2, y2, ...]
-    pub fn draw_pois_bin_scaled(&mut self, data: &[f64], scale_factor: f32) {
-        if data.is_empty() || data[0] as usize == 0 {
+    /// 区域裁剪：用背景色覆盖区域多边形以外的内容（"城市剪影"效果）
+    ///
+    /// `outside_opacity` 为区域外地图内容保留的不透明度，0.0 表示完全隐藏，
+    /// 介于 0 与 1 之间则为淡化效果
+    pub fn apply_region_mask(&mut self, region: &[PolyFeature], outside_opacity: f32) {
+        if region.is_empty() {
             return;
         }
 
-        // [超采样] 缩放因子乘以内部渲染倍数，保持 POI 视觉大小一致
-        let scale_factor = scale_factor * self.render_scale as f32;
+        let locked = shared_vertices(poly_rings(region));
+        let mut pb = PathBuilder::new();
+        for poly in region {
+            self.add_poly_to_path(&mut pb, poly, &locked);
+        }
+        let Some(path) = pb.finish() else {
+            return;
+        };
 
-        let poi_count = data[0] as usize;
-        if data.len() < 1 + poi_count * 2 {
-            #[cfg(debug_assertions)]
-            web_sys::console::log_1(
-                &format!(
-                    "❌ POI 数据长度不足: {} < {}",
-                    data.len(),
-                    1 + poi_count * 2
-                )
-                .into(),
-            );
-            return; // 数据长度不足
+        let Some(mut mask) = Mask::new(self.render_width(), self.render_height()) else {
+            return;
+        };
+        mask.fill_path(&path, FillRule::Winding, true, Transform::identity());
+        // 反转后 mask 覆盖的是区域外部
+        mask.invert();
+
+        let bg = hex_color(&self.theme.bg);
+        let cover_alpha = 1.0 - outside_opacity.clamp(0.0, 1.0);
+        let Some(cover) = Color::from_rgba(bg.red(), bg.green(), bg.blue(), cover_alpha) else {
+            return;
+        };
+
+        let mut paint = Paint::default();
+        paint.set_color(cover);
+        paint.anti_alias = self.anti_alias;
+
+        if let Some(rect) = Rect::from_xywh(
+            0.0,
+            0.0,
+            self.render_width() as f32,
+            self.render_height() as f32,
+        ) {
+            self.pixmap
+                .fill_rect(rect, &paint, Transform::identity(), Some(&mask));
         }
+    }
 
-        // 使用主题中的 POI 专用颜色
-        let poi_color = parse_hex_color(&self.theme.poi_color);
+    /// 边距以内的地图区域 `(left, top, right, bottom)`（画布像素）
+    fn map_area(&self, margins: Margins) -> (f32, f32, f32, f32) {
+        let (width, height) = (self.render_width() as f32, self.render_height() as f32);
+        (
+            (width * margins.left as f32).round(),
+            (height * margins.top as f32).round(),
+            (width * (1.0 - margins.right as f32)).round(),
+            (height * (1.0 - margins.bottom as f32)).round(),
+        )
+    }
 
-        let poi_radius = 8.0 * scale_factor; // POI 圆点半径随分辨率缩放
-        let min_spacing = 5.0 * scale_factor; // POI 之间最小间距随分辨率缩放
-        const MAX_POIS: usize = 50;
-        let min_distance_sq = (poi_radius * 2.0 + min_spacing) * (poi_radius * 2.0 + min_spacing);
+    /// 以背景色填充内嵌边距（`margins` 为各边占画布的比例），覆盖伸入边距的地图内容
+    pub fn fill_margins(&mut self, margins: Margins) {
+        let (width, height) = (self.render_width() as f32, self.render_height() as f32);
+        let (left, top, right, bottom) = self.map_area(margins);
+        let rects = [
+            Rect::from_ltrb(0.0, 0.0, width, top),
+            Rect::from_ltrb(0.0, bottom, width, height),
+            Rect::from_ltrb(0.0, top, left, bottom),
+            Rect::from_ltrb(right, top, width, bottom),
+        ];
+
+        let mut paint = Paint::default();
+        paint.set_color(hex_color(&self.theme.bg));
+        for rect in rects.into_iter().flatten() {
+            self.pixmap
+                .fill_rect(rect, &paint, Transform::identity(), None);
+        }
+    }
 
-        // 【优化】空间网格替代 O(n²) 线性扫描，平均 O(1) 碰撞检测
-        // cell_size = min_distance，只需检查 3×3 邻域即可覆盖所有可能碰撞的点
-        let cell_size = ((poi_radius * 2.0 + min_spacing).ceil() as i32).max(1);
-        let mut grid: HashMap<(i32, i32), Vec<(f32, f32)>> = HashMap::new();
+    /// 将地图区域（边距以内）裁为半径 `radius`（逻辑像素）的圆角矩形：
+    /// 圆角以外的部分以背景色覆盖
+    pub fn round_map_corners(&mut self, margins: Margins, radius: f32) {
+        let (left, top, right, bottom) = self.map_area(margins);
+        let r = (radius * self.render_scale as f32)
+            .min((right - left) / 2.0)
+            .min((bottom - top) / 2.0);
+        if r.is_nan() || r <= 0.0 {
+            return;
+        }
 
-        // 【优化】批量构建路径，所有圆点一次 fill_path 完成，减少状态切换
+        // 四分之一圆弧以三次贝塞尔近似
+        const KAPPA: f32 = 0.552_284_8;
+        let k = r * (1.0 - KAPPA);
         let mut pb = PathBuilder::new();
-        let mut rendered_count = 0usize;
-        let mut offset = 1;
+        pb.move_to(left + r, top);
+        pb.line_to(right - r, top);
+        pb.cubic_to(right - k, top, right, top + k, right, top + r);
+        pb.line_to(right, bottom - r);
+        pb.cubic_to(right, bottom - k, right - k, bottom, right - r, bottom);
+        pb.line_to(left + r, bottom);
+        pb.cubic_to(left + k, bottom, left, bottom - k, left, bottom - r);
+        pb.line_to(left, top + r);
+        pb.cubic_to(left, top + k, left + k, top, left + r, top);
+        pb.close();
+        let Some(path) = pb.finish() else {
+            return;
+        };
+        let Some(mut mask) = Mask::new(self.render_width(), self.render_height()) else {
+            return;
+        };
+        mask.fill_path(&path, FillRule::Winding, true, Transform::identity());
+        mask.invert();
 
-        // [超采样] 边界检测使用实际画布像素尺寸
-        let rw = self.render_width() as f32;
-        let rh = self.render_height() as f32;
+        let mut paint = Paint::default();
+        paint.set_color(hex_color(&self.theme.bg));
+        if let Some(rect) = Rect::from_ltrb(left, top, right, bottom) {
+            self.pixmap
+                .fill_rect(rect, &paint, Transform::identity(), Some(&mask));
+        }
+    }
 
-        for _idx in 0..poi_count {
-            // 达到最大数量则停止
-            if rendered_count >= MAX_POIS {
-                break;
+    /// 按太阳直射点为夜侧着色：晨昏线以外叠加 `color`，完全入夜处不透明度为 `opacity`
+    ///
+    /// 太阳高度角在画面尺度上变化平缓，按网格取样后双线性放大即可得到平滑的晨昏过渡
+    pub fn draw_night_tint(&mut self, sun: SubsolarPoint, color: &str, opacity: f32) {
+        let (width, height) = (self.render_width(), self.render_height());
+        let (cols, rows) = (
+            width.div_ceil(NIGHT_CELL_PX),
+            height.div_ceil(NIGHT_CELL_PX),
+        );
+        let Some(mut night) = Pixmap::new(cols, rows) else {
+            return;
+        };
+        let color = hex_color(color).to_color_u8();
+        let opacity = opacity.clamp(0.0, 1.0) * color.alpha() as f32;
+        let cell = NIGHT_CELL_PX as f64;
+        let mut any = false;
+        for (i, pixel) in night.pixels_mut().iter_mut().enumerate() {
+            let (col, row) = (i % cols as usize, i / cols as usize);
+            // 网格中心对应的投影平面坐标 -> 经纬度
+            let x = self.bounds.min_x + (col as f64 + 0.5) * cell / self.x_factor;
+            let y = self.bounds.min_y + (height as f64 - (row as f64 + 0.5) * cell) / self.y_factor;
+            let (lon, lat) = self.projector.inverse(x, y);
+            let fraction = solar::night_fraction(solar::altitude(sun, lon, lat));
+            let alpha = (opacity * fraction as f32).round() as u8;
+            if alpha > 0 {
+                any = true;
+                *pixel = ColorU8::from_rgba(color.red(), color.green(), color.blue(), alpha)
+                    .premultiply();
             }
+        }
+        if !any {
+            return;
+        }
 
-            if offset + 1 < data.len() {
-                let x = data[offset];
-                let y = data[offset + 1];
-                let (screen_x, screen_y) = self.world_to_screen((x, y));
-
-                // 检查边界
-                if screen_x >= 0.0 && screen_x <= rw && screen_y >= 0.0 && screen_y <= rh {
-                    let cx = (screen_x / cell_size as f32).floor() as i32;
-                    let cy = (screen_y / cell_size as f32).floor() as i32;
-
-                    // 精确距离检测：只检查 3×3 邻域（O(1) 平均复杂度）
-                    let mut too_close = false;
-                    'outer: for dy in -1..=1i32 {
-                        for dx in -1..=1i32 {
-                            if let Some(pts) = grid.get(&(cx + dx, cy + dy)) {
-                                for &(rx, ry) in pts {
-                                    let ddx = screen_x - rx;
-                                    let ddy = screen_y - ry;
-                                    if ddx * ddx + ddy * ddy < min_distance_sq {
-                                        too_close = true;
-                                        break 'outer;
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    if !too_close {
-                        grid.entry((cx, cy)).or_default().push((screen_x, screen_y));
-                        pb.push_circle(screen_x, screen_y, poi_radius);
-                        rendered_count += 1;
-                    }
-                }
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bilinear,
+            ..Default::default()
+        };
+        let scale = NIGHT_CELL_PX as f32;
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            night.as_ref(),
+            &paint,
+            Transform::from_scale(scale, scale),
+            None,
+        );
+    }
 
-                offset += 2;
-            }
+    /// 宝丽来边框：白色填充边距，在地图下方与右侧投下轻微阴影，
+    /// 之后的文字以深色写在底边内
+    pub fn draw_polaroid_frame(&mut self, margins: Margins) {
+        let (width, height) = (self.render_width() as f32, self.render_height() as f32);
+        let (left, top, right, bottom) = self.map_area(margins);
+        let mut paint = Paint::default();
+        paint.set_color(hex_color(POLAROID_FRAME_COLOR));
+        let frame = [
+            Rect::from_ltrb(0.0, 0.0, width, top),
+            Rect::from_ltrb(0.0, bottom, width, height),
+            Rect::from_ltrb(0.0, top, left, bottom),
+            Rect::from_ltrb(right, top, width, bottom),
+        ];
+        for rect in frame.into_iter().flatten() {
+            self.pixmap
+                .fill_rect(rect, &paint, Transform::identity(), None);
         }
 
-        // 一次性渲染所有圆点
-        if rendered_count > 0 {
-            if let Some(path) = pb.finish() {
-                let mut paint = Paint::default();
-                paint.set_color(poi_color);
-                paint.anti_alias = true;
-                self.pixmap.fill_path(
-                    &path,
-                    &paint,
+        // 阴影：逐像素外扩、略向下偏移的半透明矩形叠加，越靠近地图越深；地图区域不受影响
+        if let Some(mut mask) = Mask::new(self.render_width(), self.render_height()) {
+            if let Some(map) = Rect::from_ltrb(left, top, right, bottom) {
+                mask.fill_path(
+                    &PathBuilder::from_rect(map),
                     FillRule::Winding,
+                    false,
                     Transform::identity(),
-                    None,
                 );
             }
+            mask.invert();
+            let spread = POLAROID_SHADOW_PX * self.render_scale as f32;
+            let alpha = POLAROID_SHADOW_OPACITY / spread;
+            paint.set_color(Color::from_rgba(0.0, 0.0, 0.0, alpha).unwrap_or(Color::BLACK));
+            for i in 1..=spread as u32 {
+                let d = i as f32;
+                let shadow = Rect::from_ltrb(left - d / 2.0, top, right + d / 2.0, bottom + d);
+                if let Some(rect) = shadow {
+                    self.pixmap
+                        .fill_rect(rect, &paint, Transform::identity(), Some(&mask));
+                }
+            }
         }
 
-        #[cfg(debug_assertions)]
-        web_sys::console::log_1(
-            &format!(
-                "🔵 POI 采样完成: 原始 {} 个 → 采样后 {} 个，颜色: {}",
-                poi_count, rendered_count, &self.theme.poi_color
-            )
-            .into(),
-        );
+        // 白色底边上改用深色文字
+        self.theme.text = POLAROID_INK_COLOR.to_string();
+        self.text_panel_top = Some(bottom);
+    }
+
+    /// 以纯色填充底部 `height_pct`% 的文字面板，之后的文字在面板内居中
+    pub fn fill_text_panel(&mut self, height_pct: f64, color: &str) {
+        let width = self.render_width() as f32;
+        let height = self.render_height() as f32;
+        let top = (height * (1.0 - height_pct.clamp(0.0, 100.0) as f32 / 100.0)).round();
+        self.text_panel_top = Some(top);
+        let Some(rect) = Rect::from_ltrb(0.0, top, width, height) else {
+            return;
+        };
+        let mut paint = Paint::default();
+        paint.set_color(hex_color(color));
+        self.pixmap
+            .fill_rect(rect, &paint, Transform::identity(), None);
+    }
+
+    /// 替换渐变颜色（如按太阳高度角选取的天空色）
+    pub fn set_gradient_color(&mut self, color: String) {
+        self.theme.gradient_color = color;
     }
 
     /// 绘制渐变（顶部和底部）
     pub fn draw_gradients(&mut self) {
-        let gradient_color = parse_hex_color(&self.theme.gradient_color);
+        self.draw_gradients_in(Margins::default());
+    }
+
+    /// 在边距以内的地图区域绘制顶部和底部渐变（面板、宝丽来版式的天空色）
+    pub fn draw_gradients_in(&mut self, margins: Margins) {
+        let gradient_color = hex_color(&self.theme.gradient_color);
+        let (left, top, right, bottom) = self.map_area(margins);
+        let area = (left as u32, top as u32, right as u32, bottom as u32);
 
         // 底部渐变
-        self.draw_gradient("bottom", gradient_color);
+        self.draw_gradient("bottom", gradient_color, area);
 
         // 顶部渐变
-        self.draw_gradient("top", gradient_color);
+        self.draw_gradient("top", gradient_color, area);
     }
 
-    /// 绘制单个渐变（手动扫描线优化）
-    fn draw_gradient(&mut self, location: &str, base_color: Color) {
+    /// 绘制单个渐变（手动扫描线优化），`area` 为 `(left, top, right, bottom)` 画布像素
+    fn draw_gradient(&mut self, location: &str, base_color: Color, area: (u32, u32, u32, u32)) {
         // [超采样] 使用实际画布尺寸，确保渐变覆盖完整 2× 画布
-        let height = self.render_height();
         let width = self.render_width();
+        let (left, top) = (area.0.min(width), area.1);
+        let (right, bottom) = (area.2.clamp(left, width), area.3.min(self.render_height()));
+        let height = bottom.saturating_sub(top);
 
         let (y_start, y_end) = if location == "bottom" {
-            ((height as f32 * 0.75) as u32, height)
+            (top + (height as f32 * 0.75) as u32, bottom)
         } else {
-            (0, (height as f32 * 0.25) as u32)
+            (top, top + (height as f32 * 0.25) as u32)
         };
 
         if y_start >= y_end {
@@ -771,8 +1187,8 @@ impl MapRenderer {
             let src_g_lin = lin_base_g * src_a;
             let src_b_lin = lin_base_b * src_a;
 
-            let row_start = (y * width) as usize;
-            let row_end = row_start + width as usize;
+            let row_start = (y * width + left) as usize;
+            let row_end = (y * width + right) as usize;
             let row = &mut pixels[row_start..row_end];
 
             for p in row.iter_mut() {
@@ -829,33 +1245,57 @@ impl MapRenderer {
         }
     }
 
-    fn add_poly_to_path(&self, pb: &mut PathBuilder, poly: &PolyFeature) {
-        if poly.exterior.len() < 3 {
-            return;
+    /// 简化并追加一个多边形，外圈不足三个点时返回 false
+    fn add_poly_to_path(
+        &self,
+        pb: &mut PathBuilder,
+        poly: &PolyFeature,
+        locked: &HashSet<PointKey>,
+    ) -> bool {
+        // Visvalingam 有效面积阈值：容差的平方
+        let tolerance = self.simplify_tolerance_world();
+        let min_area = tolerance * tolerance;
+        // 外包框不足细节层级阈值的多边形不可见，直接跳过
+        if below_lod(&poly.exterior, self.lod_min_extent_world()) {
+            return false;
+        }
+        // 外圈无效时其内圈（洞）也无需绘制
+        if !self.add_ring_to_path(pb, &poly.exterior, min_area, locked, false) {
+            return false;
         }
+        for interior in &poly.interiors {
+            self.add_ring_to_path(pb, interior, min_area, locked, true);
+        }
+        true
+    }
 
-        // 外圈
-        let (x, y) = self.world_to_screen(poly.exterior[0]);
-        pb.move_to(x, y);
-        for &coord in &poly.exterior[1..] {
-            let (x, y) = self.world_to_screen(coord);
+    /// 简化并追加一个闭合环，不足三个点时跳过并返回 false
+    ///
+    /// 环方向按 `ccw` 统一（外环顺时针、洞逆时针，见 `PolyFeature::normalize_winding`），
+    /// 以非零环绕规则填充；二进制数据未必经过解析阶段的归一化，此处再确认一次
+    fn add_ring_to_path(
+        &self,
+        pb: &mut PathBuilder,
+        ring: &[(f64, f64)],
+        min_area: f64,
+        locked: &HashSet<PointKey>,
+        ccw: bool,
+    ) -> bool {
+        if ring.len() < 3 {
+            return false;
+        }
+        let mut simplified = visvalingam_ring(ring, min_area, locked);
+        orient_ring(&mut simplified, ccw);
+        let screen = self.ring_to_screen(&simplified);
+        if screen.len() < 3 {
+            return false;
+        }
+        pb.move_to(screen[0].0, screen[0].1);
+        for &(x, y) in &screen[1..] {
             pb.line_to(x, y);
         }
         pb.close();
-
-        // 内圈（洞）
-        for interior in &poly.interiors {
-            if interior.len() < 3 {
-                continue;
-            }
-            let (x, y) = self.world_to_screen(interior[0]);
-            pb.move_to(x, y);
-            for &coord in &interior[1..] {
-                let (x, y) = self.world_to_screen(coord);
-                pb.line_to(x, y);
-            }
-            pb.close();
-        }
+        true
     }
 
     /// 绘制文字（使用 fontdue）
@@ -870,7 +1310,7 @@ impl MapRenderer {
         let font = Font::from_bytes(font_data, FontSettings::default())
             .map_err(|e| format!("Failed to load font: {}", e))?;
 
-        let text_color = parse_hex_color(&self.theme.text);
+        let text_color = hex_color(&self.theme.text);
 
         // 改进：限制缩放系数
         // 取 Width/800 和 Height/800*1.1 中的较小值。
@@ -895,18 +1335,6 @@ impl MapRenderer {
             0.85
         };
 
-        // 计算基准锚点 Y 坐标 (屏幕绝对坐标)
-        let base_y_px = match self.text_position {
-            TextPosition::Top => self.render_height() as f32 * 0.10,
-            TextPosition::Center => self.render_height() as f32 * 0.50,
-            TextPosition::Bottom => self.render_height() as f32 * bottom_anchor,
-        };
-
-        // 减去 padding_offset，与 TSX 端的 rootFontSize 逻辑一致
-        // 这样文字 baseline 不会紧贴容器底部，而是留出约一个 font-size 的边距
-        let padding_offset: f32 = 16.0;
-        let base_y_px = base_y_px - padding_offset;
-
         // 定义相对偏移量 (基于 800px 宽度的标准像素值)
         // 之前的 0.05 (5%) 在 1000px 高度下是 50px
         // 之前的 0.04 (4%) 在 1000px 高度下是 40px
@@ -915,11 +1343,34 @@ impl MapRenderer {
         let coords_offset = -40.0 * scale_factor;
         // let decor_offset = 30.0 * scale_factor;
 
-        // 绘制城市名 (增加基准大小到 80.0)
+        // 城市名 (增加基准大小到 80.0)
         let formatted_city = format_city_name(city);
         // 字号阈值
         let threshold = 30;
         let city_size = calculate_font_size(&formatted_city, 80.0 * scale_factor, threshold);
+
+        // 计算基准锚点 Y 坐标 (屏幕绝对坐标)
+        let base_y_px = match (self.text_panel_top, self.text_position) {
+            // 文字面板：坐标行顶部到城市名底部的整块在面板内垂直居中
+            (Some(top), _) => {
+                let block_height = city_offset + city_size - coords_offset;
+                let panel_height = self.render_height() as f32 - top;
+                top + (panel_height - block_height) / 2.0 - coords_offset
+            }
+            (None, position) => {
+                let anchor = match position {
+                    TextPosition::Top => self.render_height() as f32 * 0.10,
+                    TextPosition::Center => self.render_height() as f32 * 0.50,
+                    TextPosition::Bottom => self.render_height() as f32 * bottom_anchor,
+                };
+                // 减去 padding_offset，与 TSX 端的 rootFontSize 逻辑一致
+                // 这样文字 baseline 不会紧贴容器底部，而是留出约一个 font-size 的边距
+                let padding_offset: f32 = 16.0;
+                anchor - padding_offset
+            }
+        };
+
+        // 绘制城市名
         // 位置：锚点 + 偏移
         self.draw_text_centered(
             &font,
@@ -963,6 +1414,152 @@ impl MapRenderer {
         Ok(())
     }
 
+    /// 在 `top`、`height`（逻辑像素）的横带内居中绘制拼贴标题与副标题，并绘制署名
+    pub fn draw_title_block(
+        &mut self,
+        title: &str,
+        subtitle: &str,
+        top: u32,
+        height: u32,
+        font_data: &[u8],
+    ) -> Result<(), String> {
+        let font = Font::from_bytes(font_data, FontSettings::default())
+            .map_err(|e| format!("Failed to load font: {}", e))?;
+        let text_color = hex_color(&self.theme.text);
+
+        // 与 draw_text 相同的缩放规则，另受标题区高度限制
+        let width_scale = self.render_width() as f32 / 1200.0;
+        let height_scale = (self.render_height() as f32 / 1200.0) * 1.1;
+        let scale_factor = width_scale.min(height_scale);
+        let band_top = (top * self.render_scale) as f32;
+        let band_height = (height * self.render_scale) as f32;
+
+        let formatted_title = format_city_name(title);
+        let title_size =
+            calculate_font_size(&formatted_title, 80.0 * scale_factor, 30).min(band_height * 0.45);
+        let subtitle = subtitle.to_uppercase();
+        let subtitle_size = if subtitle.is_empty() {
+            0.0
+        } else {
+            (28.0 * scale_factor).min(band_height * 0.2)
+        };
+        let line_gap = subtitle_size * 0.8;
+        let block_height = title_size + line_gap + subtitle_size;
+        let title_y = band_top + (band_height - block_height) / 2.0;
+        self.draw_text_centered(&font, &formatted_title, title_y, title_size, text_color);
+        if !subtitle.is_empty() {
+            let subtitle_y = title_y + title_size + line_gap;
+            self.draw_text_centered(&font, &subtitle, subtitle_y, subtitle_size, text_color);
+        }
+
+        let attr_text = "© OpenStreetMap contributors";
+        self.draw_text_bottom_right(
+            &font,
+            attr_text,
+            10.0 * scale_factor,
+            text_color,
+            scale_factor,
+        );
+        Ok(())
+    }
+
+    /// 按锚点与缩放叠加图片（保留图片的透明度）
+    pub fn draw_logo(&mut self, image: &Pixmap, logo: &LogoOverlay) {
+        let (width, height) = (self.render_width() as f32, self.render_height() as f32);
+        let scale = width * logo.scale / image.width() as f32;
+        let (logo_w, logo_h) = (image.width() as f32 * scale, image.height() as f32 * scale);
+        let margin = logo.margin * self.render_scale as f32;
+        let (left, right) = (margin, width - margin - logo_w);
+        let (top, bottom) = (margin, height - margin - logo_h);
+        let (x, y) = match logo.position {
+            LogoPosition::TopLeft => (left, top),
+            LogoPosition::TopRight => (right, top),
+            LogoPosition::BottomLeft => (left, bottom),
+            LogoPosition::BottomRight => (right, bottom),
+            LogoPosition::Center => ((width - logo_w) / 2.0, (height - logo_h) / 2.0),
+        };
+        let paint = PixmapPaint {
+            opacity: logo.opacity.clamp(0.0, 1.0),
+            quality: FilterQuality::Bicubic,
+            ..Default::default()
+        };
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            image.as_ref(),
+            &paint,
+            Transform::from_row(scale, 0.0, 0.0, scale, x, y),
+            None,
+        );
+    }
+
+    /// 在所有内容之上绘制文字水印
+    pub fn draw_watermark(
+        &mut self,
+        watermark: &Watermark,
+        font_data: &[u8],
+    ) -> Result<(), String> {
+        if watermark.text.trim().is_empty() {
+            return Ok(());
+        }
+        let font = Font::from_bytes(font_data, FontSettings::default())
+            .map_err(|e| format!("Failed to load font: {}", e))?;
+        let color = hex_color(watermark.color.as_deref().unwrap_or(&self.theme.text));
+
+        let (width, height) = (self.render_width() as f32, self.render_height() as f32);
+        let diagonal = width.hypot(height);
+        let size = match watermark.style {
+            WatermarkStyle::Tiled => watermark.size * self.render_scale as f32,
+            // 按基准字号量出文字宽度，缩放到对角线的 70%
+            WatermarkStyle::Diagonal => {
+                let reference = 100.0;
+                let text_width = text_pixmap(&font, &watermark.text, reference, color)
+                    .map_or(reference, |p| p.width() as f32);
+                reference * diagonal * 0.7 / text_width
+            }
+        };
+        let Some(tile) = text_pixmap(&font, &watermark.text, size, color) else {
+            return Ok(());
+        };
+
+        let paint = PixmapPaint {
+            opacity: watermark.opacity.clamp(0.0, 1.0),
+            quality: FilterQuality::Bilinear,
+            ..Default::default()
+        };
+        let (cx, cy) = (width / 2.0, height / 2.0);
+        let transform = Transform::from_rotate_at(watermark.angle, cx, cy);
+        let (tile_w, tile_h) = (tile.width() as f32, tile.height() as f32);
+        let mut draw = |x: f32, y: f32| {
+            self.pixmap.draw_pixmap(
+                (x - tile_w / 2.0).round() as i32,
+                (y - tile_h / 2.0).round() as i32,
+                tile.as_ref(),
+                &paint,
+                transform,
+                None,
+            );
+        };
+        match watermark.style {
+            WatermarkStyle::Diagonal => draw(cx, cy),
+            WatermarkStyle::Tiled => {
+                // 旋转前铺满以画布中心为圆心、对角线为直径的区域，旋转后仍覆盖整张画布；
+                // 奇数行错开半格
+                let step_x = tile_w + size * 2.0;
+                let step_y = tile_h + size * 2.0;
+                let cols = (diagonal / 2.0 / step_x).ceil() as i32 + 1;
+                let rows = (diagonal / 2.0 / step_y).ceil() as i32;
+                for row in -rows..=rows {
+                    let shift = if row % 2 == 0 { 0.0 } else { step_x / 2.0 };
+                    for col in -cols..=cols {
+                        draw(cx + col as f32 * step_x + shift, cy + row as f32 * step_y);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// 居中绘制文字
     fn draw_text_centered(
         &mut self,
@@ -1035,10 +1632,13 @@ impl MapRenderer {
 
         // [超采样] 使用实际画布尺寸计算右下角位置，避免文字偏移到画布中央
         let x_offset = self.render_width() as i32 - max_x - margin as i32;
-        // y 是文本块的起始位置。为了让文本底部距离边缘 margin，
-        // y 应该是 height - margin - text_height
-        // 简单估算 text_height 为 size
-        let y = self.render_height() as i32 - margin as i32 - size as i32;
+        // y 是文本块的顶部：布局以行顶为原点、基线位于 ascent 处，下行笔画（g、p、y）
+        // 最低到基线以下 |descent|，因此文本块高度为 ascent - descent，
+        // 底部恰好距离边缘 margin
+        let block_height = font
+            .horizontal_line_metrics(size)
+            .map_or(size, |m| m.ascent - m.descent);
+        let y = self.render_height() as f32 - margin - block_height;
 
         for glyph in glyphs {
             let (metrics, bitmap) = font.rasterize_config(glyph.key);
@@ -1047,13 +1647,116 @@ impl MapRenderer {
                 metrics.width,
                 metrics.height,
                 x_offset + glyph.x as i32,
-                y + glyph.y as i32,
+                (y + glyph.y).round() as i32,
                 color,
             );
         }
     }
 
     /// 绘制字形位图（实现正确的 SrcOver 混合以解决边缘发虚问题）
+    /// 在已绘制的 POI 外绘制光环，第 k 环（从 0 开始）的不透明度为 opacity × falloff^k
+    fn draw_poi_halos(
+        &mut self,
+        halo: &PoiHalo,
+        placed: &[(usize, f32, f32)],
+        poi_radius: f32,
+        scale_factor: f32,
+    ) {
+        let base = hex_color(halo.color.as_deref().unwrap_or(&self.theme.poi_color));
+        let spacing = halo.spacing.max(0.0) * scale_factor;
+        let mut paint = Paint {
+            anti_alias: self.anti_alias,
+            ..Default::default()
+        };
+        let mut opacity = halo.opacity.clamp(0.0, 1.0);
+        for ring in 0..halo.rings.min(crate::validate::MAX_HALO_RINGS) {
+            let outer = poi_radius + spacing * (ring + 1) as f32;
+            let mut pb = PathBuilder::new();
+            for &(_, x, y) in placed {
+                match halo.style {
+                    HaloStyle::Rings => pb.push_circle(x, y, outer),
+                    // 光晕：每环为一条填充的圆环带
+                    HaloStyle::Glow => {
+                        pb.push_circle(x, y, outer);
+                        pb.push_circle(x, y, outer - spacing);
+                    }
+                }
+            }
+            let mut color = base;
+            color.apply_opacity(opacity);
+            paint.set_color(color);
+            if let Some(path) = pb.finish() {
+                match halo.style {
+                    HaloStyle::Rings => {
+                        let stroke = Stroke {
+                            width: halo.width.max(0.0) * scale_factor,
+                            ..Default::default()
+                        };
+                        self.pixmap.stroke_path(
+                            &path,
+                            &paint,
+                            &stroke,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                    HaloStyle::Glow => self.pixmap.fill_path(
+                        &path,
+                        &paint,
+                        FillRule::EvenOdd,
+                        Transform::identity(),
+                        None,
+                    ),
+                }
+            }
+            opacity *= halo.falloff.clamp(0.0, 1.0);
+        }
+    }
+
+    /// 将短文字的字形外框居中于圆心，字号随字符数缩小以容纳于圆内
+    fn draw_label_in_circle(
+        &mut self,
+        font: &Font,
+        text: &str,
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+    ) {
+        let size = radius
+            * match text.chars().count() {
+                1 => 1.3,
+                2 => 1.1,
+                _ => 0.8,
+            };
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        layout.append(&[font], &TextStyle::new(text, size, 0));
+        let glyphs = layout.glyphs();
+        if glyphs.is_empty() {
+            return;
+        }
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for g in glyphs.iter().filter(|g| g.width > 0) {
+            min_x = min_x.min(g.x);
+            min_y = min_y.min(g.y);
+            max_x = max_x.max(g.x + g.width as f32);
+            max_y = max_y.max(g.y + g.height as f32);
+        }
+        let dx = center.0 - (min_x + max_x) / 2.0;
+        let dy = center.1 - (min_y + max_y) / 2.0;
+        for glyph in glyphs {
+            let (metrics, bitmap) = font.rasterize_config(glyph.key);
+            self.draw_glyph_bitmap(
+                &bitmap,
+                metrics.width,
+                metrics.height,
+                (dx + glyph.x).round() as i32,
+                (dy + glyph.y).round() as i32,
+                color,
+            );
+        }
+    }
+
     fn draw_glyph_bitmap(
         &mut self,
         bitmap: &[u8],
@@ -1143,7 +1846,7 @@ impl MapRenderer {
         }
     }
 
-    /// 绘制装饰线
+    // /// 绘制装饰线
     // fn draw_decoration_line(&mut self, color: Color, scale_factor: f32, y_px: f32) {
     //     let y = y_px;
     //     // [超采样] 使用实际画布宽度计算装饰线端点，确保线段视觉居中
@@ -1171,15 +1874,104 @@ impl MapRenderer {
 
     /// 世界坐标 -> 屏幕坐标
     fn world_to_screen(&self, coord: (f64, f64)) -> (f32, f32) {
-        let x = ((coord.0 - self.bounds.min_x) * self.x_factor) as f32;
+        let (wx, wy) = match self.projector {
+            // [跨经线] 以视口中心为参考展开 x，使 ±180° 两侧的要素连续绘制
+            Projector::WebMercator => (wrap_x(coord.0, self.center_x), coord.1),
+            projector => projector.reproject_web_mercator(coord.0, coord.1),
+        };
+        self.screen_transform().apply((wx, wy))
+    }
+
+    /// 投影平面坐标 -> 屏幕坐标的仿射变换
+    fn screen_transform(&self) -> ScreenTransform {
         // [超采样] 使用实际画布高度做 Y 轴翻转，确保地理坐标正确映射到 2× 画布
-        let y =
-            self.render_height() as f32 - ((coord.1 - self.bounds.min_y) * self.y_factor) as f32;
-        (x, y)
+        ScreenTransform {
+            origin: (self.bounds.min_x, self.bounds.min_y),
+            scale: (self.x_factor, -self.y_factor),
+            offset: (0.0, self.render_height() as f32),
+        }
+    }
+
+    /// 批量世界坐标 -> 屏幕坐标
+    ///
+    /// Web Mercator 且无需跨经线展开时走批量（可 SIMD）路径，否则逐点变换
+    fn coords_to_screen(&self, coords: &[(f64, f64)]) -> Vec<(f32, f32)> {
+        let needs_wrap = |&(x, _): &(f64, f64)| (x - self.center_x).abs() > WORLD_WIDTH / 2.0;
+        if matches!(self.projector, Projector::WebMercator) && !coords.iter().any(needs_wrap) {
+            let mut out = Vec::with_capacity(coords.len());
+            self.screen_transform().apply_all(coords, &mut out);
+            out
+        } else {
+            coords.iter().map(|&c| self.world_to_screen(c)).collect()
+        }
+    }
+
+    /// 保留 f64 精度的世界坐标 -> 屏幕坐标，用于裁剪
+    fn world_to_screen_f64(&self, coord: (f64, f64)) -> (f64, f64) {
+        let (wx, wy) = match self.projector {
+            Projector::WebMercator => (wrap_x(coord.0, self.center_x), coord.1),
+            projector => projector.reproject_web_mercator(coord.0, coord.1),
+        };
+        self.screen_transform().apply_f64((wx, wy))
+    }
+
+    /// 外扩 `CLIP_MARGIN_PX` 后的画布矩形（屏幕坐标）
+    fn clip_rect(&self) -> Viewport {
+        Viewport {
+            min_x: -CLIP_MARGIN_PX,
+            min_y: -CLIP_MARGIN_PX,
+            max_x: self.render_width() as f64 + CLIP_MARGIN_PX,
+            max_y: self.render_height() as f64 + CLIP_MARGIN_PX,
+        }
+    }
+
+    fn within_clip_rect(&self, screen: &[(f32, f32)]) -> bool {
+        let rect = self.clip_rect();
+        screen.iter().all(|&(x, y)| {
+            let (x, y) = (f64::from(x), f64::from(y));
+            (rect.min_x..=rect.max_x).contains(&x) && (rect.min_y..=rect.max_y).contains(&y)
+        })
+    }
+
+    /// 环转为屏幕坐标；超出画布的环先在 f64 下裁剪（Sutherland–Hodgman），
+    /// 避免湖泊、海域等巨大多边形产生的超大坐标拖慢光栅化或超出 f32 精度。完全不可见时为空
+    fn ring_to_screen(&self, ring: &[(f64, f64)]) -> Vec<(f32, f32)> {
+        let screen = self.coords_to_screen(ring);
+        if self.within_clip_rect(&screen) {
+            return screen;
+        }
+        let exact: Vec<(f64, f64)> = ring.iter().map(|&c| self.world_to_screen_f64(c)).collect();
+        self.clip_rect()
+            .clip_ring(&exact)
+            .into_iter()
+            .map(|(x, y)| (x as f32, y as f32))
+            .collect()
+    }
+
+    /// 折线转为屏幕坐标；超出画布的部分在边界处截断（Liang–Barsky），可能断为多段
+    fn line_to_screen(&self, line: &[(f64, f64)]) -> Vec<Vec<(f32, f32)>> {
+        let screen = self.coords_to_screen(line);
+        if self.within_clip_rect(&screen) {
+            return if screen.len() >= 2 {
+                vec![screen]
+            } else {
+                vec![]
+            };
+        }
+        let exact: Vec<(f64, f64)> = line.iter().map(|&c| self.world_to_screen_f64(c)).collect();
+        self.clip_rect()
+            .cut_polyline(&exact)
+            .into_iter()
+            .map(|part| {
+                part.into_iter()
+                    .map(|(x, y)| (x as f32, y as f32))
+                    .collect()
+            })
+            .collect()
     }
 
     /// 导出为 PNG（带 DPI 元数据）
-    pub fn encode_png(self, dpi: u32) -> Result<Vec<u8>, String> {
+    pub fn encode_png(self, dpi: u32, compression: PngCompression) -> Result<Vec<u8>, String> {
         let scale = self.render_scale as usize;
         let out_w = self.width as usize;
         let out_h = self.height as usize;
@@ -1196,9 +1988,10 @@ impl MapRenderer {
 
         // [超采样] 步骤 2：Box Filter 下采样——每 scale×scale 块的源像素取算术平均
         // Box Filter 等价于对高频锯齿做低通滤波，结合 2× 超采样可显著消除锯齿
-        let mut out_rgba: Vec<u8> = Vec::with_capacity(out_w * out_h * 4);
-        for oy in 0..out_h {
-            for ox in 0..out_w {
+        // 按输出行分块处理（启用 threads 时并行）
+        let mut out_rgba: Vec<u8> = vec![0; out_w * out_h * 4];
+        par::for_each_chunk_mut(&mut out_rgba, out_w * 4, |oy, row| {
+            for (ox, out) in row.chunks_exact_mut(4).enumerate() {
                 let mut acc = [0f32; 4];
                 for dy in 0..scale {
                     for dx in 0..scale {
@@ -1214,47 +2007,32 @@ impl MapRenderer {
                         acc[3] += a as f32;
                     }
                 }
-                out_rgba.push((acc[0] / scale_sq * 255.0 + 0.5).min(255.0) as u8);
-                out_rgba.push((acc[1] / scale_sq * 255.0 + 0.5).min(255.0) as u8);
-                out_rgba.push((acc[2] / scale_sq * 255.0 + 0.5).min(255.0) as u8);
-                out_rgba.push((acc[3] / scale_sq + 0.5).min(255.0) as u8);
+                out[0] = (acc[0] / scale_sq * 255.0 + 0.5).min(255.0) as u8;
+                out[1] = (acc[1] / scale_sq * 255.0 + 0.5).min(255.0) as u8;
+                out[2] = (acc[2] / scale_sq * 255.0 + 0.5).min(255.0) as u8;
+                out[3] = (acc[3] / scale_sq + 0.5).min(255.0) as u8;
             }
-        }
+        });
 
-        // [超采样] 步骤 3：将下采样后的 RGBA 数据编码为 PNG
-        let raw = encode_rgba_to_png(&out_rgba, out_w as u32, out_h as u32)?;
-
-        // pHYs chunk 构造（逻辑不变）
-        let ppm = (dpi as u64 * 10000 / 254) as u32; // 300 DPI = 11811
-        let mut phys: Vec<u8> = Vec::with_capacity(21);
-
-        // chunk data length = 9 bytes
-        phys.extend_from_slice(&9u32.to_be_bytes());
-        // chunk type: "pHYs"
-        phys.extend_from_slice(b"pHYs");
-        // pixels per unit X (big-endian)
-        phys.extend_from_slice(&ppm.to_be_bytes());
-        // pixels per unit Y (big-endian)
-        phys.extend_from_slice(&ppm.to_be_bytes());
-        // unit: 1 = meter
-        phys.push(1u8);
-        // CRC-32 (覆盖 type + data)
-        let crc = crc32(&phys[4..17]);
-        phys.extend_from_slice(&crc.to_be_bytes());
-
-        // 在 IHDR 之后 (offset 33) 插入 pHYs chunk
-        let insert_pos = 33;
-        let mut result = Vec::with_capacity(raw.len() + 21);
-        result.extend_from_slice(&raw[..insert_pos]);
-        result.extend_from_slice(&phys);
-        result.extend_from_slice(&raw[insert_pos..]);
-
-        Ok(result)
+        // [超采样] 步骤 3：将下采样后的 RGBA 数据编码为 PNG（含 pHYs 物理尺寸）
+        encode_rgba_to_png(&out_rgba, out_w as u32, out_h as u32, dpi, compression)
     }
 }
 
 // ── [Gamma校正] sRGB ↔ 线性光转换工具函数 ────────────────────────────────────
 
+/// 与填充色对比度更高的文字颜色（黑或白），按 WCAG 相对亮度的交叉点 0.179 取舍
+fn contrast_color(fill: Color) -> Color {
+    let luminance = 0.2126 * srgb_to_linear(fill.red())
+        + 0.7152 * srgb_to_linear(fill.green())
+        + 0.0722 * srgb_to_linear(fill.blue());
+    if luminance > 0.179 {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
 /// [Gamma校正] sRGB -> 线性光（IEC 61966-2-1 标准）
 /// 在此空间做颜色混合才能得到物理上正确的结果
 #[inline]
@@ -1281,15 +2059,19 @@ fn linear_to_srgb(c: f32) -> f32 {
 
 /// 预计算 sRGB u8 → 线性 f32 查找表（256 项）
 /// 在 `draw_gradient` 每次调用时复用，避免重复计算
-static SRGB_TO_LIN_LUT: LazyLock<[f32; 256]> = LazyLock::new(|| {
-    std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0))
-});
+static SRGB_TO_LIN_LUT: LazyLock<[f32; 256]> =
+    LazyLock::new(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
 
 /// 预计算线性 f32 → sRGB u8 查找表（1024 项，精度 1/1023）
 static LIN_TO_SRGB_LUT: LazyLock<[u8; 1024]> = LazyLock::new(|| {
     std::array::from_fn(|i| (linear_to_srgb(i as f32 / 1023.0) * 255.0 + 0.5).min(255.0) as u8)
 });
 
+/// 解析主题颜色，无效时回退为黑色（`validate_request` 会提前报告无效颜色）
+fn hex_color(hex: &str) -> Color {
+    parse_hex_color(hex).unwrap_or(Color::BLACK)
+}
+
 // ── [Road Casing] 颜色压暗工具函数 ──────────────────────────────────────────
 
 /// [Road Casing] 按比例压暗颜色，用于生成道路的描边底色（Casing）
@@ -1306,22 +2088,155 @@ fn darken_color(color: Color, factor: f32) -> Color {
 
 // ── [超采样] PNG 编码工具函数 ─────────────────────────────────────────────────
 
-/// [超采样] 将直线性 RGBA 字节数组编码为 PNG 格式（使用 `png` crate）
-fn encode_rgba_to_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
-    let mut buf = Vec::new();
-    {
-        let mut encoder = png::Encoder::new(&mut buf, width, height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        encoder.set_compression(png::Compression::Fast);
-        let mut writer = encoder
-            .write_header()
-            .map_err(|e| format!("PNG header write failed: {}", e))?;
-        writer
-            .write_image_data(rgba)
-            .map_err(|e| format!("PNG data write failed: {}", e))?;
-    }
-    Ok(buf)
+/// [超采样] 将直线性 RGBA 字节数组编码为 PNG 格式
+///
+/// 逐行滤波按行分块处理（启用 threads 时并行），随后整体压缩为单个 IDAT：
+/// fast 使用 fdeflate，其余级别使用 miniz_oxide
+fn encode_rgba_to_png(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    dpi: u32,
+    compression: PngCompression,
+) -> Result<Vec<u8>, String> {
+    let stride = width as usize * 4;
+    if rgba.len() != stride * height as usize {
+        return Err("PNG data write failed: buffer size does not match dimensions".to_string());
+    }
+
+    // 每行前加 1 字节滤波类型
+    let mut filtered = vec![0u8; (stride + 1) * height as usize];
+    let adaptive = compression != PngCompression::Fast;
+    par::for_each_chunk_mut(&mut filtered, stride + 1, |y, out| {
+        let cur = &rgba[y * stride..(y + 1) * stride];
+        let prev = (y > 0).then(|| &rgba[(y - 1) * stride..y * stride]);
+        let filter = if adaptive {
+            RowFilter::ALL
+                .into_iter()
+                .min_by_key(|&f| filter_cost(f, cur, prev))
+                .unwrap_or(RowFilter::Up)
+        } else {
+            RowFilter::Up
+        };
+        out[0] = filter as u8;
+        apply_filter(filter, cur, prev, &mut out[1..]);
+    });
+
+    let idat = match compression {
+        PngCompression::Fast => fdeflate::compress_to_vec(&filtered),
+        PngCompression::Default => miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 6),
+        PngCompression::Best => miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 9),
+    };
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 位深度、RGBA、deflate、标准滤波、无隔行
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // pHYs：每米像素数，单位 1 = 米
+    let ppm = (dpi as u64 * 10000 / 254) as u32; // 300 DPI = 11811
+    let mut phys = Vec::with_capacity(9);
+    phys.extend_from_slice(&ppm.to_be_bytes());
+    phys.extend_from_slice(&ppm.to_be_bytes());
+    phys.push(1u8);
+
+    let mut png = Vec::with_capacity(idat.len() + 64);
+    png.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+    write_png_chunk(&mut png, b"IHDR", &ihdr);
+    write_png_chunk(&mut png, b"pHYs", &phys);
+    write_png_chunk(&mut png, b"IDAT", &idat);
+    write_png_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+/// 写入一个 PNG chunk：长度 + 类型 + 数据 + CRC-32（覆盖类型与数据）
+fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// PNG 逐行滤波类型（RGBA 8 位，每像素 4 字节）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowFilter {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Average = 3,
+    Paeth = 4,
+}
+
+impl RowFilter {
+    const ALL: [RowFilter; 5] = [
+        RowFilter::None,
+        RowFilter::Sub,
+        RowFilter::Up,
+        RowFilter::Average,
+        RowFilter::Paeth,
+    ];
+
+    /// x 为当前字节，a / b / c 分别为左、上、左上字节
+    #[inline]
+    fn apply(self, x: u8, a: u8, b: u8, c: u8) -> u8 {
+        match self {
+            RowFilter::None => x,
+            RowFilter::Sub => x.wrapping_sub(a),
+            RowFilter::Up => x.wrapping_sub(b),
+            RowFilter::Average => x.wrapping_sub(((a as u16 + b as u16) / 2) as u8),
+            RowFilter::Paeth => x.wrapping_sub(paeth(a, b, c)),
+        }
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// 按滤波类型逐字节处理一行，`prev` 为上一行原始数据（首行为 None）
+fn filtered_row<'a>(
+    filter: RowFilter,
+    cur: &'a [u8],
+    prev: Option<&'a [u8]>,
+) -> impl Iterator<Item = u8> + 'a {
+    (0..cur.len()).map(move |i| {
+        let a = if i >= 4 { cur[i - 4] } else { 0 };
+        let b = prev.map_or(0, |p| p[i]);
+        let c = if i >= 4 {
+            prev.map_or(0, |p| p[i - 4])
+        } else {
+            0
+        };
+        filter.apply(cur[i], a, b, c)
+    })
+}
+
+fn apply_filter(filter: RowFilter, cur: &[u8], prev: Option<&[u8]>, out: &mut [u8]) {
+    for (o, v) in out.iter_mut().zip(filtered_row(filter, cur, prev)) {
+        *o = v;
+    }
+}
+
+/// 自适应滤波的启发式代价：滤波结果按有符号字节的绝对值之和（越小越易压缩）
+fn filter_cost(filter: RowFilter, cur: &[u8], prev: Option<&[u8]>) -> u64 {
+    filtered_row(filter, cur, prev)
+        .map(|v| (v as i8).unsigned_abs() as u64)
+        .sum()
 }
 
 /// 计算 CRC-32 (PNG 标准 ISO 3309)
@@ -1375,50 +2290,663 @@ fn crc32(data: &[u8]) -> u32 {
     crc ^ 0xFFFFFFFF
 }
 
-/// Douglas-Peucker 折线简化，在屏幕坐标空间消除亚像素级冗余点
-/// epsilon_sq：距离阈值的平方（传入 epsilon² 避免 sqrt 开销）
-/// 推荐值：道路传 0.25（= 0.5px²），多边形传 1.0（= 1.0px²）
-fn simplify_screen_coords(coords: &[(f32, f32)], epsilon_sq: f32) -> Vec<(f32, f32)> {
-    if coords.len() < 3 {
-        return coords.to_vec();
-    }
+/// 多边形集合中的所有环（外圈与内圈）
+/// 外包框宽高均小于 `min_extent`（世界坐标）的几何视为不可见
+fn below_lod(coords: &[(f64, f64)], min_extent: f64) -> bool {
+    min_extent > 0.0
+        && Aabb::from_points(coords)
+            .is_none_or(|b| b.max_x - b.min_x < min_extent && b.max_y - b.min_y < min_extent)
+}
 
-    let (first, last) = (coords[0], *coords.last().unwrap());
-    let mut max_dist_sq = 0f32;
-    let mut max_idx = 0;
+fn poly_rings(polys: &[PolyFeature]) -> impl Iterator<Item = &[(f64, f64)]> {
+    polys.iter().flat_map(|p| {
+        std::iter::once(p.exterior.as_slice()).chain(p.interiors.iter().map(Vec::as_slice))
+    })
+}
 
-    for (i, &p) in coords[1..coords.len() - 1].iter().enumerate() {
-        let d = point_to_segment_dist_sq(p, first, last);
-        if d > max_dist_sq {
-            max_dist_sq = d;
-            max_idx = i + 1;
+/// 以 (cx, cy) 为中心、外接圆半径约为 `r` 的心形（屏幕坐标）
+///
+/// 采用经典的参数方程 x = 16sin³t，y = 13cos t − 5cos 2t − 2cos 3t − cos 4t，
+/// 其范围为 x ∈ [−16, 16]、y ∈ [−17, 12]，按 17 归一化并使上下居中
+fn heart_path(cx: f32, cy: f32, r: f32) -> Option<tiny_skia::Path> {
+    const SEGMENTS: usize = 64;
+    let scale = r / 17.0;
+    let mut pb = PathBuilder::new();
+    for i in 0..SEGMENTS {
+        let t = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        let x = 16.0 * t.sin().powi(3);
+        let y = 13.0 * t.cos() - 5.0 * (2.0 * t).cos() - 2.0 * (3.0 * t).cos() - (4.0 * t).cos();
+        let (sx, sy) = (cx + x * scale, cy - (y + 2.5) * scale);
+        if i == 0 {
+            pb.move_to(sx, sy);
+        } else {
+            pb.line_to(sx, sy);
         }
     }
+    pb.close();
+    pb.finish()
+}
 
-    if max_dist_sq > epsilon_sq {
-        let mut left = simplify_screen_coords(&coords[..=max_idx], epsilon_sq);
-        let right = simplify_screen_coords(&coords[max_idx..], epsilon_sq);
-        left.pop(); // 去掉重复的分割点
-        left.extend(right);
-        left
-    } else {
-        vec![first, last]
+/// 将单行文字栅格化为透明背景的图层，像素按字形覆盖率着色
+fn text_pixmap(font: &Font, text: &str, size: f32, color: Color) -> Option<Pixmap> {
+    let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+    layout.append(&[font], &TextStyle::new(text, size, 0));
+    let glyphs = layout.glyphs();
+    let width = glyphs
+        .iter()
+        .map(|g| g.x + g.width as f32)
+        .fold(0.0, f32::max)
+        .ceil() as u32;
+    let mut pixmap = Pixmap::new(width.max(1), (layout.height().ceil() as u32).max(1))?;
+    let (w, h) = (pixmap.width() as i32, pixmap.height() as i32);
+    let pixels = pixmap.pixels_mut();
+    for glyph in glyphs {
+        let (metrics, bitmap) = font.rasterize_config(glyph.key);
+        if metrics.width == 0 {
+            continue;
+        }
+        for (i, &coverage) in bitmap.iter().enumerate() {
+            let x = glyph.x.round() as i32 + (i % metrics.width) as i32;
+            let y = glyph.y.round() as i32 + (i / metrics.width) as i32;
+            if coverage == 0 || !(0..w).contains(&x) || !(0..h).contains(&y) {
+                continue;
+            }
+            let alpha = color.alpha() * coverage as f32 / 255.0;
+            let Some(c) = Color::from_rgba(color.red(), color.green(), color.blue(), alpha) else {
+                continue;
+            };
+            let pixel = &mut pixels[(y * w + x) as usize];
+            let c = c.premultiply().to_color_u8();
+            // 相邻字形的边缘可能重叠，取覆盖率较高者
+            if c.alpha() > pixel.alpha() {
+                *pixel = c;
+            }
+        }
     }
+    Some(pixmap)
 }
 
-/// 点到线段的距离平方（避免 sqrt）
-fn point_to_segment_dist_sq(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
-    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
-    let len_sq = dx * dx + dy * dy;
-    if len_sq == 0.0 {
-        // a == b，退化为点距离
-        let (ex, ey) = (p.0 - a.0, p.1 - a.1);
-        return ex * ex + ey * ey;
-    }
-    // 投影参数 t，clamp 到 [0, 1] 保证落在线段上
-    let t = ((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq;
-    let t = t.clamp(0.0, 1.0);
-    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
-    let (ex, ey) = (p.0 - cx, p.1 - cy);
-    ex * ex + ey * ey
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Road;
+
+    fn test_theme() -> Theme {
+        Theme {
+            bg: "#FFFFFF".to_string(),
+            text: "#000000".to_string(),
+            gradient_color: "#FFFFFF".to_string(),
+            poi_color: "#FF0000".to_string(),
+            water: "#0000FF".to_string(),
+            parks: "#00FF00".to_string(),
+            road_motorway: "#000000".to_string(),
+            road_primary: "#000000".to_string(),
+            road_secondary: "#000000".to_string(),
+            road_tertiary: "#000000".to_string(),
+            road_residential: "#000000".to_string(),
+            road_default: "#000000".to_string(),
+            route_color: None,
+            buildings: None,
+        }
+    }
+
+    /// 测试辅助：以二进制多边形格式绘制水体
+    fn draw_water(renderer: &mut MapRenderer, water: &[PolyFeature]) {
+        let data = crate::data_processor::polygons_to_bin(water);
+        let ranges = crate::data_processor::polygon_bin_coord_ranges(&data);
+        let color = renderer.theme.water.clone();
+        renderer.draw_polygons_local(&LocalBin::new(&data, &ranges, (0.0, 0.0)), &color);
+    }
+
+    fn test_renderer(width: u32, height: u32) -> MapRenderer {
+        let bounds = BoundingBox::new(0.0, width as f64, 0.0, height as f64);
+        MapRenderer::new(width, height, test_theme(), bounds, TextPosition::Top).unwrap()
+    }
+
+    /// 读取逻辑坐标 (x, y) 处的内部像素
+    fn pixel_at(renderer: &MapRenderer, x: u32, y: u32) -> tiny_skia::PremultipliedColorU8 {
+        let scale = renderer.render_scale;
+        let idx = (y * scale) * renderer.render_width() + x * scale;
+        renderer.pixmap.pixels()[idx as usize]
+    }
+
+    #[test]
+    fn test_png_round_trip_all_levels() {
+        let (w, h) = (7u32, 5u32);
+        let rgba: Vec<u8> = (0..w * h * 4).map(|i| (i * 37 % 251) as u8).collect();
+        for level in [
+            PngCompression::Fast,
+            PngCompression::Default,
+            PngCompression::Best,
+        ] {
+            let png = encode_rgba_to_png(&rgba, w, h, 300, level).unwrap();
+            let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+            let phys = reader.info().pixel_dims.unwrap();
+            assert_eq!(phys.xppu, 11811);
+            let mut buf = vec![0; reader.output_buffer_size()];
+            reader.next_frame(&mut buf).unwrap();
+            assert_eq!(buf, rgba);
+        }
+    }
+
+    /// 描边耗时与每个 Path 线段数的关系（cargo test --release -- --ignored --nocapture）
+    #[test]
+    #[ignore]
+    fn test_bench_path_chunk_segments() {
+        // 伪随机折线：共 20 万段，分布在 2000×2000 画布上
+        let mut seed = 12345u32;
+        let mut rand = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) as f32 / (1u32 << 24) as f32 * 2000.0
+        };
+        // 长折线随机游走、相互交叉，接近城市路网
+        let lines: Vec<Vec<(f32, f32)>> = (0..1000)
+            .map(|_| {
+                let (mut x, mut y) = (rand(), rand());
+                (0..201)
+                    .map(|_| {
+                        x = (x + rand() / 100.0 - 10.0).clamp(0.0, 2000.0);
+                        y = (y + rand() / 100.0 - 10.0).clamp(0.0, 2000.0);
+                        (x, y)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let stroke = Stroke {
+            width: 2.0,
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..Default::default()
+        };
+        let paint = Paint {
+            anti_alias: true,
+            ..Default::default()
+        };
+
+        for chunk in [usize::MAX, 65_536, 16_384, 4096, 1024, 256] {
+            let mut pixmap = Pixmap::new(2000, 2000).unwrap();
+            let start = std::time::Instant::now();
+            let (mut pb, mut segments) = (PathBuilder::new(), 0);
+            let mut paths = Vec::new();
+            for line in &lines {
+                pb.move_to(line[0].0, line[0].1);
+                for &(x, y) in &line[1..] {
+                    pb.line_to(x, y);
+                }
+                segments += line.len() - 1;
+                if segments >= chunk {
+                    paths.extend(std::mem::take(&mut pb).finish());
+                    segments = 0;
+                }
+            }
+            paths.extend(pb.finish());
+            for path in &paths {
+                pixmap.stroke_path(path, &paint, &stroke, Transform::identity(), None);
+            }
+            println!("chunk {:>20}: {:?}", chunk, start.elapsed());
+        }
+    }
+
+    #[test]
+    fn test_composite_layer_matches_direct_draw() {
+        let water = [PolyFeature {
+            exterior: vec![(2.0, 2.0), (12.0, 2.0), (12.0, 12.0), (2.0, 12.0)],
+            interiors: vec![],
+        }];
+
+        let mut direct = test_renderer(20, 20);
+        direct.draw_background();
+        draw_water(&mut direct, &water);
+
+        let mut layered = test_renderer(20, 20);
+        let layer = layered.render_layer(|r| draw_water(r, &water)).unwrap();
+        layered.draw_background();
+        layered.composite_layer(&layer);
+
+        assert_eq!(direct.pixmap.data(), layered.pixmap.data());
+        assert_eq!(pixel_at(&layered, 0, 0).blue(), 255);
+        assert_eq!(pixel_at(&layered, 0, 0).red(), 255);
+        assert_eq!(pixel_at(&layered, 7, 12).red(), 0);
+    }
+
+    #[test]
+    fn test_lod_skips_subpixel_detail() {
+        let roads = crate::data_processor::roads_to_bin(&[Road {
+            coords: vec![(5.0, 5.0), (5.4, 5.3)],
+            road_type: RoadType::Residential,
+        }]);
+        let water = [PolyFeature {
+            exterior: vec![(14.0, 14.0), (14.5, 14.0), (14.5, 14.5)],
+            interiors: vec![],
+        }];
+        let draw = |lod_min_px: f32| {
+            let mut renderer = test_renderer(20, 20);
+            renderer.set_lod_min_px(lod_min_px);
+            renderer.draw_background();
+            renderer.draw_roads_bin_scaled(&roads, 1.0);
+            draw_water(&mut renderer, &water);
+            renderer.pixmap
+        };
+
+        let mut blank = test_renderer(20, 20);
+        blank.draw_background();
+        let blank = blank.pixmap;
+        assert_eq!(draw(1.0).data(), blank.data());
+        assert_ne!(draw(0.0).data(), blank.data());
+    }
+
+    #[test]
+    fn test_draw_stats_count_culled() {
+        let mut renderer = test_renderer(20, 20);
+        // 亚像素居住区道路、有效的高速公路、只有一个点的道路
+        let roads = [
+            3.0, 4.0, 2.0, 5.0, 5.0, 5.4, 5.3, 0.0, 2.0, 1.0, 1.0, 18.0, 18.0, 1.0, 1.0, 3.0, 3.0,
+        ];
+        renderer.draw_roads_bin_scaled(&roads, 1.0);
+        let stats = renderer.take_draw_stats();
+        assert_eq!((stats.features, stats.drawn, stats.culled), (3, 1, 2));
+        assert_eq!((stats.vertices, stats.paths), (4, 1));
+
+        let water = [
+            PolyFeature {
+                exterior: vec![(1.0, 1.0), (18.0, 3.0), (7.0, 17.0)],
+                interiors: vec![],
+            },
+            PolyFeature {
+                exterior: vec![(14.0, 14.0), (14.5, 14.0), (14.5, 14.5)],
+                interiors: vec![],
+            },
+        ];
+        draw_water(&mut renderer, &water);
+        let stats = renderer.take_draw_stats();
+        assert_eq!((stats.features, stats.drawn, stats.culled), (2, 1, 1));
+        assert_eq!((stats.vertices, stats.paths), (6, 1));
+        assert_eq!(renderer.take_draw_stats(), DrawStats::default());
+    }
+
+    #[test]
+    fn test_anti_alias_toggle() {
+        let water = [PolyFeature {
+            exterior: vec![(1.0, 1.0), (18.0, 3.0), (7.0, 17.0)],
+            interiors: vec![],
+        }];
+        let has_blended_edge = |anti_alias: bool| {
+            let mut renderer = test_renderer(20, 20);
+            renderer.set_anti_alias(anti_alias);
+            renderer.draw_background();
+            draw_water(&mut renderer, &water);
+            // 背景白色、水体蓝色：红色通道介于两者之间即为抗锯齿混合的边缘
+            renderer
+                .pixmap
+                .pixels()
+                .iter()
+                .any(|p| p.red() != 0 && p.red() != 255)
+        };
+        assert!(has_blended_edge(true));
+        assert!(!has_blended_edge(false));
+    }
+
+    #[test]
+    fn test_winding_normalized_for_holes_and_overlaps() {
+        let square = |min: f64, max: f64| vec![(min, min), (max, min), (max, max), (min, max)];
+        let draw = |water: &[PolyFeature]| {
+            let mut renderer = test_renderer(20, 20);
+            renderer.draw_background();
+            draw_water(&mut renderer, water);
+            renderer
+        };
+        let is_water = |r: &MapRenderer, x, y| pixel_at(r, x, y).red() == 0;
+
+        // 洞与外环同为逆时针：归一化后仍被挖空
+        let holed = draw(&[PolyFeature {
+            exterior: square(2.0, 18.0),
+            interiors: vec![square(6.0, 14.0)],
+        }]);
+        assert!(is_water(&holed, 4, 4));
+        assert!(!is_water(&holed, 10, 10));
+
+        // 相互重叠的两个水体：重叠处不再被奇偶规则抵消
+        let overlapping = draw(&[
+            PolyFeature {
+                exterior: square(1.0, 12.0),
+                interiors: vec![],
+            },
+            PolyFeature {
+                exterior: square(8.0, 19.0),
+                interiors: vec![],
+            },
+        ]);
+        assert!(is_water(&overlapping, 10, 10));
+    }
+
+    #[test]
+    fn test_clips_geometry_far_outside_canvas() {
+        let mut renderer = test_renderer(20, 20);
+        renderer.draw_background();
+        let (far, near) = (1e12, 10.0);
+        draw_water(
+            &mut renderer,
+            &[PolyFeature {
+                exterior: vec![(-far, -far), (far, -far), (far, far), (-far, far)],
+                interiors: vec![],
+            }],
+        );
+        assert!(renderer.pixmap.pixels().iter().all(|p| p.red() == 0));
+
+        // 穿过画布、两端远在画布外的路线
+        renderer.theme.route_color = Some("#FF0000".to_string());
+        renderer.draw_routes_bin(&[1.0, 2.0, -far, near, far, near], 2.0);
+        assert_eq!(pixel_at(&renderer, 10, 10).red(), 255);
+        assert_eq!(renderer.take_draw_stats().drawn, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_attribution_descenders_sit_above_margin() {
+        let data = crate::fonts::resolve(None).unwrap();
+        let font = Font::from_bytes(&*data, FontSettings::default()).unwrap();
+        let mut renderer = test_renderer(200, 100);
+        renderer.draw_background();
+        let black = tiny_skia::Color::from_rgba8(0, 0, 0, 255);
+        let size = 10.0 * renderer.render_scale as f32;
+        renderer.draw_text_bottom_right(&font, "gjpqy", size, black, 1.0);
+
+        // 最低一行墨迹（含下行笔画）距画布底边为 margin，允许 1 像素的取整误差
+        let width = renderer.render_width() as usize;
+        let pixels = renderer.pixmap.pixels();
+        let lowest = (0..renderer.render_height() as usize)
+            .rev()
+            .find(|&row| {
+                pixels[row * width..(row + 1) * width]
+                    .iter()
+                    .any(|p| p.red() < 128)
+            })
+            .unwrap();
+        let gap = renderer.render_height() as f32 - 1.0 - lowest as f32;
+        assert!((gap - 20.0).abs() <= 1.5, "gap {}", gap);
+    }
+
+    #[test]
+    fn test_round_map_corners() {
+        let mut renderer = test_renderer(100, 100);
+        renderer.pixmap.fill(Color::from_rgba8(255, 0, 0, 255));
+        let margins = Margins {
+            top: 0.1,
+            ..Default::default()
+        };
+        renderer.round_map_corners(margins, 20.0);
+        let is_red = |x, y| pixel_at(&renderer, x, y).blue() == 0;
+
+        // 圆角外为背景色，边的中段与内部保留地图
+        assert!(!is_red(1, 11) && !is_red(98, 98));
+        assert!(is_red(50, 11) && is_red(1, 55) && is_red(50, 50));
+        assert!(is_red(8, 18) && !is_red(3, 13));
+        // 边距以外不受影响
+        assert!(is_red(1, 1));
+    }
+
+    #[test]
+    fn test_night_tint() {
+        // 整个世界：x 覆盖经度 ±180°，太阳直射东经 90° 的赤道
+        let bounds = BoundingBox::new(
+            -WORLD_WIDTH / 2.0,
+            WORLD_WIDTH / 2.0,
+            -WORLD_WIDTH / 4.0,
+            WORLD_WIDTH / 4.0,
+        );
+        let mut renderer =
+            MapRenderer::new(200, 100, test_theme(), bounds, TextPosition::Top).unwrap();
+        renderer.pixmap.fill(Color::WHITE);
+        let sun = SubsolarPoint {
+            lon: 90.0,
+            lat: 0.0,
+        };
+        renderer.draw_night_tint(sun, "#000000", 0.5);
+        let red = |x| pixel_at(&renderer, x, 50).red();
+
+        // 西经 90° 为子夜，叠加半透明黑色；东经 90° 为正午，保持原色
+        assert!((red(50) as i32 - 128).abs() <= 2, "{}", red(50));
+        assert_eq!(red(150), 255);
+        // 晨昏线（经度 0°）附近处于过渡中
+        assert!(red(100) > 128 && red(100) < 255, "{}", red(100));
+    }
+
+    #[test]
+    fn test_polaroid_frame() {
+        let mut renderer = test_renderer(100, 100);
+        renderer.pixmap.fill(Color::from_rgba8(255, 0, 0, 255));
+        let margins = Margins {
+            top: 0.1,
+            right: 0.1,
+            bottom: 0.3,
+            left: 0.1,
+        };
+        renderer.draw_polaroid_frame(margins);
+        let rgb = |x, y| {
+            let p = pixel_at(&renderer, x, y);
+            (p.red(), p.green(), p.blue())
+        };
+
+        assert_eq!(rgb(50, 50), (255, 0, 0));
+        assert_eq!(rgb(5, 5), (255, 255, 255));
+        assert_eq!(rgb(50, 95), (255, 255, 255));
+        // 地图下方紧贴处有阴影，上方没有
+        let (shadow, _, _) = rgb(50, 70);
+        assert!(shadow < 230, "{}", shadow);
+        assert_eq!(rgb(50, 9), (255, 255, 255));
+        assert_eq!(renderer.text_panel_top, Some(140.0));
+    }
+
+    #[test]
+    fn test_logo_anchored_and_scaled() {
+        let mut image = Pixmap::new(4, 2).unwrap();
+        image.fill(Color::from_rgba8(0, 0, 255, 255));
+        let draw = |position: LogoPosition| {
+            let mut renderer = test_renderer(100, 100);
+            renderer.draw_background();
+            let logo = LogoOverlay {
+                image: "logo".to_string(),
+                position,
+                scale: 0.2,
+                margin: 10.0,
+                opacity: 1.0,
+            };
+            renderer.draw_logo(&image, &logo);
+            renderer
+        };
+        let is_blue = |r: &MapRenderer, x, y| pixel_at(r, x, y).red() == 0;
+
+        // 宽 20、高 10，距右下边缘 10
+        let corner = draw(LogoPosition::BottomRight);
+        assert!(is_blue(&corner, 71, 81) && is_blue(&corner, 88, 88));
+        assert!(!is_blue(&corner, 68, 85) && !is_blue(&corner, 85, 78));
+        assert!(!is_blue(&corner, 91, 85) && !is_blue(&corner, 85, 91));
+        let center = draw(LogoPosition::Center);
+        assert!(is_blue(&center, 50, 50) && !is_blue(&center, 50, 57));
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_watermark_covers_canvas() {
+        let data = crate::fonts::resolve(None).unwrap();
+        let marked = |style: WatermarkStyle, opacity: f32| {
+            let mut renderer = test_renderer(120, 80);
+            renderer.draw_background();
+            let watermark = Watermark {
+                text: "PREVIEW".to_string(),
+                style,
+                opacity,
+                angle: -30.0,
+                size: 10.0,
+                color: None,
+            };
+            renderer.draw_watermark(&watermark, &data).unwrap();
+            renderer
+        };
+        // 各象限内被水印覆盖的像素数
+        let quadrants = |renderer: &MapRenderer| {
+            let (w, h) = (renderer.render_width(), renderer.render_height());
+            let mut counts = [0usize; 4];
+            for (i, p) in renderer.pixmap.pixels().iter().enumerate() {
+                if p.red() < 255 {
+                    let (x, y) = (i as u32 % w, i as u32 / w);
+                    counts[usize::from(x >= w / 2) + 2 * usize::from(y >= h / 2)] += 1;
+                }
+            }
+            counts
+        };
+
+        let tiled = quadrants(&marked(WatermarkStyle::Tiled, 0.3));
+        assert!(tiled.iter().all(|&n| n > 0), "{:?}", tiled);
+        // 水印半透明：背景仍可见
+        let renderer = marked(WatermarkStyle::Diagonal, 0.3);
+        assert!(renderer.pixmap.pixels().iter().all(|p| p.red() > 150));
+        assert!(quadrants(&renderer).iter().sum::<usize>() > 0);
+        assert_eq!(quadrants(&marked(WatermarkStyle::Tiled, 0.0)), [0; 4]);
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_text_panel_holds_text() {
+        let data = crate::fonts::resolve(None).unwrap();
+        let mut renderer = test_renderer(200, 200);
+        renderer.draw_background();
+        renderer.fill_text_panel(25.0, "#0000FF");
+        renderer
+            .draw_text("Paris", "France", 48.85, 2.35, &data)
+            .unwrap();
+
+        // 面板以上保持背景色（文字位置设为 top 也被面板版式覆盖），面板内有文字
+        let bg = pixel_at(&renderer, 0, 0);
+        let width = renderer.render_width() as usize;
+        let (map, panel) = renderer.pixmap.pixels().split_at(300 * width);
+        assert!(map.iter().all(|&p| p == bg));
+        assert_eq!(pixel_at(&renderer, 0, 199).blue(), 255);
+        assert!(panel.iter().any(|p| p.blue() != 255));
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_poi_labels_contrast_with_fill() {
+        let data = crate::fonts::resolve(None).unwrap();
+        // 红色标记亮度高于交叉点，编号为黑色；深蓝色标记上为白色
+        assert_eq!(contrast_color(hex_color("#FF0000")), Color::BLACK);
+        assert_eq!(contrast_color(hex_color("#003366")), Color::WHITE);
+
+        let dark_pixels = |labels: PoiLabel| {
+            let mut renderer = test_renderer(40, 40);
+            renderer.draw_background();
+            renderer.set_poi_labels(labels, &data).unwrap();
+            renderer.draw_pois_bin(&[1.0, 20.0, 20.0]);
+            renderer
+                .pixmap
+                .pixels()
+                .iter()
+                .filter(|p| p.red() < 128)
+                .count()
+        };
+        assert_eq!(dark_pixels(PoiLabel::None), 0);
+        assert!(dark_pixels(PoiLabel::Number) > 0);
+    }
+
+    #[test]
+    fn test_dashed_lines_leave_gaps() {
+        let line = [1.0, 2.0, 0.0, 20.0, 40.0, 20.0];
+        let inked = |dash: &[f32]| {
+            let mut renderer = test_renderer(40, 40);
+            renderer.draw_background();
+            renderer.draw_dashed_lines_bin(&line, "#000000", 2.0, dash);
+            (0..40)
+                .filter(|&x| pixel_at(&renderer, x, 20).red() < 128)
+                .count()
+        };
+        // 实线覆盖整行；4 px 实线 + 4 px 空白留出间隙（圆头两端各延伸半个线宽）
+        assert_eq!(inked(&[]), 40);
+        let dashed = inked(&[4.0, 4.0]);
+        assert!((16..=32).contains(&dashed), "{}", dashed);
+        // 无效模式回退为实线
+        assert_eq!(inked(&[0.0]), 40);
+    }
+
+    #[test]
+    fn test_poi_halo_rings_fade_outward() {
+        let draw = |style: HaloStyle| {
+            let mut renderer = test_renderer(40, 40);
+            renderer.draw_background();
+            renderer.set_poi_halo(Some(PoiHalo {
+                style,
+                rings: 2,
+                spacing: 4.0,
+                width: 2.0,
+                opacity: 1.0,
+                falloff: 0.5,
+                color: Some("#0000FF".to_string()),
+            }));
+            renderer.draw_pois_bin(&[1.0, 20.0, 20.0]);
+            renderer
+        };
+        // POI 半径 8：第一环在 12、第二环在 16（逻辑像素），环间露出背景
+        let rings = draw(HaloStyle::Rings);
+        assert_eq!(pixel_at(&rings, 32, 20).red(), 0);
+        assert_eq!(pixel_at(&rings, 34, 20).red(), 255);
+        let outer = pixel_at(&rings, 36, 20).red();
+        assert!((100..=155).contains(&outer), "{}", outer);
+        assert_eq!(pixel_at(&rings, 39, 20).red(), 255);
+
+        // 光晕：8–12 不透明，12–16 半透明
+        let glow = draw(HaloStyle::Glow);
+        assert_eq!(pixel_at(&glow, 30, 20).red(), 0);
+        let outer = pixel_at(&glow, 34, 20).red();
+        assert!((100..=155).contains(&outer), "{}", outer);
+    }
+
+    #[test]
+    fn test_apply_region_mask_hides_outside() {
+        let mut renderer = test_renderer(20, 20);
+        let black = tiny_skia::Color::from_rgba8(0, 0, 0, 255);
+        renderer.pixmap.fill(black);
+
+        // 区域为左半部分（世界坐标与逻辑像素 1:1）
+        let region = vec![PolyFeature {
+            exterior: vec![(0.0, 0.0), (10.0, 0.0), (10.0, 20.0), (0.0, 20.0)],
+            interiors: vec![],
+        }];
+        renderer.apply_region_mask(&region, 0.0);
+
+        // 区域内保持原色，区域外被背景色覆盖
+        assert_eq!(pixel_at(&renderer, 3, 10).red(), 0);
+        assert_eq!(pixel_at(&renderer, 16, 10).red(), 255);
+    }
+
+    #[test]
+    fn test_home_marker_styles() {
+        let black = tiny_skia::Color::from_rgba8(0, 0, 0, 255);
+        let draw = |style: MarkerStyle| {
+            let mut renderer = test_renderer(60, 60);
+            renderer.pixmap.fill(black);
+            let marker = HomeMarker {
+                style,
+                size: 10.0,
+                color: None,
+            };
+            renderer.draw_home_marker((30.0, 30.0), &marker);
+            renderer
+        };
+        let rgb = |p: tiny_skia::PremultipliedColorU8| (p.red(), p.green(), p.blue());
+        let (red, white) = ((255, 0, 0), (255, 255, 255));
+
+        // 环：中心露出背景色，环上为 poi_color，外侧为背景色衬边
+        let ring = draw(MarkerStyle::Ring);
+        assert_eq!(rgb(pixel_at(&ring, 30, 30)), white);
+        assert_eq!(rgb(pixel_at(&ring, 38, 30)), red);
+        assert_eq!(rgb(pixel_at(&ring, 41, 30)), white);
+        assert_eq!(rgb(pixel_at(&ring, 50, 30)), (0, 0, 0));
+
+        let dot = draw(MarkerStyle::Dot);
+        assert_eq!(rgb(pixel_at(&dot, 30, 30)), red);
+        let heart = draw(MarkerStyle::Heart);
+        assert_eq!(rgb(pixel_at(&heart, 30, 30)), red);
+        assert_eq!(rgb(pixel_at(&heart, 30, 10)), (0, 0, 0));
+    }
 }
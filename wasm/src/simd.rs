@@ -0,0 +1,184 @@
+//! SIMD 加速的坐标变换
+//!
+//! 启用 `simd` feature 且以 `-C target-feature=+simd128` 编译到 wasm32 时，
+//! 使用 `core::arch::wasm32` 指令批量处理：屏幕变换每次处理两个点，f64x2 完成
+//! 平移与缩放后降为 f32 并拼入同一个 f32x4 加上偏移；投影每次处理两个点的经度。
+//! 运算顺序与标量实现相同，结果逐位一致（见 `test_batch_matches_scalar`）。
+//!
+//! 基准（test_bench_screen_transform，100 万点，wasm32-wasip1 release，Node 20，取三轮中的最后一轮）：
+//! 逐点标量约 12.3ms，`apply_all` 约 6.4ms
+
+/// 世界坐标到屏幕坐标的仿射变换：screen = (p - origin) * scale + offset
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenTransform {
+    pub origin: (f64, f64),
+    pub scale: (f64, f64),
+    pub offset: (f32, f32),
+}
+
+impl ScreenTransform {
+    #[inline]
+    pub fn apply(&self, p: (f64, f64)) -> (f32, f32) {
+        (
+            ((p.0 - self.origin.0) * self.scale.0) as f32 + self.offset.0,
+            ((p.1 - self.origin.1) * self.scale.1) as f32 + self.offset.1,
+        )
+    }
+
+    /// 保留 f64 精度的变换，用于裁剪超出画布的几何
+    #[inline]
+    pub fn apply_f64(&self, p: (f64, f64)) -> (f64, f64) {
+        (
+            (p.0 - self.origin.0) * self.scale.0 + f64::from(self.offset.0),
+            (p.1 - self.origin.1) * self.scale.1 + f64::from(self.offset.1),
+        )
+    }
+
+    /// 批量变换，结果追加到 `out`
+    pub fn apply_all(&self, points: &[(f64, f64)], out: &mut Vec<(f32, f32)>) {
+        #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+        return wasm128::apply_all(self, points, out);
+        #[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+        out.extend(points.iter().map(|&p| self.apply(p)));
+    }
+}
+
+/// 原地将经纬度投影为 Web Mercator（与 `project_point` 结果一致）
+pub fn project_in_place(coords: &mut [(f64, f64)]) {
+    #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+    return wasm128::project_in_place(coords);
+    #[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+    for coord in coords.iter_mut() {
+        *coord = crate::projection::project_point(coord.0, coord.1);
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm128 {
+    use super::ScreenTransform;
+    use crate::projection::{EARTH_RADIUS, normalize_lon, project_point};
+    use core::arch::wasm32::*;
+    use std::f64::consts::PI;
+
+    /// 每次处理两个点：两组 f64x2 变换降为 f32 后拼成 (x0, y0, x1, y1)，一次加上偏移
+    pub fn apply_all(t: &ScreenTransform, points: &[(f64, f64)], out: &mut Vec<(f32, f32)>) {
+        let origin = f64x2(t.origin.0, t.origin.1);
+        let scale = f64x2(t.scale.0, t.scale.1);
+        let offset = f32x4(t.offset.0, t.offset.1, t.offset.0, t.offset.1);
+        out.reserve(points.len());
+        let mut pairs = points.chunks_exact(2);
+        for pair in &mut pairs {
+            let a = f64x2_mul(f64x2_sub(f64x2(pair[0].0, pair[0].1), origin), scale);
+            let b = f64x2_mul(f64x2_sub(f64x2(pair[1].0, pair[1].1), origin), scale);
+            let ab =
+                i32x4_shuffle::<0, 1, 4, 5>(f32x4_demote_f64x2_zero(a), f32x4_demote_f64x2_zero(b));
+            let s = f32x4_add(ab, offset);
+            out.push((f32x4_extract_lane::<0>(s), f32x4_extract_lane::<1>(s)));
+            out.push((f32x4_extract_lane::<2>(s), f32x4_extract_lane::<3>(s)));
+        }
+        out.extend(pairs.remainder().iter().map(|&p| t.apply(p)));
+    }
+
+    /// 经度换算每次处理两个点；纬度需要 tan / asinh，仍为标量计算
+    pub fn project_in_place(coords: &mut [(f64, f64)]) {
+        let deg_to_rad = f64x2_splat(PI / 180.0);
+        let radius = f64x2_splat(EARTH_RADIUS);
+        let lat_to_y = |lat: f64| (lat * (PI / 180.0)).tan().asinh() * EARTH_RADIUS;
+        let mut pairs = coords.chunks_exact_mut(2);
+        for pair in &mut pairs {
+            let lons = f64x2(normalize_lon(pair[0].0), normalize_lon(pair[1].0));
+            let xs = f64x2_mul(f64x2_mul(lons, deg_to_rad), radius);
+            pair[0] = (f64x2_extract_lane::<0>(xs), lat_to_y(pair[0].1));
+            pair[1] = (f64x2_extract_lane::<1>(xs), lat_to_y(pair[1].1));
+        }
+        for coord in pairs.into_remainder() {
+            *coord = project_point(coord.0, coord.1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::project_point;
+
+    /// 伪随机坐标：经度 [-200, 200)，纬度 [-85, 85)，覆盖跨越 ±180° 的情况
+    fn random_points(n: usize) -> Vec<(f64, f64)> {
+        let mut seed = 12345u32;
+        let mut rand = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) as f64 / (1u32 << 24) as f64
+        };
+        (0..n)
+            .map(|_| (rand() * 400.0 - 200.0, rand() * 170.0 - 85.0))
+            .collect()
+    }
+
+    // 原生目标上验证标量实现；以 simd feature 和 +simd128 编译到 wasm32 时验证 SIMD 实现
+    // （如 `cargo test --target wasm32-wasip1 --features simd`，需设置 RUSTFLAGS）
+    #[test]
+    fn test_batch_matches_scalar() {
+        let t = ScreenTransform {
+            origin: (10.0, 20.0),
+            scale: (2.0, -2.0),
+            offset: (0.0, 100.0),
+        };
+        let points = [(10.0, 20.0), (15.0, 30.0), (12.5, 70.0)];
+        let mut out = Vec::new();
+        t.apply_all(&points, &mut out);
+        assert_eq!(out, vec![(0.0, 100.0), (10.0, 80.0), (5.0, 0.0)]);
+
+        let mut coords = [(116.4, 39.9), (-74.0, 40.7), (181.0, -10.0)];
+        project_in_place(&mut coords);
+        assert_eq!(coords[1], project_point(-74.0, 40.7));
+        assert_eq!(coords[2], project_point(181.0, -10.0));
+
+        // 奇数个点，覆盖成对处理后的余数
+        let points = random_points(1001);
+        let t = ScreenTransform {
+            origin: (-30.0, 12.5),
+            scale: (3.7, -1.3),
+            offset: (512.0, 384.0),
+        };
+        let mut out = Vec::new();
+        t.apply_all(&points, &mut out);
+        let expected: Vec<(f32, f32)> = points.iter().map(|&p| t.apply(p)).collect();
+        assert_eq!(out, expected);
+
+        let mut coords = points.clone();
+        project_in_place(&mut coords);
+        for (coord, &(lon, lat)) in coords.iter().zip(&points) {
+            assert_eq!(*coord, project_point(lon, lat));
+        }
+    }
+
+    /// 批量屏幕变换与逐点标量变换的耗时（cargo test --release -- --ignored --nocapture）
+    #[test]
+    #[ignore]
+    fn test_bench_screen_transform() {
+        let points = random_points(1_000_000);
+        let t = ScreenTransform {
+            origin: (0.0, 0.0),
+            scale: (0.5, -0.5),
+            offset: (1000.0, 1000.0),
+        };
+        let mut out = Vec::with_capacity(points.len());
+        for round in 0..3 {
+            out.clear();
+            let start = std::time::Instant::now();
+            out.extend(points.iter().map(|&p| t.apply(std::hint::black_box(p))));
+            let scalar = start.elapsed();
+            std::hint::black_box(&out);
+
+            out.clear();
+            let start = std::time::Instant::now();
+            t.apply_all(std::hint::black_box(&points), &mut out);
+            let batch = start.elapsed();
+            std::hint::black_box(&out);
+            println!(
+                "round {}: scalar {:?}, apply_all {:?}",
+                round, scalar, batch
+            );
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! 几何简化
+//!
+//! 在投影后的世界坐标（Web Mercator 米）上执行，容差由输出像素换算为米，
+//! 避免对数百万个亚像素顶点做屏幕变换和描边。
+//! 道路使用 Douglas-Peucker；水体 / 公园多边形使用 Visvalingam–Whyatt，
+//! 并保持环的有效性与相邻多边形的公共边界。
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+type Point = (f64, f64);
+
+/// 顶点的精确哈希键
+pub type PointKey = (u64, u64);
+
+pub fn point_key(p: Point) -> PointKey {
+    (p.0.to_bits(), p.1.to_bits())
+}
+
+/// 点到线段的距离平方
+pub fn segment_dist_sq(p: Point, a: Point, b: Point) -> f64 {
+    let c = closest_on_segment(p, a, b);
+    let (ex, ey) = (p.0 - c.0, p.1 - c.1);
+    ex * ex + ey * ey
+}
+
+/// 线段 ab 上距 p 最近的点
+pub fn closest_on_segment(p: Point, a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq == 0.0 {
+        0.0 // a == b，退化为点距离
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+    };
+    (a.0 + t * dx, a.1 + t * dy)
+}
+
+/// Douglas-Peucker 折线简化（迭代实现，保留首尾点）
+///
+/// `tolerance` 与坐标同单位；不大于 0 时原样返回
+pub fn douglas_peucker(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() < 3 || tolerance <= 0.0 {
+        return points.to_vec();
+    }
+    let tolerance_sq = tolerance * tolerance;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let (mut max_dist_sq, mut max_idx) = (0.0, start);
+        for i in start + 1..end {
+            let d = segment_dist_sq(points[i], points[start], points[end]);
+            if d > max_dist_sq {
+                (max_dist_sq, max_idx) = (d, i);
+            }
+        }
+        if max_dist_sq > tolerance_sq {
+            keep[max_idx] = true;
+            stack.push((start, max_idx));
+            stack.push((max_idx, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, k)| k.then_some(p))
+        .collect()
+}
+
+/// 出现在两个及以上环中的顶点（相邻多边形的公共边界）
+pub fn shared_vertices<'a>(rings: impl IntoIterator<Item = &'a [Point]>) -> HashSet<PointKey> {
+    let mut first_ring: HashMap<PointKey, usize> = HashMap::new();
+    let mut shared = HashSet::new();
+    for (ring_idx, ring) in rings.into_iter().enumerate() {
+        for &p in ring {
+            let key = point_key(p);
+            if *first_ring.entry(key).or_insert(ring_idx) != ring_idx {
+                shared.insert(key);
+            }
+        }
+    }
+    shared
+}
+
+/// 可放入堆中的面积（按 total_cmp 排序）
+#[derive(PartialEq)]
+struct Area(f64);
+
+impl Eq for Area {}
+
+impl PartialOrd for Area {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Area {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+fn triangle_area(a: Point, b: Point, c: Point) -> f64 {
+    ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.0
+}
+
+/// p 是否在三角形 abc 内（含边界）
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    let cross =
+        |o: Point, u: Point, v: Point| (u.0 - o.0) * (v.1 - o.1) - (v.0 - o.0) * (u.1 - o.1);
+    let (d1, d2, d3) = (cross(a, b, p), cross(b, c, p), cross(c, a, p));
+    let has_neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_neg && has_pos)
+}
+
+/// 顶点网格索引，用于快速查找落入待删三角形的顶点
+struct VertexGrid {
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl VertexGrid {
+    /// 三角形包围盒覆盖的格子超过该数量时退化为线性扫描
+    const MAX_QUERY_CELLS: i64 = 64;
+
+    fn new(points: &[Point], cell: f64) -> Self {
+        let mut cells: HashMap<_, Vec<usize>> = HashMap::new();
+        for (i, &p) in points.iter().enumerate() {
+            cells.entry(Self::cell_of(p, cell)).or_default().push(i);
+        }
+        Self { cell, cells }
+    }
+
+    fn cell_of(p: Point, cell: f64) -> (i64, i64) {
+        ((p.0 / cell).floor() as i64, (p.1 / cell).floor() as i64)
+    }
+
+    /// 包围盒内的候选顶点；范围过大时返回 None
+    fn query(&self, min: Point, max: Point) -> Option<impl Iterator<Item = usize> + '_> {
+        let (x0, y0) = Self::cell_of(min, self.cell);
+        let (x1, y1) = Self::cell_of(max, self.cell);
+        if (x1 - x0 + 1).saturating_mul(y1 - y0 + 1) > Self::MAX_QUERY_CELLS {
+            return None;
+        }
+        Some(
+            (x0..=x1)
+                .flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+                .filter_map(|c| self.cells.get(&c))
+                .flatten()
+                .copied(),
+        )
+    }
+}
+
+/// Visvalingam–Whyatt 闭合环简化
+///
+/// 反复移除有效面积最小的顶点，直到最小面积不小于 `min_area`。为保持环有效：
+/// 至少保留 3 个顶点；移除会让其它顶点落入被删三角形（即产生自相交）时跳过该顶点；
+/// `locked` 中的顶点（与其它环共享的边界）永不移除，相邻多边形的公共边简化结果一致。
+pub fn visvalingam_ring(ring: &[Point], min_area: f64, locked: &HashSet<PointKey>) -> Vec<Point> {
+    let closed = ring.len() > 1 && ring.first() == ring.last();
+    let pts = &ring[..ring.len() - usize::from(closed)];
+    let n = pts.len();
+    if n <= 3 || min_area <= 0.0 {
+        return ring.to_vec();
+    }
+
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut alive = vec![true; n];
+    let mut version = vec![0u32; n];
+    let mut live = n;
+    let grid = VertexGrid::new(pts, min_area.sqrt() * 2.0);
+
+    let mut heap = BinaryHeap::with_capacity(n);
+    for i in 0..n {
+        if !locked.contains(&point_key(pts[i])) {
+            let area = triangle_area(pts[prev[i]], pts[i], pts[next[i]]);
+            heap.push(Reverse((Area(area), i, 0u32)));
+        }
+    }
+
+    while let Some(Reverse((Area(area), i, v))) = heap.pop() {
+        if !alive[i] || v != version[i] {
+            continue;
+        }
+        if area >= min_area || live <= 3 {
+            break;
+        }
+        let (p, nx) = (prev[i], next[i]);
+        let (a, b, c) = (pts[p], pts[i], pts[nx]);
+
+        // 被删三角形内若有其它顶点，移除后新边会与环相交
+        let min = (a.0.min(b.0).min(c.0), a.1.min(b.1).min(c.1));
+        let max = (a.0.max(b.0).max(c.0), a.1.max(b.1).max(c.1));
+        let blocks = |j: usize| {
+            alive[j]
+                && j != p
+                && j != i
+                && j != nx
+                && pts[j] != a
+                && pts[j] != b
+                && pts[j] != c
+                && in_triangle(pts[j], a, b, c)
+        };
+        let blocked = match grid.query(min, max) {
+            Some(mut candidates) => candidates.any(blocks),
+            None => (0..n).any(blocks),
+        };
+        if blocked {
+            continue;
+        }
+
+        alive[i] = false;
+        live -= 1;
+        next[p] = nx;
+        prev[nx] = p;
+        // 相邻顶点的面积不小于刚移除的面积，保证移除顺序单调
+        for j in [p, nx] {
+            if !locked.contains(&point_key(pts[j])) {
+                version[j] += 1;
+                let new_area = triangle_area(pts[prev[j]], pts[j], pts[next[j]]).max(area);
+                heap.push(Reverse((Area(new_area), j, version[j])));
+            }
+        }
+    }
+
+    let start = (0..n).find(|&i| alive[i]).unwrap_or(0);
+    let mut out = Vec::with_capacity(live + 1);
+    let mut i = start;
+    loop {
+        out.push(pts[i]);
+        i = next[i];
+        if i == start {
+            break;
+        }
+    }
+    if closed {
+        out.push(out[0]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_douglas_peucker() {
+        let line = [(0.0, 0.0), (1.0, 0.1), (2.0, -0.1), (3.0, 5.0), (4.0, 6.0)];
+        assert_eq!(
+            douglas_peucker(&line, 0.5),
+            vec![(0.0, 0.0), (2.0, -0.1), (3.0, 5.0), (4.0, 6.0)]
+        );
+        assert_eq!(douglas_peucker(&line, 0.0), line.to_vec());
+        assert_eq!(douglas_peucker(&line, 100.0), vec![(0.0, 0.0), (4.0, 6.0)]);
+    }
+
+    #[test]
+    fn test_visvalingam_keeps_ring_valid() {
+        // 近似正方形，边上有细小锯齿
+        let ring = [
+            (0.0, 0.0),
+            (5.0, 0.1),
+            (10.0, 0.0),
+            (10.1, 5.0),
+            (10.0, 10.0),
+            (5.0, 9.9),
+            (0.0, 10.0),
+            (-0.1, 5.0),
+            (0.0, 0.0),
+        ];
+        let out = visvalingam_ring(&ring, 1.0, &HashSet::new());
+        assert_eq!(out.len(), 5);
+        assert_eq!(out.first(), out.last());
+
+        // 阈值再大也至少保留 3 个顶点
+        let tiny = visvalingam_ring(&ring, 1e9, &HashSet::new());
+        assert_eq!(tiny.len(), 4);
+    }
+
+    #[test]
+    fn test_visvalingam_preserves_shared_border() {
+        let left = [
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (5.0, 5.0),
+            (5.1, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ];
+        let right = [
+            (5.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (5.1, 10.0),
+            (5.0, 5.0),
+            (5.0, 0.0),
+        ];
+        let locked = shared_vertices([&left[..], &right[..]]);
+        assert!(locked.contains(&point_key((5.0, 5.0))));
+        let out = visvalingam_ring(&left, 100.0, &locked);
+        assert!(out.contains(&(5.0, 5.0)));
+    }
+}
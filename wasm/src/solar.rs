@@ -0,0 +1,118 @@
+//! 太阳位置
+//!
+//! 按 UTC 时刻计算太阳直射点与任一地点的太阳高度角（天文年历的低精度公式，
+//! 1950–2050 年间误差约 0.01°），用于绘制晨昏线与按高度角选取天空渐变色。
+
+use crate::projection::normalize_lon;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+/// Unix 纪元（1970-01-01T00:00Z）的儒略日
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// J2000.0 历元的儒略日
+const J2000_JD: f64 = 2_451_545.0;
+
+/// 民用晨昏的太阳高度角下限（度）：高度角从 0 降到 -6° 之间夜色逐渐加深
+pub const TWILIGHT_DEG: f64 = 6.0;
+
+/// (太阳高度角, 颜色)：夜 → 航海 / 民用晨昏 → 日落 → 金色时刻 → 白昼
+const SKY_STOPS: [(f64, [u8; 3]); 7] = [
+    (-18.0, [0x0B, 0x10, 0x26]),
+    (-12.0, [0x1B, 0x24, 0x4A]),
+    (-6.0, [0x4A, 0x3B, 0x6E]),
+    (-2.0, [0xC8, 0x6B, 0x7A]),
+    (2.0, [0xF2, 0x9E, 0x4C]),
+    (8.0, [0xF6, 0xD3, 0x8A]),
+    (20.0, [0x9C, 0xC8, 0xE8]),
+];
+
+/// 太阳直射点（度）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubsolarPoint {
+    pub lon: f64,
+    /// 即太阳赤纬
+    pub lat: f64,
+}
+
+/// 计算 `timestamp_ms`（Unix 毫秒，UTC）时刻的太阳直射点
+pub fn subsolar_point(timestamp_ms: f64) -> SubsolarPoint {
+    let d = timestamp_ms / MS_PER_DAY + UNIX_EPOCH_JD - J2000_JD;
+    // 平近点角与平黄经
+    let g = (357.529 + 0.985_600_28 * d).to_radians();
+    let q = 280.459 + 0.985_647_36 * d;
+    // 视黄经与黄赤交角
+    let l = (q + 1.915 * g.sin() + 0.020 * (2.0 * g).sin()).to_radians();
+    let e = (23.439 - 0.000_000_36 * d).to_radians();
+
+    let right_ascension = (e.cos() * l.sin()).atan2(l.cos()).to_degrees();
+    let declination = (e.sin() * l.sin()).asin().to_degrees();
+    // 格林尼治平恒星时（度）
+    let gmst = 280.460_618_37 + 360.985_647_366_29 * d;
+    SubsolarPoint {
+        lon: normalize_lon(right_ascension - gmst),
+        lat: declination,
+    }
+}
+
+/// 太阳在 (`lon`, `lat`) 处的高度角（度，负值表示在地平线以下）
+pub fn altitude(sun: SubsolarPoint, lon: f64, lat: f64) -> f64 {
+    let (phi, declination) = (lat.to_radians(), sun.lat.to_radians());
+    let hour_angle = (lon - sun.lon).to_radians();
+    (phi.sin() * declination.sin() + phi.cos() * declination.cos() * hour_angle.cos())
+        .clamp(-1.0, 1.0)
+        .asin()
+        .to_degrees()
+}
+
+/// 夜色程度（0 为白昼，1 为完全入夜），在民用晨昏内平滑过渡；无效高度角视为白昼
+pub fn night_fraction(altitude: f64) -> f64 {
+    if !altitude.is_finite() {
+        return 0.0;
+    }
+    let t = (-altitude / TWILIGHT_DEG).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// 按太阳高度角取天空颜色（#RRGGBB），在相邻色标之间线性插值
+pub fn sky_color(altitude: f64) -> String {
+    let (first, last) = (SKY_STOPS[0], SKY_STOPS[SKY_STOPS.len() - 1]);
+    let [r, g, b] = if altitude.is_nan() || altitude <= first.0 {
+        first.1
+    } else if let Some(pair) = SKY_STOPS.windows(2).find(|pair| altitude < pair[1].0) {
+        let ((a0, c0), (a1, c1)) = (pair[0], pair[1]);
+        let t = (altitude - a0) / (a1 - a0);
+        std::array::from_fn(|i| (c0[i] as f64 + (c1[i] as f64 - c0[i] as f64) * t).round() as u8)
+    } else {
+        last.1
+    };
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsolar_point_and_altitude() {
+        // 2024-06-21T12:00:00Z：夏至，直射点在北回归线、格林尼治经线附近
+        let solstice = subsolar_point(1_718_971_200_000.0);
+        assert!((solstice.lat - 23.44).abs() < 0.05, "{:?}", solstice);
+        assert!(solstice.lon.abs() < 1.0, "{:?}", solstice);
+        // 2024-03-20T03:06:00Z：春分，赤纬约为 0
+        assert!(subsolar_point(1_710_903_960_000.0).lat.abs() < 0.05);
+
+        assert!((altitude(solstice, solstice.lon, solstice.lat) - 90.0).abs() < 1e-9);
+        // 对跖点为子夜，太阳在地平线下 90°
+        assert!((altitude(solstice, solstice.lon + 180.0, -solstice.lat) + 90.0).abs() < 1e-9);
+
+        assert_eq!(night_fraction(10.0), 0.0);
+        assert_eq!(night_fraction(-TWILIGHT_DEG), 1.0);
+        assert!((night_fraction(-TWILIGHT_DEG / 2.0) - 0.5).abs() < 1e-12);
+        assert_eq!(night_fraction(f64::NAN), 0.0);
+
+        assert_eq!(sky_color(-40.0), "#0B1026");
+        assert_eq!(sky_color(60.0), "#9CC8E8");
+        assert_eq!(sky_color(-12.0), "#1B244A");
+        // -15° 位于 -18° 与 -12° 的正中
+        assert_eq!(sky_color(-15.0), "#131A38");
+    }
+}
@@ -0,0 +1,154 @@
+//! GeoJSON 流式切分
+//!
+//! 逐字节扫描分块到达的 FeatureCollection（或顶层 Feature 数组），
+//! 每凑齐一个完整的 Feature 对象就交给回调解析，随后丢弃其字节，
+//! 峰值内存只与单个要素大小相关，而不是整个响应体。
+
+/// 增量式 Feature 切分器
+#[derive(Default)]
+pub struct FeatureSplitter {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// 根节点第一层中最近读完的字符串（用于识别 "features" 键）
+    root_string: Vec<u8>,
+    last_root_key: Option<Vec<u8>>,
+    /// "features" 数组所在的嵌套深度（进入数组后的 depth）
+    features_depth: Option<usize>,
+    /// 当前正在收集的 Feature 字节
+    current: Vec<u8>,
+    capturing: bool,
+    feature_count: usize,
+}
+
+impl FeatureSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已切分出的 Feature 数量
+    pub fn feature_count(&self) -> usize {
+        self.feature_count
+    }
+
+    /// 推入一段字节，每得到一个完整 Feature 就调用一次 `on_feature`
+    pub fn push(
+        &mut self,
+        chunk: &[u8],
+        mut on_feature: impl FnMut(&[u8]) -> Result<(), String>,
+    ) -> Result<(), String> {
+        for &b in chunk {
+            if self.capturing {
+                self.current.push(b);
+            }
+
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if b == b'\\' {
+                    self.escaped = true;
+                } else if b == b'"' {
+                    self.in_string = false;
+                } else if self.depth == 1 && !self.capturing {
+                    self.root_string.push(b);
+                }
+                continue;
+            }
+
+            match b {
+                b'"' => {
+                    self.in_string = true;
+                    if self.depth == 1 {
+                        self.root_string.clear();
+                    }
+                }
+                b':' if self.depth == 1 => {
+                    self.last_root_key = Some(std::mem::take(&mut self.root_string));
+                }
+                b'{' | b'[' => {
+                    // 顶层直接是 Feature 数组
+                    if self.depth == 0 && b == b'[' {
+                        self.features_depth = Some(1);
+                    }
+                    if self.depth == 1
+                        && b == b'['
+                        && self.features_depth.is_none()
+                        && self.last_root_key.as_deref() == Some(b"features")
+                    {
+                        self.features_depth = Some(2);
+                    }
+                    if b == b'{' && !self.capturing && Some(self.depth) == self.features_depth {
+                        self.capturing = true;
+                        self.current.clear();
+                        self.current.push(b);
+                    }
+                    self.depth += 1;
+                }
+                b'}' | b']' => {
+                    if self.depth == 0 {
+                        return Err("GeoJSON stream: unbalanced closing bracket".to_string());
+                    }
+                    self.depth -= 1;
+                    if self.capturing && Some(self.depth) == self.features_depth {
+                        self.capturing = false;
+                        self.feature_count += 1;
+                        on_feature(&self.current)?;
+                        self.current = Vec::new();
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 结束输入，检查流是否完整
+    pub fn finish(&self) -> Result<(), String> {
+        if self.depth != 0 || self.in_string {
+            return Err("GeoJSON stream ended before the document was complete".to_string());
+        }
+        if self.features_depth.is_none() {
+            return Err("GeoJSON stream contains no 'features' array".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_all(doc: &[u8], chunk: usize) -> Result<Vec<String>, String> {
+        let mut splitter = FeatureSplitter::new();
+        let mut out = Vec::new();
+        for c in doc.chunks(chunk) {
+            splitter.push(c, |f| {
+                out.push(String::from_utf8(f.to_vec()).unwrap());
+                Ok(())
+            })?;
+        }
+        splitter.finish()?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_splits_features_across_chunk_boundaries() {
+        let doc = br#"{"type":"FeatureCollection","name":"a \"features\" [x]","features":[
+            {"type":"Feature","properties":{"name":"}{"},"geometry":{"type":"Point","coordinates":[1,2]}},
+            {"type":"Feature","properties":{},"geometry":null}
+        ],"bbox":[0,0,1,1]}"#;
+        for chunk in [1, 3, 7, doc.len()] {
+            let features = split_all(doc, chunk).unwrap();
+            assert_eq!(features.len(), 2);
+            assert!(features[0].starts_with('{') && features[0].ends_with('}'));
+            assert!(features[0].contains(r#""name":"}{""#));
+        }
+    }
+
+    #[test]
+    fn test_top_level_array_and_errors() {
+        assert_eq!(split_all(br#"[{"a":1},{"b":[2]}]"#, 4).unwrap().len(), 2);
+        assert!(split_all(br#"{"features":[{"a":1}"#, 4).is_err());
+        assert!(split_all(br#"{"type":"Feature"}"#, 4).is_err());
+    }
+}
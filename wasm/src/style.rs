@@ -0,0 +1,92 @@
+//! 基于标签的图层分类规则
+//!
+//! 规则按顺序匹配，第一条命中的规则决定要素所属图层。
+//! 默认规则与前端 presets 的 Overpass 查询条件一致。
+
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// 分类目标图层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StyleLayer {
+    Roads,
+    Water,
+    Parks,
+}
+
+/// 单条分类规则：标签 `key` 取 `values` 中任一值（为空时任意值均可）即归入 `layer`
+#[derive(Debug, Clone, Deserialize)]
+pub struct StyleRule {
+    pub key: String,
+    #[serde(default)]
+    pub values: Vec<String>,
+    pub layer: StyleLayer,
+}
+
+impl StyleRule {
+    fn new(key: &str, values: &[&str], layer: StyleLayer) -> Self {
+        Self {
+            key: key.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+            layer,
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        self.values.is_empty() || self.values.iter().any(|v| v == value)
+    }
+}
+
+/// 默认规则（面要素在前，道路兜底）
+pub static DEFAULT_RULES: LazyLock<Vec<StyleRule>> = LazyLock::new(|| {
+    vec![
+        StyleRule::new("natural", &["water"], StyleLayer::Water),
+        StyleRule::new("waterway", &["riverbank", "dock"], StyleLayer::Water),
+        StyleRule::new("landuse", &["reservoir", "basin"], StyleLayer::Water),
+        StyleRule::new(
+            "leisure",
+            &["park", "garden", "nature_reserve"],
+            StyleLayer::Parks,
+        ),
+        StyleRule::new("highway", &[], StyleLayer::Roads),
+    ]
+});
+
+/// 按规则为一组标签分类，`tag` 返回指定键的标签值
+pub fn classify<'a>(
+    rules: &[StyleRule],
+    tag: impl Fn(&str) -> Option<&'a str>,
+) -> Option<StyleLayer> {
+    rules
+        .iter()
+        .find(|rule| tag(&rule.key).is_some_and(|v| rule.matches(v)))
+        .map(|rule| rule.layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_first_match_wins() {
+        let tags = |k: &str| match k {
+            "highway" => Some("footway"),
+            "leisure" => Some("park"),
+            _ => None,
+        };
+        assert_eq!(classify(&DEFAULT_RULES, tags), Some(StyleLayer::Parks));
+        assert_eq!(
+            classify(&DEFAULT_RULES, |k| (k == "leisure").then_some("pitch")),
+            None
+        );
+
+        let custom: Vec<StyleRule> =
+            serde_json::from_str(r#"[{"key":"landuse","values":["forest"],"layer":"parks"}]"#)
+                .unwrap();
+        assert_eq!(
+            classify(&custom, |k| (k == "landuse").then_some("forest")),
+            Some(StyleLayer::Parks)
+        );
+    }
+}
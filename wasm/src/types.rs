@@ -1,44 +1,33 @@
+use crate::error::{RenderError, RenderErrorCode};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
+#[cfg(js_host)]
+use tsify::Tsify;
+#[cfg(js_host)]
 use wasm_bindgen::prelude::*;
 
-/// Python 标准输出的参考高度（12" × 16" @ 300 DPI）
-/// 用于计算动态的道路线宽缩放因子
-const PYTHON_STANDARD_HEIGHT_PX: f32 = 4800.0;
-
-/// 分辨率缩放因子 - 用于高分辨率输出（如 8x 放大）
-/// 当输出分辨率增加 N 倍时，道路线宽也需要相应增加以保持视觉一致性
-// const RESOLUTION_SCALE: f32 = 8.0;
-
-/// 计算道路线宽的动态缩放因子
-///
-/// 基于选定的输出尺寸和前端缩放倍数，自动计算出合理的道路线宽缩放因子。
-/// 这样可以确保无论前端如何调整缩放比例，道路的相对粗细始终与 Python 版本保持一致。
-///
-/// # 参数
-/// - `selected_size_height`: 选定尺寸的原始高度（像素），例如 A4 Portrait 的 3508
-/// - `frontend_scale`: 前端应用的缩放倍数，例如 8
+/// 道路线宽的参考输出高度：Python 版标准输出 12" × 16" @ 300 DPI
 ///
-/// # 返回值
-/// 道路线宽的缩放因子，基于实际输出分辨率与 Python 标准输出的比例
+/// 线宽模型：输出像素线宽 = 基础线宽（`RoadType::base_width`）× 输出高度 / 参考高度
+/// × `road_width_boost`；绘制时再乘以超采样倍数。线宽因此与纸面尺寸 × DPI 成正比，
+/// 同一张海报无论以何种分辨率导出，道路的相对粗细都相同。
+pub const ROAD_WIDTH_REFERENCE_HEIGHT_PX: f32 = 4800.0;
+
+/// 计算道路线宽的缩放因子（见 `ROAD_WIDTH_REFERENCE_HEIGHT_PX` 的线宽模型）
 ///
 /// # 示例
 /// ```ignore
-/// // 前端已计算：selected_size_height = 3508 * 2 = 7016
-/// let scale = calculate_road_width_scale(7016.0, 2.0);
-/// // 缩放因子 = 7016 / 4800 = 1.462
-/// let width = road_type.get_width_scaled(scale);  // 使用动态缩放
+/// // A4 Portrait @ 300 DPI：3508 / 4800 = 0.731
+/// let scale = calculate_road_width_scale(3508.0, 1.0);
+/// let width = RoadType::Primary.get_width_scaled(scale); // 0.731 输出像素
 /// ```
-pub fn calculate_road_width_scale(
-    selected_size_height: f32,
-    _frontend_scale: f32,
-    boost: f32,
-) -> f32 {
-    // selected_size_height 已经包含倍数，直接除以标准高度，并加上额外的权重增强
-    (selected_size_height / PYTHON_STANDARD_HEIGHT_PX) * boost
+pub fn calculate_road_width_scale(output_height_px: f32, boost: f32) -> f32 {
+    output_height_px / ROAD_WIDTH_REFERENCE_HEIGHT_PX * boost
 }
 
 /// 主题配色方案
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct Theme {
     pub bg: String,
     pub text: String,
@@ -52,9 +41,75 @@ pub struct Theme {
     pub road_tertiary: String,
     pub road_residential: String,
     pub road_default: String,
+    // 路线叠加层颜色（可选，缺省时使用文字颜色）
+    #[serde(default)]
+    pub route_color: Option<String>,
+    // 建筑物颜色（可选，缺省时使用公园颜色）
+    #[serde(default)]
+    pub buildings: Option<String>,
+}
+
+/// 默认主题：与前端首个预设 Nordic Frost 相同
+impl Default for Theme {
+    fn default() -> Self {
+        let c = |hex: &str| hex.to_string();
+        Theme {
+            bg: c("#F0F4F7"),
+            text: c("#2C3E50"),
+            gradient_color: c("#F0F4F7"),
+            poi_color: c("#00A8E8"),
+            water: c("#D1EAF0"),
+            parks: c("#E2E9E1"),
+            road_motorway: c("#2C3E50"),
+            road_primary: c("#5D6D7E"),
+            road_secondary: c("#85929E"),
+            road_tertiary: c("#AEB6BF"),
+            road_residential: c("#D6DBDF"),
+            road_default: c("#AEB6BF"),
+            route_color: None,
+            buildings: None,
+        }
+    }
+}
+
+impl Theme {
+    /// 主题的 JSON Schema（draft-07）
+    ///
+    /// 字段列表取自默认主题的序列化结果，与结构体定义保持同步；
+    /// 值为 null 的字段（`Option`）为可选，其余为必填
+    pub fn json_schema() -> serde_json::Value {
+        let serde_json::Value::Object(fields) = serde_json::json!(Theme::default()) else {
+            unreachable!("Theme serializes to an object");
+        };
+        // 与 `parse_hex_color` 接受的写法一致：#RGB / #RGBA / #RRGGBB / #RRGGBBAA，`#` 可省略
+        let color = serde_json::json!({
+            "type": "string",
+            "pattern": r"^\s*#?([0-9A-Fa-f]{3,4}|[0-9A-Fa-f]{6}|[0-9A-Fa-f]{8})\s*$",
+        });
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+        for (name, default) in fields {
+            let mut property = color.clone();
+            if default.is_null() {
+                property["type"] = serde_json::json!(["string", "null"]);
+            } else {
+                required.push(name.clone());
+            }
+            property["default"] = default;
+            properties.insert(name, property);
+        }
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Theme",
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 #[serde(rename_all = "lowercase")]
 pub enum TextPosition {
     Top,
@@ -62,8 +117,97 @@ pub enum TextPosition {
     Bottom,
 }
 
+/// 版式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum PosterLayout {
+    /// 文字叠加在地图上，以渐变过渡
+    #[default]
+    Overlay,
+    /// 地图位于上方，底部为纯色文字面板（不绘制渐变，忽略 `text_position`）
+    Panel,
+    /// 宝丽来：白色粗边框、加高的底边与轻微投影，文字写在底边上
+    /// （忽略 `margin_pct` 与 `text_position`；手写风格可通过 `font` 选用已注册的字体）
+    Polaroid,
+}
+
+/// 宝丽来边框宽度（占画布短边的百分比）
+pub const POLAROID_FRAME_PCT: f64 = 6.0;
+/// 宝丽来底边高度（占画布高度的百分比）
+pub const POLAROID_BOTTOM_PCT: f64 = 24.0;
+
+/// 半径的解释方式
+///
+/// - `Mercator`：半径直接作为 Web Mercator 平面上的米数（历史行为，高纬度覆盖的实际地面范围更小）
+/// - `Ground`：半径为地面真实距离（米），按中心纬度换算到投影平面，各纬度覆盖范围一致
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum RadiusMode {
+    #[default]
+    Mercator,
+    Ground,
+}
+
+/// PNG 压缩级别
+///
+/// - `Fast`：fdeflate + Up 滤波，速度最快，适合预览
+/// - `Default`：逐行自适应滤波 + zlib 6 级
+/// - `Best`：逐行自适应滤波 + zlib 9 级，文件最小，适合最终导出
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    #[default]
+    Fast,
+    Default,
+    Best,
+}
+
+/// 几何输入的坐标参考系
+///
+/// 显式声明输入数据是经纬度还是已投影的 Web Mercator 米，
+/// 取代容易导致重复投影的 `needs_projection` 推断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum Crs {
+    Wgs84,
+    Epsg3857,
+}
+
+pub fn default_geometry_crs() -> Crs {
+    Crs::Epsg3857 // 二进制分片默认已由 process_*_bin_wasm 投影
+}
+
+pub fn default_poi_crs() -> Crs {
+    Crs::Wgs84 // POI 默认为原始经纬度
+}
+
+/// 输出地图使用的平面投影
+///
+/// 默认 Web Mercator（EPSG:3857）；`utm` 指定 WGS84 UTM 分带以获得局部等角精度，
+/// `epsg` 支持少量常用代码（3857、4087、326xx、327xx）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MapProjection {
+    #[default]
+    WebMercator,
+    Utm {
+        zone: u8,
+        #[serde(default)]
+        south: bool,
+    },
+    Epsg {
+        code: u32,
+    },
+}
+
 /// 道路类型枚举（对应 Python 的 highway 分类）
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 #[serde(rename_all = "lowercase")]
 pub enum RoadType {
     Motorway,
@@ -109,54 +253,20 @@ impl RoadType {
         }
     }
 
-    /// 获取道路线宽（已乘以分辨率缩放因子）
-    ///
-    /// 返回值已经考虑了 8x 分辨率放大，确保在高分辨率输出中小道路也能清晰可见
-    /// 原始线宽 * RESOLUTION_SCALE:
-    /// - Motorway: 1.2 * 8 = 9.6
-    /// - Primary: 1.0 * 8 = 8.0
-    /// - Secondary: 0.8 * 8 = 6.4
-    /// - Tertiary: 0.6 * 8 = 4.8
-    /// - Residential/Default: 0.4 * 8 = 3.2
-    ///
-    /// 注意：此方法使用固定的 RESOLUTION_SCALE (8.0)，如需动态缩放请使用 get_width_scaled()
-    // pub fn get_width(self) -> f32 {
-    //     const RESOLUTION_SCALE: f32 = 8.0;
-    //     let base_width = match self {
-    //         RoadType::Motorway => 1.2,
-    //         RoadType::Primary => 1.0,
-    //         RoadType::Secondary => 0.8,
-    //         RoadType::Tertiary => 0.6,
-    //         RoadType::Residential | RoadType::Default => 0.4,
-    //     };
-    //     base_width * RESOLUTION_SCALE
-    // }
-
-    /// 获取道路线宽（使用动态缩放因子）
-    ///
-    /// 此方法允许传入动态计算的缩放因子，使得当前端改变分辨率倍数时能自动调整线宽。
-    /// 这是推荐的方法，特别是当前端的缩放比例不固定时。
-    ///
-    /// # 参数
-    /// - `scale_factor`: 缩放因子，通常由 calculate_road_width_scale() 计算获得
-    ///
-    /// # 返回值
-    /// 基础线宽乘以缩放因子后的结果
-    ///
-    /// # 示例
-    /// ```ignore
-    /// let scale = calculate_road_width_scale(3508.0, 8.0);  // 计算缩放因子
-    /// let width = RoadType::Primary.get_width_scaled(scale);
-    /// ```
-    pub fn get_width_scaled(self, scale_factor: f32) -> f32 {
-        let base_width = match self {
+    /// 参考输出高度下的线宽（输出像素）
+    pub fn base_width(self) -> f32 {
+        match self {
             RoadType::Motorway => 1.2,
             RoadType::Primary => 1.0,
             RoadType::Secondary => 0.8,
             RoadType::Tertiary => 0.6,
             RoadType::Residential | RoadType::Default => 0.4,
-        };
-        base_width * scale_factor
+        }
+    }
+
+    /// 按缩放因子换算后的线宽（输出像素），`scale_factor` 由 `calculate_road_width_scale` 计算
+    pub fn get_width_scaled(self, scale_factor: f32) -> f32 {
+        self.base_width() * scale_factor
     }
 }
 
@@ -188,8 +298,88 @@ impl BoundingBox {
     }
 }
 
+/// 地理边界框（WGS84 经纬度）
+///
+/// 用于让调用方直接指定海报覆盖的地理范围（例如在地图 UI 中框选），
+/// 提供时将绕过基于 center + radius 的边界计算
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct GeoBounds {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+/// 道路等级过滤，在解析阶段丢弃低于 `min_class` 的道路
+///
+/// 等级从高到低为 motorway、primary、secondary、tertiary、residential、default，
+/// 例如 `{ "min_class": "secondary" }` 只保留主干道，适合极简海报
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct RoadFilter {
+    #[serde(default)]
+    pub min_class: Option<RoadType>,
+}
+
+impl RoadFilter {
+    pub fn accepts(&self, road_type: RoadType) -> bool {
+        self.min_class
+            .is_none_or(|min| road_type.to_u32() <= min.to_u32())
+    }
+}
+
+/// 以 f32 存储的扁平二进制几何（道路 / 多边形格式不变）
+///
+/// 投影后的坐标减去 `origin`（通常为视口中心）后以 f32 存储：相对偏移远小于
+/// 墨卡托绝对坐标（约 2e7 米），f32 精度足以栅格化，常驻内存减半
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalBin {
+    pub origin: (f64, f64),
+    pub data: Vec<f32>,
+}
+
+impl LocalBin {
+    /// `coord_ranges` 为 `data` 中坐标所在的下标区间，其余数值（数量、类型）原样转换
+    pub fn new(data: &[f64], coord_ranges: &[Range<usize>], origin: (f64, f64)) -> Self {
+        let mut local: Vec<f32> = data.iter().map(|&v| v as f32).collect();
+        for range in coord_ranges {
+            for i in range.clone().step_by(2) {
+                local[i] = (data[i] - origin.0) as f32;
+                local[i + 1] = (data[i + 1] - origin.1) as f32;
+            }
+        }
+        LocalBin {
+            origin,
+            data: local,
+        }
+    }
+
+    /// 还原为 f64 绝对坐标
+    pub fn to_world(&self, coord_ranges: &[Range<usize>]) -> Vec<f64> {
+        let mut world: Vec<f64> = self.data.iter().map(|&v| v as f64).collect();
+        for range in coord_ranges {
+            for i in range.clone().step_by(2) {
+                world[i] += self.origin.0;
+                world[i + 1] += self.origin.1;
+            }
+        }
+        world
+    }
+
+    /// 要素数量（首个数值）
+    pub fn count(&self) -> usize {
+        self.data.first().map_or(0, |&c| c as usize)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
 /// 道路要素
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct Road {
     pub coords: Vec<(f64, f64)>,
     pub road_type: RoadType,
@@ -197,93 +387,852 @@ pub struct Road {
 
 /// 多边形要素（水体或公园）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct PolyFeature {
     pub exterior: Vec<(f64, f64)>,
     pub interiors: Vec<Vec<(f64, f64)>>,
 }
 
+impl PolyFeature {
+    /// 统一环方向：外环顺时针、内环（洞）逆时针
+    ///
+    /// 方向一致后可用非零环绕规则填充：洞被正确挖空，自相交或相互重叠的环不会像奇偶规则那样出现斑点
+    pub fn normalize_winding(&mut self) {
+        orient_ring(&mut self.exterior, false);
+        for ring in &mut self.interiors {
+            orient_ring(ring, true);
+        }
+    }
+}
+
+/// 环的有向面积（鞋带公式），x 向东、y 向北时逆时针为正
+pub fn ring_signed_area(ring: &[(f64, f64)]) -> f64 {
+    let Some(&last) = ring.last() else {
+        return 0.0;
+    };
+    let mut prev = last;
+    let mut sum = 0.0;
+    for &p in ring {
+        sum += prev.0 * p.1 - p.0 * prev.1;
+        prev = p;
+    }
+    sum / 2.0
+}
+
+/// 将环调整为逆时针（`ccw`）或顺时针，退化的零面积环保持不变
+pub fn orient_ring(ring: &mut [(f64, f64)], ccw: bool) {
+    let area = ring_signed_area(ring);
+    if area != 0.0 && (area > 0.0) != ccw {
+        ring.reverse();
+    }
+}
+
 /// 兴趣点 (POI) 要素
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct POI {
     pub x: f64,
     pub y: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // 分类（如 OSM 的 amenity 值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl POI {
+    pub fn new(x: f64, y: f64) -> Self {
+        POI {
+            x,
+            y,
+            ..Default::default()
+        }
+    }
+}
+
+/// GeoJSON FeatureCollection 形式的 POI 输入
+///
+/// 只读取 Point / MultiPoint 几何（WGS84 经纬度）与 `name`、`category` 属性，其它几何忽略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct PoiFeatureCollection {
+    pub features: Vec<PoiFeature>,
 }
 
-/// 渲染请求（从 JS 传入）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct PoiFeature {
+    // GeoJSON 允许 geometry / properties 为 null
+    pub geometry: Option<PoiGeometry>,
+    #[serde(default)]
+    pub properties: Option<PoiProperties>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(tag = "type")]
+pub enum PoiGeometry {
+    Point {
+        coordinates: Vec<f64>,
+    },
+    MultiPoint {
+        coordinates: Vec<Vec<f64>>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct PoiProperties {
+    #[serde(default)]
+    pub name: Option<serde_json::Value>,
+    #[serde(default)]
+    pub category: Option<serde_json::Value>,
+}
+
+/// 从 KML / GPX 等文件导入的叠加层：POI 与路线（WGS84 经纬度）
+#[derive(Debug, Default)]
+pub struct Overlay {
+    pub pois: Vec<POI>,
+    pub routes: Vec<Vec<(f64, f64)>>,
+}
+
+/// 自定义图层的几何类型，数据沿用对应内置图层的扁平格式
+///
+/// - `Line`：`[line_count, n, x1, y1, ..., xn, yn, ...]`（同路线）
+/// - `Polygon`：`[poly_count, ext_n, int_count, ...]`（同水体 / 公园）
+/// - `Point`：`[point_count, x1, y1, ...]`（同 POI）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraLayerKind {
+    Line,
+    Polygon,
+    Point,
+}
+
+/// 内置图层的绘制层级；自定义图层在 `z` 不小于某内置层级时绘制于该图层之上
+pub const LAYER_Z_WATER: i32 = 100;
+pub const LAYER_Z_PARKS: i32 = 200;
+pub const LAYER_Z_ROADS: i32 = 300;
+pub const LAYER_Z_ROUTES: i32 = 400;
+pub const LAYER_Z_POIS: i32 = 500;
+
+/// 自定义图层（校园轮廓、地块、公交线路等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct ExtraLayer {
+    pub kind: ExtraLayerKind,
+    pub data: Vec<f64>,
+    pub color: String,
+    // 线宽（line）或圆点半径（point），逻辑像素；多边形忽略
+    #[serde(default = "default_route_width")]
+    pub width: f32,
+    // 绘制层级（见 `LAYER_Z_*`，默认位于道路之上、路线之下）
+    #[serde(default = "default_extra_layer_z")]
+    pub z: i32,
+    // 坐标参考系（默认 wgs84）
+    #[serde(default = "default_poi_crs")]
+    pub crs: Crs,
+}
+
+pub fn default_extra_layer_z() -> i32 {
+    LAYER_Z_ROADS
+}
+
+/// 大圆弧连线（航线、自驾行程等），按顺序依次连接各点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct GreatCircleArcs {
+    pub points: Vec<Center>,
+    // 线条颜色（缺省同路线：主题的 route_color，再缺省为文字颜色）
+    #[serde(default)]
+    pub color: Option<String>,
+    // 线宽（逻辑像素）
+    #[serde(default = "default_route_width")]
+    pub width: f32,
+    // 虚线模式：实线段与空白交替的长度（逻辑像素），为空时画实线
+    #[serde(default)]
+    pub dash: Vec<f32>,
+}
+
+/// POI 标记内的编号，按 POI 的输入顺序编排（如行程的 1、2、3 站）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum PoiLabel {
+    #[default]
+    None,
+    // 1, 2, 3, ...
+    Number,
+    // A, B, ..., Z, AA, AB, ...
+    Letter,
+}
+
+/// POI 光环样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum HaloStyle {
+    // 描边的同心圆环（脉冲效果）
+    #[default]
+    Rings,
+    // 由内向外渐淡的实心光晕
+    Glow,
+}
+
+/// POI 标记外的同心光环，使重点位置在密集的道路上更醒目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct PoiHalo {
+    #[serde(default)]
+    pub style: HaloStyle,
+    // 环数
+    #[serde(default = "default_halo_rings")]
+    pub rings: u32,
+    // 相邻环的间距（逻辑像素）
+    #[serde(default = "default_halo_spacing")]
+    pub spacing: f32,
+    // 环线宽（逻辑像素，glow 样式忽略）
+    #[serde(default = "default_halo_width")]
+    pub width: f32,
+    // 最内环的不透明度（0–1）
+    #[serde(default = "default_halo_opacity")]
+    pub opacity: f32,
+    // 每向外一环不透明度乘以该系数（0–1）
+    #[serde(default = "default_halo_falloff")]
+    pub falloff: f32,
+    // 光环颜色（缺省使用主题的 poi_color）
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+pub fn default_halo_rings() -> u32 {
+    3
+}
+
+pub fn default_halo_spacing() -> f32 {
+    4.0
+}
+
+pub fn default_halo_width() -> f32 {
+    1.5
+}
+
+pub fn default_halo_opacity() -> f32 {
+    0.6
+}
+
+pub fn default_halo_falloff() -> f32 {
+    0.5
+}
+
+/// 中心标记样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerStyle {
+    #[default]
+    Ring,
+    Dot,
+    Heart,
+}
+
+/// 中心标记（"you are here"），绘制在投影后的中心点上
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct HomeMarker {
+    #[serde(default)]
+    pub style: MarkerStyle,
+    // 标记外接圆半径（逻辑像素）
+    #[serde(default = "default_marker_size")]
+    pub size: f32,
+    // 标记颜色（缺省使用主题的 poi_color）；外圈衬边使用主题背景色
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+pub fn default_marker_size() -> f32 {
+    12.0
+}
+
+/// 叠加图片的锚点
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "snake_case")]
+pub enum LogoPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// 标志等叠加图片，绘制在文字之上、水印之下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct LogoOverlay {
+    // 已注册图片的名称（见 `register_image`）
+    pub image: String,
+    #[serde(default)]
+    pub position: LogoPosition,
+    // 图片宽度占画布宽度的比例（0–1），高度按原图比例
+    #[serde(default = "default_logo_scale")]
+    pub scale: f32,
+    // 与画布边缘的距离（逻辑像素，center 时忽略）
+    #[serde(default = "default_logo_margin")]
+    pub margin: f32,
+    // 不透明度（0–1）
+    #[serde(default = "default_logo_opacity")]
+    pub opacity: f32,
+}
+
+pub fn default_logo_scale() -> f32 {
+    0.15
+}
+
+pub fn default_logo_margin() -> f32 {
+    24.0
+}
+
+pub fn default_logo_opacity() -> f32 {
+    1.0
+}
+
+/// 水印排列方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkStyle {
+    /// 按 `size` 字号重复铺满画布
+    #[default]
+    Tiled,
+    /// 单条横贯画布中心的大字（忽略 `size`）
+    Diagonal,
+}
+
+/// 文字水印，绘制在所有内容（含文字）之上，用于免费预览图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct Watermark {
+    pub text: String,
+    #[serde(default)]
+    pub style: WatermarkStyle,
+    // 不透明度（0–1）
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f32,
+    // 旋转角度（度，顺时针为正）
+    #[serde(default = "default_watermark_angle")]
+    pub angle: f32,
+    // 字号（逻辑像素，tiled）
+    #[serde(default = "default_watermark_size")]
+    pub size: f32,
+    // 文字颜色（缺省使用主题的 text）
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+pub fn default_watermark_opacity() -> f32 {
+    0.15
+}
+
+pub fn default_watermark_angle() -> f32 {
+    -30.0
+}
+
+pub fn default_watermark_size() -> f32 {
+    24.0
+}
+
+/// 昼夜叠加层的样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum SolarStyle {
+    /// 按晨昏线为夜侧着色（民用晨昏内渐变）
+    #[default]
+    Terminator,
+    /// 按地图中心的太阳高度角选取渐变色（替换主题的 gradient_color）；
+    /// 面板与宝丽来版式中渐变只绘制在地图区域内
+    Sky,
+}
+
+/// 昼夜叠加层：按指定时刻的太阳位置为地图着色，如"相遇那晚的夜空"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct SolarOverlay {
+    // 时刻（Unix 毫秒，UTC，即 JS 的 `Date.getTime()`）
+    pub timestamp_ms: f64,
+    #[serde(default)]
+    pub style: SolarStyle,
+    // 夜侧颜色（terminator，缺省为深夜蓝 #0B1026）
+    #[serde(default)]
+    pub night_color: Option<String>,
+    // 完全入夜处的不透明度（0–1，terminator）
+    #[serde(default = "default_night_opacity")]
+    pub night_opacity: f32,
+}
+
+pub fn default_night_opacity() -> f32 {
+    0.5
+}
+
+/// 渲染请求（MessagePack 接口）：渲染配置 + 预解析的几何数据
 #[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct RenderRequest {
-    // 地理信息
-    pub center: Center,
-    pub radius: f64,
+    #[serde(flatten)]
+    pub config: RenderConfig,
 
     // 预解析的 OSM 数据
     pub roads: Vec<Road>,
     pub water: Vec<PolyFeature>,
     pub parks: Vec<PolyFeature>,
 
-    // POI 数据（可选）
+    // 是否需要投影（如果 JS 已经完成了投影则为 false）；配置中的 crs 优先，两者冲突则报错
     #[serde(default)]
-    pub pois: Vec<POI>,
+    pub needs_projection: bool,
+}
 
-    // 主题配置
+/// 渲染配置
+///
+/// 所有渲染入口（JSON、MessagePack、二进制、容器、会话）共用的配置字段，
+/// JSON / MessagePack 请求在此基础上附带几何数据
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct RenderConfig {
+    pub center: Center,
+    pub radius: f64,
+    // 半径的解释方式（默认沿用 Web Mercator 米数）
+    #[serde(default)]
+    pub radius_mode: RadiusMode,
+    // 显式地理边界（可选，提供时忽略 radius）
+    #[serde(default)]
+    pub bounds: Option<GeoBounds>,
+    // 输出投影（默认 Web Mercator）
+    #[serde(default)]
+    pub projection: MapProjection,
+    // 画布四周的内边距百分比
+    #[serde(default)]
+    pub padding_pct: f64,
+    // 地图的内嵌边距百分比（边距内填充背景色，见 `MarginPct`）
+    #[serde(default)]
+    pub margin_pct: Option<MarginPct>,
     pub theme: Theme,
-
-    // 画布尺寸
     pub width: u32,
     pub height: u32,
-
-    // 文本信息
     pub display_city: String,
     pub display_country: String,
     pub text_position: Option<TextPosition>,
-
-    // 是否需要投影（如果 JS 已经完成了投影则为 false）
+    // 地图区域的圆角半径（逻辑像素，0 表示直角），圆角外露出背景色
     #[serde(default)]
-    pub needs_projection: bool,
-
-    // 动态道路线宽缩放相关参数
-    // 选定尺寸的原始高度（像素），例如 A4 Portrait 的 3508
-    #[serde(default = "default_selected_size_height")]
-    pub selected_size_height: u32,
-
-    // 前端应用的缩放倍数，例如 8
+    pub corner_radius: f32,
+    // 版式（默认文字叠加在地图上）
+    #[serde(default)]
+    pub layout: PosterLayout,
+    // 文字面板占画布高度的百分比（layout = panel）
+    #[serde(default = "default_text_panel_pct")]
+    pub text_panel_pct: f64,
+    // 文字面板颜色（缺省为主题背景色）
+    #[serde(default)]
+    pub text_panel_color: Option<String>,
+    // 道路线宽按此高度缩放（像素），缺省使用 height；见 `road_width_scale`
+    #[serde(default)]
+    pub selected_size_height: Option<u32>,
+    // 前端的分辨率倍数，已计入 width / height，不再单独影响线宽（保留以兼容旧请求）
     #[serde(default = "default_frontend_scale")]
     pub frontend_scale: f32,
-
-    // 道路权重增强倍率（默认 1.0）
     #[serde(default = "default_road_width_boost")]
     pub road_width_boost: f32,
+    // POI 数据（可选）：[poi_count, x1, y1, x2, y2, ...] 或 [{x, y}, ...]
+    #[serde(default)]
+    pub pois: Option<PoiInput>,
+    // 道路 / 水体 / 公园的坐标参考系（缺省为已投影的 epsg3857，见 `geometry_crs`）
+    #[serde(default)]
+    pub crs: Option<Crs>,
+    // POI 的坐标参考系（默认 wgs84）
+    #[serde(default = "default_poi_crs")]
+    pub poi_crs: Crs,
+    // POI 标记内的编号（默认不编号）
+    #[serde(default)]
+    pub poi_labels: PoiLabel,
+    // POI 标记外的光环（可选）
+    #[serde(default)]
+    pub poi_halo: Option<PoiHalo>,
+    // 路线叠加层（可选）：[route_count, n, x1, y1, ..., xn, yn, ...]
+    #[serde(default)]
+    pub routes: Option<Vec<f64>>,
+    // 路线的坐标参考系（默认 wgs84）
+    #[serde(default = "default_poi_crs")]
+    pub route_crs: Crs,
+    // 路线线宽（逻辑像素）
+    #[serde(default = "default_route_width")]
+    pub route_width: f32,
+    // 大圆弧连线（可选），绘制在路线之上
+    #[serde(default)]
+    pub arcs: Option<GreatCircleArcs>,
+    // 自定义图层（按 z 排序绘制）
+    #[serde(default)]
+    pub extra_layers: Vec<ExtraLayer>,
+    // 中心标记（可选），绘制在 POI 之上
+    #[serde(default)]
+    pub home_marker: Option<HomeMarker>,
+    // 叠加图片（可选），绘制在文字之上
+    #[serde(default)]
+    pub logo: Option<LogoOverlay>,
+    // 文字水印（可选），绘制在最上层
+    #[serde(default)]
+    pub watermark: Option<Watermark>,
+    // 昼夜叠加层（可选），按指定时刻的太阳位置着色
+    #[serde(default)]
+    pub solar: Option<SolarOverlay>,
+    // 区域裁剪多边形（WGS84 经纬度，可选）
+    #[serde(default)]
+    pub region: Option<Vec<PolyFeature>>,
+    #[serde(default)]
+    pub region_outside_opacity: f32,
+    // 是否裁剪为测地线圆（半径按地面距离解释）
+    #[serde(default)]
+    pub clip_circle: bool,
+    // 几何简化容差（输出像素）
+    #[serde(default = "default_simplify_tolerance")]
+    pub simplify_tolerance_px: f32,
+    // 细节层级阈值（输出像素，0 表示绘制全部细节）
+    #[serde(default = "default_lod_min_px")]
+    pub lod_min_px: f32,
+    // 是否抗锯齿（默认开启）
+    #[serde(default = "default_anti_alias")]
+    pub anti_alias: bool,
+    // 已注册字体的名称（见 `register_font`，缺省使用默认字体）
+    #[serde(default)]
+    pub font: Option<String>,
+    // PNG 压缩级别（预览用 fast，最终导出可用 best）
+    #[serde(default)]
+    pub png_compression: PngCompression,
 }
 
-pub fn default_road_width_boost() -> f32 {
-    1.0
+impl RenderConfig {
+    pub fn view(&self) -> ViewConfig {
+        ViewConfig {
+            center: self.center,
+            radius: self.radius,
+            radius_mode: self.radius_mode,
+            bounds: self.bounds,
+            projection: self.projection,
+            padding_pct: self.padding_pct,
+            margin_pct: self.map_margins(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// 道路线宽缩放因子：按实际输出高度（或显式的 `selected_size_height`）换算，
+    /// JSON 与二进制管线共用
+    pub fn road_width_scale(&self) -> f32 {
+        let height = self.selected_size_height.unwrap_or(self.height);
+        calculate_road_width_scale(height as f32, self.road_width_boost)
+    }
+
+    /// 面板版式中实际绘制的文字面板高度（百分比）
+    ///
+    /// 与 `map_margins` 共用底部的 `MAX_MARGIN_PCT` 上限：底部边距与面板之和超限时压缩面板，
+    /// 使地图下边缘与面板之间始终只隔底部边距
+    pub fn text_panel_height_pct(&self) -> f64 {
+        let bottom = self
+            .margin_pct
+            .map_or(0.0, |m| m.fractions().bottom * 100.0);
+        self.text_panel_pct.min(MAX_MARGIN_PCT - bottom).max(0.0)
+    }
+
+    /// 地图区域的边距：面板版式在底部边距上加上文字面板的高度
+    pub fn map_margins(&self) -> Option<MarginPct> {
+        match self.layout {
+            PosterLayout::Overlay => self.margin_pct,
+            PosterLayout::Panel => {
+                let mut sides = self.margin_pct.map(|m| m.sides()).unwrap_or_default();
+                sides.bottom += self.text_panel_pct;
+                Some(MarginPct::Sides(sides))
+            }
+            PosterLayout::Polaroid => {
+                // 边框按短边取相同的像素宽度，再换算为各边的百分比
+                let frame = self.width.min(self.height) as f64 * POLAROID_FRAME_PCT;
+                let (x, y) = (frame / self.width as f64, frame / self.height as f64);
+                Some(MarginPct::Sides(Margins {
+                    top: y,
+                    right: x,
+                    bottom: POLAROID_BOTTOM_PCT,
+                    left: x,
+                }))
+            }
+        }
+    }
+
+    /// 道路 / 水体 / 公园的坐标参考系
+    pub fn geometry_crs(&self) -> Crs {
+        self.crs.unwrap_or_else(default_geometry_crs)
+    }
+}
+
+/// 地图的内嵌边距（占画布宽 / 高的百分比）
+///
+/// 地图绘制在边距以内的矩形中，边距部分以背景色填充，呈卡纸装裱的效果。
+/// 可为单个数字（四边相同）或 `{ top, right, bottom, left }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(untagged)]
+pub enum MarginPct {
+    All(f64),
+    Sides(Margins),
+}
+
+/// 各边的边距
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct Margins {
+    #[serde(default)]
+    pub top: f64,
+    #[serde(default)]
+    pub right: f64,
+    #[serde(default)]
+    pub bottom: f64,
+    #[serde(default)]
+    pub left: f64,
+}
+
+/// 单边边距上限（百分比），两侧之和不超过画布的 90%
+pub const MAX_MARGIN_PCT: f64 = 45.0;
+
+impl MarginPct {
+    /// 各边边距（百分比）
+    pub fn sides(&self) -> Margins {
+        match *self {
+            MarginPct::All(pct) => Margins {
+                top: pct,
+                right: pct,
+                bottom: pct,
+                left: pct,
+            },
+            MarginPct::Sides(m) => m,
+        }
+    }
+
+    /// 各边边距的比例（0–0.45），超出范围或非有限的值按上限或 0 处理
+    pub fn fractions(&self) -> Margins {
+        let m = self.sides();
+        let f = |pct: f64| {
+            if pct.is_finite() {
+                pct.clamp(0.0, MAX_MARGIN_PCT) / 100.0
+            } else {
+                0.0
+            }
+        };
+        Margins {
+            top: f(m.top),
+            right: f(m.right),
+            bottom: f(m.bottom),
+            left: f(m.left),
+        }
+    }
+}
+
+/// 多城市拼贴的排列方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(rename_all = "lowercase")]
+pub enum CollageLayout {
+    /// 左右并排
+    #[default]
+    Row,
+    /// 上下堆叠
+    Column,
+    /// 网格（列数为 ⌈√n⌉）
+    Grid,
+}
+
+/// 多城市拼贴：2–4 个城市画在同一张画布上，共用主题与合并的标题区
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct CollageConfig {
+    /// 各分格的渲染配置，字段与 `RenderConfig` 相同；`theme`、`width`、`height` 由拼贴统一提供，
+    /// 分格不绘制渐变与文字
+    pub panels: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub layout: CollageLayout,
+    pub width: u32,
+    pub height: u32,
+    pub theme: Theme,
+    /// 分格之间及画布四周的间距（逻辑像素）
+    #[serde(default = "default_collage_gap")]
+    pub gap: f32,
+    /// 标题，缺省为各分格的 `display_city` 以 " & " 连接
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub subtitle: String,
+    /// 标题区占画布高度的百分比，0 表示不绘制标题
+    #[serde(default = "default_collage_title_pct")]
+    pub title_pct: f32,
+    /// 标题区位置（`top` 或 `bottom`，缺省 bottom）
+    #[serde(default)]
+    pub text_position: Option<TextPosition>,
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub png_compression: PngCompression,
+    #[serde(default)]
+    pub watermark: Option<Watermark>,
 }
 
-pub fn default_selected_size_height() -> u32 {
-    3508 // A4 Portrait 默认值
+impl CollageConfig {
+    /// 第 `index` 个分格的渲染配置，尺寸为分格的像素宽高
+    pub fn panel_config(
+        &self,
+        index: usize,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderConfig, String> {
+        let serde_json::Value::Object(mut panel) = self.panels[index].clone() else {
+            return Err(format!("Panel {} must be an object", index));
+        };
+        let shared = [
+            (
+                "theme",
+                serde_json::to_value(&self.theme).map_err(|e| e.to_string())?,
+            ),
+            ("width", width.into()),
+            ("height", height.into()),
+        ];
+        panel.extend(shared.map(|(k, v)| (k.to_string(), v)));
+        for key in ["display_city", "display_country"] {
+            panel.entry(key).or_insert_with(|| "".into());
+        }
+        if let Some(font) = &self.font {
+            panel.entry("font").or_insert_with(|| font.as_str().into());
+        }
+        serde_json::from_value(serde_json::Value::Object(panel))
+            .map_err(|e| format!("Invalid panel {}: {}", index, e))
+    }
+
+    /// 标题：显式的 `title`，否则为各分格城市名以 " & " 连接
+    pub fn title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+        let cities: Vec<&str> = self
+            .panels
+            .iter()
+            .filter_map(|p| p.get("display_city")?.as_str())
+            .filter(|c| !c.is_empty())
+            .collect();
+        cities.join(" & ")
+    }
+}
+
+/// POI 输入：扁平二进制 `[count, x1, y1, ...]`、`{ x, y }` 对象数组或 GeoJSON FeatureCollection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+#[serde(untagged)]
+pub enum PoiInput {
+    Flat(Vec<f64>),
+    Points(Vec<POI>),
+    Features(PoiFeatureCollection),
+}
+
+impl PoiInput {
+    /// 转为扁平二进制格式
+    pub fn into_bin(self) -> Vec<f64> {
+        match self {
+            PoiInput::Flat(data) => data,
+            PoiInput::Points(pois) => crate::data_processor::pois_to_bin(&pois),
+            PoiInput::Features(collection) => crate::data_processor::pois_to_bin(
+                &crate::data_processor::pois_from_features(&collection),
+            ),
+        }
+    }
+}
+
+pub fn default_road_width_boost() -> f32 {
+    1.0
 }
 
 pub fn default_frontend_scale() -> f32 {
     8.0 // 默认缩放倍数
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+pub fn default_route_width() -> f32 {
+    3.0 // 路线线宽（逻辑像素）
+}
+
+pub fn default_simplify_tolerance() -> f32 {
+    0.5 // 几何简化容差（输出像素），0 表示不简化
+}
+
+pub fn default_anti_alias() -> bool {
+    true
+}
+
+pub fn default_lod_min_px() -> f32 {
+    1.0 // 细节层级阈值（输出像素），0 表示绘制全部细节
+}
+
+pub fn default_text_panel_pct() -> f64 {
+    20.0 // 文字面板占画布高度的百分比
+}
+
+pub fn default_collage_gap() -> f32 {
+    24.0 // 拼贴分格间距（逻辑像素）
+}
+
+pub fn default_collage_title_pct() -> f32 {
+    18.0 // 拼贴标题区占画布高度的百分比
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[cfg_attr(js_host, derive(Tsify))]
 pub struct Center {
     pub lat: f64,
     pub lon: f64,
 }
 
+/// 视图参数（决定渲染边界的全部字段）
+///
+/// 与渲染配置中的同名字段一致，比例尺、坐标换算等接口可直接复用
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[cfg_attr(js_host, derive(Tsify))]
+pub struct ViewConfig {
+    pub center: Center,
+    pub radius: f64,
+    #[serde(default)]
+    pub radius_mode: RadiusMode,
+    #[serde(default)]
+    pub bounds: Option<GeoBounds>,
+    #[serde(default)]
+    pub projection: MapProjection,
+    #[serde(default)]
+    pub padding_pct: f64,
+    #[serde(default)]
+    pub margin_pct: Option<MarginPct>,
+    pub width: u32,
+    pub height: u32,
+}
+
 /// 渲染结果
-#[wasm_bindgen]
+#[cfg_attr(js_host, wasm_bindgen)]
 pub struct RenderResult {
     success: bool,
     width: u32,
     height: u32,
     data: Option<Vec<u8>>,
-    error: Option<String>,
+    error: Option<RenderError>,
+    timings: Vec<crate::profile::StageTiming>,
+    stats: Vec<crate::draw_stats::LayerStats>,
+    warnings: Vec<crate::warnings::RenderWarning>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(js_host, wasm_bindgen)]
 impl RenderResult {
     pub fn success(width: u32, height: u32, data: Vec<u8>) -> Self {
         Self {
@@ -292,17 +1241,15 @@ impl RenderResult {
             height,
             data: Some(data),
             error: None,
+            timings: crate::profile::take(),
+            stats: crate::draw_stats::take(),
+            warnings: crate::warnings::take(),
         }
     }
 
+    /// 未分类的错误（错误码为 internal）
     pub fn error(msg: String) -> Self {
-        Self {
-            success: false,
-            width: 0,
-            height: 0,
-            data: None,
-            error: Some(msg),
-        }
+        Self::failure(RenderError::new(RenderErrorCode::Internal, msg))
     }
 
     pub fn is_success(&self) -> bool {
@@ -317,11 +1264,160 @@ impl RenderResult {
         self.height
     }
 
+    /// PNG 数据（独立的 `Uint8Array`，不引用 WASM 内存）
+    ///
+    /// Node 中可用 `Buffer.from(data.buffer, data.byteOffset, data.byteLength)` 零拷贝转为 Buffer
     pub fn get_data(&self) -> Option<Vec<u8>> {
         self.data.clone()
     }
 
+    /// PNG 数据长度（字节），失败时为 0，用于预分配 `copy_data_into` 的缓冲区
+    pub fn get_data_len(&self) -> usize {
+        self.data.as_ref().map_or(0, Vec::len)
+    }
+
     pub fn get_error(&self) -> Option<String> {
-        self.error.clone()
+        self.error.as_ref().map(|e| e.message.clone())
+    }
+
+    /// 错误码，如 "invalid_config"、"canvas_too_large"
+    pub fn get_error_code(&self) -> Option<String> {
+        self.error.as_ref().map(|e| e.code.as_str().to_string())
+    }
+
+    /// 警告条数，为 0 时无需读取 `get_warnings`
+    pub fn get_warning_count(&self) -> usize {
+        self.warnings.len()
+    }
+}
+
+#[cfg(js_host)]
+#[wasm_bindgen]
+impl RenderResult {
+    /// 完整错误信息 `{ code, message, field? }`，成功时为 null
+    #[wasm_bindgen(unchecked_return_type = "RenderError | null")]
+    pub fn get_error_info(&self) -> JsValue {
+        self.error
+            .as_ref()
+            .and_then(|e| serde_wasm_bindgen::to_value(e).ok())
+            .unwrap_or(JsValue::NULL)
+    }
+
+    /// 将 PNG 数据直接写入调用方的 `Uint8Array`（可基于 SharedArrayBuffer），返回写入的字节数
+    ///
+    /// 只从 WASM 内存复制一次，省去 `get_data` 克隆 Vec 的开销；缓冲区不足 `get_data_len()` 时报错
+    pub fn copy_data_into(&self, out: &js_sys::Uint8Array) -> Result<u32, JsValue> {
+        let data = self
+            .data
+            .as_deref()
+            .ok_or_else(|| JsValue::from_str("Render failed, no data to copy"))?;
+        let len = data.len() as u32;
+        if out.length() < len {
+            return Err(JsValue::from_str(&format!(
+                "Output buffer too small: {} bytes, need {}",
+                out.length(),
+                len
+            )));
+        }
+        out.subarray(0, len).copy_from(data);
+        Ok(len)
+    }
+
+    /// 各阶段耗时 `[{ stage, ms }]`，按首次完成的顺序排列
+    #[wasm_bindgen(unchecked_return_type = "StageTiming[]")]
+    pub fn get_timings(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.timings).unwrap_or(JsValue::NULL)
+    }
+
+    /// 各图层绘制统计 `[{ layer, features, drawn, culled, vertices, paths }]`，按绘制顺序排列
+    #[wasm_bindgen(unchecked_return_type = "LayerStats[]")]
+    pub fn get_stats(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.stats).unwrap_or(JsValue::NULL)
+    }
+
+    /// 渲染警告 `[{ code, message, field?, count }]`，如被跳过的无效坐标
+    #[wasm_bindgen(unchecked_return_type = "RenderWarning[]")]
+    pub fn get_warnings(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.warnings).unwrap_or(JsValue::NULL)
+    }
+}
+
+impl RenderResult {
+    /// 完整错误信息，成功时为 `None`
+    pub fn error_info(&self) -> Option<&RenderError> {
+        self.error.as_ref()
+    }
+
+    /// 各阶段耗时，按首次完成的顺序排列
+    pub fn timings(&self) -> &[crate::profile::StageTiming] {
+        &self.timings
+    }
+
+    /// 各图层绘制统计，按绘制顺序排列
+    pub fn stats(&self) -> &[crate::draw_stats::LayerStats] {
+        &self.stats
+    }
+
+    /// 渲染警告，按首次出现的顺序排列
+    pub fn warnings(&self) -> &[crate::warnings::RenderWarning] {
+        &self.warnings
+    }
+
+    /// 取出 PNG 数据（避免 `get_data` 的拷贝）
+    pub fn into_data(self) -> Option<Vec<u8>> {
+        self.data
+    }
+
+    pub fn failure(error: RenderError) -> Self {
+        Self {
+            success: false,
+            width: 0,
+            height: 0,
+            data: None,
+            error: Some(error),
+            timings: crate::profile::take(),
+            stats: crate::draw_stats::take(),
+            warnings: crate::warnings::take(),
+        }
+    }
+}
+
+impl From<RenderError> for RenderResult {
+    fn from(error: RenderError) -> Self {
+        Self::failure(error)
+    }
+}
+
+/// 比例尺信息（由 compute_scale 返回）
+#[cfg_attr(js_host, wasm_bindgen)]
+pub struct ScaleInfo {
+    meters_per_pixel: f64,
+    approx_zoom: f64,
+    scale_denominator: f64,
+}
+
+#[cfg_attr(js_host, wasm_bindgen)]
+impl ScaleInfo {
+    pub fn new(meters_per_pixel: f64, approx_zoom: f64, scale_denominator: f64) -> Self {
+        Self {
+            meters_per_pixel,
+            approx_zoom,
+            scale_denominator,
+        }
+    }
+
+    /// 中心纬度处每个输出像素对应的地面距离（米）
+    pub fn get_meters_per_pixel(&self) -> f64 {
+        self.meters_per_pixel
+    }
+
+    /// 与当前分辨率相当的 Web Mercator 瓦片缩放级别（256px 瓦片）
+    pub fn get_approx_zoom(&self) -> f64 {
+        self.approx_zoom
+    }
+
+    /// 按 300 DPI 打印时的比例尺分母，例如 25000 表示 1:25,000
+    pub fn get_scale_denominator(&self) -> f64 {
+        self.scale_denominator
     }
 }
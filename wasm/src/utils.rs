@@ -1,70 +1,267 @@
+use crate::types::PoiLabel;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU8, Ordering};
 use tiny_skia::Color;
+#[cfg(js_host)]
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-unsafe extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    pub fn log(s: &str);
+/// 日志级别，低于当前级别的日志被丢弃
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
 
-    #[wasm_bindgen(js_namespace = console)]
-    pub fn time(s: &str);
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Off => "off",
+        }
+    }
+}
 
-    #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
-    pub fn time_end(s: &str);
+// 调试构建默认输出全部日志，发布构建仅输出警告与错误
+static LEVEL: AtomicU8 = AtomicU8::new(if cfg!(debug_assertions) {
+    LogLevel::Debug as u8
+} else {
+    LogLevel::Warn as u8
+});
 
-    #[wasm_bindgen(js_namespace = ["performance"], js_name = now)]
-    pub fn performance_now() -> f64;
+#[cfg(js_host)]
+thread_local! {
+    // JS 回调 `(level, message) => void`；未设置时输出到 console
+    static HANDLER: std::cell::RefCell<Option<js_sys::Function>> =
+        const { std::cell::RefCell::new(None) };
 }
 
-/// 解析 hex 颜色为 tiny-skia Color
-pub fn parse_hex_color(hex: &str) -> Color {
-    let hex = hex.trim_start_matches('#');
+/// 原生目标的日志输出函数
+#[cfg(not(js_host))]
+pub type LogSink = Box<dyn Fn(LogLevel, &str) + Send + Sync>;
 
-    if hex.len() != 6 {
-        // 默认黑色
-        return Color::from_rgba8(0, 0, 0, 255);
+#[cfg(not(js_host))]
+static SINK: std::sync::RwLock<Option<LogSink>> = std::sync::RwLock::new(None);
+
+pub fn set_log_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[cfg(js_host)]
+pub fn set_log_handler(handler: Option<js_sys::Function>) {
+    HANDLER.with(|h| *h.borrow_mut() = handler);
+}
+
+/// 设置原生目标的日志输出（如写入 stderr 或服务端日志），`None` 时丢弃日志
+#[cfg(not(js_host))]
+pub fn set_log_sink(sink: Option<LogSink>) {
+    if let Ok(mut s) = SINK.write() {
+        *s = sink;
     }
+}
 
-    let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-    let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-    let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
+pub fn log_enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level as u8 >= LEVEL.load(Ordering::Relaxed)
+}
 
-    Color::from_rgba8(r, g, b, 255)
+/// 按级别输出日志：优先交给注册的回调，否则写入对应的 console 方法（若存在）
+pub fn log_at(level: LogLevel, s: &str) {
+    if log_enabled(level) {
+        emit(level, s);
+    }
 }
 
-/// 检测是否为拉丁文字（与 Python 版本相同逻辑）
-pub fn is_latin_script(text: &str) -> bool {
-    if text.is_empty() {
-        return true;
+#[cfg(js_host)]
+fn emit(level: LogLevel, s: &str) {
+    let handled = HANDLER.with(|h| {
+        h.borrow().as_ref().map(|f| {
+            let _ = f.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(level.as_str()),
+                &JsValue::from_str(s),
+            );
+        })
+    });
+    if handled.is_some() {
+        return;
+    }
+    let method = match level {
+        LogLevel::Debug => "debug",
+        LogLevel::Warn => "warn",
+        LogLevel::Error => "error",
+        _ => "log",
+    };
+    // 部分 serverless / 嵌入式 JS 运行时没有 console，此时丢弃日志
+    if let Some((console, f)) = global_method("console", method) {
+        let _ = f.call1(&console, &JsValue::from_str(s));
     }
+}
+
+/// 原生与 WASI 目标没有 JS 控制台，交给 `set_log_sink` 设置的输出，未设置时丢弃
+#[cfg(not(js_host))]
+fn emit(level: LogLevel, s: &str) {
+    if let Ok(sink) = SINK.read()
+        && let Some(sink) = sink.as_ref()
+    {
+        sink(level, s);
+    }
+}
+
+pub fn log(s: &str) {
+    log_at(LogLevel::Info, s);
+}
+
+pub fn debug(s: &str) {
+    log_at(LogLevel::Debug, s);
+}
+
+pub fn warn(s: &str) {
+    log_at(LogLevel::Warn, s);
+}
 
-    let latin_count = text
+/// 查找全局对象上的方法（如 `console.log`、`performance.now`），不存在时返回 `None`
+///
+/// 不使用 `js_namespace` 静态绑定：浏览器、Node 与各类 serverless 运行时提供的全局对象不尽相同，
+/// 静态绑定在缺少对应全局时会直接抛错
+#[cfg(js_host)]
+fn global_method(object: &str, method: &str) -> Option<(JsValue, js_sys::Function)> {
+    let object = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(object)).ok()?;
+    if !object.is_object() {
+        return None;
+    }
+    let f = js_sys::Reflect::get(&object, &JsValue::from_str(method)).ok()?;
+    Some((object, f.dyn_into().ok()?))
+}
+
+/// 高精度时间（毫秒），没有 `performance` 的运行时退回 `Date.now()`
+#[cfg(js_host)]
+pub fn performance_now() -> f64 {
+    global_method("performance", "now")
+        .and_then(|(performance, now)| now.call0(&performance).ok())
+        .and_then(|t| t.as_f64())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+/// 解析 hex 颜色为 tiny-skia Color
+///
+/// 支持 `#RGB`、`#RGBA`、`#RRGGBB`、`#RRGGBBAA`，`#` 可省略，忽略首尾空白
+pub fn parse_hex_color(hex: &str) -> Result<Color, String> {
+    let trimmed = hex.trim();
+    let digits = trimmed.strip_prefix('#').unwrap_or(trimmed);
+    let invalid = || {
+        format!(
+            "Invalid color '{}', expected #RGB, #RGBA, #RRGGBB or #RRGGBBAA",
+            hex
+        )
+    };
+    let nibbles: Vec<u8> = digits
         .chars()
-        .filter(|c| c.is_alphabetic())
-        .filter(|c| (*c as u32) < 0x250)
-        .count();
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let channels: Vec<u8> = match nibbles.len() {
+        3 | 4 => nibbles.iter().map(|n| n * 17).collect(),
+        6 | 8 => nibbles.chunks_exact(2).map(|p| p[0] << 4 | p[1]).collect(),
+        _ => return Err(invalid()),
+    };
+    let alpha = channels.get(3).copied().unwrap_or(255);
+    Ok(Color::from_rgba8(
+        channels[0],
+        channels[1],
+        channels[2],
+        alpha,
+    ))
+}
 
-    let total_alpha = text.chars().filter(|c| c.is_alphabetic()).count();
+/// 字符所属的文字系统（只区分排版需要的几类）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    /// 组合附加符号，随前一个字符
+    Inherited,
+    /// 数字、标点、空白等各文字系统通用的字符
+    Common,
+    Other,
+}
 
-    if total_alpha == 0 {
-        return true;
+/// 按 Unicode 区块判断字符的文字系统
+///
+/// 拉丁文包括越南语使用的 Latin Extended Additional（U+1E00–U+1EFF）等扩展区块
+fn char_script(c: char) -> Script {
+    match c as u32 {
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F => {
+            Script::Inherited
+        }
+        0x0041..=0x005A
+        | 0x0061..=0x007A
+        | 0x00AA
+        | 0x00BA
+        | 0x00C0..=0x00D6
+        | 0x00D8..=0x00F6
+        | 0x00F8..=0x02AF
+        | 0x1D00..=0x1D7F
+        | 0x1E00..=0x1EFF
+        | 0x2C60..=0x2C7F
+        | 0xA720..=0xA7FF
+        | 0xAB30..=0xAB6F
+        | 0xFF21..=0xFF3A
+        | 0xFF41..=0xFF5A => Script::Latin,
+        _ if c.is_alphabetic() => Script::Other,
+        _ => Script::Common,
     }
+}
 
-    (latin_count as f32 / total_alpha as f32) > 0.8
+/// 检测是否为拉丁文字：字母中拉丁字母占八成以上（通用字符与组合符号不计）
+pub fn is_latin_script(text: &str) -> bool {
+    let scripts = text.chars().map(char_script);
+    let (latin, other) = scripts.fold((0usize, 0usize), |(latin, other), script| match script {
+        Script::Latin => (latin + 1, other),
+        Script::Other => (latin, other + 1),
+        _ => (latin, other),
+    });
+    if latin + other == 0 {
+        return true;
+    }
+    (latin as f32 / (latin + other) as f32) > 0.8
 }
 
-/// 格式化城市名（拉丁文加字间距，非拉丁文保持原样）
+/// 格式化城市名：拉丁文单词大写并加字间距，其它文字的单词保持原样
+///
+/// 如 "São Tomé (圣多美)" 只对 "São Tomé" 加字间距；组合附加符号紧跟其基字符
 pub fn format_city_name(city: &str) -> String {
-    if is_latin_script(city) {
+    let mut segments: Vec<String> = Vec::new();
+    let mut latin_run: Vec<&str> = Vec::new();
+    let flush = |run: &mut Vec<&str>, segments: &mut Vec<String>| {
+        if run.is_empty() {
+            return;
+        }
         // 拉丁文：大写 + 双空格字间距
-        city.chars()
-            .map(|c| c.to_string())
-            .collect::<Vec<String>>()
-            .join(" ")
-    } else {
-        // 非拉丁文：保持原样
-        city.to_string()
+        let mut spaced = String::new();
+        for c in run.join(" ").to_uppercase().chars() {
+            if !spaced.is_empty() && char_script(c) != Script::Inherited {
+                spaced.push_str("  ");
+            }
+            spaced.push(c);
+        }
+        segments.push(spaced);
+        run.clear();
+    };
+    for word in city.split_whitespace() {
+        if is_latin_script(word) {
+            latin_run.push(word);
+        } else {
+            flush(&mut latin_run, &mut segments);
+            segments.push(word.to_string());
+        }
     }
+    flush(&mut latin_run, &mut segments);
+    segments.join(" ")
 }
 
 /// 格式化坐标显示
@@ -81,6 +278,38 @@ pub fn format_coordinates(lat: f64, lon: f64) -> String {
     )
 }
 
+/// 规整虚线模式：奇数个长度按 SVG 的约定重复一次；长度须为非负有限值且总和为正，否则返回 None
+pub fn dash_pattern(dash: &[f32]) -> Option<Vec<f32>> {
+    if dash.iter().any(|d| !d.is_finite() || *d < 0.0) || dash.iter().sum::<f32>() <= 0.0 {
+        return None;
+    }
+    let mut pattern = dash.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(dash);
+    }
+    Some(pattern)
+}
+
+/// 第 `index` 个（从 0 开始）POI 的标记文字
+pub fn poi_label(kind: PoiLabel, index: usize) -> Option<String> {
+    match kind {
+        PoiLabel::None => None,
+        PoiLabel::Number => Some((index + 1).to_string()),
+        PoiLabel::Letter => {
+            // 双射 26 进制：Z 之后为 AA
+            let mut n = index + 1;
+            let mut letters = Vec::new();
+            while n > 0 {
+                n -= 1;
+                letters.push(b'A' + (n % 26) as u8);
+                n /= 26;
+            }
+            letters.reverse();
+            Some(String::from_utf8(letters).unwrap_or_default())
+        }
+    }
+}
+
 /// 动态计算字体大小
 /// 当字符数超过阈值时，字体大小按比例缩小，阈值越大，字体越大
 pub fn calculate_font_size(text: &str, base_size: f32, threshold: usize) -> f32 {
@@ -95,10 +324,74 @@ pub fn calculate_font_size(text: &str, base_size: f32, threshold: usize) -> f32
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_dash_pattern() {
+        assert_eq!(dash_pattern(&[4.0, 2.0]), Some(vec![4.0, 2.0]));
+        assert_eq!(dash_pattern(&[3.0]), Some(vec![3.0, 3.0]));
+        assert_eq!(dash_pattern(&[0.0, 0.0]), None);
+        assert_eq!(dash_pattern(&[4.0, -1.0]), None);
+        assert_eq!(dash_pattern(&[f32::NAN, 1.0]), None);
+        assert_eq!(dash_pattern(&[]), None);
+    }
+
+    #[test]
+    fn test_poi_label() {
+        assert_eq!(poi_label(PoiLabel::None, 0), None);
+        assert_eq!(poi_label(PoiLabel::Number, 0).as_deref(), Some("1"));
+        assert_eq!(poi_label(PoiLabel::Number, 11).as_deref(), Some("12"));
+        let letter = |i| poi_label(PoiLabel::Letter, i).unwrap();
+        assert_eq!(
+            [letter(0), letter(25), letter(26), letter(27)],
+            ["A", "Z", "AA", "AB"]
+        );
+        assert_eq!(letter(26 * 27), "AAA");
+    }
+
+    #[cfg(not(js_host))]
+    #[test]
+    fn test_log_level_filter() {
+        static CAPTURED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        set_log_sink(Some(Box::new(|level, s| {
+            CAPTURED
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", level.as_str(), s));
+        })));
+
+        set_log_level(LogLevel::Warn);
+        assert!(!log_enabled(LogLevel::Info));
+        assert!(log_enabled(LogLevel::Warn));
+        assert!(log_enabled(LogLevel::Error));
+        log("dropped");
+        warn("kept");
+        set_log_level(LogLevel::Off);
+        assert!(!log_enabled(LogLevel::Error));
+        assert!(!log_enabled(LogLevel::Off));
+        set_log_level(LogLevel::Debug);
+        assert!(log_enabled(LogLevel::Debug));
+        set_log_sink(None);
+
+        let captured = CAPTURED.lock().unwrap();
+        assert!(captured.contains(&"warn: kept".to_string()));
+        assert!(!captured.iter().any(|line| line.ends_with("dropped")));
+    }
+
     #[test]
     fn test_parse_hex_color() {
         let color = parse_hex_color("#FF5733");
-        assert_eq!(color, Color::from_rgba8(255, 87, 51, 255));
+        assert_eq!(color, Ok(Color::from_rgba8(255, 87, 51, 255)));
+        assert_eq!(parse_hex_color(" #fff\n"), Ok(Color::WHITE));
+        assert_eq!(
+            parse_hex_color("#0f08"),
+            Ok(Color::from_rgba8(0, 255, 0, 136))
+        );
+        assert_eq!(
+            parse_hex_color("ffffff80"),
+            Ok(Color::from_rgba8(255, 255, 255, 128))
+        );
+        for bad in ["", "#12345", "#ff 000", "blue", "#ggg"] {
+            assert!(parse_hex_color(bad).is_err(), "{}", bad);
+        }
     }
 
     #[test]
@@ -107,11 +400,21 @@ mod tests {
         assert!(is_latin_script("New York"));
         assert!(!is_latin_script("东京"));
         assert!(!is_latin_script("北京"));
+        assert!(is_latin_script("Thành phố Hồ Chí Minh"));
+        assert!(is_latin_script("Huế"));
+        assert!(is_latin_script("Ulaanbaatar"));
+        assert!(!is_latin_script("Улаанбаатар"));
     }
 
     #[test]
     fn test_format_city_name() {
         assert_eq!(format_city_name("Paris"), "P  A  R  I  S");
         assert_eq!(format_city_name("东京"), "东京");
+        assert_eq!(
+            format_city_name("São Tomé (圣多美)"),
+            "S  Ã  O     T  O  M  É (圣多美)"
+        );
+        // 组合附加符号不与基字符分开
+        assert_eq!(format_city_name("U\u{308}lan"), "U\u{308}  L  A  N");
     }
 }
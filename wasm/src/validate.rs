@@ -0,0 +1,565 @@
+//! 渲染请求预检
+//!
+//! 不投影、不绘制，只检查配置与道路分片长度，一次性返回全部问题，
+//! 前端可在发起耗时渲染前提示用户。
+
+use crate::error::{RenderError, RenderErrorCode};
+use crate::projection::WORLD_WIDTH;
+use crate::types::{MAX_MARGIN_PCT, MarginPct, PosterLayout, RenderConfig, default_frontend_scale};
+use crate::warnings;
+use std::ops::RangeInclusive;
+
+/// 内部超采样倍数（见 `MapRenderer::new`）
+const RENDER_SCALE: u64 = 2;
+/// 二进制头部长度（magic、版本、图层类型）
+const BIN_HEADER_LEN: u32 = 3;
+/// wasm32 线性内存上限（4 GiB）
+const WASM_MEMORY_LIMIT: u64 = 1 << 32;
+
+/// 每个逻辑像素占用的字节数：超采样 RGBA 画布加上缩回逻辑尺寸后的 RGBA 输出缓冲区
+const BYTES_PER_PIXEL: u64 = RENDER_SCALE * RENDER_SCALE * 4 + 4;
+
+/// 单边最大逻辑像素数：tiny-skia 要求画布每行字节数（超采样宽度 × 4）不超过 `i32::MAX`
+pub const MAX_CANVAS_SIDE: u32 = (i32::MAX as u32 / 4) / RENDER_SCALE as u32;
+
+/// 可渲染的最大逻辑像素数（宽 × 高），画布与输出缓冲区须小于 wasm 线性内存上限
+pub fn max_canvas_pixels() -> u64 {
+    (WASM_MEMORY_LIMIT - 1) / BYTES_PER_PIXEL
+}
+
+/// 渲染 `width` × `height` 的海报所需的画布内存（字节）
+pub fn canvas_bytes(width: u32, height: u32) -> u64 {
+    u64::from(width) * u64::from(height) * BYTES_PER_PIXEL
+}
+
+/// 检查画布尺寸能否创建，错误信息给出超出的上限、所需内存与同比例下可用的最大尺寸
+pub fn check_canvas_size(width: u32, height: u32) -> Result<(), RenderError> {
+    if width == 0 || height == 0 {
+        return Err(invalid(
+            "width",
+            format!("Size must be non-zero, got {}x{}", width, height),
+        ));
+    }
+    let too_large = |field: &str, message: String| {
+        Err(RenderError::new(RenderErrorCode::CanvasTooLarge, message).with_field(field))
+    };
+    for (field, side) in [("width", width), ("height", height)] {
+        if side > MAX_CANVAS_SIDE {
+            return too_large(
+                field,
+                format!(
+                    "Canvas {} {} exceeds the renderer limit of {} pixels per side",
+                    field, side, MAX_CANVAS_SIDE
+                ),
+            );
+        }
+    }
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > max_canvas_pixels() {
+        // 保持宽高比缩小到内存上限以内
+        let shrink = (max_canvas_pixels() as f64 / pixels as f64).sqrt();
+        let (max_width, max_height) = (
+            (f64::from(width) * shrink) as u32,
+            (f64::from(height) * shrink) as u32,
+        );
+        return too_large(
+            "width",
+            format!(
+                "Canvas {}x{} needs {} MiB ({}x supersampled canvas plus output), exceeding the {} MiB WASM memory limit; the largest canvas at this aspect ratio is about {}x{}",
+                width,
+                height,
+                canvas_bytes(width, height) >> 20,
+                RENDER_SCALE,
+                WASM_MEMORY_LIMIT >> 20,
+                max_width,
+                max_height
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// 半径的可用范围（米）：过小时画布上只剩几栋建筑，过大时道路细到不可见、海报只剩背景
+pub const RADIUS_RANGE: RangeInclusive<f64> = 100.0..=1_000_000.0;
+/// 画布长边与短边之比的上限
+pub const MAX_ASPECT_RATIO: u32 = 8;
+/// POI 光环的最大环数
+pub const MAX_HALO_RINGS: u32 = 16;
+
+/// `frontend_scale` 的可用范围
+pub const FRONTEND_SCALE_RANGE: RangeInclusive<f32> = 0.25..=32.0;
+
+/// 将超出可用范围的半径、宽高比与 `frontend_scale` 夹到范围内，并记入渲染警告
+///
+/// 非有限的半径无法夹取，返回错误；非有限的 `frontend_scale` 恢复为缺省值
+pub fn clamp_config(config: &mut RenderConfig) -> Result<(), RenderError> {
+    if config.bounds.is_none() {
+        let radius = config.radius;
+        if !radius.is_finite() {
+            return Err(invalid(
+                "radius",
+                format!("Radius must be finite, got {}", radius),
+            ));
+        }
+        let clamped = radius.clamp(*RADIUS_RANGE.start(), *RADIUS_RANGE.end());
+        if clamped != radius {
+            config.radius = clamped;
+            warnings::clamped(
+                "radius",
+                format!(
+                    "Radius {} m is outside [{}, {}] m; clamped to {} m",
+                    radius,
+                    RADIUS_RANGE.start(),
+                    RADIUS_RANGE.end(),
+                    clamped
+                ),
+            );
+        }
+    }
+
+    let (width, height) = (config.width, config.height);
+    if width > 0 && height > 0 {
+        let max_ratio = u64::from(MAX_ASPECT_RATIO);
+        let shrunk = if u64::from(width) > u64::from(height) * max_ratio {
+            config.width = height * MAX_ASPECT_RATIO;
+            Some(("width", width, config.width))
+        } else if u64::from(height) > u64::from(width) * max_ratio {
+            config.height = width * MAX_ASPECT_RATIO;
+            Some(("height", height, config.height))
+        } else {
+            None
+        };
+        if let Some((field, from, to)) = shrunk {
+            warnings::clamped(
+                field,
+                format!(
+                    "Canvas {}x{} exceeds the {}:1 aspect ratio limit; {} clamped from {} to {}",
+                    width, height, MAX_ASPECT_RATIO, field, from, to
+                ),
+            );
+        }
+    }
+
+    let scale = config.frontend_scale;
+    let clamped = if scale.is_finite() {
+        scale.clamp(*FRONTEND_SCALE_RANGE.start(), *FRONTEND_SCALE_RANGE.end())
+    } else {
+        default_frontend_scale()
+    };
+    if clamped != scale {
+        config.frontend_scale = clamped;
+        warnings::clamped(
+            "frontend_scale",
+            format!(
+                "frontend_scale {} is outside [{}, {}]; using {}",
+                scale,
+                FRONTEND_SCALE_RANGE.start(),
+                FRONTEND_SCALE_RANGE.end(),
+                clamped
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// 检查配置的尺寸、中心点、半径与颜色
+pub fn validate_config(config: &RenderConfig) -> Vec<RenderError> {
+    let mut errors = Vec::new();
+
+    let (lat, lon) = (config.center.lat, config.center.lon);
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        errors.push(invalid(
+            "center",
+            format!("Center out of range: lat={}, lon={}", lat, lon),
+        ));
+    }
+
+    let max_radius = WORLD_WIDTH / 2.0;
+    if config.bounds.is_none()
+        && !(config.radius.is_finite() && config.radius > 0.0 && config.radius <= max_radius)
+    {
+        errors.push(invalid(
+            "radius",
+            format!(
+                "Radius must be in (0, {:.0}] meters, got {}",
+                max_radius, config.radius
+            ),
+        ));
+    }
+
+    if let Err(e) = check_canvas_size(config.width, config.height) {
+        errors.push(e);
+    }
+
+    let theme = &config.theme;
+    let colors = [
+        ("bg", Some(&theme.bg)),
+        ("text", Some(&theme.text)),
+        ("gradient_color", Some(&theme.gradient_color)),
+        ("poi_color", Some(&theme.poi_color)),
+        ("water", Some(&theme.water)),
+        ("parks", Some(&theme.parks)),
+        ("road_motorway", Some(&theme.road_motorway)),
+        ("road_primary", Some(&theme.road_primary)),
+        ("road_secondary", Some(&theme.road_secondary)),
+        ("road_tertiary", Some(&theme.road_tertiary)),
+        ("road_residential", Some(&theme.road_residential)),
+        ("road_default", Some(&theme.road_default)),
+        ("route_color", theme.route_color.as_ref()),
+        ("buildings", theme.buildings.as_ref()),
+    ];
+    for (name, color) in colors {
+        if let Some(color) = color {
+            check_color(&mut errors, format!("theme.{}", name), color);
+        }
+    }
+    for (i, layer) in config.extra_layers.iter().enumerate() {
+        check_color(
+            &mut errors,
+            format!("extra_layers[{}].color", i),
+            &layer.color,
+        );
+    }
+    if let Some(arcs) = &config.arcs {
+        for (i, p) in arcs.points.iter().enumerate() {
+            if !(-90.0..=90.0).contains(&p.lat) || !(-180.0..=180.0).contains(&p.lon) {
+                errors.push(invalid(
+                    &format!("arcs.points[{}]", i),
+                    format!("Point out of range: lat={}, lon={}", p.lat, p.lon),
+                ));
+            }
+        }
+        if !(arcs.width.is_finite() && arcs.width > 0.0) {
+            errors.push(invalid(
+                "arcs.width",
+                format!("Arc width must be positive, got {}", arcs.width),
+            ));
+        }
+        if !arcs.dash.is_empty() && crate::utils::dash_pattern(&arcs.dash).is_none() {
+            errors.push(invalid(
+                "arcs.dash",
+                format!(
+                    "Dash lengths must be non-negative with a positive total, got {:?}",
+                    arcs.dash
+                ),
+            ));
+        }
+        if let Some(color) = &arcs.color {
+            check_color(&mut errors, "arcs.color".to_string(), color);
+        }
+    }
+    if let Some(halo) = &config.poi_halo {
+        if halo.rings > MAX_HALO_RINGS {
+            errors.push(invalid(
+                "poi_halo.rings",
+                format!(
+                    "Halo ring count must be at most {}, got {}",
+                    MAX_HALO_RINGS, halo.rings
+                ),
+            ));
+        }
+        let lengths = [("spacing", halo.spacing), ("width", halo.width)];
+        for (name, value) in lengths {
+            if !(value.is_finite() && value > 0.0) {
+                errors.push(invalid(
+                    &format!("poi_halo.{}", name),
+                    format!("Halo {} must be positive, got {}", name, value),
+                ));
+            }
+        }
+        let fractions = [("opacity", halo.opacity), ("falloff", halo.falloff)];
+        for (name, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                errors.push(invalid(
+                    &format!("poi_halo.{}", name),
+                    format!("Halo {} must be in [0, 1], got {}", name, value),
+                ));
+            }
+        }
+        if let Some(color) = &halo.color {
+            check_color(&mut errors, "poi_halo.color".to_string(), color);
+        }
+    }
+    if let Some(margins) = config.margin_pct {
+        let sides: Vec<(String, f64)> = match margins {
+            MarginPct::All(pct) => vec![("margin_pct".to_string(), pct)],
+            MarginPct::Sides(m) => [
+                ("top", m.top),
+                ("right", m.right),
+                ("bottom", m.bottom),
+                ("left", m.left),
+            ]
+            .into_iter()
+            .map(|(side, pct)| (format!("margin_pct.{}", side), pct))
+            .collect(),
+        };
+        for (field, pct) in sides {
+            if !(0.0..=MAX_MARGIN_PCT).contains(&pct) {
+                errors.push(invalid(
+                    &field,
+                    format!("Margin must be in [0, {}], got {}", MAX_MARGIN_PCT, pct),
+                ));
+            }
+        }
+    }
+    if !(config.corner_radius.is_finite() && config.corner_radius >= 0.0) {
+        errors.push(invalid(
+            "corner_radius",
+            format!(
+                "Corner radius must be non-negative, got {}",
+                config.corner_radius
+            ),
+        ));
+    }
+    if !(config.text_panel_pct > 0.0 && config.text_panel_pct <= MAX_MARGIN_PCT) {
+        errors.push(invalid(
+            "text_panel_pct",
+            format!(
+                "Text panel must be in (0, {}], got {}",
+                MAX_MARGIN_PCT, config.text_panel_pct
+            ),
+        ));
+    }
+    // 面板版式中文字面板叠在底部边距上，两者之和与单边边距共用上限
+    let bottom = config.margin_pct.map_or(0.0, |m| m.sides().bottom);
+    if config.layout == PosterLayout::Panel
+        && (0.0..=MAX_MARGIN_PCT).contains(&bottom)
+        && config.text_panel_pct <= MAX_MARGIN_PCT
+        && bottom + config.text_panel_pct > MAX_MARGIN_PCT
+    {
+        errors.push(invalid(
+            "text_panel_pct",
+            format!(
+                "Bottom margin plus text panel must not exceed {}, got {} + {}",
+                MAX_MARGIN_PCT, bottom, config.text_panel_pct
+            ),
+        ));
+    }
+    if let Some(color) = &config.text_panel_color {
+        check_color(&mut errors, "text_panel_color".to_string(), color);
+    }
+    if let Some(logo) = &config.logo {
+        if !(logo.scale > 0.0 && logo.scale <= 1.0) {
+            errors.push(invalid(
+                "logo.scale",
+                format!("Logo scale must be in (0, 1], got {}", logo.scale),
+            ));
+        }
+        if !(logo.margin.is_finite() && logo.margin >= 0.0) {
+            errors.push(invalid(
+                "logo.margin",
+                format!("Logo margin must be non-negative, got {}", logo.margin),
+            ));
+        }
+        if !(0.0..=1.0).contains(&logo.opacity) {
+            errors.push(invalid(
+                "logo.opacity",
+                format!("Logo opacity must be in [0, 1], got {}", logo.opacity),
+            ));
+        }
+    }
+    if let Some(solar) = &config.solar {
+        if !solar.timestamp_ms.is_finite() {
+            errors.push(invalid(
+                "solar.timestamp_ms",
+                format!("Solar timestamp must be finite, got {}", solar.timestamp_ms),
+            ));
+        }
+        if !(0.0..=1.0).contains(&solar.night_opacity) {
+            errors.push(invalid(
+                "solar.night_opacity",
+                format!(
+                    "Night opacity must be in [0, 1], got {}",
+                    solar.night_opacity
+                ),
+            ));
+        }
+        if let Some(color) = &solar.night_color {
+            check_color(&mut errors, "solar.night_color".to_string(), color);
+        }
+    }
+    if let Some(watermark) = &config.watermark {
+        if !(0.0..=1.0).contains(&watermark.opacity) {
+            errors.push(invalid(
+                "watermark.opacity",
+                format!(
+                    "Watermark opacity must be in [0, 1], got {}",
+                    watermark.opacity
+                ),
+            ));
+        }
+        if !(watermark.size.is_finite() && watermark.size > 0.0) {
+            errors.push(invalid(
+                "watermark.size",
+                format!("Watermark size must be positive, got {}", watermark.size),
+            ));
+        }
+        if !watermark.angle.is_finite() {
+            errors.push(invalid(
+                "watermark.angle",
+                format!("Watermark angle must be finite, got {}", watermark.angle),
+            ));
+        }
+        if let Some(color) = &watermark.color {
+            check_color(&mut errors, "watermark.color".to_string(), color);
+        }
+    }
+    if let Some(marker) = &config.home_marker {
+        if !(marker.size.is_finite() && marker.size > 0.0) {
+            errors.push(invalid(
+                "home_marker.size",
+                format!("Marker size must be positive, got {}", marker.size),
+            ));
+        }
+        if let Some(color) = &marker.color {
+            check_color(&mut errors, "home_marker.color".to_string(), color);
+        }
+    }
+
+    errors
+}
+
+/// 检查道路分片长度
+///
+/// 无头部分片为 `1 + Σ(2 + 2n)`（奇数），带头部时再加 3（偶数且至少 4），
+/// 空分片长度为 0；总字节数须能放入 wasm 线性内存
+pub fn validate_shard_lengths(lengths: &[u32]) -> Vec<RenderError> {
+    let mut errors = Vec::new();
+    for (i, &len) in lengths.iter().enumerate() {
+        if len % 2 == 0 && len != 0 && len < BIN_HEADER_LEN + 1 {
+            errors.push(
+                RenderError::bad_geometry(format!(
+                    "Road shard {} has length {}, which is not a valid road binary",
+                    i, len
+                ))
+                .with_field(format!("roads[{}]", i)),
+            );
+        }
+    }
+    let total: u64 = lengths.iter().map(|&len| u64::from(len) * 8).sum();
+    if total >= WASM_MEMORY_LIMIT {
+        errors.push(
+            RenderError::bad_geometry(format!(
+                "Road shards total {} MiB, exceeding WASM memory",
+                total >> 20
+            ))
+            .with_field("roads"),
+        );
+    }
+    errors
+}
+
+fn invalid(field: &str, message: String) -> RenderError {
+    RenderError::invalid_config(message).with_field(field)
+}
+
+/// 颜色须能被 `parse_hex_color` 解析（渲染时无效颜色会回退为黑色）
+fn check_color(errors: &mut Vec<RenderError>, field: String, color: &str) {
+    if let Err(message) = crate::utils::parse_hex_color(color) {
+        errors.push(RenderError::invalid_config(message).with_field(field));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RenderConfig {
+        serde_json::from_value(serde_json::json!({
+            "center": { "lat": 48.85, "lon": 2.35 },
+            "radius": 5000.0,
+            "theme": crate::types::Theme::default(),
+            "width": 800,
+            "height": 1000,
+            "display_city": "Paris",
+            "display_country": "France",
+            "text_position": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        assert!(validate_config(&config()).is_empty());
+        assert!(validate_shard_lengths(&[0, 1, 7, 4, 10]).is_empty());
+    }
+
+    #[test]
+    fn test_reports_all_problems() {
+        let mut config = config();
+        config.radius = -1.0;
+        config.width = 0;
+        config.theme.water = "blue".to_string();
+        config.theme.route_color = Some("#12345".to_string());
+
+        let fields: Vec<_> = validate_config(&config)
+            .into_iter()
+            .filter_map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            ["radius", "width", "theme.water", "theme.route_color"]
+        );
+
+        let errors = validate_shard_lengths(&[7, 2]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field.as_deref(), Some("roads[1]"));
+    }
+
+    #[test]
+    fn test_clamp_config_records_warnings() {
+        let mut config = config();
+        config.radius = 0.0;
+        config.width = 9000;
+        config.height = 1000;
+        config.frontend_scale = f32::NAN;
+        warnings::begin();
+        clamp_config(&mut config).unwrap();
+        assert_eq!(config.radius, *RADIUS_RANGE.start());
+        assert_eq!((config.width, config.height), (8000, 1000));
+        assert_eq!(config.frontend_scale, default_frontend_scale());
+        let fields: Vec<_> = warnings::take()
+            .into_iter()
+            .filter_map(|w| w.field)
+            .collect();
+        assert_eq!(fields, ["radius", "width", "frontend_scale"]);
+
+        // 范围内的配置保持不变
+        let mut config = self::config();
+        clamp_config(&mut config).unwrap();
+        assert!(warnings::take().is_empty());
+        config.radius = f64::INFINITY;
+        assert!(clamp_config(&mut config).is_err());
+    }
+
+    #[test]
+    fn test_canvas_too_large() {
+        let mut config = config();
+        config.width = 40_000;
+        config.height = 40_000;
+        let errors = validate_config(&config);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, RenderErrorCode::CanvasTooLarge);
+        assert!(
+            errors[0].message.contains("30517 MiB"),
+            "{}",
+            errors[0].message
+        );
+
+        // 建议的同比例尺寸本身可以通过校验
+        let (side, _) = errors[0]
+            .message
+            .rsplit_once("about ")
+            .unwrap()
+            .1
+            .split_once('x')
+            .unwrap();
+        let side: u32 = side.parse().unwrap();
+        assert!(check_canvas_size(side, side).is_ok());
+        assert!(check_canvas_size(side + 1, side + 1).is_err());
+
+        let error = check_canvas_size(MAX_CANVAS_SIDE + 1, 1).unwrap_err();
+        assert_eq!(error.field.as_deref(), Some("width"));
+        assert!(error.message.contains(&MAX_CANVAS_SIDE.to_string()));
+    }
+}
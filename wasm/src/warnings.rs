@@ -0,0 +1,102 @@
+//! 渲染警告
+//!
+//! 不致使渲染失败、但会影响结果的问题（如被跳过的无效坐标），随 `RenderResult` 返回，
+//! 便于解释"为什么少了一段路"，而不是静默地画出残缺或空白的海报。
+
+use serde::Serialize;
+use std::cell::RefCell;
+
+/// 警告类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// 坐标为 NaN / Inf 或超出坐标参考系的范围，已跳过
+    InvalidCoordinates,
+    /// 参数超出可用范围，已夹到范围内
+    Clamped,
+    /// 二进制数据与声明的数量不符，已跳过不完整或多余的部分
+    MalformedData,
+}
+
+/// 渲染警告，序列化为 `{ code, message, field?, count }`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(js_host, derive(tsify::Tsify))]
+pub struct RenderWarning {
+    pub code: WarningCode,
+    pub message: String,
+    /// 相关的配置字段或图层
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 受影响的条目数（如跳过的坐标数）
+    pub count: usize,
+}
+
+thread_local! {
+    // 与 draw_stats 相同，按调用渲染接口的线程隔离
+    static WARNINGS: RefCell<Vec<RenderWarning>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 渲染开始：清空上一次的警告
+pub fn begin() {
+    WARNINGS.with(|warnings| warnings.borrow_mut().clear());
+}
+
+/// 记录警告，同类别、同字段的警告合并计数
+pub fn record(code: WarningCode, field: Option<&str>, count: usize, message: &str) {
+    if count == 0 {
+        return;
+    }
+    WARNINGS.with(|warnings| {
+        let mut warnings = warnings.borrow_mut();
+        match warnings
+            .iter_mut()
+            .find(|w| w.code == code && w.field.as_deref() == field)
+        {
+            Some(existing) => existing.count += count,
+            None => warnings.push(RenderWarning {
+                code,
+                message: message.to_string(),
+                field: field.map(str::to_string),
+                count,
+            }),
+        }
+    });
+}
+
+/// 记录被跳过的无效坐标
+pub fn invalid_coordinates(field: Option<&str>, count: usize) {
+    record(
+        WarningCode::InvalidCoordinates,
+        field,
+        count,
+        "Skipped coordinates that are NaN, infinite or out of range",
+    );
+}
+
+/// 记录与声明数量不符、被跳过的二进制数值
+pub fn malformed_data(field: &str, count: usize) {
+    record(
+        WarningCode::MalformedData,
+        Some(field),
+        count,
+        "Binary data does not match its declared count; skipped incomplete or trailing values",
+    );
+}
+
+/// 记录被夹到可用范围内的参数
+pub fn clamped(field: &str, message: String) {
+    record(WarningCode::Clamped, Some(field), 1, &message);
+}
+
+/// 重新记录先前取出的警告（如会话准备阶段产生的警告）
+pub fn extend(previous: &[RenderWarning]) {
+    for w in previous {
+        record(w.code, w.field.as_deref(), w.count, &w.message);
+    }
+}
+
+/// 取出本次渲染的警告
+pub fn take() -> Vec<RenderWarning> {
+    WARNINGS.with(|warnings| std::mem::take(&mut *warnings.borrow_mut()))
+}